@@ -7,6 +7,7 @@ var<uniform> uniforms: Uniforms;
 struct VertexInput {
     @location(0) position: vec2<f32>,
     @location(1) color: vec4<f32>,
+    @location(2) depth: f32,
 }
 
 struct VertexOutput {
@@ -20,11 +21,13 @@ fn vs_main(
 ) -> VertexOutput {
     var out: VertexOutput;
     out.color = model.color;
-    out.clip_position = uniforms.transform * vec4<f32>(model.position, 0.0, 1.0);
+    let clip = uniforms.transform * vec4<f32>(model.position, 0.0, 1.0);
+    // Per-element layer depth keeps a translucent element from blending over itself.
+    out.clip_position = vec4<f32>(clip.xy, model.depth * clip.w, clip.w);
     return out;
 }
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return in.color;
+    return vec4<f32>(in.color.rgb * in.color.a, in.color.a);
 }
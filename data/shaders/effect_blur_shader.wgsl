@@ -0,0 +1,64 @@
+// Separable Gaussian blur for element shadows and glows.
+//
+// Drawn as a single triangle over one job's region of the shared effect atlas
+// (the viewport is set to that region); run once horizontally and once
+// vertically. Colors stay premultiplied.
+
+struct BlurParams {
+    // One texel along the blur axis, in UV units.
+    direction: vec2<f32>,
+    // Standard deviation in texels.
+    sigma: f32,
+    _pad: f32,
+    // The job's region of the atlas in UV units: origin, then size.
+    region: vec4<f32>,
+    // Samples are clamped to this UV rectangle so they never reach a
+    // neighbouring job's region.
+    clamp: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+@group(0) @binding(1)
+var source_sampler: sampler;
+@group(0) @binding(2)
+var<uniform> params: BlurParams;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let corner = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0);
+    out.uv = params.region.xy + vec2<f32>(corner.x, 1.0 - corner.y) * params.region.zw;
+    return out;
+}
+
+// Taps are spread so wide blurs stay within a fixed sample budget; linear
+// filtering fills the gaps.
+const MAX_TAPS: i32 = 24;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let sigma = max(params.sigma, 0.001);
+    let reach = ceil(sigma * 3.0);
+    let spacing = max(reach / f32(MAX_TAPS), 1.0);
+
+    var sum = vec4<f32>(0.0);
+    var weight_sum = 0.0;
+    for (var i = -MAX_TAPS; i <= MAX_TAPS; i++) {
+        let offset = f32(i) * spacing;
+        if abs(offset) > reach {
+            continue;
+        }
+        let weight = exp(-0.5 * (offset * offset) / (sigma * sigma));
+        let uv = clamp(in.uv + params.direction * offset, params.clamp.xy, params.clamp.zw);
+        sum += textureSampleLevel(source, source_sampler, uv, 0.0) * weight;
+        weight_sum += weight;
+    }
+    return sum / weight_sum;
+}
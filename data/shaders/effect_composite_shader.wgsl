@@ -0,0 +1,49 @@
+// Composites a blurred effect target back onto the canvas.
+//
+// Each effect is a canvas-space quad at its own layer depth, so shadows and
+// glows interleave with elements in z-order like any other geometry.
+
+struct Uniforms {
+    transform: mat4x4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> uniforms: Uniforms;
+
+@group(1) @binding(0)
+var effect: texture_2d<f32>;
+@group(1) @binding(1)
+var effect_sampler: sampler;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) depth: f32,
+    @location(3) alpha: f32,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) alpha: f32,
+}
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    let clip = uniforms.transform * vec4<f32>(in.position, 0.0, 1.0);
+    out.clip_position = vec4<f32>(clip.xy, in.depth * clip.w, clip.w);
+    out.uv = in.uv;
+    out.alpha = in.alpha;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // The target holds the effect color at full strength; scale it by the
+    // effect's own alpha. Colors are already premultiplied.
+    let color = textureSample(effect, effect_sampler, in.uv) * in.alpha;
+    if color.a < 0.004 {
+        discard;
+    }
+    return color;
+}
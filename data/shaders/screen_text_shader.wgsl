@@ -6,17 +6,20 @@ struct ScreenUniforms {
 @group(0) @binding(0) var<uniform> screen : ScreenUniforms;
 @group(1) @binding(0) var tex  : texture_2d<f32>;
 @group(1) @binding(1) var samp : sampler;
+@group(1) @binding(2) var color_tex : texture_2d<f32>;
 
 struct VSIn {
     @location(0) pos : vec2<f32>,
     @location(1) uv  : vec2<f32>,
     @location(2) col : vec4<f32>,
+    @location(3) page : u32,
 };
 
 struct VSOut {
     @builtin(position) clip_position : vec4<f32>,
     @location(0) uv  : vec2<f32>,
     @location(1) col : vec4<f32>,
+    @location(2) @interpolate(flat) page : u32,
 };
 
 @vertex
@@ -27,6 +30,7 @@ fn vs_main(v:VSIn) -> VSOut {
     o.clip_position = vec4<f32>(ndc_x, ndc_y, 0.0, 1.0);
     o.uv = v.uv;
     o.col = v.col;
+    o.page = v.page;
     return o;
 }
 
@@ -42,6 +46,8 @@ fn smooth_step(edge0: f32, edge1: f32, x: f32) -> f32 {
 @fragment
 fn fs_main(i:VSOut) -> @location(0) vec4<f32> {
     let msdf_sample = textureSample(tex, samp, i.uv);
+    // Sampled up front: textureSample needs uniform control flow.
+    let color_sample = textureSample(color_tex, samp, i.uv);
     
     let distance = median(msdf_sample.r, msdf_sample.g, msdf_sample.b);
     
@@ -54,5 +60,17 @@ fn fs_main(i:VSOut) -> @location(0) vec4<f32> {
     let smoothness = 0.7; 
     let alpha = smooth_step(-smoothness, smoothness, screen_px_distance);
     
-    return vec4<f32>(i.col.rgb, i.col.a * alpha);
+    // Page 1 holds premultiplied color glyphs; page 2 draws them as a
+    // solid silhouette.
+    if (i.page == 1u) {
+        return color_sample * i.col.a;
+    }
+    if (i.page == 2u) {
+        let a = i.col.a * color_sample.a;
+        return vec4<f32>(i.col.rgb * a, a);
+    }
+
+    // Premultiplied output to match the premultiplied blend state.
+    let a = i.col.a * alpha;
+    return vec4<f32>(i.col.rgb * a, a);
 } 
\ No newline at end of file
@@ -5,17 +5,20 @@ struct CanvasUniforms {
 @group(0) @binding(0) var<uniform> canvas: CanvasUniforms;
 @group(1) @binding(0) var tex: texture_2d<f32>;
 @group(1) @binding(1) var samp: sampler;
+@group(1) @binding(2) var color_tex: texture_2d<f32>;
 
 struct VSIn {
     @location(0) pos: vec2<f32>,
     @location(1) uv:  vec2<f32>,
     @location(2) col: vec4<f32>,
+    @location(3) page: u32,
 };
 
 struct VSOut {
     @builtin(position) pos: vec4<f32>,
     @location(0) uv: vec2<f32>,
     @location(1) col: vec4<f32>,
+    @location(2) @interpolate(flat) page: u32,
 };
 
 @vertex
@@ -26,6 +29,7 @@ fn vs_main(v: VSIn) -> VSOut {
     o.pos = transformed_pos;
     o.uv  = v.uv;
     o.col = v.col;
+    o.page = v.page;
     return o;
 }
 
@@ -41,6 +45,8 @@ fn smooth_step(edge0: f32, edge1: f32, x: f32) -> f32 {
 @fragment
 fn fs_main(inp: VSOut) -> @location(0) vec4<f32> {
     let msdf_sample = textureSample(tex, samp, inp.uv);
+    // Sampled up front: textureSample needs uniform control flow.
+    let color_sample = textureSample(color_tex, samp, inp.uv);
     
     let distance = median(msdf_sample.r, msdf_sample.g, msdf_sample.b);
     
@@ -53,5 +59,17 @@ fn fs_main(inp: VSOut) -> @location(0) vec4<f32> {
     let smoothness = 0.7; 
     let alpha = smooth_step(-smoothness, smoothness, screen_px_distance);
     
-    return vec4<f32>(inp.col.rgb, inp.col.a * alpha);
+    // Page 1 holds premultiplied color glyphs; page 2 draws them as a
+    // solid silhouette.
+    if (inp.page == 1u) {
+        return color_sample * inp.col.a;
+    }
+    if (inp.page == 2u) {
+        let a = inp.col.a * color_sample.a;
+        return vec4<f32>(inp.col.rgb * a, a);
+    }
+
+    // Premultiplied output to match the premultiplied blend state.
+    let a = inp.col.a * alpha;
+    return vec4<f32>(inp.col.rgb * a, a);
 }
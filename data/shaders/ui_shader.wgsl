@@ -83,5 +83,6 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
         }
     }
     
-    return final_color;
+    // Premultiplied output to match the premultiplied blend state.
+    return vec4<f32>(final_color.rgb * final_color.a, final_color.a);
 } 
\ No newline at end of file
@@ -10,30 +10,69 @@ struct Uniforms {
 @group(0) @binding(0)
 var<uniform> uniforms: Uniforms;
 
+// Gradient and stop colors per shape, PAINT_TEXELS texels per entry (see
+// SdfBatch): [gradient, stop offsets, color0, color1, color2, color3].
+// gradient is linear: [axis.xy, 0, 0]; radial: [center.xy, 1/rx, 1/ry];
+// effects: [reach, 0, 0, 0]. Stop offsets are padded with the last stop and a
+// solid fill repeats one color.
+@group(1) @binding(0)
+var paint_table: texture_2d<f32>;
+
+const PAINT_TEXELS: u32 = 6u;
+const PAINTS_PER_ROW: u32 = 256u;
+
+struct Paint {
+    gradient: vec4<f32>,
+    stops: vec4<f32>,
+    color0: vec4<f32>,
+    color1: vec4<f32>,
+    color2: vec4<f32>,
+    color3: vec4<f32>,
+}
+
+fn load_paint(index: u32) -> Paint {
+    let x = (index % PAINTS_PER_ROW) * PAINT_TEXELS;
+    let y = index / PAINTS_PER_ROW;
+    var paint: Paint;
+    paint.gradient = textureLoad(paint_table, vec2<u32>(x, y), 0);
+    paint.stops = textureLoad(paint_table, vec2<u32>(x + 1u, y), 0);
+    paint.color0 = textureLoad(paint_table, vec2<u32>(x + 2u, y), 0);
+    paint.color1 = textureLoad(paint_table, vec2<u32>(x + 3u, y), 0);
+    paint.color2 = textureLoad(paint_table, vec2<u32>(x + 4u, y), 0);
+    paint.color3 = textureLoad(paint_table, vec2<u32>(x + 5u, y), 0);
+    return paint;
+}
+
 struct SdfVertexInput {
     @location(0) position: vec2<f32>,
     @location(1) local_pos: vec2<f32>,
     @location(2) color: vec4<f32>,
-    @location(3) shape_params: vec4<f32>,  // [shape_type, half_width, half_height, stroke_width]
-    @location(4) fill_params: vec4<f32>,   // [fill_flag, unused, unused, unused]
+    @location(3) shape_params: vec4<f32>,  // [shape_type, half_width, half_height, stroke_width]; local axes follow the shape's rotation
+    @location(4) fill_params: vec4<f32>,   // [mode, corner_radius, depth, gradient_kind]
+    @location(5) paint: u32,               // paint_table entry
 }
 
+// Kept small: WebGL2 allows only 31 inter-stage components.
 struct SdfVertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) local_pos: vec2<f32>,
     @location(1) color: vec4<f32>,
     @location(2) shape_params: vec4<f32>,
     @location(3) fill_params: vec4<f32>,
+    @location(4) @interpolate(flat) paint: u32,
 }
 
 @vertex
 fn vs_main(in: SdfVertexInput) -> SdfVertexOutput {
     var out: SdfVertexOutput;
-    out.clip_position = uniforms.transform * vec4<f32>(in.position, 0.0, 1.0);
+    let clip = uniforms.transform * vec4<f32>(in.position, 0.0, 1.0);
+    // fill_params.z carries the element's layer depth (see SdfBatch::set_depth).
+    out.clip_position = vec4<f32>(clip.xy, in.fill_params.z * clip.w, clip.w);
     out.local_pos = in.local_pos;
     out.color = in.color;
     out.shape_params = in.shape_params;
     out.fill_params = in.fill_params;
+    out.paint = in.paint;
     return out;
 }
 
@@ -43,18 +82,110 @@ fn sdf_rect(p: vec2<f32>, half_size: vec2<f32>) -> f32 {
     return length(max(d, vec2<f32>(0.0, 0.0))) + min(max(d.x, d.y), 0.0);
 }
 
-// SDF for a circle/ellipse centered at origin
+// Approximate SDF for an ellipse centered at origin. Dividing the implicit
+// function by its gradient keeps the distance accurate near the boundary even
+// for very elongated ellipses, which is all the stroke band needs.
 fn sdf_ellipse(p: vec2<f32>, radii: vec2<f32>) -> f32 {
-    // Normalize to unit circle space
-    let normalized = p / radii;
-    return (length(normalized) - 1.0) * min(radii.x, radii.y);
+    let r = max(radii, vec2<f32>(0.001, 0.001));
+    let k0 = length(p / r);
+    let k1 = length(p / (r * r));
+    if k1 < 1e-6 {
+        return -min(r.x, r.y);
+    }
+    return k0 * (k0 - 1.0) / k1;
+}
+
+// SDF for a rectangle with rounded corners; radius is pre-clamped on the CPU
+fn sdf_rounded_rect(p: vec2<f32>, half_size: vec2<f32>, radius: f32) -> f32 {
+    return sdf_rect(p, half_size - vec2<f32>(radius, radius)) - radius;
+}
+
+fn ndot(a: vec2<f32>, b: vec2<f32>) -> f32 {
+    return a.x * b.x - a.y * b.y;
 }
 
-// SDF for a diamond (rotated square / rhombus) centered at origin
+// Exact SDF for a diamond (rhombus) centered at origin with given half-diagonals
 fn sdf_diamond(p: vec2<f32>, half_size: vec2<f32>) -> f32 {
-    let normalized = abs(p) / half_size;
-    let d = (normalized.x + normalized.y - 1.0) * min(half_size.x, half_size.y) * 0.7071;
-    return d;
+    let q = abs(p);
+    let b = half_size;
+    let h = clamp(ndot(b - 2.0 * q, b) / max(dot(b, b), 1e-6), -1.0, 1.0);
+    let d = length(q - 0.5 * b * vec2<f32>(1.0 - h, 1.0 + h));
+    return d * sign(q.x * b.y + q.y * b.x - b.x * b.y);
+}
+
+// Diamond with rounded corners: shrink the rhombus so that offsetting it by
+// the radius lands back on the original edges.
+fn sdf_rounded_diamond(p: vec2<f32>, half_size: vec2<f32>, radius: f32) -> f32 {
+    let inradius = half_size.x * half_size.y / max(length(half_size), 1e-6);
+    let r = clamp(radius, 0.0, inradius);
+    let k = 1.0 - r / max(inradius, 1e-6);
+    return sdf_diamond(p, half_size * k) - r;
+}
+
+// Distance to the segment a-b; offsetting it by the stroke gives round caps.
+fn sdf_segment(p: vec2<f32>, a: vec2<f32>, b: vec2<f32>) -> f32 {
+    let pa = p - a;
+    let ba = b - a;
+    let h = clamp(dot(pa, ba) / max(dot(ba, ba), 1e-6), 0.0, 1.0);
+    return length(pa - ba * h);
+}
+
+// Arrowhead barbs in a box of the given half-size: the tip sits at the middle
+// of the right edge and the barbs end at the left corners.
+fn sdf_arrowhead(p: vec2<f32>, half_size: vec2<f32>) -> f32 {
+    let q = vec2<f32>(p.x, abs(p.y));
+    return sdf_segment(q, vec2<f32>(half_size.x, 0.0), vec2<f32>(-half_size.x, half_size.y));
+}
+
+// Gradient position for a point; mirrors FillStyle::gradient_t on the CPU.
+fn gradient_t(p: vec2<f32>, kind: u32, g: vec4<f32>) -> f32 {
+    switch kind {
+        case 1u: {
+            return dot(p, g.xy) + 0.5;
+        }
+        case 2u: {
+            return length((p - g.xy) * g.zw);
+        }
+        default: {
+            return 0.0;
+        }
+    }
+}
+
+// Blends through the stops in order; padded stops repeat the last color so
+// they contribute nothing.
+fn gradient_color(t: f32, paint: Paint) -> vec4<f32> {
+    let s = paint.stops;
+    let tc = clamp(t, 0.0, 1.0);
+    var color = paint.color0;
+    color = mix(color, paint.color1, clamp((tc - s.x) / max(s.y - s.x, 1e-5), 0.0, 1.0));
+    color = mix(color, paint.color2, clamp((tc - s.y) / max(s.z - s.y, 1e-5), 0.0, 1.0));
+    color = mix(color, paint.color3, clamp((tc - s.z) / max(s.w - s.z, 1e-5), 0.0, 1.0));
+    return color;
+}
+
+// Modes carried in fill_params.x; must match SdfBatch.
+const MODE_FILLED: u32 = 1u;
+const MODE_SHADOW: u32 = 2u;
+const MODE_GLOW: u32 = 3u;
+
+// Shadow or glow for a shape. `src` is the distance to what the shape covers:
+// its interior when filled, otherwise just the stroke band.
+fn effect_color(d: f32, half_stroke: f32, mode: u32, in: SdfVertexOutput, paint: Paint) -> vec4<f32> {
+    var src = abs(d) - half_stroke;
+    if in.fill_params.w > 0.5 {
+        src = d - half_stroke;
+    }
+    let reach = max(paint.gradient.x, 0.5);
+    var coverage: f32;
+    if mode == MODE_SHADOW {
+        coverage = 1.0 - smoothstep(-reach, reach, src);
+    } else {
+        let falloff = 1.0 - clamp(src / reach, 0.0, 1.0);
+        coverage = falloff * falloff;
+    }
+    let a = paint.color0.a * coverage;
+    return vec4<f32>(paint.color0.rgb * a, a);
 }
 
 @fragment
@@ -63,7 +194,9 @@ fn fs_main(in: SdfVertexOutput) -> @location(0) vec4<f32> {
     let half_w = in.shape_params.y;
     let half_h = in.shape_params.z;
     let stroke_width = in.shape_params.w;
-    let is_filled = in.fill_params.x > 0.5;
+    let mode = u32(in.fill_params.x + 0.5);
+    let is_filled = mode == MODE_FILLED;
+    let corner_radius = in.fill_params.y;
 
     let p = in.local_pos;
 
@@ -72,7 +205,7 @@ fn fs_main(in: SdfVertexOutput) -> @location(0) vec4<f32> {
     switch shape_type {
         case 0u: {
             // Rectangle
-            d = sdf_rect(p, vec2<f32>(half_w, half_h));
+            d = sdf_rounded_rect(p, vec2<f32>(half_w, half_h), corner_radius);
         }
         case 1u: {
             // Circle / Ellipse
@@ -80,7 +213,15 @@ fn fs_main(in: SdfVertexOutput) -> @location(0) vec4<f32> {
         }
         case 2u: {
             // Diamond
-            d = sdf_diamond(p, vec2<f32>(half_w, half_h));
+            d = sdf_rounded_diamond(p, vec2<f32>(half_w, half_h), corner_radius);
+        }
+        case 3u: {
+            // Line segment along the local x axis
+            d = sdf_segment(p, vec2<f32>(-half_w, 0.0), vec2<f32>(half_w, 0.0));
+        }
+        case 4u: {
+            // Arrowhead
+            d = sdf_arrowhead(p, vec2<f32>(half_w, half_h));
         }
         default: {
             d = sdf_ellipse(p, vec2<f32>(half_w, half_h));
@@ -91,20 +232,36 @@ fn fs_main(in: SdfVertexOutput) -> @location(0) vec4<f32> {
     let pixel_dist = length(vec2<f32>(dpdx(d), dpdy(d)));
     let aa_width = max(pixel_dist, 0.5);
 
-    var alpha: f32;
+    // Stroke band centered on the outline
+    let half_stroke = stroke_width * 0.5;
+
+    if mode == MODE_SHADOW || mode == MODE_GLOW {
+        let effect = effect_color(d, half_stroke, mode, in, load_paint(in.paint));
+        if effect.a < 0.004 {
+            discard;
+        }
+        return effect;
+    }
+    let stroke_cov = 1.0 - smoothstep(-aa_width, aa_width, abs(d) - half_stroke);
+    let stroke_a = in.color.a * stroke_cov;
+
+    // Background fills the interior underneath the stroke
+    var fill_color = vec4<f32>(0.0);
+    var fill_a = 0.0;
     if is_filled {
-        // Filled shape: smooth step at the boundary
-        alpha = 1.0 - smoothstep(-aa_width, aa_width, d);
-    } else {
-        // Stroke only: band around the boundary
-        let half_stroke = stroke_width * 0.5;
-        let inner_dist = abs(d) - half_stroke;
-        alpha = 1.0 - smoothstep(-aa_width, aa_width, inner_dist);
+        let kind = u32(in.fill_params.w + 0.5);
+        let paint = load_paint(in.paint);
+        fill_color = gradient_color(gradient_t(p, kind, paint.gradient), paint);
+        fill_a = fill_color.a * (1.0 - smoothstep(-aa_width, aa_width, d));
     }
 
-    if alpha < 0.01 {
+    // Premultiplied "stroke over fill", matching the premultiplied blend state.
+    let rgb = in.color.rgb * stroke_a + fill_color.rgb * fill_a * (1.0 - stroke_a);
+    let a = stroke_a + fill_a * (1.0 - stroke_a);
+
+    if a < 0.01 {
         discard;
     }
 
-    return vec4<f32>(in.color.rgb, in.color.a * alpha);
+    return vec4<f32>(rgb, a);
 }
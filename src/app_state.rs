@@ -1,12 +1,22 @@
 use crate::canvas::{CanvasTransform, Uniforms};
 use crate::document::Document;
 use crate::drawing::{Element, ElementId, Tool, sync_id_counters};
+use crate::effects::EffectRenderer;
+use crate::find::FindBar;
+use crate::fonts::FontFamily;
 use crate::history::{Action, History};
+use crate::keymap::Keymap;
+use crate::palette::CommandPalette;
+use crate::properties::PropertiesPanel;
+use crate::rich_text::SpanStyle;
 use crate::state::{
-    Canvas, ColorPickerState, GeometryBuffers, GpuContext, InputState, SdfBuffers, SelectionState,
-    TextInput, UiBuffers, UiScreenBuffers, UiScreenUniforms, UserInputState::Idle,
+    AntiAliasing, Canvas, ColorPickerState, CopiedText, DrawRun, GeometryBuffers, GpuContext,
+    GridStyle, InputState, SdfBuffers, SelectionState, Settings, TextInput, UiBuffers,
+    UiScreenBuffers, UiScreenUniforms, UserInputState::Idle,
 };
+use crate::styles::{CopiedStyle, DEFAULT_FONT_SIZE, ToolStyle};
 use crate::text_renderer::TextRenderer;
+use crate::theme::Theme;
 use crate::ui::UiRenderer;
 use crate::vertex::Vertex;
 use std::sync::Arc;
@@ -31,19 +41,30 @@ pub struct State {
     pub geometry: GeometryBuffers,
     pub sdf_geo: SdfBuffers,
     pub ui_geo: UiBuffers,
+    pub draw_runs: Vec<DrawRun>,
     pub input: InputState,
     pub typing: TextInput,
 
     pub elements: Vec<Element>,
     pub history: History,
     pub current_tool: Tool,
-    pub current_color: [f32; 4],
+    /// Style of the current drawing tool, used for new elements.
+    pub style: ToolStyle,
     pub color_picker: ColorPickerState,
-    pub stroke_width: f32,
     pub clipboard: Vec<Element>,
+    /// Last text copied inside a text box, for pasting with its formatting.
+    pub text_clipboard: CopiedText,
+    /// Set by Copy style, applied by Paste style.
+    pub copied_style: Option<CopiedStyle>,
 
     pub ui_renderer: UiRenderer,
     pub text_renderer: TextRenderer,
+    pub effect_renderer: EffectRenderer,
+    pub settings: Settings,
+    pub keymap: Keymap,
+    pub palette: CommandPalette,
+    pub find: FindBar,
+    pub properties: PropertiesPanel,
     pub ui_screen: UiScreenBuffers,
 
     /// Path of the currently open file (native only).
@@ -84,7 +105,9 @@ impl State {
 
         let (device, queue) = adapter
             .request_device(&wgpu::DeviceDescriptor {
-                required_features: wgpu::Features::empty(),
+                // Lets MSAA use every sample count the adapter supports, not just 4x.
+                required_features: adapter.features()
+                    & wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
                 required_limits: if cfg!(target_arch = "wasm32") {
                     wgpu::Limits::downlevel_webgl2_defaults()
                 } else {
@@ -152,108 +175,6 @@ impl State {
             label: Some("uniform_bind_group"),
         });
 
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(
-                include_str!("../data/shaders/draw_shader.wgsl").into(),
-            ),
-        });
-
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&uniform_bind_group_layout],
-                push_constant_ranges: &[],
-            });
-
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
-                buffers: &[Vertex::desc()],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-            cache: None,
-        });
-
-        let sdf_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("SDF Vector Shape Shader"),
-            source: wgpu::ShaderSource::Wgsl(
-                include_str!("../data/shaders/vector_sdf_shader.wgsl").into(),
-            ),
-        });
-
-        let sdf_render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("SDF Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &sdf_shader,
-                entry_point: Some("vs_main"),
-                buffers: &[crate::vertex::SdfVertex::desc()],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &sdf_shader,
-                entry_point: Some("fs_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: None,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-            cache: None,
-        });
-
-        let ui_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("UI Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("../data/shaders/ui_shader.wgsl").into()),
-        });
-
         let ui_uniform_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[wgpu::BindGroupLayoutEntry {
@@ -269,61 +190,68 @@ impl State {
                 label: Some("ui_uniform_bind_group_layout"),
             });
 
-        let ui_render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("UI Render Pipeline Layout"),
-                bind_group_layouts: &[&ui_uniform_bind_group_layout],
-                push_constant_ranges: &[],
+        let sdf_paint_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                }],
+                label: Some("sdf_paint_bind_group_layout"),
             });
 
-        let ui_render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("UI Render Pipeline"),
-            layout: Some(&ui_render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &ui_shader,
-                entry_point: Some("vs_main"),
-                buffers: &[crate::vertex::UiVertex::desc()],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &ui_shader,
-                entry_point: Some("fs_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: None,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-            cache: None,
-        });
+        let supported_sample_counts = supported_sample_counts(&adapter, &device, config.format);
+        let mut settings = load_settings();
+        settings.anti_aliasing = AntiAliasing::from_sample_count(usable_sample_count(
+            &supported_sample_counts,
+            settings.anti_aliasing,
+        ));
+        let sample_count = settings.anti_aliasing.sample_count();
+        let pipelines = CanvasPipelines::new(
+            &device,
+            config.format,
+            &uniform_bind_group_layout,
+            &ui_uniform_bind_group_layout,
+            &sdf_paint_bind_group_layout,
+            sample_count,
+        );
 
         let surface_format = config.format;
+        let depth_texture = crate::texture::Texture::create_depth_texture(
+            &device,
+            &config,
+            sample_count,
+            "Depth Texture",
+        );
+        let msaa_texture = (sample_count > 1).then(|| {
+            crate::texture::Texture::create_msaa_texture(
+                &device,
+                &config,
+                sample_count,
+                "MSAA Texture",
+            )
+        });
 
         let gpu = GpuContext {
             surface,
             device,
             queue,
             config,
-            render_pipeline,
-            sdf_render_pipeline,
-            ui_render_pipeline,
+            uniform_bind_group_layout,
+            ui_uniform_bind_group_layout,
+            sdf_paint_bind_group_layout,
+            render_pipeline: pipelines.render,
+            sdf_render_pipeline: pipelines.sdf,
+            ui_render_pipeline: pipelines.ui,
+            depth_texture,
+            msaa_texture,
+            sample_count,
+            supported_sample_counts,
         };
 
         let canvas = Canvas {
@@ -339,11 +267,7 @@ impl State {
             count: 0,
         };
 
-        let sdf_geo = SdfBuffers {
-            vertex: None,
-            index: None,
-            count: 0,
-        };
+        let sdf_geo = SdfBuffers::new(&gpu.device, &gpu.sdf_paint_bind_group_layout);
 
         let ui_geo = UiBuffers {
             vertex: None,
@@ -361,6 +285,7 @@ impl State {
             transform_snapshot: Vec::new(),
             selection: SelectionState::new(),
             preview_element: None,
+            guides: Vec::new(),
         };
 
         let typing = TextInput {
@@ -371,15 +296,34 @@ impl State {
             cursor_pos: 0,
             cursor_visible: false,
             blink_timer: Instant::now(),
+            font_size: DEFAULT_FONT_SIZE,
+            font: FontFamily::default(),
+            editing_label: false,
+            spans: Vec::new(),
+            style: SpanStyle::default(),
+            anchor: None,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            last_edit: None,
+            dragging: false,
+            last_click: None,
         };
 
-        let ui_renderer = UiRenderer::new();
+        let mut ui_renderer = UiRenderer::new();
+        ui_renderer.set_theme(settings.theme.theme());
         let text_renderer = TextRenderer::new(
             &gpu.device,
             &gpu.queue,
             surface_format,
-            &uniform_bind_group_layout,
-            &ui_uniform_bind_group_layout,
+            &gpu.uniform_bind_group_layout,
+            &gpu.ui_uniform_bind_group_layout,
+            sample_count,
+        );
+        let effect_renderer = EffectRenderer::new(
+            &gpu.device,
+            surface_format,
+            &gpu.uniform_bind_group_layout,
+            sample_count,
         );
 
         let ui_screen_uniforms = UiScreenUniforms {
@@ -396,7 +340,7 @@ impl State {
                 });
 
         let ui_screen_bind_group = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &ui_uniform_bind_group_layout,
+            layout: &gpu.ui_uniform_bind_group_layout,
             entries: &[wgpu::BindGroupEntry {
                 binding: 0,
                 resource: ui_screen_uniform_buffer.as_entire_binding(),
@@ -417,23 +361,187 @@ impl State {
             geometry,
             sdf_geo,
             ui_geo,
+            draw_runs: Vec::new(),
             input,
             typing,
             elements: Vec::new(),
             history: History::default(),
             current_tool: Tool::Pen,
-            current_color: [0.0, 0.0, 0.0, 1.0],
+            style: settings
+                .tool_styles
+                .get(&Tool::Pen)
+                .cloned()
+                .unwrap_or_default(),
             color_picker: ColorPickerState::new(),
-            stroke_width: 2.0,
             clipboard: Vec::new(),
+            text_clipboard: CopiedText::default(),
+            copied_style: None,
             ui_renderer,
             text_renderer,
+            effect_renderer,
+            settings,
+            keymap: Keymap::load(),
+            palette: CommandPalette::default(),
+            find: FindBar::default(),
+            properties: PropertiesPanel::default(),
             ui_screen,
             current_file_path: None,
             document_name: "Untitled".to_string(),
         }
     }
 
+    /// Switch to `anti_aliasing`, falling back to the closest sample count the
+    /// adapter supports, and rebuild everything that depends on it.
+    pub fn set_anti_aliasing(&mut self, anti_aliasing: AntiAliasing) {
+        let sample_count = usable_sample_count(&self.gpu.supported_sample_counts, anti_aliasing);
+        self.settings.anti_aliasing = AntiAliasing::from_sample_count(sample_count);
+        self.save_settings();
+        if sample_count == self.gpu.sample_count {
+            return;
+        }
+
+        self.gpu.sample_count = sample_count;
+        let pipelines = CanvasPipelines::new(
+            &self.gpu.device,
+            self.gpu.config.format,
+            &self.gpu.uniform_bind_group_layout,
+            &self.gpu.ui_uniform_bind_group_layout,
+            &self.gpu.sdf_paint_bind_group_layout,
+            sample_count,
+        );
+        self.gpu.render_pipeline = pipelines.render;
+        self.gpu.sdf_render_pipeline = pipelines.sdf;
+        self.gpu.ui_render_pipeline = pipelines.ui;
+        self.text_renderer
+            .set_sample_count(&self.gpu.device, sample_count);
+        self.effect_renderer
+            .set_sample_count(&self.gpu.device, sample_count);
+        self.recreate_render_targets();
+    }
+
+    /// The current theme with the element inversion setting applied.
+    pub fn theme(&self) -> Theme {
+        let mut theme = *self.settings.theme.theme();
+        if let Some(invert) = self.settings.invert_elements {
+            theme.invert_elements = invert;
+        }
+        theme
+    }
+
+    /// Switch to the next theme. Element colors are only transformed when drawn.
+    pub fn cycle_theme(&mut self) {
+        self.settings.theme = self.settings.theme.next();
+        self.ui_renderer.set_theme(self.settings.theme.theme());
+        self.save_settings();
+        log::info!("Theme: {:?}", self.settings.theme);
+    }
+
+    /// Flip whether element colors are drawn inverted. From then on the
+    /// choice sticks regardless of the theme.
+    pub fn toggle_invert_elements(&mut self) {
+        let invert = !self.theme().invert_elements;
+        self.settings.invert_elements = Some(invert);
+        self.save_settings();
+        log::info!(
+            "Invert element colors: {}",
+            if invert { "on" } else { "off" }
+        );
+    }
+
+    /// Show or hide the grid. Snapping to it is toggled separately.
+    pub fn toggle_grid(&mut self) {
+        let grid = &mut self.settings.grid;
+        grid.visible = !grid.visible;
+        log::info!("Grid: {}", if grid.visible { "on" } else { "off" });
+        self.save_settings();
+    }
+
+    pub fn cycle_grid_style(&mut self) {
+        let grid = &mut self.settings.grid;
+        grid.style = match grid.style {
+            GridStyle::Lines => GridStyle::Dots,
+            GridStyle::Dots => GridStyle::Lines,
+        };
+        self.save_settings();
+        log::info!("Grid style: {:?}", self.settings.grid.style);
+    }
+
+    pub fn toggle_grid_snapping(&mut self) {
+        let grid = &mut self.settings.grid;
+        grid.snap_to_grid = !grid.snap_to_grid;
+        log::info!(
+            "Snap to grid: {}",
+            if grid.snap_to_grid { "on" } else { "off" }
+        );
+        self.save_settings();
+    }
+
+    pub fn toggle_object_snapping(&mut self) {
+        let grid = &mut self.settings.grid;
+        grid.snap_to_objects = !grid.snap_to_objects;
+        log::info!(
+            "Snap to objects: {}",
+            if grid.snap_to_objects { "on" } else { "off" }
+        );
+        self.save_settings();
+    }
+
+    /// Persist settings next to the autosave (native) or in localStorage (WASM).
+    pub fn save_settings(&self) {
+        let json = match serde_json::to_string_pretty(&self.settings) {
+            Ok(json) => json,
+            Err(e) => {
+                log::error!("Failed to serialize settings: {}", e);
+                return;
+            }
+        };
+        #[cfg(target_arch = "wasm32")]
+        {
+            crate::platform::save_to_local_storage(SETTINGS_STORAGE_KEY, &json);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if let Ok(path) = crate::platform::settings_path()
+                && let Err(e) = crate::platform::save_to_file(path.to_str().unwrap_or(""), &json)
+            {
+                log::warn!("Failed to save settings: {}", e);
+            }
+        }
+    }
+
+    /// Step to the next anti-aliasing level the adapter supports, wrapping to off.
+    pub fn cycle_anti_aliasing(&mut self) {
+        let current = self.gpu.sample_count;
+        let next = self
+            .gpu
+            .supported_sample_counts
+            .iter()
+            .copied()
+            .find(|&count| count > current)
+            .unwrap_or(1);
+        self.set_anti_aliasing(AntiAliasing::from_sample_count(next));
+        log::info!("Anti-aliasing: {:?}", self.settings.anti_aliasing);
+    }
+
+    /// Recreate the depth and MSAA targets to match the surface size and sample count.
+    pub fn recreate_render_targets(&mut self) {
+        let gpu = &mut self.gpu;
+        gpu.depth_texture = crate::texture::Texture::create_depth_texture(
+            &gpu.device,
+            &gpu.config,
+            gpu.sample_count,
+            "Depth Texture",
+        );
+        gpu.msaa_texture = (gpu.sample_count > 1).then(|| {
+            crate::texture::Texture::create_msaa_texture(
+                &gpu.device,
+                &gpu.config,
+                gpu.sample_count,
+                "MSAA Texture",
+            )
+        });
+    }
+
     pub fn window(&self) -> &Arc<Window> {
         &self.window
     }
@@ -468,7 +576,7 @@ impl State {
             0,
             bytemuck::cast_slice(&[self.canvas.uniform]),
         );
-        self.sync_picker_to_color(self.current_color);
+        self.sync_picker_to_color(self.style.stroke);
     }
 
     /// Save to the current file path or show Save As dialog (native only).
@@ -586,6 +694,25 @@ impl State {
         }
     }
 
+    /// Save the drawing as an SVG image, or download it on the web.
+    pub fn export_svg(&self) {
+        let svg = crate::svg::document_svg(&self.elements);
+        let filename = format!("{}.svg", self.document_name);
+        #[cfg(target_arch = "wasm32")]
+        crate::platform::trigger_download(&filename, &svg);
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let path = match crate::platform::save_file_dialog(&filename) {
+                crate::platform::FileDialogResult::Selected(path) => path,
+                crate::platform::FileDialogResult::Cancelled => return,
+            };
+            match crate::platform::save_to_file(&path, &svg) {
+                Ok(()) => log::info!("Exported SVG to {}", path),
+                Err(e) => log::error!("Failed to export SVG: {}", e),
+            }
+        }
+    }
+
     pub fn find_index_by_id(&self, id: ElementId) -> Option<usize> {
         self.elements.iter().position(|element| element.id == id)
     }
@@ -751,14 +878,16 @@ impl State {
         self.color_picker.hue = h;
         self.color_picker.saturation = s;
         self.color_picker.value = v;
+        self.color_picker.alpha = color[3];
     }
 
     pub fn picker_color(&self) -> [f32; 4] {
-        hsv_to_rgb(
+        let [r, g, b, _] = hsv_to_rgb(
             self.color_picker.hue,
             self.color_picker.saturation,
             self.color_picker.value,
-        )
+        );
+        [r, g, b, self.color_picker.alpha]
     }
 }
 
@@ -808,3 +937,254 @@ fn hsv_to_rgb(h: f32, s: f32, v: f32) -> [f32; 4] {
     let m = v - c;
     [r1 + m, g1 + m, b1 + m, 1.0]
 }
+
+/// The largest supported sample count that doesn't exceed what `anti_aliasing` asks for.
+fn usable_sample_count(supported: &[u32], anti_aliasing: AntiAliasing) -> u32 {
+    let requested = anti_aliasing.sample_count();
+    supported
+        .iter()
+        .copied()
+        .filter(|&count| count <= requested)
+        .max()
+        .unwrap_or(1)
+}
+
+/// Load persisted settings, falling back to defaults when none are stored or
+/// they fail to parse.
+fn load_settings() -> Settings {
+    #[cfg(target_arch = "wasm32")]
+    let json = crate::platform::load_from_local_storage(SETTINGS_STORAGE_KEY);
+    #[cfg(not(target_arch = "wasm32"))]
+    let json = crate::platform::settings_path()
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok());
+
+    let Some(json) = json else {
+        return Settings::default();
+    };
+    serde_json::from_str(&json).unwrap_or_else(|e| {
+        log::warn!("Failed to parse settings: {}", e);
+        Settings::default()
+    })
+}
+
+#[cfg(target_arch = "wasm32")]
+const SETTINGS_STORAGE_KEY: &str = "wcanvas_settings";
+
+/// Sample counts that work for both `format` and the depth buffer on this
+/// adapter. Without adapter-specific format features only 1x and 4x are allowed.
+fn supported_sample_counts(
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+) -> Vec<u32> {
+    let color = adapter.get_texture_format_features(format).flags;
+    let depth = adapter
+        .get_texture_format_features(crate::texture::Texture::DEPTH_FORMAT)
+        .flags;
+    let adapter_specific = device
+        .features()
+        .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES);
+    AntiAliasing::ALL
+        .iter()
+        .map(|aa| aa.sample_count())
+        .filter(|&count| count == 1 || adapter_specific || count == 4)
+        .filter(|&count| color.sample_count_supported(count) && depth.sample_count_supported(count))
+        .collect()
+}
+
+/// Pipelines that draw into the main pass and so must share its sample count.
+struct CanvasPipelines {
+    render: wgpu::RenderPipeline,
+    sdf: wgpu::RenderPipeline,
+    ui: wgpu::RenderPipeline,
+}
+
+impl CanvasPipelines {
+    fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        uniform_bind_group_layout: &wgpu::BindGroupLayout,
+        ui_uniform_bind_group_layout: &wgpu::BindGroupLayout,
+        sdf_paint_bind_group_layout: &wgpu::BindGroupLayout,
+        sample_count: u32,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                include_str!("../data/shaders/draw_shader.wgsl").into(),
+            ),
+        });
+
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &[uniform_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[Vertex::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: crate::texture::Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Greater,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        let sdf_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("SDF Vector Shape Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                include_str!("../data/shaders/vector_sdf_shader.wgsl").into(),
+            ),
+        });
+
+        let sdf_render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("SDF Render Pipeline Layout"),
+                bind_group_layouts: &[uniform_bind_group_layout, sdf_paint_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let sdf_render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("SDF Render Pipeline"),
+            layout: Some(&sdf_render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &sdf_shader,
+                entry_point: Some("vs_main"),
+                buffers: &[crate::vertex::SdfVertex::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &sdf_shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: crate::texture::Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Greater,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        let ui_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("UI Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../data/shaders/ui_shader.wgsl").into()),
+        });
+
+        let ui_render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("UI Render Pipeline Layout"),
+                bind_group_layouts: &[ui_uniform_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let ui_render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("UI Render Pipeline"),
+            layout: Some(&ui_render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &ui_shader,
+                entry_point: Some("vs_main"),
+                buffers: &[crate::vertex::UiVertex::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &ui_shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: crate::texture::Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            render: render_pipeline,
+            sdf: sdf_render_pipeline,
+            ui: ui_render_pipeline,
+        }
+    }
+}
@@ -0,0 +1,379 @@
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::State;
+use crate::drawing::{ElementId, GroupId};
+use crate::history::Action;
+
+type Bounds = ([f32; 2], [f32; 2]);
+
+/// Gap left between cells by tidy-up, in canvas units.
+const TIDY_SPACING: f32 = 20.0;
+
+/// Which edge or center the selection lines up on. The center variants name
+/// the direction elements move: centering horizontally shares one x.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alignment {
+    Left,
+    CenterHorizontal,
+    Right,
+    Top,
+    CenterVertical,
+    Bottom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Distribution {
+    /// Equal gaps between neighbors, left to right.
+    HorizontalGaps,
+    /// Equal gaps between neighbors, top to bottom.
+    VerticalGaps,
+    /// Equal steps between horizontal centers.
+    HorizontalCenters,
+    /// Equal steps between vertical centers.
+    VerticalCenters,
+}
+
+/// What alignment measures against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AlignReference {
+    /// The bounds of the whole selection.
+    #[default]
+    Selection,
+    /// The visible part of the canvas.
+    Canvas,
+    /// The last clicked element, which stays put.
+    KeyObject,
+}
+
+impl AlignReference {
+    pub fn next(self) -> Self {
+        match self {
+            AlignReference::Selection => AlignReference::Canvas,
+            AlignReference::Canvas => AlignReference::KeyObject,
+            AlignReference::KeyObject => AlignReference::Selection,
+        }
+    }
+}
+
+/// Arrange actions offered by the toolbar and shortcuts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArrangeCommand {
+    Align(Alignment),
+    Distribute(Distribution),
+    TidyUp,
+    CycleReference,
+}
+
+impl ArrangeCommand {
+    /// Toolbar order for the arrange bar.
+    pub const TOOLBAR: [ArrangeCommand; 12] = [
+        ArrangeCommand::Align(Alignment::Left),
+        ArrangeCommand::Align(Alignment::CenterHorizontal),
+        ArrangeCommand::Align(Alignment::Right),
+        ArrangeCommand::Align(Alignment::Top),
+        ArrangeCommand::Align(Alignment::CenterVertical),
+        ArrangeCommand::Align(Alignment::Bottom),
+        ArrangeCommand::Distribute(Distribution::HorizontalGaps),
+        ArrangeCommand::Distribute(Distribution::VerticalGaps),
+        ArrangeCommand::Distribute(Distribution::HorizontalCenters),
+        ArrangeCommand::Distribute(Distribution::VerticalCenters),
+        ArrangeCommand::TidyUp,
+        ArrangeCommand::CycleReference,
+    ];
+}
+
+/// Elements that move together: a group, or a single ungrouped element.
+struct Unit {
+    ids: Vec<ElementId>,
+    bounds: Bounds,
+}
+
+impl State {
+    pub fn arrange_selection(&mut self, command: ArrangeCommand) {
+        match command {
+            ArrangeCommand::Align(alignment) => self.align_selection(alignment),
+            ArrangeCommand::Distribute(distribution) => self.distribute_selection(distribution),
+            ArrangeCommand::TidyUp => self.tidy_up_selection(),
+            ArrangeCommand::CycleReference => {
+                self.settings.align_reference = self.settings.align_reference.next();
+                log::info!("Align to: {:?}", self.settings.align_reference);
+                self.save_settings();
+            }
+        }
+    }
+
+    /// Align every unit of the selection to the current reference. The key
+    /// object never moves; with the canvas as reference a lone element aligns too.
+    pub fn align_selection(&mut self, alignment: Alignment) {
+        let units = self.selection_units();
+        let key = self.key_unit(&units);
+        let target = match self.settings.align_reference {
+            AlignReference::Canvas => Some(self.visible_canvas_bounds()),
+            AlignReference::KeyObject => key.map(|index| units[index].bounds),
+            AlignReference::Selection => None,
+        };
+        let min_units = match self.settings.align_reference {
+            AlignReference::Canvas => 1,
+            _ => 2,
+        };
+        if units.len() < min_units {
+            return;
+        }
+        let target = target.unwrap_or_else(|| union(units.iter().map(|unit| unit.bounds)));
+
+        let offsets = units
+            .iter()
+            .enumerate()
+            .map(|(index, unit)| {
+                if Some(index) == key && self.settings.align_reference == AlignReference::KeyObject
+                {
+                    [0.0, 0.0]
+                } else {
+                    align_offset(unit.bounds, target, alignment)
+                }
+            })
+            .collect();
+        self.move_units(&units, offsets);
+    }
+
+    pub fn distribute_selection(&mut self, distribution: Distribution) {
+        let units = self.selection_units();
+        if units.len() < 3 {
+            return;
+        }
+        let bounds: Vec<Bounds> = units.iter().map(|unit| unit.bounds).collect();
+        self.move_units(&units, distribute_offsets(&bounds, distribution));
+    }
+
+    /// Lay the selection out in a near-square grid in reading order, starting
+    /// at its top-left corner, each unit centered in an equal cell.
+    pub fn tidy_up_selection(&mut self) {
+        let units = self.selection_units();
+        if units.len() < 2 {
+            return;
+        }
+        let bounds: Vec<Bounds> = units.iter().map(|unit| unit.bounds).collect();
+        self.move_units(&units, tidy_offsets(&bounds, TIDY_SPACING));
+    }
+
+    /// The selection split into groups and ungrouped elements.
+    fn selection_units(&self) -> Vec<Unit> {
+        let mut units: Vec<(Option<GroupId>, Unit)> = Vec::new();
+        for element in &self.elements {
+            if !self.input.selection.is_selected(element.id) {
+                continue;
+            }
+            let bounds = element.bounding_box();
+            let existing = element.group_id.and_then(|group| {
+                units
+                    .iter_mut()
+                    .find(|(unit_group, _)| *unit_group == Some(group))
+            });
+            match existing {
+                Some((_, unit)) => {
+                    unit.ids.push(element.id);
+                    unit.bounds = union([unit.bounds, bounds]);
+                }
+                None => units.push((
+                    element.group_id,
+                    Unit {
+                        ids: vec![element.id],
+                        bounds,
+                    },
+                )),
+            }
+        }
+        units.into_iter().map(|(_, unit)| unit).collect()
+    }
+
+    /// Index of the unit holding the last clicked element, if it is selected.
+    fn key_unit(&self, units: &[Unit]) -> Option<usize> {
+        let (id, _) = self.input.selection.last_clicked?;
+        units.iter().position(|unit| unit.ids.contains(&id))
+    }
+
+    fn visible_canvas_bounds(&self) -> Bounds {
+        let transform = &self.canvas.transform;
+        (
+            transform.screen_to_canvas([0.0, 0.0]),
+            transform.screen_to_canvas([self.size.width as f32, self.size.height as f32]),
+        )
+    }
+
+    /// Translate each unit by its offset and record it all as one action.
+    fn move_units(&mut self, units: &[Unit], offsets: Vec<[f32; 2]>) {
+        let ids: Vec<ElementId> = units
+            .iter()
+            .flat_map(|unit| unit.ids.iter().copied())
+            .collect();
+        let before = self.snapshot_elements(&ids);
+        for (unit, offset) in units.iter().zip(offsets) {
+            if offset == [0.0, 0.0] {
+                continue;
+            }
+            for id in &unit.ids {
+                if let Some(element) = self.find_element_mut_by_id(*id) {
+                    element.shape.translate_by(offset[0], offset[1]);
+                }
+            }
+        }
+        let after = self.snapshot_elements(&ids);
+        if before != after {
+            self.record_action(Action::Move { before, after });
+        }
+    }
+}
+
+fn align_offset(bounds: Bounds, target: Bounds, alignment: Alignment) -> [f32; 2] {
+    let center = |b: Bounds, axis: usize| (b.0[axis] + b.1[axis]) * 0.5;
+    match alignment {
+        Alignment::Left => [target.0[0] - bounds.0[0], 0.0],
+        Alignment::CenterHorizontal => [center(target, 0) - center(bounds, 0), 0.0],
+        Alignment::Right => [target.1[0] - bounds.1[0], 0.0],
+        Alignment::Top => [0.0, target.0[1] - bounds.0[1]],
+        Alignment::CenterVertical => [0.0, center(target, 1) - center(bounds, 1)],
+        Alignment::Bottom => [0.0, target.1[1] - bounds.1[1]],
+    }
+}
+
+/// Offsets that spread `bounds` evenly between the outermost two, which stay put.
+fn distribute_offsets(bounds: &[Bounds], distribution: Distribution) -> Vec<[f32; 2]> {
+    let (axis, by_centers) = match distribution {
+        Distribution::HorizontalGaps => (0, false),
+        Distribution::VerticalGaps => (1, false),
+        Distribution::HorizontalCenters => (0, true),
+        Distribution::VerticalCenters => (1, true),
+    };
+    let center = |b: &Bounds| (b.0[axis] + b.1[axis]) * 0.5;
+    let mut order: Vec<usize> = (0..bounds.len()).collect();
+    order.sort_by(|&a, &b| center(&bounds[a]).total_cmp(&center(&bounds[b])));
+
+    let mut offsets = vec![[0.0, 0.0]; bounds.len()];
+    let (Some(&first), Some(&last)) = (order.first(), order.last()) else {
+        return offsets;
+    };
+    let steps = (order.len() - 1) as f32;
+    if by_centers {
+        let step = (center(&bounds[last]) - center(&bounds[first])) / steps;
+        for (rank, &index) in order.iter().enumerate() {
+            let wanted = center(&bounds[first]) + step * rank as f32;
+            offsets[index][axis] = wanted - center(&bounds[index]);
+        }
+    } else {
+        let span = bounds[last].1[axis] - bounds[first].0[axis];
+        let total: f32 = bounds.iter().map(|b| b.1[axis] - b.0[axis]).sum();
+        let gap = (span - total) / steps;
+        let mut cursor = bounds[first].0[axis];
+        for &index in &order {
+            offsets[index][axis] = cursor - bounds[index].0[axis];
+            cursor += bounds[index].1[axis] - bounds[index].0[axis] + gap;
+        }
+    }
+    offsets
+}
+
+fn tidy_offsets(bounds: &[Bounds], spacing: f32) -> Vec<[f32; 2]> {
+    let columns = (bounds.len() as f32).sqrt().ceil() as usize;
+    let cell = bounds.iter().fold([0.0f32, 0.0f32], |cell, b| {
+        [cell[0].max(b.1[0] - b.0[0]), cell[1].max(b.1[1] - b.0[1])]
+    });
+    let origin = union(bounds.iter().copied()).0;
+
+    // Reading order: by row of the top edge, then left to right.
+    let mut order: Vec<usize> = (0..bounds.len()).collect();
+    order.sort_by(|&a, &b| {
+        bounds[a].0[1]
+            .total_cmp(&bounds[b].0[1])
+            .then(bounds[a].0[0].total_cmp(&bounds[b].0[0]))
+    });
+
+    let mut offsets = vec![[0.0, 0.0]; bounds.len()];
+    for (slot, &index) in order.iter().enumerate() {
+        let (row, column) = (slot / columns, slot % columns);
+        let b = bounds[index];
+        let cell_center = [
+            origin[0] + column as f32 * (cell[0] + spacing) + cell[0] * 0.5,
+            origin[1] + row as f32 * (cell[1] + spacing) + cell[1] * 0.5,
+        ];
+        offsets[index] = [
+            cell_center[0] - (b.0[0] + b.1[0]) * 0.5,
+            cell_center[1] - (b.0[1] + b.1[1]) * 0.5,
+        ];
+    }
+    offsets
+}
+
+fn union(bounds: impl IntoIterator<Item = Bounds>) -> Bounds {
+    bounds
+        .into_iter()
+        .fold(([f32::INFINITY; 2], [f32::NEG_INFINITY; 2]), |acc, b| {
+            (
+                [acc.0[0].min(b.0[0]), acc.0[1].min(b.0[1])],
+                [acc.1[0].max(b.1[0]), acc.1[1].max(b.1[1])],
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 10-unit-tall box spanning `x0..x1`.
+    fn span(x0: f32, x1: f32) -> Bounds {
+        ([x0, 0.0], [x1, 10.0])
+    }
+
+    fn transposed(bounds: Bounds) -> Bounds {
+        ([bounds.0[1], bounds.0[0]], [bounds.1[1], bounds.1[0]])
+    }
+
+    #[test]
+    fn alignment_moves_onto_the_target_edge_or_center() {
+        let bounds = ([10.0, 20.0], [30.0, 60.0]);
+        let target = ([0.0, 0.0], [100.0, 100.0]);
+        let offset = |alignment| align_offset(bounds, target, alignment);
+        assert_eq!(offset(Alignment::Left), [-10.0, 0.0]);
+        assert_eq!(offset(Alignment::CenterHorizontal), [30.0, 0.0]);
+        assert_eq!(offset(Alignment::Right), [70.0, 0.0]);
+        assert_eq!(offset(Alignment::Top), [0.0, -20.0]);
+        assert_eq!(offset(Alignment::CenterVertical), [0.0, 10.0]);
+        assert_eq!(offset(Alignment::Bottom), [0.0, 40.0]);
+    }
+
+    #[test]
+    fn distribution_evens_gaps_or_centers_between_the_outermost() {
+        // Out of order on purpose; the outermost two stay put.
+        let bounds = [span(90.0, 100.0), span(0.0, 20.0), span(30.0, 40.0)];
+        assert_eq!(
+            distribute_offsets(&bounds, Distribution::HorizontalGaps),
+            [[0.0, 0.0], [0.0, 0.0], [20.0, 0.0]]
+        );
+        assert_eq!(
+            distribute_offsets(&bounds, Distribution::HorizontalCenters),
+            [[0.0, 0.0], [0.0, 0.0], [17.5, 0.0]]
+        );
+        assert_eq!(
+            distribute_offsets(&bounds.map(transposed), Distribution::VerticalGaps),
+            [[0.0, 0.0], [0.0, 0.0], [0.0, 20.0]]
+        );
+        assert_eq!(
+            distribute_offsets(&bounds.map(transposed), Distribution::VerticalCenters),
+            [[0.0, 0.0], [0.0, 0.0], [0.0, 17.5]]
+        );
+    }
+
+    #[test]
+    fn tidy_up_fills_a_grid_in_reading_order() {
+        let square = |x: f32, y: f32| ([x, y], [x + 10.0, y + 10.0]);
+        let bounds = [
+            square(50.0, 0.0),
+            square(0.0, 0.0),
+            square(0.0, 40.0),
+            square(30.0, 20.0),
+        ];
+        assert_eq!(
+            tidy_offsets(&bounds, 20.0),
+            [[-20.0, 0.0], [0.0, 0.0], [30.0, -10.0], [-30.0, 10.0]]
+        );
+    }
+}
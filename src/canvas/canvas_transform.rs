@@ -4,6 +4,10 @@ pub struct CanvasTransform {
 }
 
 impl CanvasTransform {
+    /// How far the view zooms out and in.
+    pub const MIN_SCALE: f32 = 0.1;
+    pub const MAX_SCALE: f32 = 10.0;
+
     pub fn new() -> Self {
         Self {
             offset: [0.0, 0.0],
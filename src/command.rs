@@ -0,0 +1,272 @@
+use serde::{Deserialize, Serialize};
+
+use crate::arrange::{Alignment, ArrangeCommand, Distribution};
+use crate::drawing::Tool;
+
+/// Everything a shortcut can trigger. Variant names double as the command
+/// names in the keymap config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Command {
+    ClearSelection,
+    DeleteSelection,
+    ToggleFill,
+    ToggleShadow,
+    ToggleGlow,
+    CycleGradient,
+    SendBackward,
+    BringForward,
+    SendToBack,
+    BringToFront,
+    DecreaseStrokeWidth,
+    IncreaseStrokeWidth,
+    DecreaseOpacity,
+    IncreaseOpacity,
+    Duplicate,
+    Copy,
+    Paste,
+    CopyStyle,
+    PasteStyle,
+    Group,
+    Ungroup,
+    Undo,
+    Redo,
+    Save,
+    Open,
+    ExportSvg,
+    ZoomIn,
+    ZoomOut,
+    CycleAntiAliasing,
+    CycleTheme,
+    ToggleInvertElements,
+    ToggleGrid,
+    CycleGridStyle,
+    ToggleGridSnapping,
+    ToggleObjectSnapping,
+    AlignLeft,
+    AlignCenterHorizontal,
+    AlignRight,
+    AlignTop,
+    AlignCenterVertical,
+    AlignBottom,
+    DistributeHorizontalGaps,
+    DistributeVerticalGaps,
+    DistributeHorizontalCenters,
+    DistributeVerticalCenters,
+    TidyUp,
+    CycleAlignReference,
+    EditText,
+    TextAlignLeft,
+    TextAlignCenter,
+    TextAlignRight,
+    TextAlignTop,
+    TextAlignMiddle,
+    TextAlignBottom,
+    ToggleTextWrap,
+    CycleFont,
+    LoadFont,
+    FindText,
+    ToolSelect,
+    ToolPen,
+    ToolRectangle,
+    ToolCircle,
+    ToolDiamond,
+    ToolArrow,
+    ToolText,
+    ToolLine,
+    ToolEraser,
+    ShowCommandPalette,
+    SaveStylePreset,
+}
+
+impl Command {
+    /// Every command, in the order the command palette lists them.
+    pub const ALL: &[Command] = &[
+        Command::ClearSelection,
+        Command::DeleteSelection,
+        Command::ToggleFill,
+        Command::ToggleShadow,
+        Command::ToggleGlow,
+        Command::CycleGradient,
+        Command::SendBackward,
+        Command::BringForward,
+        Command::SendToBack,
+        Command::BringToFront,
+        Command::DecreaseStrokeWidth,
+        Command::IncreaseStrokeWidth,
+        Command::DecreaseOpacity,
+        Command::IncreaseOpacity,
+        Command::Duplicate,
+        Command::Copy,
+        Command::Paste,
+        Command::CopyStyle,
+        Command::PasteStyle,
+        Command::Group,
+        Command::Ungroup,
+        Command::Undo,
+        Command::Redo,
+        Command::Save,
+        Command::Open,
+        Command::ExportSvg,
+        Command::ZoomIn,
+        Command::ZoomOut,
+        Command::CycleAntiAliasing,
+        Command::CycleTheme,
+        Command::ToggleInvertElements,
+        Command::ToggleGrid,
+        Command::CycleGridStyle,
+        Command::ToggleGridSnapping,
+        Command::ToggleObjectSnapping,
+        Command::AlignLeft,
+        Command::AlignCenterHorizontal,
+        Command::AlignRight,
+        Command::AlignTop,
+        Command::AlignCenterVertical,
+        Command::AlignBottom,
+        Command::DistributeHorizontalGaps,
+        Command::DistributeVerticalGaps,
+        Command::DistributeHorizontalCenters,
+        Command::DistributeVerticalCenters,
+        Command::TidyUp,
+        Command::CycleAlignReference,
+        Command::EditText,
+        Command::TextAlignLeft,
+        Command::TextAlignCenter,
+        Command::TextAlignRight,
+        Command::TextAlignTop,
+        Command::TextAlignMiddle,
+        Command::TextAlignBottom,
+        Command::ToggleTextWrap,
+        Command::CycleFont,
+        Command::LoadFont,
+        Command::FindText,
+        Command::ToolSelect,
+        Command::ToolPen,
+        Command::ToolRectangle,
+        Command::ToolCircle,
+        Command::ToolDiamond,
+        Command::ToolArrow,
+        Command::ToolText,
+        Command::ToolLine,
+        Command::ToolEraser,
+        Command::ShowCommandPalette,
+        Command::SaveStylePreset,
+    ];
+
+    /// Human-readable name, as shown in the command palette.
+    pub fn title(self) -> &'static str {
+        match self {
+            Command::ClearSelection => "Clear selection",
+            Command::DeleteSelection => "Delete selection",
+            Command::ToggleFill => "Toggle fill",
+            Command::ToggleShadow => "Toggle shadow",
+            Command::ToggleGlow => "Toggle glow",
+            Command::CycleGradient => "Cycle gradient",
+            Command::SendBackward => "Send backward",
+            Command::BringForward => "Bring forward",
+            Command::SendToBack => "Send to back",
+            Command::BringToFront => "Bring to front",
+            Command::DecreaseStrokeWidth => "Decrease stroke width",
+            Command::IncreaseStrokeWidth => "Increase stroke width",
+            Command::DecreaseOpacity => "Decrease opacity",
+            Command::IncreaseOpacity => "Increase opacity",
+            Command::Duplicate => "Duplicate",
+            Command::Copy => "Copy",
+            Command::Paste => "Paste",
+            Command::CopyStyle => "Copy style",
+            Command::PasteStyle => "Paste style",
+            Command::Group => "Group selection",
+            Command::Ungroup => "Ungroup selection",
+            Command::Undo => "Undo",
+            Command::Redo => "Redo",
+            Command::Save => "Save",
+            Command::Open => "Open file",
+            Command::ExportSvg => "Export SVG...",
+            Command::ZoomIn => "Zoom in",
+            Command::ZoomOut => "Zoom out",
+            Command::CycleAntiAliasing => "Cycle anti-aliasing",
+            Command::CycleTheme => "Cycle theme",
+            Command::ToggleInvertElements => "Toggle inverted element colors",
+            Command::ToggleGrid => "Toggle grid",
+            Command::CycleGridStyle => "Cycle grid style",
+            Command::ToggleGridSnapping => "Toggle snapping to grid",
+            Command::ToggleObjectSnapping => "Toggle snapping to objects",
+            Command::AlignLeft => "Align left",
+            Command::AlignCenterHorizontal => "Align centers horizontally",
+            Command::AlignRight => "Align right",
+            Command::AlignTop => "Align top",
+            Command::AlignCenterVertical => "Align centers vertically",
+            Command::AlignBottom => "Align bottom",
+            Command::DistributeHorizontalGaps => "Distribute horizontal gaps",
+            Command::DistributeVerticalGaps => "Distribute vertical gaps",
+            Command::DistributeHorizontalCenters => "Distribute horizontal centers",
+            Command::DistributeVerticalCenters => "Distribute vertical centers",
+            Command::TidyUp => "Tidy up",
+            Command::CycleAlignReference => "Cycle align reference",
+            Command::EditText => "Edit text or label",
+            Command::TextAlignLeft => "Text: align left",
+            Command::TextAlignCenter => "Text: align center",
+            Command::TextAlignRight => "Text: align right",
+            Command::TextAlignTop => "Text: align top",
+            Command::TextAlignMiddle => "Text: align middle",
+            Command::TextAlignBottom => "Text: align bottom",
+            Command::ToggleTextWrap => "Text: toggle wrapping",
+            Command::CycleFont => "Text: next font",
+            Command::LoadFont => "Load font file...",
+            Command::FindText => "Find and replace text...",
+            Command::ToolSelect => "Tool: Select",
+            Command::ToolPen => "Tool: Pen",
+            Command::ToolRectangle => "Tool: Rectangle",
+            Command::ToolCircle => "Tool: Circle",
+            Command::ToolDiamond => "Tool: Diamond",
+            Command::ToolArrow => "Tool: Arrow",
+            Command::ToolText => "Tool: Text",
+            Command::ToolLine => "Tool: Line",
+            Command::ToolEraser => "Tool: Eraser",
+            Command::ShowCommandPalette => "Command palette",
+            Command::SaveStylePreset => "Save style preset...",
+        }
+    }
+
+    /// The tool this command switches to, if it is a tool command.
+    pub fn tool(self) -> Option<Tool> {
+        Some(match self {
+            Command::ToolSelect => Tool::Select,
+            Command::ToolPen => Tool::Pen,
+            Command::ToolRectangle => Tool::Rectangle,
+            Command::ToolCircle => Tool::Circle,
+            Command::ToolDiamond => Tool::Diamond,
+            Command::ToolArrow => Tool::Arrow,
+            Command::ToolText => Tool::Text,
+            Command::ToolLine => Tool::Line,
+            Command::ToolEraser => Tool::Eraser,
+            _ => return None,
+        })
+    }
+
+    /// The arrange action this command runs, if it is an arrange command.
+    pub fn arrange(self) -> Option<ArrangeCommand> {
+        Some(match self {
+            Command::AlignLeft => ArrangeCommand::Align(Alignment::Left),
+            Command::AlignCenterHorizontal => ArrangeCommand::Align(Alignment::CenterHorizontal),
+            Command::AlignRight => ArrangeCommand::Align(Alignment::Right),
+            Command::AlignTop => ArrangeCommand::Align(Alignment::Top),
+            Command::AlignCenterVertical => ArrangeCommand::Align(Alignment::CenterVertical),
+            Command::AlignBottom => ArrangeCommand::Align(Alignment::Bottom),
+            Command::DistributeHorizontalGaps => {
+                ArrangeCommand::Distribute(Distribution::HorizontalGaps)
+            }
+            Command::DistributeVerticalGaps => {
+                ArrangeCommand::Distribute(Distribution::VerticalGaps)
+            }
+            Command::DistributeHorizontalCenters => {
+                ArrangeCommand::Distribute(Distribution::HorizontalCenters)
+            }
+            Command::DistributeVerticalCenters => {
+                ArrangeCommand::Distribute(Distribution::VerticalCenters)
+            }
+            Command::TidyUp => ArrangeCommand::TidyUp,
+            Command::CycleAlignReference => ArrangeCommand::CycleReference,
+            _ => return None,
+        })
+    }
+}
@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::drawing::Element;
 
-pub const SCHEMA_VERSION: u32 = 1;
+pub const SCHEMA_VERSION: u32 = 4;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Document {
@@ -50,15 +50,99 @@ impl Document {
     }
 
     pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
-        serde_json::from_str(json)
+        let mut value: serde_json::Value = serde_json::from_str(json)?;
+        migrate(&mut value);
+        serde_json::from_value(value)
     }
 }
 
+/// Upgrades a raw document from any older schema version to `SCHEMA_VERSION`
+/// in place, one version step at a time.
+fn migrate(value: &mut serde_json::Value) {
+    let version = value
+        .get("version")
+        .and_then(serde_json::Value::as_u64)
+        .unwrap_or(1) as u32;
+
+    if version < 2 {
+        migrate_v1_circle_radii(value);
+    }
+    if version < 3 {
+        migrate_v2_fill_to_background(value);
+    }
+    if version < 4 {
+        migrate_v3_background_to_fill_style(value);
+    }
+
+    if let Some(obj) = value.as_object_mut() {
+        obj.insert("version".to_string(), SCHEMA_VERSION.into());
+    }
+}
+
+/// v1 circles stored a single `radius`; v2 stores per-axis `radii`.
+fn migrate_v1_circle_radii(value: &mut serde_json::Value) {
+    for shape in shapes_mut(value) {
+        if shape.get("type").and_then(serde_json::Value::as_str) != Some("Circle") {
+            continue;
+        }
+        if let Some(obj) = shape.as_object_mut()
+            && let Some(radius) = obj.remove("radius")
+        {
+            obj.insert("radii".to_string(), serde_json::json!([radius, radius]));
+        }
+    }
+}
+
+/// v2 shapes had a `fill` flag painting the interior in the stroke color; v3
+/// stores an optional `background` color instead.
+fn migrate_v2_fill_to_background(value: &mut serde_json::Value) {
+    for shape in shapes_mut(value) {
+        let Some(obj) = shape.as_object_mut() else {
+            continue;
+        };
+        let Some(fill) = obj.remove("fill") else {
+            continue;
+        };
+        let background = if fill.as_bool().unwrap_or(false) {
+            obj.get("color").cloned().unwrap_or(serde_json::Value::Null)
+        } else {
+            serde_json::Value::Null
+        };
+        obj.insert("background".to_string(), background);
+    }
+}
+
+/// Version 3 stored `background` as a bare color; version 4 stores a
+/// `FillStyle` so backgrounds can also be gradients.
+fn migrate_v3_background_to_fill_style(value: &mut serde_json::Value) {
+    for shape in shapes_mut(value) {
+        let Some(obj) = shape.as_object_mut() else {
+            continue;
+        };
+        if let Some(background) = obj.get_mut("background")
+            && background.is_array()
+        {
+            let color = background.take();
+            *background = serde_json::json!({ "Solid": color });
+        }
+    }
+}
+
+fn shapes_mut(value: &mut serde_json::Value) -> impl Iterator<Item = &mut serde_json::Value> {
+    value
+        .get_mut("elements")
+        .and_then(serde_json::Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(|element| element.get_mut("shape"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::drawing::{DrawingElement, Element, ElementId};
+    use crate::drawing::{DrawingElement, Effects, Element, ElementId, Glow, Shadow, ShapeLabel};
     use crate::rough::RoughOptions;
+    use crate::vector::style::FillStyle;
 
     #[test]
     fn test_empty_document_roundtrip() {
@@ -81,6 +165,9 @@ mod tests {
                     color: [0.0, 0.0, 0.0, 1.0],
                     width: 2.0,
                 },
+                label: None,
+                opacity: 1.0,
+                effects: Effects::default(),
             },
             Element {
                 id: ElementId(2),
@@ -98,6 +185,9 @@ mod tests {
                         ..RoughOptions::default()
                     }),
                 },
+                label: None,
+                opacity: 1.0,
+                effects: Effects::default(),
             },
             Element {
                 id: ElementId(3),
@@ -106,9 +196,25 @@ mod tests {
                     position: [50.0, 50.0],
                     size: [200.0, 100.0],
                     color: [0.0, 1.0, 0.0, 1.0],
-                    fill: true,
+                    background: Some(FillStyle::linear(
+                        0.0,
+                        [0.8, 1.0, 0.8, 1.0],
+                        [1.0, 1.0, 1.0, 1.0],
+                    )),
                     stroke_width: 1.5,
                     rough_style: None,
+                    roundness: crate::drawing::Roundness::Adaptive,
+                },
+                label: Some(ShapeLabel {
+                    content: "Inside".to_string(),
+                    color: [0.0, 0.0, 0.0, 1.0],
+                    font_size: 20.0,
+                    font: Default::default(),
+                }),
+                opacity: 0.5,
+                effects: Effects {
+                    shadow: Some(Shadow::default()),
+                    glow: None,
                 },
             },
             Element {
@@ -116,12 +222,15 @@ mod tests {
                 group_id: None,
                 shape: DrawingElement::Circle {
                     center: [150.0, 150.0],
-                    radius: 75.0,
+                    radii: [75.0, 50.0],
                     color: [0.0, 0.0, 1.0, 1.0],
-                    fill: false,
+                    background: None,
                     stroke_width: 2.0,
                     rough_style: None,
                 },
+                label: None,
+                opacity: 1.0,
+                effects: Effects::default(),
             },
             Element {
                 id: ElementId(5),
@@ -130,10 +239,14 @@ mod tests {
                     position: [300.0, 100.0],
                     size: [80.0, 60.0],
                     color: [1.0, 1.0, 0.0, 1.0],
-                    fill: false,
+                    background: None,
                     stroke_width: 2.5,
                     rough_style: None,
+                    roundness: crate::drawing::Roundness::Fixed(6.0),
                 },
+                label: None,
+                opacity: 1.0,
+                effects: Effects::default(),
             },
             Element {
                 id: ElementId(6),
@@ -145,6 +258,9 @@ mod tests {
                     width: 2.0,
                     rough_style: None,
                 },
+                label: None,
+                opacity: 1.0,
+                effects: Effects::default(),
             },
             Element {
                 id: ElementId(7),
@@ -154,6 +270,16 @@ mod tests {
                     content: "Hello, wcanvas!".to_string(),
                     color: [0.0, 0.0, 0.0, 1.0],
                     size: 32.0,
+                    font: crate::fonts::FontFamily::Code,
+                },
+                label: None,
+                opacity: 1.0,
+                effects: Effects {
+                    shadow: None,
+                    glow: Some(Glow {
+                        radius: 20.0,
+                        color: [0.2, 0.6, 1.0, 0.8],
+                    }),
                 },
             },
             Element {
@@ -167,7 +293,20 @@ mod tests {
                     color: [0.2, 0.2, 0.2, 1.0],
                     font_size: 16.0,
                     state: crate::drawing::BoxState::Idle,
+                    format: Default::default(),
+                    font: crate::fonts::FontFamily::Custom("Inter".to_string()),
+                    spans: vec![crate::rich_text::TextSpan {
+                        range: 9..13,
+                        style: crate::rich_text::SpanStyle {
+                            bold: true,
+                            link: Some("https://example.com".to_string()),
+                            ..Default::default()
+                        },
+                    }],
                 },
+                label: None,
+                opacity: 1.0,
+                effects: Effects::default(),
             },
         ];
 
@@ -178,6 +317,20 @@ mod tests {
         assert_eq!(doc2.version, SCHEMA_VERSION);
         assert_eq!(doc2.name, "Test Drawing");
         assert_eq!(doc2.elements.len(), 8);
+        assert_eq!(doc2.elements[2].opacity, 0.5);
+        assert_eq!(
+            doc2.elements[2].shape.background(),
+            Some(&FillStyle::linear(
+                0.0,
+                [0.8, 1.0, 0.8, 1.0],
+                [1.0, 1.0, 1.0, 1.0]
+            ))
+        );
+        assert_eq!(doc2.elements[2].effects.shadow, Some(Shadow::default()));
+        assert_eq!(doc2.elements[2].label, elements[2].label);
+        assert_eq!(doc2.elements[0].label, None);
+        assert_eq!(doc2.elements[6].effects, elements[6].effects);
+        assert!(doc2.elements[0].effects.is_empty());
         assert_eq!(doc2.canvas_view.offset, [10.0, 20.0]);
         assert!((doc2.canvas_view.zoom - 1.5).abs() < f32::EPSILON);
     }
@@ -186,6 +339,107 @@ mod tests {
     fn test_schema_version_present() {
         let doc = Document::new();
         let json = doc.to_json().unwrap();
-        assert!(json.contains("\"version\": 1"));
+        assert!(json.contains(&format!("\"version\": {SCHEMA_VERSION}")));
+    }
+
+    #[test]
+    fn test_v1_circle_migrates_to_radii() {
+        let json = r#"{
+            "version": 1,
+            "name": "Old",
+            "canvas_view": { "offset": [0.0, 0.0], "zoom": 1.0 },
+            "elements": [{
+                "id": 1,
+                "group_id": null,
+                "shape": {
+                    "type": "Circle",
+                    "center": [10.0, 20.0],
+                    "radius": 30.0,
+                    "color": [0.0, 0.0, 0.0, 1.0],
+                    "fill": false,
+                    "stroke_width": 2.0,
+                    "rough_style": null
+                }
+            }]
+        }"#;
+
+        let doc = Document::from_json(json).unwrap();
+        assert_eq!(doc.version, SCHEMA_VERSION);
+        match &doc.elements[0].shape {
+            DrawingElement::Circle { radii, .. } => assert_eq!(*radii, [30.0, 30.0]),
+            other => panic!("expected circle, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_v2_fill_migrates_to_background() {
+        let json = r#"{
+            "version": 2,
+            "name": "Old",
+            "canvas_view": { "offset": [0.0, 0.0], "zoom": 1.0 },
+            "elements": [
+                {
+                    "id": 1,
+                    "group_id": null,
+                    "shape": {
+                        "type": "Rectangle",
+                        "position": [0.0, 0.0],
+                        "size": [10.0, 10.0],
+                        "color": [1.0, 0.0, 0.0, 1.0],
+                        "fill": true,
+                        "stroke_width": 2.0,
+                        "rough_style": null
+                    }
+                },
+                {
+                    "id": 2,
+                    "group_id": null,
+                    "shape": {
+                        "type": "Diamond",
+                        "position": [0.0, 0.0],
+                        "size": [10.0, 10.0],
+                        "color": [0.0, 0.0, 1.0, 1.0],
+                        "fill": false,
+                        "stroke_width": 2.0,
+                        "rough_style": null
+                    }
+                }
+            ]
+        }"#;
+
+        let doc = Document::from_json(json).unwrap();
+        assert_eq!(
+            doc.elements[0].shape.background(),
+            Some(&FillStyle::Solid([1.0, 0.0, 0.0, 1.0]))
+        );
+        assert_eq!(doc.elements[1].shape.background(), None);
+    }
+
+    #[test]
+    fn test_v3_background_migrates_to_fill_style() {
+        let json = r#"{
+            "version": 3,
+            "name": "Old",
+            "canvas_view": { "offset": [0.0, 0.0], "zoom": 1.0 },
+            "elements": [{
+                "id": 1,
+                "group_id": null,
+                "shape": {
+                    "type": "Circle",
+                    "center": [10.0, 20.0],
+                    "radii": [30.0, 30.0],
+                    "color": [0.0, 0.0, 0.0, 1.0],
+                    "background": [0.2, 0.4, 0.6, 1.0],
+                    "stroke_width": 2.0,
+                    "rough_style": null
+                }
+            }]
+        }"#;
+
+        let doc = Document::from_json(json).unwrap();
+        assert_eq!(
+            doc.elements[0].shape.background(),
+            Some(&FillStyle::Solid([0.2, 0.4, 0.6, 1.0]))
+        );
     }
 }
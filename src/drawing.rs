@@ -1,4 +1,9 @@
+use crate::fonts::FontFamily;
+use crate::rich_text::{self, TextSpan};
+use crate::text_layout::TextLayout;
+use crate::vector::style::FillStyle;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
 
 static NEXT_ELEMENT_ID: AtomicU64 = AtomicU64::new(1);
@@ -40,7 +45,7 @@ pub fn sync_id_counters(elements: &[Element]) {
     NEXT_GROUP_ID.fetch_max(next_group, Ordering::Relaxed);
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Tool {
     Pen,
     Line,
@@ -60,11 +65,181 @@ pub enum BoxState {
     Editing,
 }
 
+/// Which of a shape's colors a color edit applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorTarget {
+    #[default]
+    Stroke,
+    Background,
+}
+
+/// Horizontal placement of each line inside a text box.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// Vertical placement of the text block inside a text box taller than it.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum VerticalAlign {
+    #[default]
+    Top,
+    Middle,
+    Bottom,
+}
+
+/// How a text box's size follows its content.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum TextSizing {
+    /// The box grows to fit the longest line; nothing wraps.
+    #[default]
+    AutoGrow,
+    /// Lines wrap to the box width and the box only grows downward.
+    FixedWidth,
+}
+
+/// Layout settings of a text box.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextFormat {
+    pub align: TextAlign,
+    pub vertical_align: VerticalAlign,
+    pub sizing: TextSizing,
+}
+
+/// Corner treatment for rectangles and diamonds.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Roundness {
+    #[default]
+    Sharp,
+    /// Radius scales with the shape (a quarter of the short side, capped).
+    Adaptive,
+    /// Fixed corner radius in canvas units.
+    Fixed(f32),
+}
+
+impl Roundness {
+    const ADAPTIVE_FACTOR: f32 = 0.25;
+    const ADAPTIVE_MAX: f32 = 32.0;
+
+    pub fn is_round(self) -> bool {
+        self != Roundness::Sharp
+    }
+
+    /// Corner radius for a shape of the given size, never more than half the short side.
+    pub fn radius_for(self, size: [f32; 2]) -> f32 {
+        let short_side = size[0].abs().min(size[1].abs());
+        let radius = match self {
+            Roundness::Sharp => 0.0,
+            Roundness::Adaptive => (short_side * Self::ADAPTIVE_FACTOR).min(Self::ADAPTIVE_MAX),
+            Roundness::Fixed(radius) => radius.max(0.0),
+        };
+        radius.min(short_side * 0.5)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Element {
     pub id: ElementId,
     pub group_id: Option<GroupId>,
     pub shape: DrawingElement,
+    /// Applied uniformly on top of every color the shape carries.
+    #[serde(default = "default_opacity")]
+    pub opacity: f32,
+    #[serde(default, skip_serializing_if = "Effects::is_empty")]
+    pub effects: Effects,
+    /// Text owned by a closed shape or arrow; it moves and resizes with it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<ShapeLabel>,
+}
+
+/// A label drawn centered inside its shape, or on an arrow's midpoint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShapeLabel {
+    pub content: String,
+    pub color: [f32; 4],
+    pub font_size: f32,
+    #[serde(default)]
+    pub font: FontFamily,
+}
+
+fn default_opacity() -> f32 {
+    1.0
+}
+
+/// Drop shadow cast by an element, in canvas units.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Shadow {
+    pub offset: [f32; 2],
+    pub blur: f32,
+    pub color: [f32; 4],
+}
+
+impl Default for Shadow {
+    fn default() -> Self {
+        Self {
+            offset: [6.0, 8.0],
+            blur: 12.0,
+            color: [0.0, 0.0, 0.0, 0.3],
+        }
+    }
+}
+
+/// Soft halo that fades out `radius` canvas units from the element's outline.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Glow {
+    pub radius: f32,
+    pub color: [f32; 4],
+}
+
+impl Default for Glow {
+    fn default() -> Self {
+        Self {
+            radius: 14.0,
+            color: [1.0, 0.8, 0.2, 0.7],
+        }
+    }
+}
+
+/// Optional visual effects drawn beneath an element.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Effects {
+    #[serde(default)]
+    pub shadow: Option<Shadow>,
+    #[serde(default)]
+    pub glow: Option<Glow>,
+}
+
+impl Effects {
+    pub fn is_empty(&self) -> bool {
+        self.shadow.is_none() && self.glow.is_none()
+    }
+
+    pub fn toggle_shadow(&mut self) {
+        self.shadow = match self.shadow {
+            Some(_) => None,
+            None => Some(Shadow::default()),
+        };
+    }
+
+    pub fn toggle_glow(&mut self) {
+        self.glow = match self.glow {
+            Some(_) => None,
+            None => Some(Glow::default()),
+        };
+    }
+
+    pub fn map_colors(&mut self, f: impl Fn([f32; 4]) -> [f32; 4]) {
+        if let Some(shadow) = self.shadow.as_mut() {
+            shadow.color = f(shadow.color);
+        }
+        if let Some(glow) = self.glow.as_mut() {
+            glow.color = f(glow.color);
+        }
+    }
 }
 
 impl Element {
@@ -73,9 +248,42 @@ impl Element {
             id: ElementId::next(),
             group_id: None,
             shape,
+            opacity: default_opacity(),
+            effects: Effects::default(),
+            label: None,
         }
     }
 
+    /// The label as a text box placed on the shape, ready to lay out.
+    pub fn label_box(&self) -> Option<DrawingElement> {
+        self.label
+            .as_ref()
+            .and_then(|label| self.shape.label_box(label))
+    }
+
+    /// Whether `pos` is on the shape or on its label.
+    pub fn hit_test(&self, pos: [f32; 2]) -> bool {
+        self.shape.hit_test(pos) || self.label_box().is_some_and(|label| label.hit_test(pos))
+    }
+
+    /// The shape as it should be drawn, with the element opacity folded into its colors.
+    pub fn rendered_shape(&self) -> DrawingElement {
+        let mut shape = self.shape.clone();
+        if self.opacity < 1.0 {
+            let opacity = self.opacity.clamp(0.0, 1.0);
+            shape.map_colors(|color| [color[0], color[1], color[2], color[3] * opacity]);
+        }
+        shape
+    }
+
+    /// The effects as they should be drawn, faded by the element opacity.
+    pub fn rendered_effects(&self) -> Effects {
+        let opacity = self.opacity.clamp(0.0, 1.0);
+        let mut effects = self.effects;
+        effects.map_colors(|color| [color[0], color[1], color[2], color[3] * opacity]);
+        effects
+    }
+
     pub fn with_group(mut self, group_id: GroupId) -> Self {
         self.group_id = Some(group_id);
         self
@@ -105,15 +313,19 @@ pub enum DrawingElement {
         position: [f32; 2],
         size: [f32; 2],
         color: [f32; 4],
-        fill: bool,
+        /// Interior paint; `None` leaves the shape transparent.
+        background: Option<FillStyle>,
         stroke_width: f32,
         rough_style: Option<crate::rough::RoughOptions>,
+        #[serde(default)]
+        roundness: Roundness,
     },
     Circle {
         center: [f32; 2],
-        radius: f32,
+        radii: [f32; 2],
         color: [f32; 4],
-        fill: bool,
+        /// Interior paint; `None` leaves the shape transparent.
+        background: Option<FillStyle>,
         stroke_width: f32,
         rough_style: Option<crate::rough::RoughOptions>,
     },
@@ -121,9 +333,12 @@ pub enum DrawingElement {
         position: [f32; 2],
         size: [f32; 2],
         color: [f32; 4],
-        fill: bool,
+        /// Interior paint; `None` leaves the shape transparent.
+        background: Option<FillStyle>,
         stroke_width: f32,
         rough_style: Option<crate::rough::RoughOptions>,
+        #[serde(default)]
+        roundness: Roundness,
     },
     Arrow {
         start: [f32; 2],
@@ -137,6 +352,8 @@ pub enum DrawingElement {
         content: String,
         color: [f32; 4],
         size: f32,
+        #[serde(default)]
+        font: FontFamily,
     },
     TextBox {
         id: u64,
@@ -146,6 +363,13 @@ pub enum DrawingElement {
         color: [f32; 4],
         font_size: f32,
         state: BoxState,
+        #[serde(default)]
+        format: TextFormat,
+        #[serde(default)]
+        font: FontFamily,
+        /// Formatted runs of `content`; text outside them is plain.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        spans: Vec<TextSpan>,
     },
 }
 
@@ -176,24 +400,248 @@ impl DrawingElement {
         }
     }
 
-    pub fn set_fill(&mut self, fill: bool) -> bool {
+    pub fn background(&self) -> Option<&FillStyle> {
         match self {
-            DrawingElement::Rectangle { fill: value, .. }
-            | DrawingElement::Circle { fill: value, .. }
-            | DrawingElement::Diamond { fill: value, .. } => {
-                *value = fill;
+            DrawingElement::Rectangle { background, .. }
+            | DrawingElement::Circle { background, .. }
+            | DrawingElement::Diamond { background, .. } => background.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Applies `color` to the stroke or background; returns false when the
+    /// shape has no such color (backgrounds only exist on closed shapes).
+    pub fn set_target_color(&mut self, target: ColorTarget, color: Option<[f32; 4]>) -> bool {
+        match target {
+            ColorTarget::Stroke => match color {
+                Some(color) => {
+                    self.set_color(color);
+                    true
+                }
+                None => false,
+            },
+            ColorTarget::Background => self.set_background(color.map(FillStyle::Solid)),
+        }
+    }
+
+    pub fn map_colors(&mut self, f: impl Fn([f32; 4]) -> [f32; 4]) {
+        let color = f(self.color());
+        self.set_color(color);
+        if let Some(mut background) = self.background().cloned() {
+            background.map_colors(&f);
+            self.set_background(Some(background));
+        }
+        if let DrawingElement::TextBox { spans, .. } = self {
+            for span in spans {
+                span.style.color = span.style.color.map(&f);
+            }
+        }
+    }
+
+    pub fn set_background(&mut self, fill: Option<FillStyle>) -> bool {
+        match self {
+            DrawingElement::Rectangle { background, .. }
+            | DrawingElement::Circle { background, .. }
+            | DrawingElement::Diamond { background, .. } => {
+                *background = fill;
                 true
             }
             _ => false,
         }
     }
 
-    pub fn toggle_fill(&mut self) -> bool {
+    /// Clears the background, or sets it to `fallback` when the shape has none.
+    pub fn toggle_background(&mut self, fallback: [f32; 4]) -> bool {
+        let next = match self.background() {
+            Some(_) => None,
+            None => Some(FillStyle::Solid(fallback)),
+        };
+        self.set_background(next)
+    }
+
+    /// Steps the background through solid, linear gradient and radial
+    /// gradient. The gradients fade from the base color to a lighter tint.
+    pub fn cycle_background_gradient(&mut self) -> bool {
+        let next = match self.background() {
+            Some(FillStyle::Solid(color)) => {
+                FillStyle::linear(std::f32::consts::FRAC_PI_2, *color, lighten(*color, 0.6))
+            }
+            Some(fill @ FillStyle::LinearGradient { .. }) => {
+                let (_, colors) = fill.packed_stops();
+                FillStyle::radial(colors[colors.len() - 1], colors[0])
+            }
+            Some(fill @ FillStyle::RadialGradient { .. }) => FillStyle::Solid(fill.base_color()),
+            None => return false,
+        };
+        self.set_background(Some(next))
+    }
+
+    pub fn roundness(&self) -> Option<Roundness> {
         match self {
-            DrawingElement::Rectangle { fill, .. }
-            | DrawingElement::Circle { fill, .. }
-            | DrawingElement::Diamond { fill, .. } => {
-                *fill = !*fill;
+            DrawingElement::Rectangle { roundness, .. }
+            | DrawingElement::Diamond { roundness, .. } => Some(*roundness),
+            _ => None,
+        }
+    }
+
+    /// Hand-drawn style parameters, for shapes drawn rough.
+    pub fn rough_style(&self) -> Option<&crate::rough::RoughOptions> {
+        match self {
+            DrawingElement::Line { rough_style, .. }
+            | DrawingElement::Rectangle { rough_style, .. }
+            | DrawingElement::Circle { rough_style, .. }
+            | DrawingElement::Diamond { rough_style, .. }
+            | DrawingElement::Arrow { rough_style, .. } => rough_style.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn rough_style_mut(&mut self) -> Option<&mut crate::rough::RoughOptions> {
+        match self {
+            DrawingElement::Line { rough_style, .. }
+            | DrawingElement::Rectangle { rough_style, .. }
+            | DrawingElement::Circle { rough_style, .. }
+            | DrawingElement::Diamond { rough_style, .. }
+            | DrawingElement::Arrow { rough_style, .. } => rough_style.as_mut(),
+            _ => None,
+        }
+    }
+
+    /// Typeface of text; `None` for other shapes.
+    pub fn font(&self) -> Option<&FontFamily> {
+        match self {
+            DrawingElement::Text { font, .. } | DrawingElement::TextBox { font, .. } => Some(font),
+            _ => None,
+        }
+    }
+
+    pub fn set_font(&mut self, family: FontFamily) -> bool {
+        match self {
+            DrawingElement::Text { font, .. } | DrawingElement::TextBox { font, .. } => {
+                *font = family;
+            }
+            _ => return false,
+        }
+        self.refit_text();
+        true
+    }
+
+    /// The content of text; `None` for other shapes.
+    pub fn text_content(&self) -> Option<&str> {
+        match self {
+            DrawingElement::Text { content, .. } | DrawingElement::TextBox { content, .. } => {
+                Some(content)
+            }
+            _ => None,
+        }
+    }
+
+    /// Shapes that can own a label.
+    pub fn can_hold_label(&self) -> bool {
+        self.is_closed() || matches!(self, DrawingElement::Arrow { .. })
+    }
+
+    /// `label` as a text box: wrapped and centered in the area inside a
+    /// closed shape, or hugging its text on an arrow's midpoint.
+    pub fn label_box(&self, label: &ShapeLabel) -> Option<DrawingElement> {
+        let (pos, size, sizing) = match self {
+            DrawingElement::Rectangle {
+                position,
+                size,
+                stroke_width,
+                ..
+            } => {
+                let inset = stroke_width * 0.5;
+                (
+                    [
+                        position[0].min(position[0] + size[0]) + inset,
+                        position[1].min(position[1] + size[1]) + inset,
+                    ],
+                    [size[0].abs() - inset * 2.0, size[1].abs() - inset * 2.0],
+                    TextSizing::FixedWidth,
+                )
+            }
+            // The largest rectangles inside an ellipse and a rhombus.
+            DrawingElement::Circle { center, radii, .. } => {
+                let half = [
+                    radii[0].abs() * std::f32::consts::FRAC_1_SQRT_2,
+                    radii[1].abs() * std::f32::consts::FRAC_1_SQRT_2,
+                ];
+                (
+                    [center[0] - half[0], center[1] - half[1]],
+                    [half[0] * 2.0, half[1] * 2.0],
+                    TextSizing::FixedWidth,
+                )
+            }
+            DrawingElement::Diamond { position, size, .. } => {
+                let center = [position[0] + size[0] * 0.5, position[1] + size[1] * 0.5];
+                let half = [size[0].abs() * 0.25, size[1].abs() * 0.25];
+                (
+                    [center[0] - half[0], center[1] - half[1]],
+                    [half[0] * 2.0, half[1] * 2.0],
+                    TextSizing::FixedWidth,
+                )
+            }
+            DrawingElement::Arrow { start, end, .. } => {
+                let format = TextFormat::default();
+                let size =
+                    TextLayout::shared(&label.content, &[], &label.font, label.font_size, None)
+                        .box_size([0.0, 0.0], &format);
+                (
+                    [
+                        (start[0] + end[0] - size[0]) * 0.5,
+                        (start[1] + end[1] - size[1]) * 0.5,
+                    ],
+                    size,
+                    TextSizing::AutoGrow,
+                )
+            }
+            _ => return None,
+        };
+        Some(DrawingElement::TextBox {
+            id: 0,
+            pos,
+            size,
+            content: label.content.clone(),
+            color: label.color,
+            font_size: label.font_size,
+            state: BoxState::Idle,
+            format: TextFormat {
+                align: TextAlign::Center,
+                vertical_align: VerticalAlign::Middle,
+                sizing,
+            },
+            font: label.font.clone(),
+            spans: Vec::new(),
+        })
+    }
+
+    /// Closed shapes are the ones that can carry a background.
+    pub fn is_closed(&self) -> bool {
+        matches!(
+            self,
+            DrawingElement::Rectangle { .. }
+                | DrawingElement::Circle { .. }
+                | DrawingElement::Diamond { .. }
+        )
+    }
+
+    pub fn is_text(&self) -> bool {
+        matches!(
+            self,
+            DrawingElement::Text { .. } | DrawingElement::TextBox { .. }
+        )
+    }
+
+    pub fn set_roundness(&mut self, roundness: Roundness) -> bool {
+        match self {
+            DrawingElement::Rectangle {
+                roundness: value, ..
+            }
+            | DrawingElement::Diamond {
+                roundness: value, ..
+            } => {
+                *value = roundness;
                 true
             }
             _ => false,
@@ -236,6 +684,7 @@ impl DrawingElement {
             }
             DrawingElement::TextBox { font_size, .. } => {
                 *font_size = stroke_width.max(8.0);
+                self.refit_text();
                 true
             }
         }
@@ -317,29 +766,29 @@ impl DrawingElement {
             }
             DrawingElement::Circle {
                 center,
-                radius,
+                radii,
                 stroke_width,
                 ..
             } => {
                 let padding = *stroke_width + 4.0;
                 (
-                    [center[0] - radius - padding, center[1] - radius - padding],
-                    [center[0] + radius + padding, center[1] + radius + padding],
+                    [
+                        center[0] - radii[0] - padding,
+                        center[1] - radii[1] - padding,
+                    ],
+                    [
+                        center[0] + radii[0] + padding,
+                        center[1] + radii[1] + padding,
+                    ],
                 )
             }
             DrawingElement::Text {
                 position,
                 content,
                 size,
+                font,
                 ..
-            } => {
-                let width = text_width(content, *size);
-                let height = *size * 1.2;
-                (
-                    [position[0], position[1] - height],
-                    [position[0] + width, position[1]],
-                )
-            }
+            } => text_bounds(*position, content, font, *size),
             DrawingElement::TextBox { pos, size, .. } => {
                 ([pos[0], pos[1]], [pos[0] + size[0], pos[1] + size[1]])
             }
@@ -388,37 +837,57 @@ impl DrawingElement {
                 position,
                 content,
                 size,
+                font,
                 ..
             } => {
-                let width = text_width(content, *size);
-                let height = *size * 1.2;
-                pos[0] >= position[0] - 5.0
-                    && pos[0] <= position[0] + width + 5.0
-                    && pos[1] >= position[1] - height
-                    && pos[1] <= position[1] + 5.0
+                let (min, max) = text_bounds(*position, content, font, *size);
+                pos[0] >= min[0] - 5.0
+                    && pos[0] <= max[0] + 5.0
+                    && pos[1] >= min[1] - 5.0
+                    && pos[1] <= max[1] + 5.0
             }
             DrawingElement::TextBox {
                 pos: element_pos,
                 size,
                 ..
+            } => {
+                pos[0] >= element_pos[0]
+                    && pos[0] <= element_pos[0] + size[0]
+                    && pos[1] >= element_pos[1]
+                    && pos[1] <= element_pos[1] + size[1]
             }
-            | DrawingElement::Rectangle {
-                position: element_pos,
+            DrawingElement::Rectangle {
+                position,
                 size,
+                stroke_width,
+                roundness,
                 ..
+            } => {
+                let (local, half) = local_to_center(pos, *position, *size);
+                let radius = roundness.radius_for(*size);
+                rounded_rect_distance(local, half, radius) <= stroke_width * 0.5
             }
-            | DrawingElement::Diamond {
-                position: element_pos,
+            DrawingElement::Diamond {
+                position,
                 size,
+                stroke_width,
+                roundness,
                 ..
             } => {
-                pos[0] >= element_pos[0]
-                    && pos[0] <= element_pos[0] + size[0]
-                    && pos[1] >= element_pos[1]
-                    && pos[1] <= element_pos[1] + size[1]
+                let (local, half) = local_to_center(pos, *position, *size);
+                let radius = roundness.radius_for(*size);
+                rounded_diamond_distance(local, half, radius) <= stroke_width * 0.5
             }
-            DrawingElement::Circle { center, radius, .. } => {
-                ((pos[0] - center[0]).powi(2) + (pos[1] - center[1]).powi(2)).sqrt() <= *radius
+            DrawingElement::Circle {
+                center,
+                radii,
+                stroke_width,
+                ..
+            } => {
+                let tolerance = stroke_width * 0.5 + 4.0;
+                let rx = radii[0] + tolerance;
+                let ry = radii[1] + tolerance;
+                ((pos[0] - center[0]) / rx).powi(2) + ((pos[1] - center[1]) / ry).powi(2) <= 1.0
             }
             DrawingElement::Arrow {
                 start, end, width, ..
@@ -432,6 +901,114 @@ impl DrawingElement {
         }
     }
 
+    /// Text laid out as it is drawn, with the baseline origin of each line;
+    /// `None` for shapes that aren't text.
+    /// The layout is shared with other users of the same text, so text
+    /// drawn, measured and hit-tested every frame is only shaped once.
+    pub fn text_layout(&self) -> Option<(Arc<TextLayout>, Vec<[f32; 2]>)> {
+        match self {
+            DrawingElement::Text {
+                position,
+                content,
+                size,
+                font,
+                ..
+            } => {
+                let layout = TextLayout::shared(content, &[], font, *size, None);
+                let origins = (0..layout.lines.len())
+                    .map(|index| {
+                        [
+                            position[0],
+                            position[1] + index as f32 * layout.line_height(),
+                        ]
+                    })
+                    .collect();
+                Some((layout, origins))
+            }
+            DrawingElement::TextBox {
+                pos,
+                size,
+                content,
+                font_size,
+                format,
+                font,
+                spans,
+                ..
+            } => {
+                let layout =
+                    TextLayout::shared_for_box(content, spans, font, *font_size, *size, format);
+                let origins = layout.box_origins(*pos, *size, format);
+                Some((layout, origins))
+            }
+            _ => None,
+        }
+    }
+
+    /// The link under `pos` in a text box's content, if any.
+    pub fn link_at(&self, pos: [f32; 2]) -> Option<String> {
+        let DrawingElement::TextBox { content, spans, .. } = self else {
+            return None;
+        };
+        if spans.is_empty() {
+            return None;
+        }
+        let (layout, origins) = self.text_layout()?;
+        layout.style_at(content, &origins, pos)?.link.clone()
+    }
+
+    /// A text box as an SVG `<text>` element: one `<tspan>` per line, split
+    /// into nested `<tspan>`s where the formatting changes.
+    pub fn svg_text(&self) -> Option<String> {
+        let DrawingElement::TextBox {
+            pos,
+            size,
+            content,
+            color,
+            font_size,
+            format,
+            font,
+            spans,
+            ..
+        } = self
+        else {
+            return None;
+        };
+        let layout = TextLayout::for_box(content, spans, font, *font_size, *size, format);
+        let origins = layout.box_origins(*pos, *size, format);
+        let mut svg = format!(
+            "<text font-family=\"{}\" font-size=\"{}\" fill=\"{}\" xml:space=\"preserve\">",
+            rich_text::escape_xml(font.css_name()),
+            font_size,
+            rich_text::svg_color(*color)
+        );
+        for (line, origin) in layout.lines.iter().zip(&origins) {
+            svg.push_str(&format!("<tspan x=\"{}\" y=\"{}\">", origin[0], origin[1]));
+            for (range, style) in rich_text::pieces(spans, line.range.clone()) {
+                svg.push_str(&rich_text::svg_tspan(&content[range], style, *color));
+            }
+            svg.push_str("</tspan>");
+        }
+        svg.push_str("</text>");
+        Some(svg)
+    }
+
+    /// Resize a text box to fit its content; other shapes are untouched.
+    pub fn refit_text(&mut self) {
+        if let DrawingElement::TextBox {
+            size,
+            content,
+            font_size,
+            format,
+            font,
+            spans,
+            ..
+        } = self
+        {
+            *size = TextLayout::for_box(content, spans, font, *font_size, *size, format)
+                .box_size(*size, format);
+        }
+    }
+
     pub fn resize_to_bounds(
         &mut self,
         old_bounds: ([f32; 2], [f32; 2]),
@@ -457,21 +1034,24 @@ impl DrawingElement {
 
         match self {
             DrawingElement::Rectangle { position, size, .. }
-            | DrawingElement::Diamond { position, size, .. }
-            | DrawingElement::TextBox {
-                pos: position,
-                size,
-                ..
-            } => {
+            | DrawingElement::Diamond { position, size, .. } => {
                 *position = adjusted_bounds.0;
                 *size = new_size;
             }
-            DrawingElement::Circle { center, radius, .. } => {
+            DrawingElement::TextBox {
+                pos, size, format, ..
+            } => {
+                *pos = adjusted_bounds.0;
+                *size = new_size;
+                format.sizing = TextSizing::FixedWidth;
+                self.refit_text();
+            }
+            DrawingElement::Circle { center, radii, .. } => {
                 *center = [
                     (adjusted_bounds.0[0] + adjusted_bounds.1[0]) * 0.5,
                     (adjusted_bounds.0[1] + adjusted_bounds.1[1]) * 0.5,
                 ];
-                *radius = new_size[0].min(new_size[1]) * 0.5;
+                *radii = [new_size[0] * 0.5, new_size[1] * 0.5];
             }
             DrawingElement::Line { start, end, .. } | DrawingElement::Arrow { start, end, .. } => {
                 *start = scale_point(*start, old_bounds, adjusted_bounds);
@@ -490,13 +1070,22 @@ impl DrawingElement {
     }
 }
 
-fn text_width(content: &str, size: f32) -> f32 {
-    content
-        .lines()
-        .map(|line| line.chars().count() as f32)
-        .fold(1.0, f32::max)
-        * size
-        * 0.6
+/// Measured extent of free text whose first baseline starts at `position`.
+fn text_bounds(
+    position: [f32; 2],
+    content: &str,
+    font: &FontFamily,
+    size: f32,
+) -> ([f32; 2], [f32; 2]) {
+    let layout = TextLayout::shared(content, &[], font, size, None);
+    let top = position[1] - layout.font.ascent(size);
+    (
+        [position[0], top],
+        [
+            position[0] + layout.width().max(size * 0.6),
+            top + layout.height(),
+        ],
+    )
 }
 
 fn point_to_line_distance(point: [f32; 2], line_start: [f32; 2], line_end: [f32; 2]) -> f32 {
@@ -519,6 +1108,57 @@ fn point_to_line_distance(point: [f32; 2], line_start: [f32; 2], line_end: [f32;
     ((point[0] - projection[0]).powi(2) + (point[1] - projection[1]).powi(2)).sqrt()
 }
 
+fn local_to_center(point: [f32; 2], position: [f32; 2], size: [f32; 2]) -> ([f32; 2], [f32; 2]) {
+    let half = [size[0].abs() * 0.5, size[1].abs() * 0.5];
+    let center = [position[0] + size[0] * 0.5, position[1] + size[1] * 0.5];
+    ([point[0] - center[0], point[1] - center[1]], half)
+}
+
+/// Signed distance to a rounded rectangle centered at the origin.
+/// Mirrors `sdf_rounded_rect` in `vector_sdf_shader.wgsl`.
+fn rounded_rect_distance(p: [f32; 2], half: [f32; 2], radius: f32) -> f32 {
+    let dx = p[0].abs() - (half[0] - radius);
+    let dy = p[1].abs() - (half[1] - radius);
+    let outside = (dx.max(0.0).powi(2) + dy.max(0.0).powi(2)).sqrt();
+    outside + dx.max(dy).min(0.0) - radius
+}
+
+/// Signed distance to a rhombus with rounded corners centered at the origin.
+/// Mirrors `sdf_rounded_diamond` in `vector_sdf_shader.wgsl`.
+fn rounded_diamond_distance(p: [f32; 2], half: [f32; 2], radius: f32) -> f32 {
+    let (b, radius) = inset_rhombus(half, radius);
+    let q = [p[0].abs(), p[1].abs()];
+    let dot_bb = (b[0] * b[0] + b[1] * b[1]).max(f32::EPSILON);
+    let ndot = (b[0] - 2.0 * q[0]) * b[0] - (b[1] - 2.0 * q[1]) * b[1];
+    let h = (ndot / dot_bb).clamp(-1.0, 1.0);
+    let closest = [0.5 * b[0] * (1.0 - h), 0.5 * b[1] * (1.0 + h)];
+    let dist = ((q[0] - closest[0]).powi(2) + (q[1] - closest[1]).powi(2)).sqrt();
+    let side = q[0] * b[1] + q[1] * b[0] - b[0] * b[1];
+    dist * side.signum() - radius
+}
+
+/// Mixes `color` toward white by `amount`, keeping alpha.
+fn lighten(color: [f32; 4], amount: f32) -> [f32; 4] {
+    [
+        color[0] + (1.0 - color[0]) * amount,
+        color[1] + (1.0 - color[1]) * amount,
+        color[2] + (1.0 - color[2]) * amount,
+        color[3],
+    ]
+}
+
+/// Shrinks a rhombus so that offsetting it outward by the (clamped) radius
+/// restores the original edges, returning the inset half-diagonals and radius.
+fn inset_rhombus(half: [f32; 2], radius: f32) -> ([f32; 2], f32) {
+    let edge = (half[0] * half[0] + half[1] * half[1])
+        .sqrt()
+        .max(f32::EPSILON);
+    let inradius = half[0] * half[1] / edge;
+    let radius = radius.clamp(0.0, inradius);
+    let k = 1.0 - radius / inradius.max(f32::EPSILON);
+    ([half[0] * k, half[1] * k], radius)
+}
+
 fn scale_point(
     point: [f32; 2],
     old_bounds: ([f32; 2], [f32; 2]),
@@ -0,0 +1,833 @@
+use crate::text_renderer::TextRenderer;
+use crate::vertex::{EffectVertex, Vertex};
+use std::ops::Range;
+
+/// Largest region one job gets; bigger effects render at reduced resolution.
+const MAX_TARGET_SIZE: f32 = 2048.0;
+/// Smallest atlas edge. The atlas grows in powers of two from here and is
+/// kept across frames, so zooming doesn't reallocate it every frame.
+const MIN_ATLAS_SIZE: u32 = 256;
+/// Empty texels between regions, so filtering at the edge of one job's
+/// region never reads its neighbour.
+const ATLAS_GUTTER: u32 = 1;
+
+/// A shadow or glow for an element that the SDF pipeline cannot draw itself
+/// (strokes, lines, arrows and text). The element's coverage is rendered into an
+/// offscreen target, blurred, and composited back at the element's layer depth.
+pub struct EffectJob {
+    /// Canvas-space area around the source, grown to fit the blur.
+    pub bounds: ([f32; 2], [f32; 2]),
+    /// Where the blurred result lands relative to `bounds`.
+    pub offset: [f32; 2],
+    /// Gaussian standard deviation in canvas units.
+    pub sigma: f32,
+    pub alpha: f32,
+    pub depth: f32,
+    /// Index range of the source in the mask geometry.
+    pub mask: Range<u32>,
+    /// Index range of the source in the text renderer's effect glyphs.
+    pub text: Range<u32>,
+}
+
+impl EffectJob {
+    /// Index range of this job's quad in the composite buffer.
+    pub fn composite_indices(index: usize) -> Range<u32> {
+        let start = index as u32 * 6;
+        start..start + 6
+    }
+}
+
+/// The offscreen textures every job of a frame shares: each job renders its
+/// mask into its own region, which is blurred into the scratch texture and
+/// back.
+struct EffectAtlas {
+    size: [u32; 2],
+    mask_view: wgpu::TextureView,
+    scratch_view: wgpu::TextureView,
+    horizontal_bind_group: wgpu::BindGroup,
+    vertical_bind_group: wgpu::BindGroup,
+    composite_bind_group: wgpu::BindGroup,
+}
+
+/// A job's place in the atlas, in texels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Region {
+    origin: [u32; 2],
+    size: [u32; 2],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct BlurParams {
+    direction: [f32; 2],
+    sigma: f32,
+    _pad: f32,
+    /// The job's region in UV units: origin, then size.
+    region: [f32; 4],
+    /// Where samples are clamped to: half a texel inside the region.
+    clamp: [f32; 4],
+}
+
+pub struct EffectRenderer {
+    format: wgpu::TextureFormat,
+    mask_pipeline: wgpu::RenderPipeline,
+    blur_pipeline: wgpu::RenderPipeline,
+    composite_pipeline: wgpu::RenderPipeline,
+    composite_shader: wgpu::ShaderModule,
+    composite_layout: wgpu::PipelineLayout,
+    uniform_layout: wgpu::BindGroupLayout,
+    blur_layout: wgpu::BindGroupLayout,
+    texture_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    atlas: Option<EffectAtlas>,
+    /// Each job's region of the atlas, in job order.
+    regions: Vec<Region>,
+    /// Per-job transforms into the job's region, kept across frames and
+    /// added to as more jobs show up.
+    transforms: Vec<(wgpu::Buffer, wgpu::BindGroup)>,
+    /// Horizontal and vertical blur parameters of every job, read at
+    /// dynamic offsets of `params_stride`.
+    params: Option<wgpu::Buffer>,
+    params_stride: u32,
+    jobs: Vec<EffectJob>,
+    mask_vertex: Option<wgpu::Buffer>,
+    mask_index: Option<wgpu::Buffer>,
+    composite_vertex: Option<wgpu::Buffer>,
+    composite_index: Option<wgpu::Buffer>,
+}
+
+impl EffectRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        canvas_bind_group_layout: &wgpu::BindGroupLayout,
+        sample_count: u32,
+    ) -> Self {
+        // Offscreen transforms share the canvas layout so the text pipeline can
+        // draw glyphs into effect targets.
+        let uniform_layout = canvas_bind_group_layout.clone();
+        let texture_entries = [
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ];
+        let texture_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("effect_texture_bind_group_layout"),
+            entries: &texture_entries,
+        });
+        let blur_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("effect_blur_bind_group_layout"),
+            entries: &[
+                texture_entries[0],
+                texture_entries[1],
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<BlurParams>() as u64
+                        ),
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("effect_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let blend_target = [Some(wgpu::ColorTargetState {
+            format,
+            blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+            write_mask: wgpu::ColorWrites::ALL,
+        })];
+        let replace_target = [Some(wgpu::ColorTargetState {
+            format,
+            blend: None,
+            write_mask: wgpu::ColorWrites::ALL,
+        })];
+
+        let draw_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Effect Mask Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                include_str!("../data/shaders/draw_shader.wgsl").into(),
+            ),
+        });
+        let mask_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Effect Mask Pipeline Layout"),
+            bind_group_layouts: &[&uniform_layout],
+            push_constant_ranges: &[],
+        });
+        let mask_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Effect Mask Pipeline"),
+            layout: Some(&mask_layout),
+            vertex: wgpu::VertexState {
+                module: &draw_shader,
+                entry_point: Some("vs_main"),
+                buffers: &[Vertex::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &draw_shader,
+                entry_point: Some("fs_main"),
+                targets: &blend_target,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let blur_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Effect Blur Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                include_str!("../data/shaders/effect_blur_shader.wgsl").into(),
+            ),
+        });
+        let blur_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Effect Blur Pipeline Layout"),
+            bind_group_layouts: &[&blur_layout],
+            push_constant_ranges: &[],
+        });
+        let blur_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Effect Blur Pipeline"),
+            layout: Some(&blur_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &blur_shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &blur_shader,
+                entry_point: Some("fs_main"),
+                targets: &replace_target,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let composite_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Effect Composite Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                include_str!("../data/shaders/effect_composite_shader.wgsl").into(),
+            ),
+        });
+        let composite_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Effect Composite Pipeline Layout"),
+            bind_group_layouts: &[canvas_bind_group_layout, &texture_layout],
+            push_constant_ranges: &[],
+        });
+        let composite_pipeline = create_composite_pipeline(
+            device,
+            &composite_shader,
+            &composite_layout,
+            format,
+            sample_count,
+        );
+
+        Self {
+            format,
+            mask_pipeline,
+            blur_pipeline,
+            composite_pipeline,
+            composite_shader,
+            composite_layout,
+            uniform_layout,
+            blur_layout,
+            texture_layout,
+            sampler,
+            atlas: None,
+            regions: Vec::new(),
+            transforms: Vec::new(),
+            params: None,
+            params_stride: (std::mem::size_of::<BlurParams>() as u32)
+                .next_multiple_of(device.limits().min_uniform_buffer_offset_alignment),
+            jobs: Vec::new(),
+            mask_vertex: None,
+            mask_index: None,
+            composite_vertex: None,
+            composite_index: None,
+        }
+    }
+
+    /// Rebuild the composite pipeline for a new MSAA sample count. Offscreen
+    /// targets stay single-sampled.
+    pub fn set_sample_count(&mut self, device: &wgpu::Device, sample_count: u32) {
+        self.composite_pipeline = create_composite_pipeline(
+            device,
+            &self.composite_shader,
+            &self.composite_layout,
+            self.format,
+            sample_count,
+        );
+    }
+
+    /// Upload this frame's jobs and their mask geometry. `zoom` converts canvas
+    /// units to target pixels so blurs stay sharp at any zoom level. Jobs are
+    /// packed into one atlas, at a reduced resolution when they don't fit.
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        jobs: Vec<EffectJob>,
+        mask: (Vec<Vertex>, Vec<u32>),
+        zoom: f32,
+    ) {
+        let (mask_vertices, mask_indices) = mask;
+        upload(
+            device,
+            queue,
+            &mut self.mask_vertex,
+            "Effect Mask Vertex Buffer",
+            wgpu::BufferUsages::VERTEX,
+            bytemuck::cast_slice(&mask_vertices),
+        );
+        upload(
+            device,
+            queue,
+            &mut self.mask_index,
+            "Effect Mask Index Buffer",
+            wgpu::BufferUsages::INDEX,
+            bytemuck::cast_slice(&mask_indices),
+        );
+        self.jobs = jobs;
+        if self.jobs.is_empty() {
+            self.regions.clear();
+            return;
+        }
+
+        let limit = device.limits().max_texture_dimension_2d;
+        let current = self
+            .atlas
+            .as_ref()
+            .map_or([MIN_ATLAS_SIZE; 2], |atlas| atlas.size);
+        let mut resolution = 1.0;
+        let (scales, regions, atlas_size) = loop {
+            let scales: Vec<f32> = self
+                .jobs
+                .iter()
+                .map(|job| {
+                    let extent = job_extent(job);
+                    zoom.min(MAX_TARGET_SIZE / extent[0])
+                        .min(MAX_TARGET_SIZE / extent[1])
+                        * resolution
+                })
+                .collect();
+            let sizes: Vec<[u32; 2]> = self
+                .jobs
+                .iter()
+                .zip(&scales)
+                .map(|(job, scale)| {
+                    job_extent(job).map(|edge| (edge * scale).ceil().max(1.0) as u32)
+                })
+                .collect();
+            let widest = sizes.iter().map(|size| size[0]).max().unwrap_or(0) + ATLAS_GUTTER;
+            let width = current[0].max(widest.next_power_of_two()).min(limit);
+            let (regions, height) = pack_regions(&sizes, width);
+            if height <= limit || resolution < 1.0 / 1024.0 {
+                let height = current[1].max(height.next_power_of_two()).min(limit);
+                break (scales, regions, [width, height]);
+            }
+            resolution *= 0.5;
+        };
+
+        while self.transforms.len() < self.jobs.len() {
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Effect Transform Buffer"),
+                size: std::mem::size_of::<[[f32; 4]; 4]>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("effect_transform_bind_group"),
+                layout: &self.uniform_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+            });
+            self.transforms.push((buffer, bind_group));
+        }
+
+        let stride = self.params_stride as usize;
+        let mut params = vec![0u8; stride * 2 * self.jobs.len()];
+        let mut composite_vertices = Vec::with_capacity(self.jobs.len() * 4);
+        let mut composite_indices = Vec::with_capacity(self.jobs.len() * 6);
+        for (index, ((job, region), scale)) in
+            self.jobs.iter().zip(&regions).zip(&scales).enumerate()
+        {
+            let (min, max) = job.bounds;
+            queue.write_buffer(
+                &self.transforms[index].0,
+                0,
+                bytemuck::cast_slice(&[bounds_to_clip(min, max)]),
+            );
+
+            let texel = [1.0 / atlas_size[0] as f32, 1.0 / atlas_size[1] as f32];
+            let uv_min = [
+                region.origin[0] as f32 * texel[0],
+                region.origin[1] as f32 * texel[1],
+            ];
+            let uv_size = [
+                region.size[0] as f32 * texel[0],
+                region.size[1] as f32 * texel[1],
+            ];
+            let uv_max = [uv_min[0] + uv_size[0], uv_min[1] + uv_size[1]];
+            let blur = |direction: [f32; 2]| BlurParams {
+                direction,
+                sigma: job.sigma * scale,
+                _pad: 0.0,
+                region: [uv_min[0], uv_min[1], uv_size[0], uv_size[1]],
+                clamp: [
+                    uv_min[0] + texel[0] * 0.5,
+                    uv_min[1] + texel[1] * 0.5,
+                    uv_max[0] - texel[0] * 0.5,
+                    uv_max[1] - texel[1] * 0.5,
+                ],
+            };
+            for (pass, direction) in [[texel[0], 0.0], [0.0, texel[1]]].into_iter().enumerate() {
+                let offset = (index * 2 + pass) * stride;
+                params[offset..offset + std::mem::size_of::<BlurParams>()]
+                    .copy_from_slice(bytemuck::bytes_of(&blur(direction)));
+            }
+
+            let (x0, y0) = (min[0] + job.offset[0], min[1] + job.offset[1]);
+            let (x1, y1) = (max[0] + job.offset[0], max[1] + job.offset[1]);
+            let base = (index * 4) as u32;
+            for (position, uv) in [
+                ([x0, y0], uv_min),
+                ([x1, y0], [uv_max[0], uv_min[1]]),
+                ([x1, y1], uv_max),
+                ([x0, y1], [uv_min[0], uv_max[1]]),
+            ] {
+                composite_vertices.push(EffectVertex {
+                    position,
+                    uv,
+                    depth: job.depth,
+                    alpha: job.alpha,
+                });
+            }
+            composite_indices.extend_from_slice(&[
+                base,
+                base + 1,
+                base + 2,
+                base,
+                base + 2,
+                base + 3,
+            ]);
+        }
+        self.regions = regions;
+
+        let params_grew = self
+            .params
+            .as_ref()
+            .is_none_or(|buffer| buffer.size() < params.len() as u64);
+        upload(
+            device,
+            queue,
+            &mut self.params,
+            "Effect Blur Params Buffer",
+            wgpu::BufferUsages::UNIFORM,
+            &params,
+        );
+        // The blur bind groups hold the params buffer, so a new buffer needs
+        // them rebuilt as well.
+        if let Some(params) = &self.params
+            && (params_grew
+                || self
+                    .atlas
+                    .as_ref()
+                    .is_none_or(|atlas| atlas.size != atlas_size))
+        {
+            self.atlas = Some(self.create_atlas(device, atlas_size, params));
+        }
+        upload(
+            device,
+            queue,
+            &mut self.composite_vertex,
+            "Effect Composite Vertex Buffer",
+            wgpu::BufferUsages::VERTEX,
+            bytemuck::cast_slice(&composite_vertices),
+        );
+        upload(
+            device,
+            queue,
+            &mut self.composite_index,
+            "Effect Composite Index Buffer",
+            wgpu::BufferUsages::INDEX,
+            bytemuck::cast_slice(&composite_indices),
+        );
+    }
+
+    /// Render every job's mask into its region of the atlas and blur it.
+    /// Must run before the main pass composites them.
+    pub fn render_targets(&self, encoder: &mut wgpu::CommandEncoder, text: &TextRenderer) {
+        let Some(atlas) = self.atlas.as_ref().filter(|_| !self.jobs.is_empty()) else {
+            return;
+        };
+        let jobs = || self.jobs.iter().zip(&self.regions).enumerate();
+        {
+            let mut pass = begin_offscreen_pass(encoder, &atlas.mask_view, "Effect Mask Pass");
+            for (index, (job, region)) in jobs() {
+                region.restrict(&mut pass);
+                let transform = &self.transforms[index].1;
+                if let (Some(vertex), Some(index)) = (&self.mask_vertex, &self.mask_index)
+                    && !job.mask.is_empty()
+                {
+                    pass.set_pipeline(&self.mask_pipeline);
+                    pass.set_bind_group(0, transform, &[]);
+                    pass.set_vertex_buffer(0, vertex.slice(..));
+                    pass.set_index_buffer(index.slice(..), wgpu::IndexFormat::Uint32);
+                    pass.draw_indexed(job.mask.clone(), 0, 0..1);
+                }
+                text.draw_effect_glyphs(&mut pass, transform, job.text.clone());
+            }
+        }
+        for (destination, bind_group, direction, label) in [
+            (
+                &atlas.scratch_view,
+                &atlas.horizontal_bind_group,
+                0,
+                "Effect Blur H",
+            ),
+            (
+                &atlas.mask_view,
+                &atlas.vertical_bind_group,
+                1,
+                "Effect Blur V",
+            ),
+        ] {
+            let mut pass = begin_offscreen_pass(encoder, destination, label);
+            pass.set_pipeline(&self.blur_pipeline);
+            for (index, (_, region)) in jobs() {
+                region.restrict(&mut pass);
+                let offset = (index * 2 + direction) as u32 * self.params_stride;
+                pass.set_bind_group(0, bind_group, &[offset]);
+                pass.draw(0..3, 0..1);
+            }
+        }
+    }
+
+    /// Composite one job's blurred region into the main pass.
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        canvas_bind_group: &'a wgpu::BindGroup,
+        indices: Range<u32>,
+    ) {
+        let (Some(vertex), Some(index), Some(atlas)) =
+            (&self.composite_vertex, &self.composite_index, &self.atlas)
+        else {
+            return;
+        };
+        render_pass.set_pipeline(&self.composite_pipeline);
+        render_pass.set_bind_group(0, canvas_bind_group, &[]);
+        render_pass.set_bind_group(1, &atlas.composite_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, vertex.slice(..));
+        render_pass.set_index_buffer(index.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(indices, 0, 0..1);
+    }
+
+    fn create_atlas(
+        &self,
+        device: &wgpu::Device,
+        size: [u32; 2],
+        params: &wgpu::Buffer,
+    ) -> EffectAtlas {
+        let create_view = |label| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width: size[0],
+                        height: size[1],
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: self.format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+        let mask_view = create_view("Effect Mask Atlas");
+        let scratch_view = create_view("Effect Scratch Atlas");
+
+        let blur_bind_group = |source: &wgpu::TextureView| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("effect_blur_bind_group"),
+                layout: &self.blur_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(source),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                            buffer: params,
+                            offset: 0,
+                            size: wgpu::BufferSize::new(std::mem::size_of::<BlurParams>() as u64),
+                        }),
+                    },
+                ],
+            })
+        };
+        let horizontal_bind_group = blur_bind_group(&mask_view);
+        let vertical_bind_group = blur_bind_group(&scratch_view);
+        let composite_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("effect_composite_bind_group"),
+            layout: &self.texture_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&mask_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        });
+
+        EffectAtlas {
+            size,
+            mask_view,
+            scratch_view,
+            horizontal_bind_group,
+            vertical_bind_group,
+            composite_bind_group,
+        }
+    }
+}
+
+impl Region {
+    /// Limit drawing in `pass` to this region.
+    fn restrict(&self, pass: &mut wgpu::RenderPass<'_>) {
+        let [x, y] = self.origin;
+        let [w, h] = self.size;
+        pass.set_viewport(x as f32, y as f32, w as f32, h as f32, 0.0, 1.0);
+        pass.set_scissor_rect(x, y, w, h);
+    }
+}
+
+/// Canvas-space size of a job's bounds, never zero.
+fn job_extent(job: &EffectJob) -> [f32; 2] {
+    let (min, max) = job.bounds;
+    [(max[0] - min[0]).max(1.0), (max[1] - min[1]).max(1.0)]
+}
+
+/// Shelf-pack regions of `sizes` into an atlas `width` texels wide, tallest
+/// first, with a gutter after each. Returns the regions in the order of
+/// `sizes` and the height used.
+fn pack_regions(sizes: &[[u32; 2]], width: u32) -> (Vec<Region>, u32) {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&index| std::cmp::Reverse(sizes[index][1]));
+    let mut regions = vec![
+        Region {
+            origin: [0, 0],
+            size: [0, 0],
+        };
+        sizes.len()
+    ];
+    let (mut x, mut y, mut shelf) = (0, 0, 0);
+    for index in order {
+        let size = sizes[index];
+        if x > 0 && x + size[0] + ATLAS_GUTTER > width {
+            x = 0;
+            y += shelf;
+            shelf = 0;
+        }
+        regions[index] = Region {
+            origin: [x, y],
+            size,
+        };
+        x += size[0] + ATLAS_GUTTER;
+        shelf = shelf.max(size[1] + ATLAS_GUTTER);
+    }
+    (regions, y + shelf)
+}
+
+/// Write `contents` into the buffer in `slot`, replacing it with a larger
+/// one, rounded up to a power of two, only when it doesn't fit.
+fn upload(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    slot: &mut Option<wgpu::Buffer>,
+    label: &str,
+    usage: wgpu::BufferUsages,
+    contents: &[u8],
+) {
+    if contents.is_empty() {
+        return;
+    }
+    let size = contents.len() as u64;
+    if slot.as_ref().is_none_or(|buffer| buffer.size() < size) {
+        *slot = Some(device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: size.next_power_of_two(),
+            usage: usage | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        }));
+    }
+    if let Some(buffer) = slot {
+        queue.write_buffer(buffer, 0, contents);
+    }
+}
+
+fn create_composite_pipeline(
+    device: &wgpu::Device,
+    shader: &wgpu::ShaderModule,
+    layout: &wgpu::PipelineLayout,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Effect Composite Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            buffers: &[EffectVertex::desc()],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: crate::texture::Texture::DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Greater,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+        cache: None,
+    })
+}
+
+fn begin_offscreen_pass<'a>(
+    encoder: &'a mut wgpu::CommandEncoder,
+    view: &'a wgpu::TextureView,
+    label: &'a str,
+) -> wgpu::RenderPass<'a> {
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        occlusion_query_set: None,
+        timestamp_writes: None,
+    })
+}
+
+/// Orthographic transform mapping the canvas rectangle `min..max` onto the
+/// viewport, with `min` at the top-left texel.
+fn bounds_to_clip(min: [f32; 2], max: [f32; 2]) -> [[f32; 4]; 4] {
+    let sx = 2.0 / (max[0] - min[0]).max(f32::EPSILON);
+    let sy = 2.0 / (max[1] - min[1]).max(f32::EPSILON);
+    [
+        [sx, 0.0, 0.0, 0.0],
+        [0.0, -sy, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [-1.0 - min[0] * sx, 1.0 + min[1] * sy, 0.0, 1.0],
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overlaps(a: &Region, b: &Region) -> bool {
+        let apart = |axis: usize| {
+            a.origin[axis] + a.size[axis] + ATLAS_GUTTER <= b.origin[axis]
+                || b.origin[axis] + b.size[axis] + ATLAS_GUTTER <= a.origin[axis]
+        };
+        !apart(0) && !apart(1)
+    }
+
+    #[test]
+    fn packed_regions_keep_their_order_and_never_touch() {
+        let sizes = [[100, 40], [60, 90], [200, 10], [120, 90], [30, 30]];
+        let (regions, height) = pack_regions(&sizes, 256);
+
+        for (region, size) in regions.iter().zip(&sizes) {
+            assert_eq!(region.size, *size);
+            assert!(region.origin[0] + region.size[0] <= 256);
+            assert!(region.origin[1] + region.size[1] <= height);
+        }
+        for (i, a) in regions.iter().enumerate() {
+            for b in &regions[i + 1..] {
+                assert!(!overlaps(a, b), "{a:?} and {b:?} touch");
+            }
+        }
+    }
+
+    #[test]
+    fn regions_wrap_onto_a_new_shelf_when_a_row_is_full() {
+        let (regions, height) = pack_regions(&[[150, 20], [150, 20]], 256);
+        assert_eq!(regions[0].origin, [0, 0]);
+        assert_eq!(regions[1].origin, [0, 20 + ATLAS_GUTTER]);
+        assert_eq!(height, 2 * (20 + ATLAS_GUTTER));
+    }
+
+    #[test]
+    fn nothing_to_pack_uses_no_space() {
+        let (regions, height) = pack_regions(&[], 256);
+        assert!(regions.is_empty());
+        assert_eq!(height, 0);
+    }
+}
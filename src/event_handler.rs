@@ -1,10 +1,22 @@
 use crate::app_state::State;
-use crate::drawing::{BoxState, DrawingElement, Element, ElementId, GroupId, Tool};
+use crate::canvas::CanvasTransform;
+use crate::command::Command;
+use crate::drawing::{
+    BoxState, ColorTarget, DrawingElement, Effects, Element, ElementId, GroupId, Roundness,
+    ShapeLabel, TextAlign, TextFormat, TextSizing, Tool, VerticalAlign,
+};
+use crate::fonts::{self, FontFamily};
 use crate::history::Action;
+use crate::keymap::{KeyCombo, KeyPress};
+use crate::rich_text::SpanStyle;
+use crate::snapping::SnapCandidates;
 use crate::state::ResizeHandle;
 use crate::state::UserInputState::{Dragging, Drawing, Idle, MarqueeSelecting, Panning, Resizing};
-use crate::ui::ColorInteraction;
+use crate::text_edit::CaretMotion;
+use crate::text_layout::TEXT_PADDING;
+use crate::ui::{ColorInteraction, ToolbarInteraction};
 use crate::update_logic::handle_positions;
+use crate::vector::style::FillStyle;
 use rand::Rng;
 use winit::event::*;
 use winit::keyboard::KeyCode;
@@ -37,6 +49,7 @@ impl State {
             self.gpu
                 .surface
                 .configure(&self.gpu.device, &self.gpu.config);
+            self.recreate_render_targets();
 
             self.canvas.uniform.update_transform(
                 &self.canvas.transform,
@@ -114,15 +127,74 @@ impl State {
     }
 
     fn handle_left_press(&mut self) -> bool {
+        let screen_size = (self.size.width as f32, self.size.height as f32);
+        let canvas_pos = self.canvas.transform.screen_to_canvas(self.input.mouse_pos);
+        if self.typing.active
+            && !self.ui_renderer.is_mouse_over_ui(
+                self.input.mouse_pos,
+                screen_size,
+                &self.color_picker,
+            )
+            && self.press_in_text(canvas_pos)
+        {
+            return true;
+        }
         if self.commit_active_text_if_needed(false) {
             return true;
         }
 
-        if let Some(tool) = self.ui_renderer.handle_click(
+        if self.palette.open {
+            let screen_size = (self.size.width as f32, self.size.height as f32);
+            if let Some(row) = self
+                .ui_renderer
+                .command_palette_row_at(self.input.mouse_pos, screen_size)
+            {
+                self.run_palette_match(self.palette.scroll() + row);
+            } else if !self
+                .ui_renderer
+                .command_palette_contains(self.input.mouse_pos, screen_size)
+            {
+                self.palette.open = false;
+            }
+            return true;
+        }
+
+        if self.find.open {
+            let screen_size = (self.size.width as f32, self.size.height as f32);
+            if let Some(part) = self
+                .ui_renderer
+                .find_bar_part_at(self.input.mouse_pos, screen_size)
+            {
+                self.click_find_bar(part);
+                return true;
+            }
+            if self
+                .ui_renderer
+                .find_bar_contains(self.input.mouse_pos, screen_size)
+            {
+                return true;
+            }
+            self.find.open = false;
+        }
+
+        self.commit_property_edit();
+        if let Some((row, part)) = self.ui_renderer.property_at(
+            self.input.mouse_pos,
+            (self.size.width as f32, self.size.height as f32),
+        ) {
+            self.click_property(row, part);
+            return true;
+        }
+
+        if let Some(interaction) = self.ui_renderer.handle_click(
             self.input.mouse_pos,
             (self.size.width as f32, self.size.height as f32),
         ) {
-            self.current_tool = tool;
+            match interaction {
+                ToolbarInteraction::Tool(tool) => self.set_tool(tool),
+                ToolbarInteraction::ToggleRoundness => self.toggle_roundness(),
+                ToolbarInteraction::Arrange(command) => self.arrange_selection(command),
+            }
             return true;
         }
 
@@ -133,20 +205,20 @@ impl State {
         ) {
             ColorInteraction::None => {}
             ColorInteraction::Color(color) => {
-                self.apply_ui_color(color);
+                self.apply_ui_color(ColorTarget::Stroke, Some(color));
                 return true;
             }
-            ColorInteraction::TogglePicker => {
-                self.color_picker.open = !self.color_picker.open;
-                self.color_picker.drag_mode = None;
-                if self.color_picker.open {
-                    self.sync_picker_to_color(self.current_color);
-                }
+            ColorInteraction::Background(color) => {
+                self.apply_ui_color(ColorTarget::Background, color);
+                return true;
+            }
+            ColorInteraction::TogglePicker(target) => {
+                self.toggle_picker(target);
                 return true;
             }
             ColorInteraction::BeginDrag(mode, color) => {
                 self.color_picker.drag_mode = Some(mode);
-                self.apply_ui_color(color);
+                self.apply_ui_color(self.color_picker.target, Some(color));
                 return true;
             }
         }
@@ -166,7 +238,6 @@ impl State {
             return true;
         }
 
-        let canvas_pos = self.canvas.transform.screen_to_canvas(self.input.mouse_pos);
         match self.current_tool {
             Tool::Select => self.handle_select_press(canvas_pos),
             Tool::Pen => {
@@ -181,7 +252,13 @@ impl State {
                 true
             }
             Tool::Text => {
-                self.start_text_editing(None, canvas_pos, String::new());
+                self.start_text_editing(
+                    None,
+                    canvas_pos,
+                    String::new(),
+                    self.style.font_size,
+                    self.style.font.clone(),
+                );
                 true
             }
             Tool::Eraser => {
@@ -196,6 +273,7 @@ impl State {
 
     fn handle_left_release(&mut self) -> bool {
         self.color_picker.drag_mode = None;
+        self.typing.dragging = false;
         match self.input.state {
             Panning => {
                 self.input.state = Idle;
@@ -225,6 +303,11 @@ impl State {
     }
 
     fn handle_cursor_moved(&mut self) -> bool {
+        if self.typing.dragging {
+            let canvas_pos = self.canvas.transform.screen_to_canvas(self.input.mouse_pos);
+            self.drag_text_selection(canvas_pos);
+            return true;
+        }
         if let Some(drag_mode) = self.color_picker.drag_mode {
             if let Some(color) = self.ui_renderer.handle_color_drag(
                 self.input.mouse_pos,
@@ -232,7 +315,7 @@ impl State {
                 drag_mode,
                 (self.size.width as f32, self.size.height as f32),
             ) {
-                self.apply_ui_color(color);
+                self.apply_ui_color(self.color_picker.target, Some(color));
             }
             return true;
         }
@@ -293,8 +376,6 @@ impl State {
         }
 
         let is_ctrl_or_cmd = self.input.modifiers.control_key() || self.input.modifiers.super_key();
-        let is_shift = self.input.modifiers.shift_key();
-        let is_alt = self.input.modifiers.alt_key();
         let keycode = match key_event.physical_key {
             winit::keyboard::PhysicalKey::Code(code) => code,
             _ => return false,
@@ -303,143 +384,129 @@ impl State {
         if self.typing.active {
             return self.handle_text_input_key(key_event, keycode, is_ctrl_or_cmd);
         }
+        if self.palette.open {
+            return self.handle_palette_key(key_event, keycode);
+        }
+        if self.find.open {
+            return self.handle_find_key(key_event, keycode);
+        }
+        if self.properties.editing.is_some() {
+            return self.handle_property_key(key_event, keycode);
+        }
 
-        match keycode {
-            KeyCode::Escape => {
-                self.input.selection.clear();
-                self.current_tool = Tool::Select;
+        match self
+            .keymap
+            .press(KeyCombo::new(keycode, self.input.modifiers))
+        {
+            KeyPress::Command(command) => {
+                self.run_command(command);
                 true
             }
-            KeyCode::Delete | KeyCode::Backspace => {
+            KeyPress::Pending => true,
+            KeyPress::Unbound => false,
+        }
+    }
+
+    /// Carry out a command, whether it came from a shortcut or elsewhere.
+    pub fn run_command(&mut self, command: Command) {
+        if let Some(tool) = command.tool() {
+            self.set_tool(tool);
+            return;
+        }
+        if let Some(arrange) = command.arrange() {
+            self.arrange_selection(arrange);
+            return;
+        }
+        match command {
+            Command::ClearSelection => {
+                self.input.selection.clear();
+                self.set_tool(Tool::Select);
+            }
+            Command::DeleteSelection => {
                 if !self.input.selection.selected_ids.is_empty() {
                     let ids = self.input.selection.selected_ids.clone();
                     self.remove_ids_with_history(&ids);
-                    true
-                } else {
-                    false
                 }
             }
-            KeyCode::KeyF => {
-                self.toggle_fill_on_selection();
-                true
-            }
-            KeyCode::BracketLeft => {
-                if is_ctrl_or_cmd {
-                    self.reorder_selection(false, true);
-                } else if is_alt {
-                    self.adjust_selection_stroke_width(-0.5);
-                } else {
-                    self.reorder_selection(false, false);
-                }
-                true
-            }
-            KeyCode::BracketRight => {
-                if is_ctrl_or_cmd {
-                    self.reorder_selection(true, true);
-                } else if is_alt {
-                    self.adjust_selection_stroke_width(0.5);
-                } else {
-                    self.reorder_selection(true, false);
+            Command::ToggleFill => self.toggle_fill_on_selection(),
+            Command::ToggleShadow => self.update_selection_effects(Effects::toggle_shadow),
+            Command::ToggleGlow => self.update_selection_effects(Effects::toggle_glow),
+            Command::CycleGradient => self.cycle_gradient_on_selection(),
+            Command::SendBackward => self.reorder_selection(false, false),
+            Command::BringForward => self.reorder_selection(true, false),
+            Command::SendToBack => self.reorder_selection(false, true),
+            Command::BringToFront => self.reorder_selection(true, true),
+            Command::DecreaseStrokeWidth => self.adjust_selection_stroke_width(-0.5),
+            Command::IncreaseStrokeWidth => self.adjust_selection_stroke_width(0.5),
+            Command::DecreaseOpacity => self.adjust_selection_opacity(-0.1),
+            Command::IncreaseOpacity => self.adjust_selection_opacity(0.1),
+            Command::Duplicate => self.duplicate_selection(),
+            Command::Copy => self.copy_selection(),
+            Command::Paste => self.paste_selection(),
+            Command::EditText => {
+                if let [id] = self.input.selection.selected_ids[..] {
+                    self.begin_editing_if_text(id);
                 }
-                true
-            }
-            KeyCode::KeyD if is_ctrl_or_cmd => {
-                self.duplicate_selection();
-                true
-            }
-            KeyCode::KeyC if is_ctrl_or_cmd => {
-                self.copy_selection();
-                true
-            }
-            KeyCode::KeyV if is_ctrl_or_cmd => {
-                self.paste_selection();
-                true
-            }
-            KeyCode::KeyG if is_ctrl_or_cmd && is_shift => {
-                self.ungroup_selection();
-                true
-            }
-            KeyCode::KeyG if is_ctrl_or_cmd => {
-                self.group_selection();
-                true
-            }
-            KeyCode::KeyY if is_ctrl_or_cmd => {
-                self.redo();
-                true
             }
-            KeyCode::KeyZ if is_ctrl_or_cmd => {
-                self.undo();
-                true
-            }
-            KeyCode::KeyS if is_ctrl_or_cmd => {
+            Command::TextAlignLeft => self.update_text_format(|format| {
+                format.align = TextAlign::Left;
+            }),
+            Command::TextAlignCenter => self.update_text_format(|format| {
+                format.align = TextAlign::Center;
+            }),
+            Command::TextAlignRight => self.update_text_format(|format| {
+                format.align = TextAlign::Right;
+            }),
+            Command::TextAlignTop => self.update_text_format(|format| {
+                format.vertical_align = VerticalAlign::Top;
+            }),
+            Command::TextAlignMiddle => self.update_text_format(|format| {
+                format.vertical_align = VerticalAlign::Middle;
+            }),
+            Command::TextAlignBottom => self.update_text_format(|format| {
+                format.vertical_align = VerticalAlign::Bottom;
+            }),
+            Command::ToggleTextWrap => self.update_text_format(|format| {
+                format.sizing = match format.sizing {
+                    TextSizing::AutoGrow => TextSizing::FixedWidth,
+                    TextSizing::FixedWidth => TextSizing::AutoGrow,
+                };
+            }),
+            Command::CycleFont => self.cycle_font(),
+            Command::LoadFont => {
+                #[cfg(not(target_arch = "wasm32"))]
+                self.load_font();
+            }
+            Command::FindText => self.open_find_bar(),
+            Command::CopyStyle => self.copy_style(),
+            Command::PasteStyle => self.paste_style(),
+            Command::Group => self.group_selection(),
+            Command::Ungroup => self.ungroup_selection(),
+            Command::Undo => self.undo(),
+            Command::Redo => self.redo(),
+            Command::Save => {
                 #[cfg(not(target_arch = "wasm32"))]
                 self.save();
                 #[cfg(target_arch = "wasm32")]
                 self.export_download();
-                true
             }
-            KeyCode::KeyO if is_ctrl_or_cmd => {
+            Command::Open => {
                 #[cfg(not(target_arch = "wasm32"))]
                 self.open();
-                true
             }
-            KeyCode::ArrowLeft if is_alt => {
-                self.align_selection(HAlign::Left);
-                true
-            }
-            KeyCode::ArrowRight if is_alt => {
-                self.align_selection(HAlign::Right);
-                true
-            }
-            KeyCode::ArrowUp if is_alt => {
-                self.align_selection(HAlign::CenterX);
-                true
-            }
-            KeyCode::ArrowDown if is_alt => {
-                self.align_selection(HAlign::CenterY);
-                true
-            }
-            KeyCode::Minus if is_ctrl_or_cmd => {
-                self.zoom_at_mouse(0.9);
-                true
-            }
-            KeyCode::Equal if is_ctrl_or_cmd => {
-                self.zoom_at_mouse(1.1);
-                true
-            }
-            KeyCode::Digit1 => {
-                self.current_tool = Tool::Select;
-                true
-            }
-            KeyCode::Digit2 => {
-                self.current_tool = Tool::Pen;
-                true
-            }
-            KeyCode::Digit3 => {
-                self.current_tool = Tool::Rectangle;
-                true
-            }
-            KeyCode::Digit4 => {
-                self.current_tool = Tool::Circle;
-                true
-            }
-            KeyCode::Digit5 => {
-                self.current_tool = Tool::Arrow;
-                true
-            }
-            KeyCode::Digit6 => {
-                self.current_tool = Tool::Text;
-                true
-            }
-            KeyCode::Digit7 => {
-                self.current_tool = Tool::Line;
-                true
-            }
-            KeyCode::Digit8 => {
-                self.current_tool = Tool::Eraser;
-                true
-            }
-            _ => false,
+            Command::ExportSvg => self.export_svg(),
+            Command::ZoomIn => self.zoom_at_mouse(1.1),
+            Command::ZoomOut => self.zoom_at_mouse(0.9),
+            Command::CycleAntiAliasing => self.cycle_anti_aliasing(),
+            Command::CycleTheme => self.cycle_theme(),
+            Command::ToggleInvertElements => self.toggle_invert_elements(),
+            Command::ToggleGrid => self.toggle_grid(),
+            Command::CycleGridStyle => self.cycle_grid_style(),
+            Command::ToggleGridSnapping => self.toggle_grid_snapping(),
+            Command::ToggleObjectSnapping => self.toggle_object_snapping(),
+            Command::ShowCommandPalette => self.toggle_command_palette(),
+            Command::SaveStylePreset => self.prompt_style_preset_name(),
+            _ => {}
         }
     }
 
@@ -449,52 +516,94 @@ impl State {
         keycode: KeyCode,
         is_ctrl_or_cmd: bool,
     ) -> bool {
+        let modifiers = self.input.modifiers;
+        let word = modifiers.control_key() || modifiers.alt_key();
         match keycode {
             KeyCode::Escape => {
                 self.commit_active_text_if_needed(true);
                 true
             }
             KeyCode::Enter => {
-                self.insert_text_at_cursor("\n");
+                self.typing.insert_text("\n");
                 true
             }
             KeyCode::Backspace => {
-                if self.typing.cursor_pos > 0 {
-                    let remove_at = self.typing.cursor_pos - 1;
-                    self.typing.buffer.remove(remove_at);
-                    self.typing.cursor_pos -= 1;
-                }
+                self.typing.delete_backward(word);
                 true
             }
             KeyCode::Delete => {
-                if self.typing.cursor_pos < self.typing.buffer.len() {
-                    self.typing.buffer.remove(self.typing.cursor_pos);
-                }
+                self.typing.delete_forward(word);
                 true
             }
-            KeyCode::ArrowLeft => {
-                self.typing.cursor_pos = self.typing.cursor_pos.saturating_sub(1);
+            KeyCode::ArrowLeft
+            | KeyCode::ArrowRight
+            | KeyCode::ArrowUp
+            | KeyCode::ArrowDown
+            | KeyCode::Home
+            | KeyCode::End => {
+                // Word jumps on Ctrl (Alt on macOS); Cmd jumps to the line
+                // or text ends as macOS does.
+                let line = modifiers.super_key();
+                let motion = match keycode {
+                    KeyCode::ArrowLeft if line => CaretMotion::LineStart,
+                    KeyCode::ArrowRight if line => CaretMotion::LineEnd,
+                    KeyCode::ArrowUp if line => CaretMotion::TextStart,
+                    KeyCode::ArrowDown if line => CaretMotion::TextEnd,
+                    KeyCode::ArrowLeft if word => CaretMotion::WordLeft,
+                    KeyCode::ArrowRight if word => CaretMotion::WordRight,
+                    KeyCode::ArrowLeft => CaretMotion::Left,
+                    KeyCode::ArrowRight => CaretMotion::Right,
+                    KeyCode::ArrowUp => CaretMotion::Up,
+                    KeyCode::ArrowDown => CaretMotion::Down,
+                    KeyCode::Home if is_ctrl_or_cmd => CaretMotion::TextStart,
+                    KeyCode::End if is_ctrl_or_cmd => CaretMotion::TextEnd,
+                    KeyCode::Home => CaretMotion::LineStart,
+                    _ => CaretMotion::LineEnd,
+                };
+                self.move_caret(motion, modifiers.shift_key());
                 true
             }
-            KeyCode::ArrowRight => {
-                self.typing.cursor_pos = (self.typing.cursor_pos + 1).min(self.typing.buffer.len());
+            KeyCode::KeyB if is_ctrl_or_cmd => {
+                self.typing.toggle_style(|style| &mut style.bold);
                 true
             }
-            KeyCode::ArrowUp => {
-                self.move_text_cursor_vertically(-1);
+            KeyCode::KeyI if is_ctrl_or_cmd => {
+                self.typing.toggle_style(|style| &mut style.italic);
                 true
             }
-            KeyCode::ArrowDown => {
-                self.move_text_cursor_vertically(1);
+            KeyCode::KeyA if is_ctrl_or_cmd => {
+                self.typing.select_all();
+                true
+            }
+            KeyCode::KeyC if is_ctrl_or_cmd => {
+                self.copy_text_selection();
+                true
+            }
+            KeyCode::KeyX if is_ctrl_or_cmd => {
+                self.cut_text_selection();
+                true
+            }
+            KeyCode::KeyV if is_ctrl_or_cmd => {
+                self.paste_text();
+                true
+            }
+            KeyCode::KeyZ if is_ctrl_or_cmd && modifiers.shift_key() => {
+                self.typing.redo();
+                true
+            }
+            KeyCode::KeyZ if is_ctrl_or_cmd => {
+                self.typing.undo();
+                true
+            }
+            KeyCode::KeyY if is_ctrl_or_cmd => {
+                self.typing.redo();
                 true
             }
-            KeyCode::KeyC if is_ctrl_or_cmd => true,
-            KeyCode::KeyV if is_ctrl_or_cmd => true,
             _ => {
                 if let Some(text) = &key_event.text {
                     let visible: String = text.chars().filter(|ch| !ch.is_control()).collect();
                     if !visible.is_empty() {
-                        self.insert_text_at_cursor(&visible);
+                        self.typing.insert_text(&visible);
                         return true;
                     }
                 }
@@ -507,7 +616,7 @@ impl State {
         if self.typing.active {
             let visible: String = text.chars().filter(|ch| !ch.is_control()).collect();
             if !visible.is_empty() {
-                self.insert_text_at_cursor(&visible);
+                self.typing.insert_text(&visible);
             }
             return true;
         }
@@ -528,6 +637,16 @@ impl State {
         }
 
         if let Some(hit_id) = self.find_element_id_at_position(canvas_pos) {
+            // Ctrl/Cmd+click follows a link instead of selecting.
+            let modifiers = self.input.modifiers;
+            if (modifiers.control_key() || modifiers.super_key())
+                && let Some(link) = self
+                    .find_element_by_id(hit_id)
+                    .and_then(|element| element.shape.link_at(canvas_pos))
+            {
+                crate::platform::open_url(&link);
+                return true;
+            }
             let double_click = self.is_double_click(hit_id);
             let clicked_ids = if double_click {
                 vec![hit_id]
@@ -536,6 +655,7 @@ impl State {
             };
 
             if double_click && self.begin_editing_if_text(hit_id) {
+                self.select_word_at(canvas_pos);
                 return true;
             }
 
@@ -584,6 +704,7 @@ impl State {
             }
         }
         self.input.selection.drag_origin = Some([origin[0] + snap_dx, origin[1] + snap_dy]);
+        self.update_snap_guides();
     }
 
     fn resize_selection_to(&mut self, canvas_pos: [f32; 2]) {
@@ -599,7 +720,7 @@ impl State {
         let dx = canvas_pos[0] - origin[0];
         let dy = canvas_pos[1] - origin[1];
         let new_bounds = apply_resize_handle(start_bounds, handle, dx, dy);
-        let snapped = self.snap_bounds(new_bounds);
+        let snapped = self.snap_bounds(new_bounds, start_bounds);
         let lock_aspect = self.input.modifiers.shift_key();
 
         for snapshot in self.input.transform_snapshot.clone() {
@@ -610,9 +731,11 @@ impl State {
                     .resize_to_bounds(start_bounds, snapped, lock_aspect);
             }
         }
+        self.update_snap_guides();
     }
 
     fn finish_transform(&mut self, kind: ActionKind) {
+        self.input.guides.clear();
         let before = self.input.transform_snapshot.clone();
         if before.is_empty() {
             return;
@@ -668,12 +791,20 @@ impl State {
         editing_id: Option<ElementId>,
         pos: [f32; 2],
         content: String,
+        font_size: f32,
+        font: FontFamily,
     ) {
         self.typing.active = true;
+        self.typing.font_size = font_size;
+        self.typing.font = font;
         self.typing.editing_id = editing_id;
+        self.typing.editing_label = false;
         self.typing.pos_canvas = pos;
         self.typing.buffer = content;
         self.typing.cursor_pos = self.typing.buffer.len();
+        self.typing.spans.clear();
+        self.typing.style = SpanStyle::default();
+        self.typing.reset_session();
         self.typing.cursor_visible = true;
         self.typing.blink_timer = Instant::now();
     }
@@ -687,14 +818,20 @@ impl State {
         }
 
         let content = self.typing.buffer.clone();
-        let pos = self.typing.pos_canvas;
-        let size = textbox_size(&content, 32.0);
         let editing_id = self.typing.editing_id;
+        let shape = self.typing_text_box(content.clone(), BoxState::Idle);
         self.typing.active = false;
         self.typing.editing_id = None;
         self.typing.buffer.clear();
+        self.typing.spans.clear();
         self.typing.cursor_pos = 0;
 
+        if std::mem::take(&mut self.typing.editing_label) {
+            if let Some(id) = editing_id {
+                self.commit_label(id, content);
+            }
+            return true;
+        }
         if content.is_empty() {
             return true;
         }
@@ -702,15 +839,7 @@ impl State {
         if let Some(id) = editing_id {
             if let Some(before) = self.find_element_by_id(id).cloned() {
                 if let Some(element) = self.find_element_mut_by_id(id) {
-                    element.shape = DrawingElement::TextBox {
-                        id: id.0,
-                        pos,
-                        size,
-                        content,
-                        color: before.shape.color(),
-                        font_size: 32.0,
-                        state: BoxState::Idle,
-                    };
+                    element.shape = shape;
                     let after = element.clone();
                     self.record_action(Action::ModifyProperty {
                         before: vec![before],
@@ -719,16 +848,7 @@ impl State {
                 }
             }
         } else {
-            let element = Element::new(DrawingElement::TextBox {
-                id: 0,
-                pos,
-                size,
-                content,
-                color: self.current_color,
-                font_size: 32.0,
-                state: BoxState::Idle,
-            });
-            let mut element = element;
+            let mut element = Element::new(shape);
             if let DrawingElement::TextBox { id, .. } = &mut element.shape {
                 *id = element.id.0;
             }
@@ -741,95 +861,303 @@ impl State {
         true
     }
 
+    /// Set, replace or, when `content` is blank, remove the label of `id`.
+    fn commit_label(&mut self, id: ElementId, content: String) {
+        let font_size = self.typing.font_size;
+        let font = self.typing.font.clone();
+        let Some(element) = self.find_element_mut_by_id(id) else {
+            return;
+        };
+        let before = element.clone();
+        element.label = if content.trim().is_empty() {
+            None
+        } else {
+            let color = before
+                .label
+                .as_ref()
+                .map_or(before.shape.color(), |label| label.color);
+            Some(ShapeLabel {
+                content,
+                color,
+                font_size,
+                font,
+            })
+        };
+        let after = element.clone();
+        if before != after {
+            self.record_action(Action::ModifyProperty {
+                before: vec![before],
+                after: vec![after],
+            });
+        }
+    }
+
+    /// Whether the label of `id` is open for typing, so the typed copy
+    /// replaces it on screen.
+    pub fn is_editing_label(&self, id: ElementId) -> bool {
+        self.typing.active && self.typing.editing_label && self.typing.editing_id == Some(id)
+    }
+
+    /// The text box being typed into, holding `content` and sized to fit.
+    /// An edited box keeps its own color, size and format; a label is laid
+    /// out on its shape.
+    pub fn typing_text_box(&self, content: String, state: BoxState) -> DrawingElement {
+        let editing = self
+            .typing
+            .editing_id
+            .and_then(|id| self.find_element_by_id(id));
+        if self.typing.editing_label
+            && let Some(element) = editing
+        {
+            let color = element
+                .label
+                .as_ref()
+                .map_or(element.shape.color(), |label| label.color);
+            let label = ShapeLabel {
+                content: content.clone(),
+                color,
+                font_size: self.typing.font_size,
+                font: self.typing.font.clone(),
+            };
+            if let Some(shape) = element.shape.label_box(&label) {
+                return shape;
+            }
+        }
+        let mut shape = self
+            .typing
+            .editing_id
+            .and_then(|id| self.find_element_by_id(id))
+            .map(|element| element.shape.clone())
+            .filter(DrawingElement::is_text)
+            .unwrap_or(DrawingElement::TextBox {
+                id: 0,
+                pos: self.typing.pos_canvas,
+                size: [0.0, 0.0],
+                content: String::new(),
+                color: self.style.stroke,
+                font_size: self.typing.font_size,
+                state,
+                format: TextFormat::default(),
+                font: self.typing.font.clone(),
+                spans: Vec::new(),
+            });
+        if let DrawingElement::TextBox {
+            content: value,
+            state: value_state,
+            spans,
+            ..
+        } = &mut shape
+        {
+            *value = content;
+            *value_state = state;
+            *spans = self.typing.spans.clone();
+        }
+        shape.refit_text();
+        shape
+    }
+
     fn begin_editing_if_text(&mut self, id: ElementId) -> bool {
         let Some(element) = self.find_element_by_id(id).cloned() else {
             return false;
         };
 
         match element.shape {
-            DrawingElement::TextBox { pos, content, .. } => {
-                self.start_text_editing(Some(id), pos, content);
+            DrawingElement::TextBox {
+                pos,
+                content,
+                font_size,
+                font,
+                spans,
+                ..
+            } => {
+                self.start_text_editing(Some(id), pos, content, font_size, font);
+                self.typing.spans = spans;
+                self.typing.set_cursor(self.typing.cursor_pos);
                 true
             }
             DrawingElement::Text {
-                position, content, ..
+                position,
+                content,
+                size,
+                font,
+                ..
             } => {
+                let pos = [
+                    position[0] - TEXT_PADDING,
+                    position[1] - fonts::resolve(&font).ascent(size) - TEXT_PADDING,
+                ];
                 if let Some(target) = self.find_element_mut_by_id(id) {
                     target.shape = DrawingElement::TextBox {
                         id: id.0,
-                        pos: position,
-                        size: textbox_size(&content, 32.0),
+                        pos,
+                        size: [0.0, 0.0],
                         content: content.clone(),
                         color: target.shape.color(),
-                        font_size: 32.0,
+                        font_size: size,
                         state: BoxState::Editing,
+                        format: TextFormat::default(),
+                        font: font.clone(),
+                        spans: Vec::new(),
                     };
+                    target.shape.refit_text();
                 }
-                self.start_text_editing(Some(id), position, content);
+                self.start_text_editing(Some(id), pos, content, size, font);
+                true
+            }
+            shape if shape.can_hold_label() => {
+                let (content, font_size, font) = match element.label {
+                    Some(label) => (label.content, label.font_size, label.font),
+                    None => {
+                        let style = self
+                            .settings
+                            .tool_styles
+                            .get(&Tool::Text)
+                            .cloned()
+                            .unwrap_or_default();
+                        (String::new(), style.font_size, style.font)
+                    }
+                };
+                self.start_text_editing(Some(id), shape.center(), content, font_size, font);
+                self.typing.editing_label = true;
                 true
             }
             _ => false,
         }
     }
 
-    fn insert_text_at_cursor(&mut self, text: &str) {
-        self.typing.buffer.insert_str(self.typing.cursor_pos, text);
-        self.typing.cursor_pos += text.len();
-        self.typing.cursor_visible = true;
-        self.typing.blink_timer = Instant::now();
+    fn apply_color_to_selection(&mut self, target: ColorTarget, color: Option<[f32; 4]>) {
+        let ids = self.input.selection.selected_ids.clone();
+        if !ids.is_empty() {
+            let before = self.snapshot_elements(&ids);
+            let mut changed = false;
+            for id in &ids {
+                if let Some(element) = self.find_element_mut_by_id(*id) {
+                    changed |= element.shape.set_target_color(target, color);
+                }
+            }
+            if changed {
+                let after = self.snapshot_elements(&ids);
+                self.record_action(Action::ModifyProperty { before, after });
+            }
+        }
+        self.set_current_target_color(target, color);
     }
 
-    fn move_text_cursor_vertically(&mut self, direction: i32) {
-        let cursor = self.typing.cursor_pos.min(self.typing.buffer.len());
-        let before = &self.typing.buffer[..cursor];
-        let current_col = before.chars().rev().take_while(|ch| *ch != '\n').count();
-        let current_line = before.chars().filter(|ch| *ch == '\n').count();
-        let lines: Vec<&str> = self.typing.buffer.split('\n').collect();
-        let target_line = if direction < 0 {
-            current_line.saturating_sub(1)
+    fn apply_ui_color(&mut self, target: ColorTarget, color: Option<[f32; 4]>) {
+        if self.current_tool == Tool::Select && !self.input.selection.selected_ids.is_empty() {
+            self.apply_color_to_selection(target, color);
         } else {
-            (current_line + 1).min(lines.len().saturating_sub(1))
+            self.set_current_target_color(target, color);
+        }
+    }
+
+    /// Updates the color used for new shapes, keeping the picker in step when
+    /// it is editing the same target.
+    fn set_current_target_color(&mut self, target: ColorTarget, color: Option<[f32; 4]>) {
+        match target {
+            ColorTarget::Stroke => match color {
+                Some(color) => self.style.stroke = color,
+                None => return,
+            },
+            ColorTarget::Background => self.style.background = color,
+        }
+        if let Some(color) = color
+            && self.color_picker.target == target
+        {
+            self.sync_picker_to_color(color);
+        }
+    }
+
+    /// Opens the custom color picker for `target`, or closes it when it is
+    /// already editing that target.
+    fn toggle_picker(&mut self, target: ColorTarget) {
+        self.color_picker.drag_mode = None;
+        if self.color_picker.open && self.color_picker.target == target {
+            self.color_picker.open = false;
+            return;
+        }
+        self.color_picker.open = true;
+        self.color_picker.target = target;
+        let color = match target {
+            ColorTarget::Stroke => self.style.stroke,
+            ColorTarget::Background => self.style.background.unwrap_or([1.0, 1.0, 1.0, 1.0]),
         };
-        let mut target_index = 0usize;
-        for (line_index, line) in lines.iter().enumerate() {
-            if line_index == target_line {
-                target_index += current_col.min(line.chars().count());
-                break;
+        self.sync_picker_to_color(color);
+    }
+
+    /// Background for the drag preview, faded like the preview stroke.
+    fn preview_background(&self) -> Option<FillStyle> {
+        self.style
+            .background
+            .map(|[r, g, b, a]| FillStyle::Solid([r, g, b, a * 0.5]))
+    }
+
+    fn toggle_fill_on_selection(&mut self) {
+        let ids = self.input.selection.selected_ids.clone();
+        if ids.is_empty() {
+            return;
+        }
+        let before = self.snapshot_elements(&ids);
+        let mut changed = false;
+        let fallback = self.style.background;
+        for id in &ids {
+            if let Some(element) = self.find_element_mut_by_id(*id) {
+                let fallback = fallback.unwrap_or(element.shape.color());
+                changed |= element.shape.toggle_background(fallback);
             }
-            target_index += line.len() + 1;
         }
-        self.typing.cursor_pos = target_index.min(self.typing.buffer.len());
+        if changed {
+            let after = self.snapshot_elements(&ids);
+            self.record_action(Action::ModifyProperty { before, after });
+        }
     }
 
-    fn apply_color_to_selection(&mut self, color: [f32; 4]) {
+    /// Steps selected backgrounds through solid, linear and radial fills.
+    fn cycle_gradient_on_selection(&mut self) {
         let ids = self.input.selection.selected_ids.clone();
         if ids.is_empty() {
-            self.current_color = color;
-            self.sync_picker_to_color(color);
             return;
         }
         let before = self.snapshot_elements(&ids);
+        let mut changed = false;
         for id in &ids {
             if let Some(element) = self.find_element_mut_by_id(*id) {
-                element.shape.set_color(color);
+                changed |= element.shape.cycle_background_gradient();
             }
         }
-        let after = self.snapshot_elements(&ids);
-        self.current_color = color;
-        self.sync_picker_to_color(color);
-        self.record_action(Action::ModifyProperty { before, after });
+        if changed {
+            let after = self.snapshot_elements(&ids);
+            self.record_action(Action::ModifyProperty { before, after });
+        }
     }
 
-    fn apply_ui_color(&mut self, color: [f32; 4]) {
-        if self.current_tool == Tool::Select && !self.input.selection.selected_ids.is_empty() {
-            self.apply_color_to_selection(color);
-        } else {
-            self.current_color = color;
-            self.sync_picker_to_color(color);
+    fn update_selection_effects(&mut self, update: impl Fn(&mut Effects)) {
+        let ids = self.input.selection.selected_ids.clone();
+        if ids.is_empty() {
+            return;
+        }
+        let before = self.snapshot_elements(&ids);
+        for id in &ids {
+            if let Some(element) = self.find_element_mut_by_id(*id) {
+                update(&mut element.effects);
+            }
+        }
+        let after = self.snapshot_elements(&ids);
+        if before != after {
+            self.record_action(Action::ModifyProperty { before, after });
         }
     }
 
-    fn toggle_fill_on_selection(&mut self) {
+    /// Flips between sharp and adaptive corners for new shapes and for any
+    /// selected rectangles and diamonds.
+    fn toggle_roundness(&mut self) {
+        self.style.roundness = if self.style.roundness.is_round() {
+            Roundness::Sharp
+        } else {
+            Roundness::Adaptive
+        };
+
+        let roundness = self.style.roundness;
         let ids = self.input.selection.selected_ids.clone();
         if ids.is_empty() {
             return;
@@ -838,7 +1166,7 @@ impl State {
         let mut changed = false;
         for id in &ids {
             if let Some(element) = self.find_element_mut_by_id(*id) {
-                changed |= element.shape.toggle_fill();
+                changed |= element.shape.set_roundness(roundness);
             }
         }
         if changed {
@@ -847,10 +1175,27 @@ impl State {
         }
     }
 
+    fn adjust_selection_opacity(&mut self, delta: f32) {
+        let ids = self.input.selection.selected_ids.clone();
+        if ids.is_empty() {
+            return;
+        }
+        let before = self.snapshot_elements(&ids);
+        for id in &ids {
+            if let Some(element) = self.find_element_mut_by_id(*id) {
+                element.opacity = (element.opacity + delta).clamp(0.1, 1.0);
+            }
+        }
+        let after = self.snapshot_elements(&ids);
+        if before != after {
+            self.record_action(Action::ModifyProperty { before, after });
+        }
+    }
+
     fn adjust_selection_stroke_width(&mut self, delta: f32) {
         let ids = self.input.selection.selected_ids.clone();
         if ids.is_empty() {
-            self.stroke_width = (self.stroke_width + delta).max(0.5);
+            self.style.stroke_width = (self.style.stroke_width + delta).max(0.5);
             return;
         }
         let before = self.snapshot_elements(&ids);
@@ -1039,44 +1384,6 @@ impl State {
         }]));
     }
 
-    fn align_selection(&mut self, align: HAlign) {
-        let ids = self.input.selection.selected_ids.clone();
-        if ids.len() < 2 {
-            return;
-        }
-        let Some(bounds) = self.selection_bounds() else {
-            return;
-        };
-        let before = self.snapshot_elements(&ids);
-        for id in &ids {
-            if let Some(element) = self.find_element_mut_by_id(*id) {
-                let element_bounds = element.bounding_box();
-                let dx = match align {
-                    HAlign::Left => bounds.0[0] - element_bounds.0[0],
-                    HAlign::Right => bounds.1[0] - element_bounds.1[0],
-                    HAlign::CenterX => {
-                        ((bounds.0[0] + bounds.1[0]) - (element_bounds.0[0] + element_bounds.1[0]))
-                            * 0.5
-                    }
-                    HAlign::CenterY => {
-                        ((bounds.0[1] + bounds.1[1]) - (element_bounds.0[1] + element_bounds.1[1]))
-                            * 0.5
-                    }
-                };
-                let dy = match align {
-                    HAlign::CenterY => {
-                        ((bounds.0[1] + bounds.1[1]) - (element_bounds.0[1] + element_bounds.1[1]))
-                            * 0.5
-                    }
-                    _ => 0.0,
-                };
-                element.shape.translate_by(dx, dy);
-            }
-        }
-        let after = self.snapshot_elements(&ids);
-        self.record_action(Action::ModifyProperty { before, after });
-    }
-
     fn selection_bounds(&self) -> Option<([f32; 2], [f32; 2])> {
         let mut iter = self
             .elements
@@ -1111,7 +1418,7 @@ impl State {
         self.elements
             .iter()
             .rev()
-            .find(|element| element.shape.hit_test(pos))
+            .find(|element| element.hit_test(pos))
             .map(|element| element.id)
     }
 
@@ -1138,7 +1445,7 @@ impl State {
             .unwrap_or(false)
     }
 
-    fn flush_canvas_transform(&mut self) {
+    pub fn flush_canvas_transform(&mut self) {
         self.canvas.uniform.update_transform(
             &self.canvas.transform,
             (self.size.width as f32, self.size.height as f32),
@@ -1173,7 +1480,11 @@ impl State {
     fn zoom_at_mouse(&mut self, zoom_factor: f32) {
         let mouse_canvas_before = self.canvas.transform.screen_to_canvas(self.input.mouse_pos);
         self.canvas.transform.scale *= zoom_factor;
-        self.canvas.transform.scale = self.canvas.transform.scale.clamp(0.1, 10.0);
+        self.canvas.transform.scale = self
+            .canvas
+            .transform
+            .scale
+            .clamp(CanvasTransform::MIN_SCALE, CanvasTransform::MAX_SCALE);
         let mouse_canvas_after = self.canvas.transform.screen_to_canvas(self.input.mouse_pos);
         self.canvas.transform.offset[0] +=
             (mouse_canvas_after[0] - mouse_canvas_before[0]) * self.canvas.transform.scale;
@@ -1188,8 +1499,8 @@ impl State {
                 if self.input.current_stroke.len() > 1 {
                     Some(Element::new(DrawingElement::Stroke {
                         points: self.input.current_stroke.clone(),
-                        color: self.current_color,
-                        width: self.stroke_width,
+                        color: self.style.stroke,
+                        width: self.style.stroke_width,
                     }))
                 } else {
                     None
@@ -1199,40 +1510,36 @@ impl State {
                 self.shape_from_drag(|position, size, rough_style| DrawingElement::Rectangle {
                     position,
                     size,
-                    color: self.current_color,
-                    fill: false,
-                    stroke_width: self.stroke_width,
+                    color: self.style.stroke,
+                    background: self.style.background.map(FillStyle::Solid),
+                    stroke_width: self.style.stroke_width,
                     rough_style: Some(rough_style),
+                    roundness: self.style.roundness,
                 })
             }
-            Tool::Circle => {
-                if let Some(start) = self.input.drag_start {
-                    let end = self.canvas.transform.screen_to_canvas(self.input.mouse_pos);
-                    let radius = ((end[0] - start[0]).powi(2) + (end[1] - start[1]).powi(2)).sqrt();
-                    let mut rough_options = self.random_rough_options(0.4, 0.4, 0.5, 32.0, 0.1);
-                    rough_options.stroke_width = self.stroke_width;
-                    Some(Element::new(DrawingElement::Circle {
-                        center: start,
-                        radius,
-                        color: self.current_color,
-                        fill: false,
-                        stroke_width: self.stroke_width,
-                        rough_style: Some(rough_options),
-                    }))
-                } else {
-                    None
-                }
-            }
+            Tool::Circle => self.ellipse_drag_bounds().map(|(position, size)| {
+                let mut rough_options = self.random_rough_options(0.4, 0.4, 0.5, 32.0, 0.1);
+                rough_options.stroke_width = self.style.stroke_width;
+                Element::new(DrawingElement::Circle {
+                    center: [position[0] + size[0] * 0.5, position[1] + size[1] * 0.5],
+                    radii: [size[0] * 0.5, size[1] * 0.5],
+                    color: self.style.stroke,
+                    background: self.style.background.map(FillStyle::Solid),
+                    stroke_width: self.style.stroke_width,
+                    rough_style: Some(rough_options),
+                })
+            }),
             Tool::Arrow => self.line_like_from_drag(true),
             Tool::Line => self.line_like_from_drag(false),
             Tool::Diamond => {
                 self.shape_from_drag(|position, size, rough_style| DrawingElement::Diamond {
                     position,
                     size,
-                    color: self.current_color,
-                    fill: false,
-                    stroke_width: self.stroke_width,
+                    color: self.style.stroke,
+                    background: self.style.background.map(FillStyle::Solid),
+                    stroke_width: self.style.stroke_width,
                     rough_style: Some(rough_style),
+                    roundness: self.style.roundness,
                 })
             }
             _ => None,
@@ -1261,29 +1568,46 @@ impl State {
         let position = [start[0].min(end[0]), start[1].min(end[1])];
         let size = [(end[0] - start[0]).abs(), (end[1] - start[1]).abs()];
         let mut rough_options = self.random_rough_options(0.6, 0.8, 1.0, 16.0, 0.2);
-        rough_options.stroke_width = self.stroke_width;
+        rough_options.stroke_width = self.style.stroke_width;
         Some(Element::new(shape_fn(position, size, rough_options)))
     }
 
+    /// Bounding box of an ellipse dragged from one corner to the other.
+    /// Holding Shift constrains it to a perfect circle anchored at the start corner.
+    fn ellipse_drag_bounds(&self) -> Option<([f32; 2], [f32; 2])> {
+        let start = self.input.drag_start?;
+        let mut end = self.canvas.transform.screen_to_canvas(self.input.mouse_pos);
+        if self.input.modifiers.shift_key() {
+            let side = (end[0] - start[0]).abs().max((end[1] - start[1]).abs());
+            end = [
+                start[0] + side.copysign(end[0] - start[0]),
+                start[1] + side.copysign(end[1] - start[1]),
+            ];
+        }
+        let position = [start[0].min(end[0]), start[1].min(end[1])];
+        let size = [(end[0] - start[0]).abs(), (end[1] - start[1]).abs()];
+        Some((position, size))
+    }
+
     fn line_like_from_drag(&self, is_arrow: bool) -> Option<Element> {
         let start = self.input.drag_start?;
         let end = self.canvas.transform.screen_to_canvas(self.input.mouse_pos);
         let mut rough_options = self.random_rough_options(0.5, 0.6, 0.8, 8.0, 0.1);
-        rough_options.stroke_width = self.stroke_width;
+        rough_options.stroke_width = self.style.stroke_width;
         let shape = if is_arrow {
             DrawingElement::Arrow {
                 start,
                 end,
-                color: self.current_color,
-                width: self.stroke_width,
+                color: self.style.stroke,
+                width: self.style.stroke_width,
                 rough_style: Some(rough_options),
             }
         } else {
             DrawingElement::Line {
                 start,
                 end,
-                color: self.current_color,
-                width: self.stroke_width,
+                color: self.style.stroke,
+                width: self.style.stroke_width,
                 rough_style: Some(rough_options),
             }
         };
@@ -1300,10 +1624,12 @@ impl State {
     ) -> crate::rough::RoughOptions {
         let mut rough_options = crate::rough::RoughOptions::default();
         let mut rng = rand::rng();
-        rough_options.roughness = roughness_base + rng.random::<f32>() * roughness_variation;
-        rough_options.bowing = roughness_base + rng.random::<f32>() * roughness_variation;
+        let scale = self.style.roughness;
+        rough_options.roughness =
+            (roughness_base + rng.random::<f32>() * roughness_variation) * scale;
+        rough_options.bowing = (roughness_base + rng.random::<f32>() * roughness_variation) * scale;
         rough_options.max_randomness_offset =
-            randomness_base + rng.random::<f32>() * randomness_base;
+            (randomness_base + rng.random::<f32>() * randomness_base) * scale;
         rough_options.curve_step_count =
             (step_count + (rng.random::<f32>() * step_count * 0.25)) as u32;
         rough_options.curve_tightness = rng.random::<f32>() * tightness;
@@ -1326,32 +1652,31 @@ impl State {
                         position,
                         size,
                         color: [
-                            self.current_color[0],
-                            self.current_color[1],
-                            self.current_color[2],
+                            self.style.stroke[0],
+                            self.style.stroke[1],
+                            self.style.stroke[2],
                             0.5,
                         ],
-                        fill: false,
-                        stroke_width: self.stroke_width,
+                        background: self.preview_background(),
+                        stroke_width: self.style.stroke_width,
                         rough_style: None,
+                        roundness: self.style.roundness,
                     });
                 }
             }
             Tool::Circle => {
-                if let Some(start) = self.input.drag_start {
-                    let end = self.canvas.transform.screen_to_canvas(self.input.mouse_pos);
-                    let radius = ((end[0] - start[0]).powi(2) + (end[1] - start[1]).powi(2)).sqrt();
+                if let Some((position, size)) = self.ellipse_drag_bounds() {
                     self.input.preview_element = Some(DrawingElement::Circle {
-                        center: start,
-                        radius,
+                        center: [position[0] + size[0] * 0.5, position[1] + size[1] * 0.5],
+                        radii: [size[0] * 0.5, size[1] * 0.5],
                         color: [
-                            self.current_color[0],
-                            self.current_color[1],
-                            self.current_color[2],
+                            self.style.stroke[0],
+                            self.style.stroke[1],
+                            self.style.stroke[2],
                             0.5,
                         ],
-                        fill: false,
-                        stroke_width: self.stroke_width,
+                        background: self.preview_background(),
+                        stroke_width: self.style.stroke_width,
                         rough_style: None,
                     });
                 }
@@ -1363,12 +1688,12 @@ impl State {
                         start,
                         end,
                         color: [
-                            self.current_color[0],
-                            self.current_color[1],
-                            self.current_color[2],
+                            self.style.stroke[0],
+                            self.style.stroke[1],
+                            self.style.stroke[2],
                             0.5,
                         ],
-                        width: self.stroke_width,
+                        width: self.style.stroke_width,
                         rough_style: None,
                     });
                 }
@@ -1380,12 +1705,12 @@ impl State {
                         start,
                         end,
                         color: [
-                            self.current_color[0],
-                            self.current_color[1],
-                            self.current_color[2],
+                            self.style.stroke[0],
+                            self.style.stroke[1],
+                            self.style.stroke[2],
                             0.5,
                         ],
-                        width: self.stroke_width,
+                        width: self.style.stroke_width,
                         rough_style: None,
                     });
                 }
@@ -1399,14 +1724,15 @@ impl State {
                         position,
                         size,
                         color: [
-                            self.current_color[0],
-                            self.current_color[1],
-                            self.current_color[2],
+                            self.style.stroke[0],
+                            self.style.stroke[1],
+                            self.style.stroke[2],
                             0.5,
                         ],
-                        fill: false,
-                        stroke_width: self.stroke_width,
+                        background: self.preview_background(),
+                        stroke_width: self.style.stroke_width,
                         rough_style: None,
+                        roundness: self.style.roundness,
                     });
                 }
             }
@@ -1416,50 +1742,98 @@ impl State {
         }
     }
 
+    /// Holding Alt bypasses grid and object snapping.
+    fn snapping_bypassed(&self) -> bool {
+        self.input.modifiers.alt_key()
+    }
+
     fn snap_delta_for_selection(&self, dx: f32, dy: f32) -> (f32, f32) {
-        let mut snapped_dx = snap_to_grid(dx);
-        let mut snapped_dy = snap_to_grid(dy);
+        if self.snapping_bypassed() {
+            return (dx, dy);
+        }
+        let grid = self.settings.grid;
+        let mut snapped_dx = grid.snap(dx);
+        let mut snapped_dy = grid.snap(dy);
+        if !grid.snap_to_objects {
+            return (snapped_dx, snapped_dy);
+        }
         if let Some(bounds) = self.selection_bounds() {
             let moved = (
                 [bounds.0[0] + snapped_dx, bounds.0[1] + snapped_dy],
                 [bounds.1[0] + snapped_dx, bounds.1[1] + snapped_dy],
             );
+            let threshold = self.snap_threshold();
             let candidates = self.snap_candidates(&self.input.selection.selected_ids);
-            if let Some(adjust) =
-                snap_against_candidates(moved.0[0], moved.1[0], &candidates.x_edges)
-            {
+            if let Some(adjust) = candidates.snap_offset(moved, 0, threshold) {
                 snapped_dx += adjust;
             }
-            if let Some(adjust) =
-                snap_against_candidates(moved.0[1], moved.1[1], &candidates.y_edges)
-            {
+            if let Some(adjust) = candidates.snap_offset(moved, 1, threshold) {
                 snapped_dy += adjust;
             }
         }
         (snapped_dx, snapped_dy)
     }
 
-    fn snap_bounds(&self, bounds: ([f32; 2], [f32; 2])) -> ([f32; 2], [f32; 2]) {
+    /// Snap resized bounds to the grid, then snap the edges that moved away
+    /// from `start` onto other elements' edges and centers.
+    fn snap_bounds(
+        &self,
+        bounds: ([f32; 2], [f32; 2]),
+        start: ([f32; 2], [f32; 2]),
+    ) -> ([f32; 2], [f32; 2]) {
         let mut snapped = normalize_bounds(bounds);
-        snapped.0[0] = snap_to_grid(snapped.0[0]);
-        snapped.0[1] = snap_to_grid(snapped.0[1]);
-        snapped.1[0] = snap_to_grid(snapped.1[0]);
-        snapped.1[1] = snap_to_grid(snapped.1[1]);
+        if self.snapping_bypassed() {
+            return snapped;
+        }
+        let grid = self.settings.grid;
+        snapped.0[0] = grid.snap(snapped.0[0]);
+        snapped.0[1] = grid.snap(snapped.0[1]);
+        snapped.1[0] = grid.snap(snapped.1[0]);
+        snapped.1[1] = grid.snap(snapped.1[1]);
+        if !grid.snap_to_objects {
+            return snapped;
+        }
+
+        let threshold = self.snap_threshold();
+        let candidates = self.snap_candidates(&self.input.selection.selected_ids);
+        for axis in 0..2 {
+            for edge in [&mut snapped.0[axis], &mut snapped.1[axis]] {
+                let unmoved = (*edge - start.0[axis]).abs() < f32::EPSILON
+                    || (*edge - start.1[axis]).abs() < f32::EPSILON;
+                if unmoved {
+                    continue;
+                }
+                if let Some(adjust) = candidates.snap_edge(*edge, axis, threshold) {
+                    *edge += adjust;
+                }
+            }
+        }
         snapped
     }
 
+    /// Snap distance in canvas units; the setting is in screen pixels.
+    fn snap_threshold(&self) -> f32 {
+        self.settings.grid.snap_threshold / self.canvas.transform.scale
+    }
+
     fn snap_candidates(&self, excluding: &[ElementId]) -> SnapCandidates {
-        let mut x_edges = Vec::new();
-        let mut y_edges = Vec::new();
-        for element in &self.elements {
-            if excluding.contains(&element.id) {
-                continue;
-            }
-            let bounds = element.bounding_box();
-            x_edges.extend([bounds.0[0], (bounds.0[0] + bounds.1[0]) * 0.5, bounds.1[0]]);
-            y_edges.extend([bounds.0[1], (bounds.0[1] + bounds.1[1]) * 0.5, bounds.1[1]]);
-        }
-        SnapCandidates { x_edges, y_edges }
+        SnapCandidates::new(
+            self.elements
+                .iter()
+                .filter(|element| !excluding.contains(&element.id))
+                .map(|element| element.bounding_box())
+                .collect(),
+        )
+    }
+
+    /// Refresh the smart guides for the selection's current position.
+    fn update_snap_guides(&mut self) {
+        self.input.guides = match self.selection_bounds() {
+            Some(bounds) if self.settings.grid.snap_to_objects && !self.snapping_bypassed() => self
+                .snap_candidates(&self.input.selection.selected_ids)
+                .guides(bounds),
+            _ => Vec::new(),
+        };
     }
 }
 
@@ -1469,52 +1843,6 @@ enum ActionKind {
     Modify,
 }
 
-#[derive(Clone, Copy)]
-enum HAlign {
-    Left,
-    Right,
-    CenterX,
-    CenterY,
-}
-
-struct SnapCandidates {
-    x_edges: Vec<f32>,
-    y_edges: Vec<f32>,
-}
-
-fn textbox_size(content: &str, font_size: f32) -> [f32; 2] {
-    let width = content
-        .lines()
-        .map(|line| line.chars().count() as f32 * font_size * 0.6)
-        .fold(font_size * 0.8, f32::max)
-        + 16.0;
-    let height = content.lines().count().max(1) as f32 * font_size * 1.2 + 16.0;
-    [width, height]
-}
-
-fn snap_to_grid(value: f32) -> f32 {
-    const GRID: f32 = 10.0;
-    (value / GRID).round() * GRID
-}
-
-fn snap_against_candidates(min: f32, max: f32, candidates: &[f32]) -> Option<f32> {
-    let center = (min + max) * 0.5;
-    let probes = [min, center, max];
-    let mut best: Option<f32> = None;
-    for probe in probes {
-        for candidate in candidates {
-            let delta = *candidate - probe;
-            if delta.abs() <= 8.0 {
-                match best {
-                    Some(best_delta) if best_delta.abs() <= delta.abs() => {}
-                    _ => best = Some(delta),
-                }
-            }
-        }
-    }
-    best
-}
-
 fn normalize_bounds(bounds: ([f32; 2], [f32; 2])) -> ([f32; 2], [f32; 2]) {
     (
         [bounds.0[0].min(bounds.1[0]), bounds.0[1].min(bounds.1[1])],
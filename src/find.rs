@@ -0,0 +1,583 @@
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+use winit::event::KeyEvent;
+use winit::keyboard::KeyCode;
+
+use crate::app_state::State;
+use crate::canvas::CanvasTransform;
+use crate::command::Command;
+use crate::drawing::{DrawingElement, Element, ElementId};
+use crate::history::Action;
+use crate::keymap::KeyCombo;
+use crate::platform;
+use crate::rich_text::{self, TextSpan};
+use crate::ui::FindBarPart;
+
+/// A hit is zoomed to at least this tall on screen, in pixels.
+const MIN_HIT_HEIGHT: f32 = 18.0;
+/// Most of the screen a hit may cover before the view zooms out to fit it.
+const MAX_HIT_SHARE: f32 = 0.6;
+/// A hit closer than this to the screen edge is scrolled to the center.
+const REVEAL_MARGIN: f32 = 48.0;
+
+/// The Ctrl+F find bar over the content of Text and TextBox elements.
+#[derive(Debug, Default)]
+pub struct FindBar {
+    pub open: bool,
+    pub query: String,
+    pub replacement: String,
+    pub match_case: bool,
+    /// The query is a regular expression, and the replacement may refer to
+    /// its groups as `$1`.
+    pub regex: bool,
+    /// Typing goes to the replacement rather than the query.
+    pub replacing: bool,
+    /// Index into the current matches.
+    pub current: usize,
+    /// The last search, reused until it goes stale.
+    results: Option<FindResults>,
+}
+
+/// Hits of one search, kept until the query, its options or the text it
+/// searched change.
+#[derive(Debug)]
+struct FindResults {
+    query: String,
+    match_case: bool,
+    regex: bool,
+    pattern: Option<Regex>,
+    /// Every text element as it was searched, to notice edits, moves and
+    /// restyles.
+    searched: Vec<(ElementId, DrawingElement)>,
+    matches: Vec<TextMatch>,
+    /// Highlight rectangles of each hit, laid out the first time they are
+    /// drawn.
+    rects: Option<Vec<Vec<[[f32; 2]; 2]>>>,
+}
+
+/// A hit in the content of a text element.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextMatch {
+    pub id: ElementId,
+    pub range: Range<usize>,
+}
+
+/// Where a hit is on the canvas, and whether it is the one being shown.
+pub struct FindHighlight<'a> {
+    pub rects: &'a [[[f32; 2]; 2]],
+    pub current: bool,
+}
+
+impl FindBar {
+    /// The query as a pattern; `None` when it is empty or not a valid
+    /// regular expression.
+    pub fn pattern(&self) -> Option<Regex> {
+        if self.query.is_empty() {
+            return None;
+        }
+        let source = if self.regex {
+            self.query.clone()
+        } else {
+            regex::escape(&self.query)
+        };
+        RegexBuilder::new(&source)
+            .case_insensitive(!self.match_case)
+            .build()
+            .ok()
+    }
+
+    /// The hits of the query in `elements`, searching again only when the
+    /// query, its options or the text elements changed since last time.
+    fn search(&mut self, elements: &[Element]) -> &mut FindResults {
+        let fresh = self.results.as_ref().is_some_and(|results| {
+            results.query == self.query
+                && results.match_case == self.match_case
+                && results.regex == self.regex
+                && text_shapes(elements).eq(results.searched.iter().map(|(id, shape)| (*id, shape)))
+        });
+        if !fresh {
+            let pattern = self.pattern();
+            let matches = pattern
+                .as_ref()
+                .map(|pattern| find_all(elements, pattern))
+                .unwrap_or_default();
+            self.results = Some(FindResults {
+                query: self.query.clone(),
+                match_case: self.match_case,
+                regex: self.regex,
+                pattern,
+                searched: text_shapes(elements)
+                    .map(|(id, shape)| (id, shape.clone()))
+                    .collect(),
+                matches,
+                rects: None,
+            });
+        }
+        self.results.as_mut().unwrap()
+    }
+}
+
+impl FindResults {
+    /// Highlight rectangles of every hit, each element laid out once.
+    fn rects(&mut self) -> &[Vec<[[f32; 2]; 2]>] {
+        let (searched, matches) = (&self.searched, &self.matches);
+        self.rects.get_or_insert_with(|| {
+            let mut rects = Vec::with_capacity(matches.len());
+            for (id, shape) in searched {
+                if !matches.iter().any(|hit| hit.id == *id) {
+                    continue;
+                }
+                let hits = matches.iter().filter(|hit| hit.id == *id);
+                let (Some((layout, origins)), Some(content)) =
+                    (shape.text_layout(), shape.text_content())
+                else {
+                    rects.extend(hits.map(|_| Vec::new()));
+                    continue;
+                };
+                rects.extend(
+                    hits.map(|hit| layout.selection_rects(content, &origins, hit.range.clone())),
+                );
+            }
+            rects
+        })
+    }
+}
+
+impl State {
+    /// Show the find bar with the query focused, keeping the last search.
+    pub fn open_find_bar(&mut self) {
+        self.palette.open = false;
+        self.find.open = true;
+        self.find.replacing = false;
+        self.reveal_current_match();
+    }
+
+    /// Every hit of the query, in drawing order.
+    pub fn find_matches(&mut self) -> &[TextMatch] {
+        &self.find.search(&self.elements).matches
+    }
+
+    /// Step to the next hit, or the previous one when `backward`, and bring
+    /// it into view.
+    pub fn find_next(&mut self, backward: bool) {
+        let count = self.find_matches().len();
+        if count == 0 {
+            return;
+        }
+        let current = self.find.current.min(count - 1);
+        self.find.current = if backward {
+            (current + count - 1) % count
+        } else {
+            (current + 1) % count
+        };
+        self.reveal_current_match();
+    }
+
+    /// Replace the hit being shown and move on to the next one after it.
+    pub fn replace_current_match(&mut self) {
+        let current = self.find.current;
+        let results = self.find.search(&self.elements);
+        let (Some(pattern), Some(hit)) = (
+            results.pattern.clone(),
+            results.matches.get(current).cloned(),
+        ) else {
+            return;
+        };
+        let replacement = self.find.replacement.clone();
+        let expand = self.find.regex;
+        let before = self.snapshot_elements(&[hit.id]);
+        let Some(element) = self.find_element_mut_by_id(hit.id) else {
+            return;
+        };
+        let end = replace_in_shape(
+            &mut element.shape,
+            &pattern,
+            &replacement,
+            expand,
+            &[hit.range],
+        );
+        let after = self.snapshot_elements(&[hit.id]);
+        if before != after {
+            self.record_action(Action::ModifyProperty { before, after });
+        }
+
+        // Carry on after the new text, so a replacement that matches the
+        // query again isn't found again.
+        let order = |id: ElementId| self.elements.iter().position(|element| element.id == id);
+        let from = (order(hit.id), end);
+        let next = self
+            .find
+            .search(&self.elements)
+            .matches
+            .iter()
+            .position(|next| (order(next.id), next.range.start) >= from)
+            .unwrap_or(0);
+        self.find.current = next;
+        self.reveal_current_match();
+    }
+
+    /// Replace every hit, recorded as one edit.
+    pub fn replace_all_matches(&mut self) {
+        let results = self.find.search(&self.elements);
+        let Some(pattern) = results.pattern.clone() else {
+            return;
+        };
+        let matches = results.matches.clone();
+        let mut ids: Vec<ElementId> = matches.iter().map(|hit| hit.id).collect();
+        ids.dedup();
+        if ids.is_empty() {
+            return;
+        }
+        let replacement = self.find.replacement.clone();
+        let expand = self.find.regex;
+        let before = self.snapshot_elements(&ids);
+        for id in &ids {
+            let ranges: Vec<Range<usize>> = matches
+                .iter()
+                .filter(|hit| hit.id == *id)
+                .map(|hit| hit.range.clone())
+                .collect();
+            if let Some(element) = self.find_element_mut_by_id(*id) {
+                replace_in_shape(&mut element.shape, &pattern, &replacement, expand, &ranges);
+            }
+        }
+        let after = self.snapshot_elements(&ids);
+        if before != after {
+            self.record_action(Action::ModifyProperty { before, after });
+        }
+        self.find.current = 0;
+    }
+
+    /// Where the hits are on the canvas, for highlighting.
+    pub fn find_highlights(&mut self) -> Vec<FindHighlight<'_>> {
+        if !self.find.open {
+            return Vec::new();
+        }
+        let current = self.find.current;
+        self.find
+            .search(&self.elements)
+            .rects()
+            .iter()
+            .enumerate()
+            .map(|(index, rects)| FindHighlight {
+                rects,
+                current: index == current,
+            })
+            .collect()
+    }
+
+    /// Clicks on the find bar's fields, toggles and buttons.
+    pub fn click_find_bar(&mut self, part: FindBarPart) {
+        match part {
+            FindBarPart::Query => self.find.replacing = false,
+            FindBarPart::Replacement => self.find.replacing = true,
+            FindBarPart::MatchCase => {
+                self.find.match_case = !self.find.match_case;
+                self.restart_find();
+            }
+            FindBarPart::Regex => {
+                self.find.regex = !self.find.regex;
+                self.restart_find();
+            }
+            FindBarPart::Replace => self.replace_current_match(),
+            FindBarPart::ReplaceAll => self.replace_all_matches(),
+        }
+    }
+
+    /// Keys while the find bar is open: typing edits the focused field,
+    /// Enter steps through hits (or replaces, from the replace field), Tab
+    /// switches fields and Escape closes. Everything is swallowed.
+    pub fn handle_find_key(&mut self, key_event: &KeyEvent, keycode: KeyCode) -> bool {
+        let modifiers = self.input.modifiers;
+        let is_ctrl_or_cmd = modifiers.control_key() || modifiers.super_key();
+        match keycode {
+            KeyCode::Escape => self.find.open = false,
+            KeyCode::Enter if self.find.replacing && is_ctrl_or_cmd => self.replace_all_matches(),
+            KeyCode::Enter if self.find.replacing => self.replace_current_match(),
+            KeyCode::Enter | KeyCode::F3 => self.find_next(modifiers.shift_key()),
+            KeyCode::Tab => self.find.replacing = !self.find.replacing,
+            KeyCode::KeyC if modifiers.alt_key() => self.click_find_bar(FindBarPart::MatchCase),
+            KeyCode::KeyR if modifiers.alt_key() => self.click_find_bar(FindBarPart::Regex),
+            KeyCode::Backspace if is_ctrl_or_cmd => self.edit_find_field(String::clear),
+            KeyCode::Backspace => self.edit_find_field(|field| {
+                field.pop();
+            }),
+            KeyCode::KeyV if is_ctrl_or_cmd => {
+                if let Some(text) = platform::clipboard_text()
+                    && let Some(line) = text.lines().next()
+                {
+                    self.edit_find_field(|field| field.push_str(line));
+                }
+            }
+            _ if self
+                .keymap
+                .is_bound(Command::FindText, KeyCombo::new(keycode, modifiers)) =>
+            {
+                self.find.replacing = false;
+            }
+            _ => {
+                if let Some(text) = &key_event.text {
+                    let visible: String = text.chars().filter(|ch| !ch.is_control()).collect();
+                    if !visible.is_empty() {
+                        self.edit_find_field(|field| field.push_str(&visible));
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// Change the focused field. Editing the query starts the search over;
+    /// editing the replacement leaves the hits alone.
+    fn edit_find_field(&mut self, edit: impl FnOnce(&mut String)) {
+        if self.find.replacing {
+            edit(&mut self.find.replacement);
+        } else {
+            edit(&mut self.find.query);
+            self.restart_find();
+        }
+    }
+
+    /// Show the first hit after the query or its options change.
+    fn restart_find(&mut self) {
+        self.find.current = 0;
+        self.reveal_current_match();
+    }
+
+    /// Pan to the hit being shown when it is near or past the screen edge,
+    /// zooming in when it is too small to read or out when it doesn't fit.
+    fn reveal_current_match(&mut self) {
+        let current = self.find.current;
+        let Some([min, max]) = self
+            .find
+            .search(&self.elements)
+            .rects()
+            .get(current)
+            .into_iter()
+            .flatten()
+            .copied()
+            .reduce(|[a_min, a_max], [b_min, b_max]| {
+                [
+                    [a_min[0].min(b_min[0]), a_min[1].min(b_min[1])],
+                    [a_max[0].max(b_max[0]), a_max[1].max(b_max[1])],
+                ]
+            })
+        else {
+            return;
+        };
+
+        let screen = [self.size.width as f32, self.size.height as f32];
+        let transform = &mut self.canvas.transform;
+        let size = [(max[0] - min[0]).max(1.0), (max[1] - min[1]).max(1.0)];
+        let fit = (screen[0] * MAX_HIT_SHARE / size[0]).min(screen[1] * MAX_HIT_SHARE / size[1]);
+        let scale = transform
+            .scale
+            .max(MIN_HIT_HEIGHT / size[1])
+            .min(fit)
+            .clamp(CanvasTransform::MIN_SCALE, CanvasTransform::MAX_SCALE);
+        let top_left = transform.canvas_to_screen(min);
+        let bottom_right = transform.canvas_to_screen(max);
+        let visible = top_left[0] >= REVEAL_MARGIN
+            && top_left[1] >= REVEAL_MARGIN
+            && bottom_right[0] <= screen[0] - REVEAL_MARGIN
+            && bottom_right[1] <= screen[1] - REVEAL_MARGIN;
+        if scale == transform.scale && visible {
+            return;
+        }
+        let center = [(min[0] + max[0]) * 0.5, (min[1] + max[1]) * 0.5];
+        transform.scale = scale;
+        transform.offset = [
+            screen[0] * 0.5 - center[0] * scale,
+            screen[1] * 0.5 - center[1] * scale,
+        ];
+        self.flush_canvas_transform();
+    }
+}
+
+/// Every text element, for noticing changes to what was searched.
+fn text_shapes(elements: &[Element]) -> impl Iterator<Item = (ElementId, &DrawingElement)> {
+    elements
+        .iter()
+        .filter(|element| element.shape.text_content().is_some())
+        .map(|element| (element.id, &element.shape))
+}
+
+/// Every hit of `pattern` in the text elements, in drawing order.
+fn find_all(elements: &[Element], pattern: &Regex) -> Vec<TextMatch> {
+    elements
+        .iter()
+        .flat_map(|element| {
+            let content = element.shape.text_content().unwrap_or_default();
+            find_in(content, pattern)
+                .into_iter()
+                .map(|range| TextMatch {
+                    id: element.id,
+                    range,
+                })
+        })
+        .collect()
+}
+
+/// Byte ranges of the non-empty matches of `pattern` in `content`.
+fn find_in(content: &str, pattern: &Regex) -> Vec<Range<usize>> {
+    pattern
+        .find_iter(content)
+        .map(|found| found.range())
+        .filter(|range| !range.is_empty())
+        .collect()
+}
+
+/// Replace `ranges` of a text shape's content, last first so the earlier
+/// ones stay put, and refit it. Returns where the first replacement ends.
+fn replace_in_shape(
+    shape: &mut DrawingElement,
+    pattern: &Regex,
+    replacement: &str,
+    expand: bool,
+    ranges: &[Range<usize>],
+) -> usize {
+    let mut plain = Vec::new();
+    let (content, spans) = match shape {
+        DrawingElement::Text { content, .. } => (content, &mut plain),
+        DrawingElement::TextBox { content, spans, .. } => (content, spans),
+        _ => return 0,
+    };
+    let mut end = 0;
+    for range in ranges.iter().rev() {
+        end = replace_range(content, spans, pattern, replacement, expand, range.clone());
+    }
+    shape.refit_text();
+    end
+}
+
+/// Replace `range`, a match of `pattern`, with `replacement`, expanding
+/// `$1`-style groups when `expand`. The new text takes the formatting of
+/// the first character it replaces. Returns where the new text ends.
+fn replace_range(
+    content: &mut String,
+    spans: &mut Vec<TextSpan>,
+    pattern: &Regex,
+    replacement: &str,
+    expand: bool,
+    range: Range<usize>,
+) -> usize {
+    let mut text = String::new();
+    match pattern.captures_at(content, range.start) {
+        Some(captures) if expand && captures.get(0).is_some_and(|found| found.range() == range) => {
+            captures.expand(replacement, &mut text);
+        }
+        _ => text.push_str(replacement),
+    }
+    let style = rich_text::pieces(spans, range.clone())
+        .first()
+        .and_then(|(_, style)| style.cloned())
+        .unwrap_or_default();
+    rich_text::remove(spans, range.clone());
+    content.replace_range(range.clone(), &text);
+    if !text.is_empty() {
+        rich_text::insert(spans, range.start, text.len(), &style);
+    }
+    range.start + text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rich_text::SpanStyle;
+
+    fn bar(query: &str, match_case: bool, regex: bool) -> FindBar {
+        FindBar {
+            query: query.to_string(),
+            match_case,
+            regex,
+            ..FindBar::default()
+        }
+    }
+
+    #[test]
+    fn queries_honor_case_and_regex() {
+        let content = "Cat cat (cat) c.t (c.t)";
+        let ranges = |find: FindBar| find_in(content, &find.pattern().unwrap());
+        assert_eq!(ranges(bar("cat", false, false)), [0..3, 4..7, 9..12]);
+        assert_eq!(ranges(bar("cat", true, false)), [4..7, 9..12]);
+        assert_eq!(ranges(bar("c.t", true, false)), [14..17, 19..22]);
+        assert_eq!(ranges(bar(r"\(c.t\)", true, true)), [8..13, 18..23]);
+        assert!(bar("(", false, true).pattern().is_none());
+        assert!(
+            bar("x*", false, true)
+                .pattern()
+                .is_some_and(|p| find_in(content, &p).is_empty())
+        );
+    }
+
+    fn text(content: &str) -> Element {
+        Element::new(DrawingElement::Text {
+            position: [0.0, 0.0],
+            content: content.to_string(),
+            color: [0.0, 0.0, 0.0, 1.0],
+            size: 20.0,
+            font: Default::default(),
+        })
+    }
+
+    #[test]
+    fn searches_are_reused_until_the_query_or_text_changes() {
+        let mut elements = vec![text("a cat"), text("no match"), text("cat cat")];
+        let mut find = bar("cat", false, false);
+        assert_eq!(find.search(&elements).matches.len(), 3);
+        assert_eq!(find.search(&elements).rects().len(), 3);
+        // Still laid out: nothing changed.
+        assert!(find.search(&elements).rects.is_some());
+
+        find.match_case = true;
+        assert!(find.search(&elements).rects.is_none());
+        find.query = "a c".to_string();
+        assert_eq!(find.search(&elements).matches.len(), 1);
+
+        if let DrawingElement::Text { content, .. } = &mut elements[1].shape {
+            *content = "a cat too".to_string();
+        }
+        assert_eq!(find.search(&elements).matches.len(), 2);
+        elements.remove(0);
+        assert_eq!(
+            find.search(&elements).matches,
+            [TextMatch {
+                id: elements[0].id,
+                range: 0..3,
+            }]
+        );
+    }
+
+    #[test]
+    fn replacements_expand_groups_and_keep_formatting() {
+        let bold = SpanStyle {
+            bold: true,
+            ..SpanStyle::default()
+        };
+        let mut content = "see Alice and Bob".to_string();
+        let mut spans = vec![TextSpan {
+            range: 4..9,
+            style: bold.clone(),
+        }];
+        let pattern = bar(r"(\w+) and (\w+)", true, true).pattern().unwrap();
+        let end = replace_range(&mut content, &mut spans, &pattern, "$2 & $1", true, 4..17);
+        assert_eq!(content, "see Bob & Alice");
+        assert_eq!(end, content.len());
+        assert_eq!(
+            spans,
+            [TextSpan {
+                range: 4..15,
+                style: bold
+            }]
+        );
+
+        let pattern = bar("$", true, false).pattern().unwrap();
+        let mut content = "$5 or $6".to_string();
+        let ranges = find_in(&content, &pattern);
+        for range in ranges.into_iter().rev() {
+            replace_range(&mut content, &mut Vec::new(), &pattern, "US$", false, range);
+        }
+        assert_eq!(content, "US$5 or US$6");
+    }
+}
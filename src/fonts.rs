@@ -0,0 +1,850 @@
+use std::collections::HashSet;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock, RwLock};
+
+use ab_glyph::{Font, FontArc, GlyphId, PxScale, ScaleFont};
+use rustybuzz::{Direction, UnicodeBuffer};
+use serde::{Deserialize, Serialize};
+use unicode_bidi::BidiInfo;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Typeface of a text element. Custom families name a user-supplied font
+/// file; text in a family that isn't installed falls back to hand-drawn.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum FontFamily {
+    #[default]
+    HandDrawn,
+    Normal,
+    /// Monospaced, drawn with the bundled DejaVu Sans Mono.
+    Code,
+    Custom(String),
+}
+
+impl FontFamily {
+    pub fn name(&self) -> &str {
+        match self {
+            FontFamily::HandDrawn => "Hand-drawn",
+            FontFamily::Normal => "Normal",
+            FontFamily::Code => "Code",
+            FontFamily::Custom(name) => name,
+        }
+    }
+
+    /// The family as a CSS `font-family` list, for exported text.
+    pub fn css_name(&self) -> &str {
+        match self {
+            FontFamily::HandDrawn => "Virgil, cursive",
+            FontFamily::Normal => "sans-serif",
+            FontFamily::Code => "'DejaVu Sans Mono', monospace",
+            FontFamily::Custom(name) => name,
+        }
+    }
+}
+
+/// Index of a loaded font; glyphs are cached per font.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FontId(usize);
+
+/// Advance of the box drawn for a character no font has, as a multiple of
+/// the font size.
+pub const TOFU_ADVANCE: f32 = 0.6;
+
+/// Glyph id placed for a character no font has; drawn as a tofu box.
+pub const TOFU: GlyphId = GlyphId(0);
+
+/// System fonts worth falling back to, best first, matched against the
+/// start of the lowercased file name. Only regular weights are used.
+const SYSTEM_FALLBACKS: &[&str] = &[
+    "dejavusans",
+    "notosans-regular",
+    "notosanscjk",
+    "notoserifcjk",
+    "droidsansfallback",
+    "wqy-",
+    "notocoloremoji",
+    "notoemoji",
+    "notosanssymbols",
+    "symbola",
+    "unifont",
+];
+
+const NOT_REGULAR: &[&str] = &[
+    "bold",
+    "italic",
+    "oblique",
+    "light",
+    "thin",
+    "black",
+    "medium",
+    "condensed",
+    "mono",
+];
+
+struct LoadedFont {
+    family: FontFamily,
+    font: FontArc,
+    /// The same font prepared for shaping.
+    face: Arc<ShapingFace>,
+    /// Only used for glyphs other fonts lack; not offered as a family.
+    fallback: bool,
+}
+
+impl LoadedFont {
+    fn new(family: FontFamily, font: FontArc) -> Result<Self, String> {
+        let face = ShapingFace::new(font.clone()).ok_or("font can't be shaped")?;
+        Ok(Self {
+            family,
+            font,
+            face: Arc::new(face),
+            fallback: false,
+        })
+    }
+
+    /// The file's data is owned by the rasterizer and shared with the
+    /// shaper, and freed once no layout uses the font any more.
+    fn from_file(family: FontFamily, bytes: Vec<u8>) -> Result<Self, String> {
+        let font = FontArc::try_from_vec(bytes).map_err(|e| e.to_string())?;
+        Self::new(family, font)
+    }
+}
+
+/// A shaping face borrowing the data of the rasterizer's copy of the font,
+/// so each font file is held in memory once.
+struct ShapingFace {
+    face: rustybuzz::Face<'static>,
+    /// Owns the data `face` borrows; dropped after it.
+    _data: FontArc,
+}
+
+impl ShapingFace {
+    fn new(font: FontArc) -> Option<Self> {
+        // SAFETY: the data lives in `font`'s shared allocation, which never
+        // moves and which the clone kept here holds alive for as long as
+        // `face` exists. `face` is only lent out for the lifetime of `self`.
+        let data: &'static [u8] = unsafe { std::mem::transmute(font.font_data()) };
+        let face = rustybuzz::Face::from_slice(data, 0)?;
+        Some(Self { face, _data: font })
+    }
+
+    fn get(&self) -> &rustybuzz::Face<'_> {
+        &self.face
+    }
+}
+
+/// A system font file that may become a fallback.
+struct Candidate {
+    path: PathBuf,
+    /// Characters the file has, once its cmap has been read.
+    coverage: Option<Coverage>,
+}
+
+/// The characters a font has, as sorted, disjoint inclusive ranges of
+/// code points.
+#[derive(Debug, Default, PartialEq)]
+struct Coverage(Vec<(u32, u32)>);
+
+impl Coverage {
+    /// Coverage of the Unicode cmap subtables of `data`; empty when it
+    /// isn't a font.
+    fn of_font(data: &[u8]) -> Self {
+        let Ok(face) = rustybuzz::ttf_parser::Face::parse(data, 0) else {
+            return Self::default();
+        };
+        let mut codepoints = Vec::new();
+        if let Some(cmap) = face.tables().cmap {
+            for subtable in cmap
+                .subtables
+                .into_iter()
+                .filter(|table| table.is_unicode())
+            {
+                subtable.codepoints(|codepoint| {
+                    if subtable.glyph_index(codepoint).is_some_and(|id| id.0 != 0) {
+                        codepoints.push(codepoint);
+                    }
+                });
+            }
+        }
+        Self::from_codepoints(codepoints)
+    }
+
+    fn from_codepoints(mut codepoints: Vec<u32>) -> Self {
+        codepoints.sort_unstable();
+        let mut ranges: Vec<(u32, u32)> = Vec::new();
+        for codepoint in codepoints {
+            match ranges.last_mut() {
+                Some((_, end)) if codepoint <= *end + 1 => *end = (*end).max(codepoint),
+                _ => ranges.push((codepoint, codepoint)),
+            }
+        }
+        Self(ranges)
+    }
+
+    fn contains(&self, ch: char) -> bool {
+        let codepoint = ch as u32;
+        let index = self.0.partition_point(|&(_, end)| end < codepoint);
+        self.0
+            .get(index)
+            .is_some_and(|&(start, _)| start <= codepoint)
+    }
+}
+
+#[derive(Default)]
+struct FontRegistry {
+    fonts: Vec<LoadedFont>,
+    /// Fonts tried, in order, for characters a text's own font lacks.
+    fallbacks: Vec<usize>,
+    /// System fonts not loaded yet that may become fallbacks, best first;
+    /// `None` until the font directories have been searched.
+    candidates: Option<Vec<Candidate>>,
+    /// Characters no font has, so they aren't searched for again.
+    uncovered: HashSet<char>,
+    /// Missing families already reported, so the log isn't flooded per frame.
+    warned: HashSet<String>,
+    /// Bumped whenever a font is added or replaced, which may change how
+    /// text lays out.
+    generation: u64,
+}
+
+static REGISTRY: LazyLock<RwLock<FontRegistry>> = LazyLock::new(|| {
+    let hand_drawn: &'static [u8] = include_bytes!("../data/fonts/Virgil.ttf");
+    let normal: &'static [u8] = include_bytes!("../data/fonts/Basic-Regular.ttf");
+    let code: &'static [u8] = include_bytes!("../data/fonts/DejaVuSansMono.ttf");
+    let mut registry = FontRegistry::default();
+    for (family, data) in [
+        (FontFamily::HandDrawn, hand_drawn),
+        (FontFamily::Normal, normal),
+        (FontFamily::Code, code),
+    ] {
+        let font = FontArc::try_from_slice(data).unwrap();
+        registry.fonts.push(LoadedFont::new(family, font).unwrap());
+    }
+    // The bundled fonts cover each other first: Basic has glyphs Virgil
+    // lacks and the other way round, and DejaVu adds symbols and scripts
+    // neither has.
+    registry.fallbacks = vec![1, 0, 2];
+    registry.load_user_fonts();
+    RwLock::new(registry)
+});
+
+impl FontRegistry {
+    /// Register every TTF/OTF file in the user fonts directory.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_user_fonts(&mut self) {
+        let entries = match crate::platform::fonts_dir().and_then(std::fs::read_dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::warn!("Failed to read fonts directory: {}", e);
+                return;
+            }
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if is_font_file(&path)
+                && let Err(e) = std::fs::read(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|bytes| self.register(&path, bytes))
+            {
+                log::warn!("Failed to load font {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn load_user_fonts(&mut self) {}
+
+    /// Add a custom family named after the file, replacing one of that name.
+    fn register(&mut self, path: &std::path::Path, bytes: Vec<u8>) -> Result<FontFamily, String> {
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or("font file has no name")?;
+        let family = FontFamily::Custom(name.to_string());
+        let loaded = LoadedFont::from_file(family.clone(), bytes)?;
+        match self
+            .fonts
+            .iter_mut()
+            .find(|font| !font.fallback && font.family == family)
+        {
+            Some(existing) => *existing = loaded,
+            None => self.fonts.push(loaded),
+        }
+        self.generation += 1;
+        log::info!("Loaded font {:?}", name);
+        Ok(family)
+    }
+
+    fn position(&self, family: &FontFamily) -> Option<usize> {
+        self.fonts
+            .iter()
+            .position(|font| !font.fallback && font.family == *family)
+    }
+
+    /// The first fallback other than `skip` that has `ch`.
+    fn covering(&self, ch: char, skip: FontId) -> Option<ResolvedFont> {
+        self.fallbacks
+            .iter()
+            .copied()
+            .filter(|&index| index != skip.0)
+            .find(|&index| self.fonts[index].font.glyph_id(ch) != TOFU)
+            .map(|index| self.resolved(index))
+    }
+
+    /// Load the best system fallback font that has `ch`. Each file's cmap
+    /// is read once and remembered, so only fonts that are used stay
+    /// loaded and no file is read again to rule it out.
+    fn load_fallback_for(&mut self, ch: char, skip: FontId) -> Option<ResolvedFont> {
+        if let Some(found) = self.covering(ch, skip) {
+            return Some(found);
+        }
+        let candidates = self.candidates.get_or_insert_with(|| {
+            find_system_fallbacks()
+                .into_iter()
+                .map(|path| Candidate {
+                    path,
+                    coverage: None,
+                })
+                .collect()
+        });
+        for index in 0..candidates.len() {
+            let candidate = &mut candidates[index];
+            if candidate
+                .coverage
+                .as_ref()
+                .is_some_and(|coverage| !coverage.contains(ch))
+            {
+                continue;
+            }
+            let bytes = match std::fs::read(&candidate.path) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    log::warn!(
+                        "Failed to read fallback font {}: {}",
+                        candidate.path.display(),
+                        e
+                    );
+                    candidate.coverage = Some(Coverage::default());
+                    continue;
+                }
+            };
+            let coverage = Coverage::of_font(&bytes);
+            if !coverage.contains(ch) {
+                candidate.coverage = Some(coverage);
+                continue;
+            }
+            let name = candidate
+                .path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or("")
+                .to_string();
+            match LoadedFont::from_file(FontFamily::Custom(name), bytes) {
+                Ok(loaded) => {
+                    let path = candidates.remove(index).path;
+                    log::info!("Loaded fallback font {}", path.display());
+                    self.fonts.push(LoadedFont {
+                        fallback: true,
+                        ..loaded
+                    });
+                    self.fallbacks.push(self.fonts.len() - 1);
+                    self.generation += 1;
+                    return Some(self.resolved(self.fonts.len() - 1));
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Failed to load fallback font {}: {}",
+                        candidate.path.display(),
+                        e
+                    );
+                    candidate.coverage = Some(Coverage::default());
+                }
+            }
+        }
+        self.uncovered.insert(ch);
+        None
+    }
+
+    fn resolved(&self, index: usize) -> ResolvedFont {
+        let loaded = &self.fonts[index];
+        ResolvedFont {
+            id: FontId(index),
+            font: loaded.font.clone(),
+            face: loaded.face.clone(),
+        }
+    }
+}
+
+/// Fallback font files in the system font directories, best first.
+fn find_system_fallbacks() -> Vec<PathBuf> {
+    let mut found: Vec<(usize, PathBuf)> = Vec::new();
+    let mut dirs = crate::platform::system_font_dirs();
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+            let is_font = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| ["ttf", "otf", "ttc"].contains(&ext.to_lowercase().as_str()));
+            let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let stem = stem.to_lowercase();
+            if !is_font || NOT_REGULAR.iter().any(|word| stem.contains(word)) {
+                continue;
+            }
+            if let Some(rank) = SYSTEM_FALLBACKS
+                .iter()
+                .position(|prefix| stem.starts_with(prefix))
+            {
+                found.push((rank, path));
+            }
+        }
+    }
+    found.sort();
+    found.into_iter().map(|(_, path)| path).collect()
+}
+
+/// A font other than `skip` that has `ch`: the bundled fonts first, then
+/// fallback fonts from the system, loaded the first time they're needed.
+fn fallback_for(ch: char, skip: FontId) -> Option<ResolvedFont> {
+    {
+        let registry = REGISTRY.read().unwrap();
+        if let Some(found) = registry.covering(ch, skip) {
+            return Some(found);
+        }
+        let exhausted = registry.candidates.as_ref().is_some_and(Vec::is_empty);
+        if exhausted || registry.uncovered.contains(&ch) {
+            return None;
+        }
+    }
+    REGISTRY.write().unwrap().load_fallback_for(ch, skip)
+}
+
+/// Joiners, variation selectors and control characters draw nothing.
+fn is_invisible(ch: char) -> bool {
+    ch.is_control()
+        || matches!(
+            ch,
+            '\u{200B}'..='\u{200F}'
+                | '\u{202A}'..='\u{202E}'
+                | '\u{2066}'..='\u{2069}'
+                | '\u{FE00}'..='\u{FE0F}'
+                | '\u{E0100}'..='\u{E01EF}'
+        )
+}
+
+fn is_font_file(path: &std::path::Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("ttf") || ext.eq_ignore_ascii_case("otf"))
+}
+
+/// Every family that can be picked, built-ins first.
+pub fn families() -> Vec<FontFamily> {
+    let registry = REGISTRY.read().unwrap();
+    registry
+        .fonts
+        .iter()
+        .filter(|font| !font.fallback)
+        .map(|font| font.family.clone())
+        .collect()
+}
+
+/// Load a TTF/OTF file as a custom family and keep a copy in the user fonts
+/// directory so it is there next time.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn install_font_file(path: &str) -> Result<FontFamily, String> {
+    let path = std::path::Path::new(path);
+    if !is_font_file(path) {
+        return Err("not a TTF or OTF file".to_string());
+    }
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    let family = REGISTRY.write().unwrap().register(path, bytes.clone())?;
+    if let Some(name) = path.file_name() {
+        let copy =
+            crate::platform::fonts_dir().and_then(|dir| std::fs::write(dir.join(name), &bytes));
+        if let Err(e) = copy {
+            log::warn!("Failed to keep a copy of {}: {}", path.display(), e);
+        }
+    }
+    Ok(family)
+}
+
+/// Changes whenever fonts are added or replaced; layouts made before a
+/// change may be out of date.
+pub fn generation() -> u64 {
+    REGISTRY.read().unwrap().generation
+}
+
+/// The font `family` is drawn with, falling back to hand-drawn when it
+/// isn't installed.
+pub fn resolve(family: &FontFamily) -> ResolvedFont {
+    let registry = REGISTRY.read().unwrap();
+    if let Some(index) = registry.position(family) {
+        return registry.resolved(index);
+    }
+    // Hand-drawn is always registered first.
+    let fallback = registry.resolved(0);
+    let name = family.name().to_string();
+    if !registry.warned.contains(&name) {
+        drop(registry);
+        log::warn!("Font {:?} is not installed; using hand-drawn", name);
+        REGISTRY.write().unwrap().warned.insert(name);
+    }
+    fallback
+}
+
+/// A font for bold or italic text, with the styles it lacks faked when
+/// drawn.
+#[derive(Debug, Clone)]
+pub struct StyledFont {
+    pub font: ResolvedFont,
+    pub fake_bold: bool,
+    pub fake_italic: bool,
+}
+
+/// The face of `family` for bold and italic text. Custom families use an
+/// installed `-Bold`, `-Italic` or `-BoldItalic` sibling when there is one;
+/// everything else is faked from the regular face.
+pub fn resolve_styled(family: &FontFamily, bold: bool, italic: bool) -> StyledFont {
+    if let FontFamily::Custom(name) = family
+        && (bold || italic)
+    {
+        let base = name.strip_suffix("-Regular").unwrap_or(name);
+        let registry = REGISTRY.read().unwrap();
+        let variants = [
+            (bold && italic, "-BoldItalic"),
+            (bold, "-Bold"),
+            (italic, "-Italic"),
+        ];
+        for (wanted, suffix) in variants {
+            let variant = FontFamily::Custom(format!("{}{}", base, suffix));
+            if wanted && let Some(index) = registry.position(&variant) {
+                return StyledFont {
+                    font: registry.resolved(index),
+                    fake_bold: bold && !suffix.contains("Bold"),
+                    fake_italic: italic && !suffix.contains("Italic"),
+                };
+            }
+        }
+    }
+    StyledFont {
+        font: resolve(family),
+        fake_bold: bold,
+        fake_italic: italic,
+    }
+}
+
+/// The font behind `id`, for rasterizing its glyphs.
+pub fn font_by_id(id: FontId) -> FontArc {
+    REGISTRY.read().unwrap().fonts[id.0].font.clone()
+}
+
+/// A glyph placed on a line, `x` measured from the line start and `y`
+/// from the baseline.
+#[derive(Debug, Clone, Copy)]
+pub struct PlacedGlyph {
+    pub font: FontId,
+    pub id: GlyphId,
+    pub x: f32,
+    pub y: f32,
+}
+
+/// A font ready for layout.
+#[derive(Clone)]
+pub struct ResolvedFont {
+    pub id: FontId,
+    font: FontArc,
+    face: Arc<ShapingFace>,
+}
+
+impl std::fmt::Debug for ResolvedFont {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResolvedFont")
+            .field("id", &self.id)
+            .finish()
+    }
+}
+
+/// Where a grapheme cluster of a line was drawn, `x` measured from the line
+/// start. Carets and selections go through these so they follow the
+/// reordered right-to-left runs rather than the logical order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClusterBox {
+    /// Byte range of the cluster in the line's text.
+    pub range: Range<usize>,
+    pub x: f32,
+    pub width: f32,
+    /// Whether the cluster reads right to left, so its logical start is
+    /// on its right edge.
+    pub rtl: bool,
+}
+
+impl ClusterBox {
+    /// Offset of the caret before the cluster.
+    pub fn leading_x(&self) -> f32 {
+        if self.rtl {
+            self.x + self.width
+        } else {
+            self.x
+        }
+    }
+
+    /// Offset of the caret after the cluster.
+    pub fn trailing_x(&self) -> f32 {
+        if self.rtl {
+            self.x
+        } else {
+            self.x + self.width
+        }
+    }
+}
+
+/// Part of a bidi run drawn with one font; tofu when no font has it.
+struct Segment {
+    range: Range<usize>,
+    font: Option<ResolvedFont>,
+}
+
+/// Glyphs of one line, placed left to right.
+struct LinePlacer {
+    glyphs: Vec<PlacedGlyph>,
+    clusters: Vec<ClusterBox>,
+    pen: f32,
+    px: f32,
+}
+
+impl ResolvedFont {
+    /// Place the glyphs of one line in visual order: bidi runs reordered,
+    /// each shaped with kerning and ligatures. Characters the font lacks come from a fallback font, or
+    /// become tofu when no font has them. Returns the glyphs with the line's
+    /// advance width.
+    pub fn place_line(&self, text: &str, px: f32) -> (Vec<PlacedGlyph>, f32) {
+        let placer = self.placer(text, px);
+        (placer.glyphs, placer.pen)
+    }
+
+    /// The grapheme clusters of one line in visual order, as placed by
+    /// [`ResolvedFont::place_line`].
+    pub fn clusters(&self, text: &str, px: f32) -> Vec<ClusterBox> {
+        self.placer(text, px).clusters
+    }
+
+    fn placer(&self, text: &str, px: f32) -> LinePlacer {
+        let mut placer = LinePlacer {
+            glyphs: Vec::with_capacity(text.len()),
+            clusters: Vec::new(),
+            pen: 0.0,
+            px,
+        };
+        let bidi = BidiInfo::new(text, None);
+        for paragraph in &bidi.paragraphs {
+            let (levels, runs) = bidi.visual_runs(paragraph, paragraph.range.clone());
+            for run in runs {
+                let rtl = levels[run.start].is_rtl();
+                let mut segments = self.segments(text, run);
+                if rtl {
+                    segments.reverse();
+                }
+                for segment in segments {
+                    placer.place(text, segment, rtl);
+                }
+            }
+        }
+        placer
+    }
+
+    /// Advance width of `text` on one line.
+    pub fn measure(&self, text: &str, px: f32) -> f32 {
+        self.place_line(text, px).1
+    }
+
+    /// Distance from the top of a line to its baseline.
+    pub fn ascent(&self, px: f32) -> f32 {
+        self.font.as_scaled(PxScale::from(px)).ascent()
+    }
+
+    /// Split `range` of `text` where the font drawing it changes, never
+    /// inside a grapheme cluster.
+    fn segments(&self, text: &str, range: Range<usize>) -> Vec<Segment> {
+        let mut segments: Vec<Segment> = Vec::new();
+        for (offset, cluster) in text[range.clone()].grapheme_indices(true) {
+            let start = range.start + offset;
+            let end = start + cluster.len();
+            let font = self.font_for(cluster);
+            match segments.last_mut() {
+                Some(last)
+                    if last.font.as_ref().map(|font| font.id)
+                        == font.as_ref().map(|font| font.id) =>
+                {
+                    last.range.end = end;
+                }
+                _ => segments.push(Segment {
+                    range: start..end,
+                    font,
+                }),
+            }
+        }
+        segments
+    }
+
+    /// The font for a grapheme cluster: this one when it has the base
+    /// character, otherwise a fallback.
+    fn font_for(&self, cluster: &str) -> Option<ResolvedFont> {
+        let Some(base) = cluster.chars().find(|ch| !is_invisible(*ch)) else {
+            return Some(self.clone());
+        };
+        if self.font.glyph_id(base) != TOFU {
+            return Some(self.clone());
+        }
+        fallback_for(base, self.id).or_else(|| base.is_whitespace().then(|| self.clone()))
+    }
+}
+
+impl LinePlacer {
+    /// Record the grapheme clusters of `text`, which starts at byte `start`
+    /// of the segment at `offset`, as drawn from the pen to `end`. Clusters
+    /// the shaper joined into one glyph, like ligatures, share its width.
+    fn push_clusters(&mut self, offset: usize, text: &str, start: usize, end: f32, rtl: bool) {
+        let mut graphemes: Vec<_> = text.grapheme_indices(true).collect();
+        if rtl {
+            graphemes.reverse();
+        }
+        let width = (end - self.pen) / graphemes.len().max(1) as f32;
+        for (slot, (at, cluster)) in graphemes.into_iter().enumerate() {
+            let at = offset + start + at;
+            self.clusters.push(ClusterBox {
+                range: at..at + cluster.len(),
+                x: self.pen + width * slot as f32,
+                width,
+                rtl,
+            });
+        }
+    }
+
+    /// Shape `segment` and append its glyphs after the ones placed so far.
+    fn place(&mut self, text: &str, segment: Segment, rtl: bool) {
+        let content = &text[segment.range.clone()];
+        let Some(font) = segment.font else {
+            let mut clusters: Vec<_> = content.grapheme_indices(true).collect();
+            if rtl {
+                clusters.reverse();
+            }
+            for (start, cluster) in clusters {
+                let start = segment.range.start + start;
+                self.glyphs.push(PlacedGlyph {
+                    font: FontId(0),
+                    id: TOFU,
+                    x: self.pen,
+                    y: 0.0,
+                });
+                self.clusters.push(ClusterBox {
+                    range: start..start + cluster.len(),
+                    x: self.pen,
+                    width: self.px * TOFU_ADVANCE,
+                    rtl,
+                });
+                self.pen += self.px * TOFU_ADVANCE;
+            }
+            return;
+        };
+
+        let mut buffer = UnicodeBuffer::new();
+        buffer.push_str(content);
+        buffer.set_direction(if rtl {
+            Direction::RightToLeft
+        } else {
+            Direction::LeftToRight
+        });
+        buffer.guess_segment_properties();
+        let shaped = rustybuzz::shape(font.face.get(), &[], buffer);
+        let scale = font.font.as_scaled(PxScale::from(self.px)).h_scale_factor();
+        let infos = shaped.glyph_infos();
+        let positions = shaped.glyph_positions();
+        let mut starts: Vec<usize> = infos.iter().map(|info| info.cluster as usize).collect();
+        starts.sort_unstable();
+        starts.dedup();
+
+        // Glyphs of one cluster are hidden or shown together, judged by the
+        // cluster's first character.
+        let mut index = 0;
+        while index < infos.len() {
+            let cluster = infos[index].cluster;
+            let end = index
+                + infos[index..]
+                    .iter()
+                    .take_while(|info| info.cluster == cluster)
+                    .count();
+            let mut x = self.pen;
+            // Shapers draw joiners and bidi controls as blank glyphs, and
+            // whitespace a font lacks as notdef; neither should show.
+            let first = content[cluster as usize..].chars().next().unwrap_or(' ');
+            for (info, position) in infos[index..end].iter().zip(&positions[index..end]) {
+                let id = GlyphId(info.glyph_id as u16);
+                let hidden = is_invisible(first) || (first.is_whitespace() && id == TOFU);
+                if !hidden {
+                    self.glyphs.push(PlacedGlyph {
+                        font: font.id,
+                        id,
+                        x: x + position.x_offset as f32 * scale,
+                        y: -position.y_offset as f32 * scale,
+                    });
+                }
+                x += position.x_advance as f32 * scale;
+            }
+            let start = cluster as usize;
+            let stop = starts
+                .iter()
+                .copied()
+                .find(|&next| next > start)
+                .unwrap_or(content.len());
+            self.push_clusters(segment.range.start, &content[start..stop], start, x, rtl);
+            self.pen = x;
+            index = end;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coverage_merges_code_points_into_ranges() {
+        let coverage = Coverage::from_codepoints(vec![0x63, 0x41, 0x42, 0x43, 0x61, 0x62, 0x42]);
+        assert_eq!(coverage, Coverage(vec![(0x41, 0x43), (0x61, 0x63)]));
+        assert!(coverage.contains('A') && coverage.contains('C') && coverage.contains('b'));
+        assert!(!coverage.contains('@') && !coverage.contains('D') && !coverage.contains('d'));
+        assert!(!Coverage::default().contains('A'));
+    }
+
+    #[test]
+    fn coverage_is_read_from_the_cmap() {
+        let coverage = Coverage::of_font(include_bytes!("../data/fonts/DejaVuSansMono.ttf"));
+        assert!(coverage.contains('M') && coverage.contains('\u{416}'));
+        assert!(!coverage.contains('\u{6F22}'));
+        assert_eq!(Coverage::of_font(b"not a font"), Coverage::default());
+    }
+
+    #[test]
+    fn re_registering_a_family_replaces_it_without_breaking_old_layouts() {
+        let bytes = include_bytes!("../data/fonts/DejaVuSansMono.ttf");
+        let path = std::path::Path::new("Mono.ttf");
+        let mut registry = FontRegistry::default();
+        registry.register(path, bytes.to_vec()).unwrap();
+        let old = registry.resolved(0);
+        let width = old.measure("wrap", 20.0);
+        assert!(width > 0.0);
+
+        registry.register(path, bytes.to_vec()).unwrap();
+        assert_eq!(registry.fonts.len(), 1);
+        // The replaced font's data lives on while something still uses it.
+        assert_eq!(old.measure("wrap", 20.0), width);
+        assert!(registry.register(path, b"not a font".to_vec()).is_err());
+    }
+}
@@ -0,0 +1,514 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::Deserialize;
+use winit::keyboard::{KeyCode, ModifiersState};
+
+use crate::command::Command;
+
+#[cfg(target_arch = "wasm32")]
+const KEYMAP_STORAGE_KEY: &str = "wcanvas_keymap";
+
+/// Key names accepted in bindings. The first name listed for a key is the
+/// one used when a binding is displayed.
+const KEY_NAMES: &[(&str, KeyCode)] = &[
+    ("A", KeyCode::KeyA),
+    ("B", KeyCode::KeyB),
+    ("C", KeyCode::KeyC),
+    ("D", KeyCode::KeyD),
+    ("E", KeyCode::KeyE),
+    ("F", KeyCode::KeyF),
+    ("G", KeyCode::KeyG),
+    ("H", KeyCode::KeyH),
+    ("I", KeyCode::KeyI),
+    ("J", KeyCode::KeyJ),
+    ("K", KeyCode::KeyK),
+    ("L", KeyCode::KeyL),
+    ("M", KeyCode::KeyM),
+    ("N", KeyCode::KeyN),
+    ("O", KeyCode::KeyO),
+    ("P", KeyCode::KeyP),
+    ("Q", KeyCode::KeyQ),
+    ("R", KeyCode::KeyR),
+    ("S", KeyCode::KeyS),
+    ("T", KeyCode::KeyT),
+    ("U", KeyCode::KeyU),
+    ("V", KeyCode::KeyV),
+    ("W", KeyCode::KeyW),
+    ("X", KeyCode::KeyX),
+    ("Y", KeyCode::KeyY),
+    ("Z", KeyCode::KeyZ),
+    ("0", KeyCode::Digit0),
+    ("1", KeyCode::Digit1),
+    ("2", KeyCode::Digit2),
+    ("3", KeyCode::Digit3),
+    ("4", KeyCode::Digit4),
+    ("5", KeyCode::Digit5),
+    ("6", KeyCode::Digit6),
+    ("7", KeyCode::Digit7),
+    ("8", KeyCode::Digit8),
+    ("9", KeyCode::Digit9),
+    ("Escape", KeyCode::Escape),
+    ("Esc", KeyCode::Escape),
+    ("Delete", KeyCode::Delete),
+    ("Backspace", KeyCode::Backspace),
+    ("Enter", KeyCode::Enter),
+    ("Tab", KeyCode::Tab),
+    ("Space", KeyCode::Space),
+    ("Left", KeyCode::ArrowLeft),
+    ("Right", KeyCode::ArrowRight),
+    ("Up", KeyCode::ArrowUp),
+    ("Down", KeyCode::ArrowDown),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("-", KeyCode::Minus),
+    ("Minus", KeyCode::Minus),
+    ("=", KeyCode::Equal),
+    ("Equal", KeyCode::Equal),
+    ("[", KeyCode::BracketLeft),
+    ("]", KeyCode::BracketRight),
+    ("'", KeyCode::Quote),
+    (";", KeyCode::Semicolon),
+    (",", KeyCode::Comma),
+    (".", KeyCode::Period),
+    ("/", KeyCode::Slash),
+    ("\\", KeyCode::Backslash),
+    ("`", KeyCode::Backquote),
+    ("F1", KeyCode::F1),
+    ("F2", KeyCode::F2),
+    ("F3", KeyCode::F3),
+    ("F4", KeyCode::F4),
+    ("F5", KeyCode::F5),
+    ("F6", KeyCode::F6),
+    ("F7", KeyCode::F7),
+    ("F8", KeyCode::F8),
+    ("F9", KeyCode::F9),
+    ("F10", KeyCode::F10),
+    ("F11", KeyCode::F11),
+    ("F12", KeyCode::F12),
+];
+
+/// One key press with the modifiers held. `primary` is Ctrl, or Cmd on
+/// macOS; the two are treated alike, so "Mod", "Ctrl" and "Cmd" all mean it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyCombo {
+    pub key: KeyCode,
+    pub primary: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl KeyCombo {
+    pub fn new(key: KeyCode, modifiers: ModifiersState) -> Self {
+        Self {
+            key,
+            primary: modifiers.control_key() || modifiers.super_key(),
+            shift: modifiers.shift_key(),
+            alt: modifiers.alt_key(),
+        }
+    }
+
+    /// Parse "Mod+Shift+Z" style text. Names are case-insensitive.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        let key = parts.pop()?;
+        let mut combo = Self {
+            key: KEY_NAMES
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(key))?
+                .1,
+            primary: false,
+            shift: false,
+            alt: false,
+        };
+        for modifier in parts {
+            match modifier.to_ascii_lowercase().as_str() {
+                "mod" | "ctrl" | "control" | "cmd" | "command" | "super" | "meta" => {
+                    combo.primary = true
+                }
+                "shift" => combo.shift = true,
+                "alt" | "option" | "opt" => combo.alt = true,
+                _ => return None,
+            }
+        }
+        Some(combo)
+    }
+}
+
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.primary {
+            f.write_str(if cfg!(target_os = "macos") {
+                "Cmd+"
+            } else {
+                "Ctrl+"
+            })?;
+        }
+        if self.alt {
+            f.write_str("Alt+")?;
+        }
+        if self.shift {
+            f.write_str("Shift+")?;
+        }
+        let name = KEY_NAMES
+            .iter()
+            .find(|(_, key)| *key == self.key)
+            .map_or("?", |(name, _)| name);
+        f.write_str(name)
+    }
+}
+
+/// The user keymap file: `{"bindings": {"Undo": ["Mod+Z"], "ToolPen": ["P"]}}`.
+/// Each listed command replaces its default bindings; an empty list unbinds
+/// it. A binding of several space-separated combos is a chord.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct KeymapConfig {
+    pub bindings: HashMap<Command, Vec<String>>,
+}
+
+#[derive(Debug)]
+pub enum KeymapError {
+    Parse(serde_json::Error),
+    InvalidBinding {
+        command: Command,
+        binding: String,
+    },
+    /// Two commands share a binding, or one binding begins with another so
+    /// the longer chord can never be reached.
+    Conflict {
+        first: (Command, String),
+        second: (Command, String),
+    },
+}
+
+impl fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeymapError::Parse(e) => write!(f, "invalid keymap file: {}", e),
+            KeymapError::InvalidBinding { command, binding } => {
+                write!(f, "invalid binding {:?} for {:?}", binding, command)
+            }
+            KeymapError::Conflict { first, second } => write!(
+                f,
+                "{:?} ({}) conflicts with {:?} ({})",
+                first.0, first.1, second.0, second.1
+            ),
+        }
+    }
+}
+
+/// What a key press resolved to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyPress {
+    Command(Command),
+    /// The press started or continued a chord; wait for the next one.
+    Pending,
+    Unbound,
+}
+
+struct Binding {
+    sequence: Vec<KeyCombo>,
+    command: Command,
+}
+
+/// Resolves key presses to commands, tracking partially typed chords.
+pub struct Keymap {
+    bindings: Vec<Binding>,
+    pending: Vec<KeyCombo>,
+}
+
+impl Keymap {
+    /// The default bindings merged with `config`, validated for conflicts.
+    pub fn new(config: &KeymapConfig) -> Result<Self, Vec<KeymapError>> {
+        let mut sources: Vec<(Command, String)> = default_bindings()
+            .iter()
+            .filter(|(command, _)| !config.bindings.contains_key(command))
+            .flat_map(|(command, bindings)| {
+                bindings
+                    .iter()
+                    .map(move |binding| (*command, binding.to_string()))
+            })
+            .collect();
+        for (command, bindings) in &config.bindings {
+            sources.extend(bindings.iter().map(|binding| (*command, binding.clone())));
+        }
+
+        let mut errors = Vec::new();
+        let mut parsed: Vec<(Binding, String)> = Vec::new();
+        for (command, text) in sources {
+            let sequence: Option<Vec<KeyCombo>> =
+                text.split_whitespace().map(KeyCombo::parse).collect();
+            match sequence {
+                Some(sequence) if !sequence.is_empty() => {
+                    parsed.push((Binding { sequence, command }, text))
+                }
+                _ => errors.push(KeymapError::InvalidBinding {
+                    command,
+                    binding: text,
+                }),
+            }
+        }
+
+        for (index, (a, a_text)) in parsed.iter().enumerate() {
+            for (b, b_text) in &parsed[index + 1..] {
+                let shared = a.sequence.len().min(b.sequence.len());
+                let same_command = a.command == b.command && a.sequence == b.sequence;
+                if a.sequence[..shared] == b.sequence[..shared] && !same_command {
+                    errors.push(KeymapError::Conflict {
+                        first: (a.command, a_text.clone()),
+                        second: (b.command, b_text.clone()),
+                    });
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(Self {
+            bindings: parsed.into_iter().map(|(binding, _)| binding).collect(),
+            pending: Vec::new(),
+        })
+    }
+
+    /// The user keymap from `~/.wcanvas/keymap.json` (native) or localStorage
+    /// (WASM). Any error is logged and the defaults are used instead.
+    pub fn load() -> Self {
+        #[cfg(target_arch = "wasm32")]
+        let json = crate::platform::load_from_local_storage(KEYMAP_STORAGE_KEY);
+        #[cfg(not(target_arch = "wasm32"))]
+        let json = crate::platform::keymap_path()
+            .ok()
+            .and_then(|path| std::fs::read_to_string(path).ok());
+
+        let config = match json.map(|json| serde_json::from_str(&json)) {
+            None => Ok(KeymapConfig::default()),
+            Some(result) => result.map_err(|e| vec![KeymapError::Parse(e)]),
+        };
+        match config.and_then(|config| Self::new(&config)) {
+            Ok(keymap) => keymap,
+            Err(errors) => {
+                for error in errors {
+                    log::warn!("Keymap: {}", error);
+                }
+                log::warn!("Keymap: falling back to the default bindings");
+                Self::new(&KeymapConfig::default()).expect("default keymap is valid")
+            }
+        }
+    }
+
+    /// The first binding of `command`, formatted for display.
+    pub fn shortcut(&self, command: Command) -> Option<String> {
+        let binding = self.bindings.iter().find(|b| b.command == command)?;
+        let combos: Vec<String> = binding.sequence.iter().map(|c| c.to_string()).collect();
+        Some(combos.join(" "))
+    }
+
+    /// Whether `combo` on its own triggers `command`.
+    pub fn is_bound(&self, command: Command, combo: KeyCombo) -> bool {
+        self.bindings
+            .iter()
+            .any(|b| b.command == command && b.sequence == [combo])
+    }
+
+    /// Feed one key press. A press that breaks off a chord is looked up again
+    /// on its own, so a stray first key never swallows the next shortcut.
+    /// Holding a modifier on its own is no step of a chord, so it neither
+    /// breaks off nor advances one.
+    pub fn press(&mut self, combo: KeyCombo) -> KeyPress {
+        if is_modifier(combo.key) {
+            return if self.pending.is_empty() {
+                KeyPress::Unbound
+            } else {
+                KeyPress::Pending
+            };
+        }
+        self.pending.push(combo);
+        if let Some(binding) = self.bindings.iter().find(|b| b.sequence == self.pending) {
+            self.pending.clear();
+            return KeyPress::Command(binding.command);
+        }
+        if self
+            .bindings
+            .iter()
+            .any(|b| b.sequence.starts_with(&self.pending))
+        {
+            return KeyPress::Pending;
+        }
+        let interrupted_chord = self.pending.len() > 1;
+        self.pending.clear();
+        if interrupted_chord {
+            self.press(combo)
+        } else {
+            KeyPress::Unbound
+        }
+    }
+}
+
+/// Whether `key` is a modifier key, which only ever changes other presses.
+fn is_modifier(key: KeyCode) -> bool {
+    matches!(
+        key,
+        KeyCode::ShiftLeft
+            | KeyCode::ShiftRight
+            | KeyCode::ControlLeft
+            | KeyCode::ControlRight
+            | KeyCode::AltLeft
+            | KeyCode::AltRight
+            | KeyCode::SuperLeft
+            | KeyCode::SuperRight
+    )
+}
+
+/// Built-in bindings. Digits follow the toolbar order.
+fn default_bindings() -> Vec<(Command, &'static [&'static str])> {
+    let redo: &'static [&'static str] = if cfg!(target_os = "macos") {
+        &["Mod+Shift+Z"]
+    } else {
+        &["Mod+Y", "Mod+Shift+Z"]
+    };
+    vec![
+        (Command::ClearSelection, &["Escape"]),
+        (Command::DeleteSelection, &["Delete", "Backspace"]),
+        (Command::ToggleFill, &["F"]),
+        (Command::ToggleShadow, &["H"]),
+        (Command::ToggleGlow, &["Shift+H"]),
+        (Command::CycleGradient, &["G"]),
+        (Command::SendBackward, &["["]),
+        (Command::BringForward, &["]"]),
+        (Command::SendToBack, &["Mod+["]),
+        (Command::BringToFront, &["Mod+]"]),
+        (Command::DecreaseStrokeWidth, &["Alt+["]),
+        (Command::IncreaseStrokeWidth, &["Alt+]"]),
+        (Command::DecreaseOpacity, &["Alt+Shift+["]),
+        (Command::IncreaseOpacity, &["Alt+Shift+]"]),
+        (Command::Duplicate, &["Mod+D"]),
+        (Command::Copy, &["Mod+C"]),
+        (Command::Paste, &["Mod+V"]),
+        (Command::CopyStyle, &["Mod+Alt+C"]),
+        (Command::PasteStyle, &["Mod+Alt+V"]),
+        (Command::Group, &["Mod+G"]),
+        (Command::Ungroup, &["Mod+Shift+G"]),
+        (Command::Undo, &["Mod+Z"]),
+        (Command::Redo, redo),
+        (Command::Save, &["Mod+S"]),
+        (Command::Open, &["Mod+O"]),
+        (Command::ZoomIn, &["Mod+="]),
+        (Command::ZoomOut, &["Mod+-"]),
+        (Command::CycleAntiAliasing, &["Alt+A"]),
+        (Command::CycleTheme, &["Alt+Shift+D"]),
+        (Command::ToggleGrid, &["Mod+'"]),
+        (Command::CycleGridStyle, &["Mod+Shift+'"]),
+        (Command::ToggleGridSnapping, &["Alt+Shift+S"]),
+        (Command::ToggleObjectSnapping, &["Alt+S"]),
+        (Command::AlignLeft, &["Alt+Left"]),
+        (
+            Command::AlignCenterHorizontal,
+            &["Alt+Shift+Left", "Alt+Shift+Right"],
+        ),
+        (Command::AlignRight, &["Alt+Right"]),
+        (Command::AlignTop, &["Alt+Up"]),
+        (
+            Command::AlignCenterVertical,
+            &["Alt+Shift+Up", "Alt+Shift+Down"],
+        ),
+        (Command::AlignBottom, &["Alt+Down"]),
+        (Command::DistributeHorizontalGaps, &["Alt+H"]),
+        (Command::DistributeVerticalGaps, &["Alt+V"]),
+        (Command::DistributeHorizontalCenters, &["Alt+Shift+H"]),
+        (Command::DistributeVerticalCenters, &["Alt+Shift+V"]),
+        (Command::TidyUp, &["Alt+T"]),
+        (Command::CycleAlignReference, &["Alt+K"]),
+        (Command::EditText, &["Enter"]),
+        (Command::TextAlignLeft, &["Mod+Shift+L"]),
+        (Command::TextAlignCenter, &["Mod+Shift+E"]),
+        (Command::TextAlignRight, &["Mod+Shift+R"]),
+        (Command::FindText, &["Mod+F"]),
+        (Command::ToolSelect, &["1"]),
+        (Command::ToolPen, &["2"]),
+        (Command::ToolRectangle, &["3"]),
+        (Command::ToolCircle, &["4"]),
+        (Command::ToolDiamond, &["5"]),
+        (Command::ToolArrow, &["6"]),
+        (Command::ToolText, &["7"]),
+        (Command::ToolLine, &["8"]),
+        (Command::ToolEraser, &["9"]),
+        (Command::ShowCommandPalette, &["Mod+K"]),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(json: &str) -> KeymapConfig {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn defaults_have_no_conflicts() {
+        assert!(Keymap::new(&KeymapConfig::default()).is_ok());
+    }
+
+    #[test]
+    fn parses_modifiers_in_any_case() {
+        let combo = KeyCombo::parse("ctrl+SHIFT+z").unwrap();
+        assert_eq!(combo.key, KeyCode::KeyZ);
+        assert!(combo.primary && combo.shift && !combo.alt);
+        assert!(KeyCombo::parse("Hyper+Z").is_none());
+        assert!(KeyCombo::parse("Mod+").is_none());
+    }
+
+    #[test]
+    fn resolves_chords() {
+        let mut keymap = Keymap::new(&config(r#"{"bindings": {"TidyUp": ["Mod+J T"]}}"#)).unwrap();
+        let mod_j = KeyCombo::parse("Mod+J").unwrap();
+        let t = KeyCombo::parse("T").unwrap();
+        assert_eq!(keymap.press(mod_j), KeyPress::Pending);
+        assert_eq!(keymap.press(t), KeyPress::Command(Command::TidyUp));
+        // Breaking off a chord looks the new key up on its own.
+        assert_eq!(keymap.press(mod_j), KeyPress::Pending);
+        assert_eq!(
+            keymap.press(KeyCombo::parse("F").unwrap()),
+            KeyPress::Command(Command::ToggleFill)
+        );
+    }
+
+    #[test]
+    fn modifier_keys_on_their_own_keep_a_chord_going() {
+        let mut keymap =
+            Keymap::new(&config(r#"{"bindings": {"TidyUp": ["Mod+J Shift+T"]}}"#)).unwrap();
+        let shift = KeyCombo {
+            key: KeyCode::ShiftLeft,
+            primary: false,
+            shift: true,
+            alt: false,
+        };
+        assert_eq!(keymap.press(shift), KeyPress::Unbound);
+        assert_eq!(
+            keymap.press(KeyCombo::parse("Mod+J").unwrap()),
+            KeyPress::Pending
+        );
+        assert_eq!(keymap.press(shift), KeyPress::Pending);
+        assert_eq!(
+            keymap.press(KeyCombo::parse("Shift+T").unwrap()),
+            KeyPress::Command(Command::TidyUp)
+        );
+    }
+
+    #[test]
+    fn rejects_conflicts() {
+        assert!(Keymap::new(&config(r#"{"bindings": {"Undo": ["F"]}}"#)).is_err());
+        assert!(Keymap::new(&config(r#"{"bindings": {"TidyUp": ["F T"]}}"#)).is_err());
+        // Rebinding the other command out of the way resolves it.
+        assert!(
+            Keymap::new(&config(
+                r#"{"bindings": {"Undo": ["F"], "ToggleFill": ["Shift+F"]}}"#
+            ))
+            .is_ok()
+        );
+    }
+}
@@ -1,17 +1,32 @@
 mod app;
 mod app_state;
+mod arrange;
 mod canvas;
+mod command;
 mod document;
 mod drawing;
+mod effects;
 mod event_handler;
+mod find;
+mod fonts;
 mod history;
+mod keymap;
 mod math;
+mod palette;
 mod platform;
+mod properties;
 mod renderer;
+mod rich_text;
 mod rough;
+mod snapping;
 mod state;
+mod styles;
+mod svg;
+mod text_edit;
+mod text_layout;
 mod text_renderer;
 mod texture;
+pub mod theme;
 mod ui;
 mod update_logic;
 pub mod vector;
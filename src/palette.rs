@@ -0,0 +1,264 @@
+use winit::event::KeyEvent;
+use winit::keyboard::KeyCode;
+
+use crate::app_state::State;
+use crate::command::Command;
+use crate::keymap::KeyCombo;
+
+/// How many commands run from the palette are remembered.
+const MAX_RECENT: usize = 5;
+/// Rows visible at once; the list scrolls to keep the selection in view.
+pub const PALETTE_ROWS: usize = 8;
+
+/// The Ctrl+K command palette: a query and the highlighted match.
+#[derive(Debug, Default)]
+pub struct CommandPalette {
+    pub open: bool,
+    pub query: String,
+    /// Index into the current matches.
+    pub selected: usize,
+    /// The query is the name for a new style preset rather than a search.
+    pub naming_preset: bool,
+}
+
+/// One row of the palette.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PaletteItem {
+    Command(Command),
+    /// Apply the style preset at this index.
+    ApplyPreset(usize),
+    /// Save the current style under the typed name.
+    SavePreset,
+}
+
+impl CommandPalette {
+    /// Index of the first visible match.
+    pub fn scroll(&self) -> usize {
+        (self.selected + 1).saturating_sub(PALETTE_ROWS)
+    }
+}
+
+impl State {
+    pub fn toggle_command_palette(&mut self) {
+        if self.palette.open {
+            self.palette.open = false;
+        } else {
+            self.find.open = false;
+            self.palette = CommandPalette {
+                open: true,
+                ..CommandPalette::default()
+            };
+        }
+    }
+
+    /// Open the palette to type a name for a new style preset.
+    pub fn prompt_style_preset_name(&mut self) {
+        self.palette = CommandPalette {
+            open: true,
+            naming_preset: true,
+            ..CommandPalette::default()
+        };
+    }
+
+    pub fn palette_item_title(&self, item: PaletteItem) -> String {
+        match item {
+            PaletteItem::Command(command) => command.title().to_string(),
+            PaletteItem::ApplyPreset(index) => self
+                .settings
+                .style_presets
+                .get(index)
+                .map_or_else(String::new, |preset| {
+                    format!("Apply style: {}", preset.name)
+                }),
+            PaletteItem::SavePreset => {
+                format!("Save style preset \"{}\"", self.palette.query.trim())
+            }
+        }
+    }
+
+    /// Items matching the query, best first. With an empty query recent
+    /// commands lead, then the rest in their usual order, then style presets.
+    pub fn palette_matches(&self) -> Vec<PaletteItem> {
+        if self.palette.naming_preset {
+            return if self.palette.query.trim().is_empty() {
+                Vec::new()
+            } else {
+                vec![PaletteItem::SavePreset]
+            };
+        }
+
+        let recent = &self.settings.recent_commands;
+        let listed = Command::ALL
+            .iter()
+            .copied()
+            .filter(|command| *command != Command::ShowCommandPalette);
+        let presets = (0..self.settings.style_presets.len()).map(PaletteItem::ApplyPreset);
+        if self.palette.query.trim().is_empty() {
+            let mut items: Vec<PaletteItem> =
+                recent.iter().copied().map(PaletteItem::Command).collect();
+            items.extend(
+                listed
+                    .filter(|command| !recent.contains(command))
+                    .map(PaletteItem::Command),
+            );
+            items.extend(presets);
+            return items;
+        }
+
+        let mut scored: Vec<(i32, PaletteItem)> = listed
+            .map(PaletteItem::Command)
+            .chain(presets)
+            .filter_map(|item| {
+                let score = fuzzy_score(&self.palette.query, &self.palette_item_title(item))?;
+                let recency = match item {
+                    PaletteItem::Command(command) => recent
+                        .iter()
+                        .position(|c| *c == command)
+                        .map_or(0, |rank| (MAX_RECENT - rank) as i32),
+                    _ => 0,
+                };
+                Some((score + recency, item))
+            })
+            .collect();
+        // Stable, so equal scores keep the usual order.
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored.into_iter().map(|(_, item)| item).collect()
+    }
+
+    /// Run the match at `index`, close the palette and remember the command.
+    pub fn run_palette_match(&mut self, index: usize) {
+        let Some(item) = self.palette_matches().get(index).copied() else {
+            return;
+        };
+        self.palette.open = false;
+        let command = match item {
+            PaletteItem::Command(command) => command,
+            PaletteItem::ApplyPreset(index) => return self.apply_style_preset(index),
+            PaletteItem::SavePreset => {
+                let name = std::mem::take(&mut self.palette.query);
+                return self.save_style_preset(&name);
+            }
+        };
+        let recent = &mut self.settings.recent_commands;
+        recent.retain(|c| *c != command);
+        recent.insert(0, command);
+        recent.truncate(MAX_RECENT);
+        self.save_settings();
+        self.run_command(command);
+    }
+
+    /// Keys while the palette is open: typing filters, arrows move, Enter
+    /// runs and Escape closes. Everything is swallowed.
+    pub fn handle_palette_key(&mut self, key_event: &KeyEvent, keycode: KeyCode) -> bool {
+        let count = self.palette_matches().len();
+        match keycode {
+            KeyCode::Escape => self.palette.open = false,
+            KeyCode::Enter => self.run_palette_match(self.palette.selected),
+            KeyCode::ArrowDown if count > 0 => {
+                self.palette.selected = (self.palette.selected + 1) % count;
+            }
+            KeyCode::ArrowUp if count > 0 => {
+                self.palette.selected = (self.palette.selected + count - 1) % count;
+            }
+            KeyCode::Backspace => {
+                self.palette.query.pop();
+                self.palette.selected = 0;
+            }
+            _ if self.keymap.is_bound(
+                Command::ShowCommandPalette,
+                KeyCombo::new(keycode, self.input.modifiers),
+            ) =>
+            {
+                self.palette.open = false;
+            }
+            _ => {
+                if let Some(text) = &key_event.text {
+                    let visible: String = text.chars().filter(|ch| !ch.is_control()).collect();
+                    if !visible.is_empty() {
+                        self.palette.query.push_str(&visible);
+                        self.palette.selected = 0;
+                    }
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Score `text` against `query` when every query character appears in it in
+/// order, ignoring case and spaces. Runs of adjacent characters and matches
+/// at word starts score higher; skipped characters cost a little.
+fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for wanted in query
+        .to_lowercase()
+        .chars()
+        .filter(|ch| !ch.is_whitespace())
+    {
+        let found = position + text[position..].iter().position(|ch| *ch == wanted)?;
+        score += 1;
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 8;
+        }
+        match previous {
+            Some(previous) if found == previous + 1 => score += 5,
+            Some(previous) => score -= (found - previous - 1).min(5) as i32,
+            None => score -= found.min(5) as i32,
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranked<'a>(query: &str, titles: &[&'a str]) -> Vec<&'a str> {
+        let mut scored: Vec<(i32, &str)> = titles
+            .iter()
+            .filter_map(|title| Some((fuzzy_score(query, title)?, *title)))
+            .collect();
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored.into_iter().map(|(_, title)| title).collect()
+    }
+
+    #[test]
+    fn scores_reward_word_starts_and_runs_and_charge_for_skips() {
+        assert_eq!(fuzzy_score("a", "a"), Some(9));
+        assert_eq!(fuzzy_score("ab", "ab"), Some(15));
+        assert_eq!(fuzzy_score("b", "a b"), Some(7));
+        assert_eq!(fuzzy_score("b", "aab"), Some(-1));
+        assert_eq!(fuzzy_score("b", "aaaaaaaab"), Some(-4));
+        assert_eq!(fuzzy_score("ba", "ab"), None);
+        assert_eq!(fuzzy_score("A B", "ab"), fuzzy_score("ab", "AB"));
+    }
+
+    #[test]
+    fn titles_rank_by_score_and_drop_non_matches() {
+        let titles = [
+            "Toggle grid",
+            "Cycle anti-aliasing",
+            "Align left",
+            "Distribute horizontal gaps",
+            "Tidy up",
+        ];
+        assert_eq!(
+            ranked("al", &titles),
+            [
+                "Align left",
+                "Distribute horizontal gaps",
+                "Cycle anti-aliasing"
+            ]
+        );
+        assert_eq!(
+            ranked("tu", &titles),
+            ["Tidy up", "Distribute horizontal gaps"]
+        );
+        assert!(ranked("xyz", &titles).is_empty());
+    }
+}
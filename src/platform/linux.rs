@@ -24,6 +24,57 @@ pub fn save_file_dialog(default_name: &str) -> FileDialogResult {
     FileDialogResult::Cancelled
 }
 
+pub fn open_url(url: &str) {
+    if let Err(e) = Command::new("xdg-open").arg(url).spawn() {
+        log::warn!("Failed to open {}: {}", url, e);
+    }
+}
+
+/// Try the Wayland tools first, then the X11 ones.
+const CLIPBOARD_WRITERS: &[&[&str]] = &[
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard"],
+    &["xsel", "--clipboard", "--input"],
+];
+const CLIPBOARD_READERS: &[&[&str]] = &[
+    &["wl-paste", "--no-newline"],
+    &["xclip", "-selection", "clipboard", "-o"],
+    &["xsel", "--clipboard", "--output"],
+];
+
+pub fn set_clipboard_text(text: &str) -> bool {
+    use std::io::Write;
+    use std::process::Stdio;
+    CLIPBOARD_WRITERS.iter().any(|args| {
+        // The tools keep serving the clipboard from a forked child, so only
+        // wait for the parent and leave the output pipes closed.
+        let Ok(mut child) = Command::new(args[0])
+            .args(&args[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            return false;
+        };
+        let written = child
+            .stdin
+            .take()
+            .is_some_and(|mut stdin| stdin.write_all(text.as_bytes()).is_ok());
+        child.wait().is_ok_and(|status| status.success()) && written
+    })
+}
+
+pub fn clipboard_text() -> Option<String> {
+    CLIPBOARD_READERS.iter().find_map(|args| {
+        let output = Command::new(args[0]).args(&args[1..]).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        String::from_utf8(output.stdout).ok()
+    })
+}
+
 fn try_zenity_open() -> Option<String> {
     let output = Command::new("zenity")
         .args([
@@ -42,3 +42,32 @@ pub fn save_file_dialog(default_name: &str) -> FileDialogResult {
         FileDialogResult::Cancelled
     }
 }
+
+pub fn open_url(url: &str) {
+    if let Err(e) = std::process::Command::new("open").arg(url).spawn() {
+        log::warn!("Failed to open {}: {}", url, e);
+    }
+}
+
+pub fn set_clipboard_text(text: &str) -> bool {
+    use std::io::Write;
+    let Ok(mut child) = std::process::Command::new("pbcopy")
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+    else {
+        return false;
+    };
+    let written = child
+        .stdin
+        .take()
+        .is_some_and(|mut stdin| stdin.write_all(text.as_bytes()).is_ok());
+    child.wait().is_ok_and(|status| status.success()) && written
+}
+
+pub fn clipboard_text() -> Option<String> {
+    let output = std::process::Command::new("pbpaste").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
@@ -56,6 +56,79 @@ pub fn save_file_dialog(default_name: &str) -> FileDialogResult {
     }
 }
 
+/// Open a link from a drawing in the default browser. Links without a
+/// scheme are taken as web addresses; schemes other than http, https and
+/// mailto are refused so a drawing can't launch arbitrary handlers.
+pub fn open_url(link: &str) {
+    let link = link.trim();
+    // A colon before any dot starts a scheme; `example.com:8080` has none.
+    let url = match link.split_once(':') {
+        Some((scheme, _)) if !scheme.contains('.') => {
+            if !["http", "https", "mailto"].contains(&scheme.to_ascii_lowercase().as_str()) {
+                log::warn!("Refusing to open link {:?}", link);
+                return;
+            }
+            link.to_string()
+        }
+        _ => format!("https://{}", link),
+    };
+    #[cfg(target_arch = "wasm32")]
+    {
+        wasm::open_url(&url);
+    }
+    #[cfg(target_os = "macos")]
+    {
+        macos::open_url(&url);
+    }
+    #[cfg(target_os = "linux")]
+    {
+        linux::open_url(&url);
+    }
+    #[cfg(not(any(target_os = "macos", target_os = "linux", target_arch = "wasm32")))]
+    {
+        log::warn!("Opening links is not supported on this platform: {}", url);
+    }
+}
+
+/// Put `text` on the system clipboard. Returns false when no clipboard
+/// could be reached.
+pub fn set_clipboard_text(text: &str) -> bool {
+    #[cfg(target_arch = "wasm32")]
+    {
+        wasm::set_clipboard_text(text)
+    }
+    #[cfg(target_os = "macos")]
+    {
+        macos::set_clipboard_text(text)
+    }
+    #[cfg(target_os = "linux")]
+    {
+        linux::set_clipboard_text(text)
+    }
+    #[cfg(not(any(target_os = "macos", target_os = "linux", target_arch = "wasm32")))]
+    {
+        let _ = text;
+        false
+    }
+}
+
+/// Text on the system clipboard, or `None` when it can't be read. The web
+/// only hands out the clipboard asynchronously, so it is never read there.
+pub fn clipboard_text() -> Option<String> {
+    #[cfg(target_os = "macos")]
+    {
+        macos::clipboard_text()
+    }
+    #[cfg(target_os = "linux")]
+    {
+        linux::clipboard_text()
+    }
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    {
+        None
+    }
+}
+
 /// Save document JSON to the filesystem (native only).
 #[cfg(not(target_arch = "wasm32"))]
 pub fn save_to_file(path: &str, content: &str) -> Result<(), std::io::Error> {
@@ -90,6 +163,43 @@ pub fn autosave_path() -> Result<std::path::PathBuf, std::io::Error> {
     Ok(autosave_dir()?.join("autosave.wcanvas"))
 }
 
+/// Get the settings file path, stored alongside the autosave.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn settings_path() -> Result<std::path::PathBuf, std::io::Error> {
+    Ok(autosave_dir()?.join("settings.json"))
+}
+
+/// Get the user keymap file path, stored alongside the settings.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn keymap_path() -> Result<std::path::PathBuf, std::io::Error> {
+    Ok(autosave_dir()?.join("keymap.json"))
+}
+
+/// Get the directory of user-supplied fonts, creating it if needed.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn fonts_dir() -> Result<std::path::PathBuf, std::io::Error> {
+    let dir = autosave_dir()?.join("fonts");
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Directories searched for fallback fonts, system-wide ones first.
+#[cfg(target_os = "linux")]
+pub fn system_font_dirs() -> Vec<std::path::PathBuf> {
+    let home = std::path::PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| ".".to_string()));
+    vec![
+        "/usr/share/fonts".into(),
+        "/usr/local/share/fonts".into(),
+        home.join(".local/share/fonts"),
+        home.join(".fonts"),
+    ]
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn system_font_dirs() -> Vec<std::path::PathBuf> {
+    Vec::new()
+}
+
 // WASM persistence functions
 #[cfg(target_arch = "wasm32")]
 pub fn save_to_local_storage(key: &str, json: &str) {
@@ -132,3 +132,20 @@ pub fn trigger_file_open(callback: impl FnOnce(String) + 'static) {
         let _ = body.remove_child(&input);
     }
 }
+
+pub fn open_url(url: &str) {
+    if let Some(window) = web_sys::window()
+        && let Err(e) = window.open_with_url_and_target(url, "_blank")
+    {
+        log::warn!("Failed to open {}: {:?}", url, e);
+    }
+}
+
+pub fn set_clipboard_text(text: &str) -> bool {
+    let Some(window) = web_sys::window() else {
+        return false;
+    };
+    // Resolves later; a refusal only leaves the app's own copy to paste.
+    let _ = window.navigator().clipboard().write_text(text);
+    true
+}
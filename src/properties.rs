@@ -0,0 +1,578 @@
+use rand::Rng;
+use winit::event::KeyEvent;
+use winit::keyboard::KeyCode;
+
+use crate::app_state::State;
+use crate::drawing::{DrawingElement, Element, ElementId, Roundness, Shadow, TextFormat};
+use crate::fonts::{self, FontFamily};
+use crate::history::Action;
+use crate::ui::{PropertiesView, PropertyControl, PropertyPart};
+use crate::vector::style::FillStyle;
+
+/// One row of the properties panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Property {
+    X,
+    Y,
+    Width,
+    Height,
+    StrokeWidth,
+    FontSize,
+    Font,
+    Opacity,
+    Roughness,
+    Bowing,
+    Seed,
+    Fill,
+    Shadow,
+    Rounded,
+}
+
+/// A property's value on one element.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PropertyValue {
+    Number(f32),
+    Flag(bool),
+    Seed(u64),
+    /// Index into [`fonts::families`].
+    Font(usize),
+}
+
+/// A panel row: the property and its value across the selection, `None`
+/// when the selected elements disagree.
+#[derive(Debug, Clone, Copy)]
+pub struct PropertyRow {
+    pub property: Property,
+    pub value: Option<PropertyValue>,
+}
+
+/// A numeric field being typed into.
+#[derive(Debug, Clone)]
+pub struct PropertyEdit {
+    pub property: Property,
+    pub text: String,
+}
+
+#[derive(Debug, Default)]
+pub struct PropertiesPanel {
+    pub editing: Option<PropertyEdit>,
+}
+
+impl Property {
+    /// Panel order.
+    const ALL: [Property; 14] = [
+        Property::X,
+        Property::Y,
+        Property::Width,
+        Property::Height,
+        Property::StrokeWidth,
+        Property::FontSize,
+        Property::Font,
+        Property::Opacity,
+        Property::Roughness,
+        Property::Bowing,
+        Property::Seed,
+        Property::Fill,
+        Property::Shadow,
+        Property::Rounded,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Property::X => "X",
+            Property::Y => "Y",
+            Property::Width => "Width",
+            Property::Height => "Height",
+            Property::StrokeWidth => "Stroke",
+            Property::FontSize => "Font size",
+            Property::Font => "Font",
+            Property::Opacity => "Opacity",
+            Property::Roughness => "Roughness",
+            Property::Bowing => "Bowing",
+            Property::Seed => "Seed",
+            Property::Fill => "Fill",
+            Property::Shadow => "Shadow",
+            Property::Rounded => "Rounded",
+        }
+    }
+
+    fn is_toggle(self) -> bool {
+        matches!(self, Property::Fill | Property::Shadow | Property::Rounded)
+    }
+
+    /// What the -/+ buttons change the stored value by.
+    fn step(self) -> f32 {
+        match self {
+            Property::StrokeWidth | Property::Roughness | Property::Bowing => 0.25,
+            Property::FontSize => 2.0,
+            Property::Opacity => 0.1,
+            _ => 1.0,
+        }
+    }
+
+    /// Opacity is stored as 0..1 but shown as a percentage.
+    fn display_scale(self) -> f32 {
+        match self {
+            Property::Opacity => 100.0,
+            _ => 1.0,
+        }
+    }
+
+    /// The stored value typed `text` stands for, if it is a finite number.
+    fn parse(self, text: &str) -> Option<f32> {
+        let value = text
+            .trim()
+            .parse::<f32>()
+            .ok()
+            .filter(|value| value.is_finite())?;
+        Some(value / self.display_scale())
+    }
+
+    fn read(self, element: &Element) -> Option<PropertyValue> {
+        let (min, max) = element.bounding_box();
+        let shape = &element.shape;
+        let number = match self {
+            Property::X => min[0],
+            Property::Y => min[1],
+            Property::Width => max[0] - min[0],
+            Property::Height => max[1] - min[1],
+            Property::StrokeWidth if !shape.is_text() => shape.stroke_width(),
+            Property::FontSize if shape.is_text() => shape.stroke_width(),
+            Property::Opacity => element.opacity,
+            Property::Roughness => shape.rough_style()?.roughness,
+            Property::Bowing => shape.rough_style()?.bowing,
+            Property::Seed => {
+                return Some(PropertyValue::Seed(shape.rough_style()?.seed.unwrap_or(0)));
+            }
+            Property::Fill => {
+                shape.is_closed().then_some(())?;
+                return Some(PropertyValue::Flag(shape.background().is_some()));
+            }
+            Property::Font => {
+                let font = shape.font()?;
+                let index = fonts::families().iter().position(|family| family == font);
+                return Some(PropertyValue::Font(index.unwrap_or(0)));
+            }
+            Property::Shadow => return Some(PropertyValue::Flag(element.effects.shadow.is_some())),
+            Property::Rounded => {
+                return Some(PropertyValue::Flag(shape.roundness()?.is_round()));
+            }
+            Property::StrokeWidth | Property::FontSize => return None,
+        };
+        Some(PropertyValue::Number(number))
+    }
+
+    /// Set a numeric property on one element; false when it doesn't apply.
+    fn write(self, element: &mut Element, value: f32) -> bool {
+        let bounds = element.bounding_box();
+        let (min, max) = bounds;
+        let shape = &mut element.shape;
+        match self {
+            Property::X => shape.translate_by(value - min[0], 0.0),
+            Property::Y => shape.translate_by(0.0, value - min[1]),
+            Property::Width => {
+                shape.resize_to_bounds(bounds, (min, [min[0] + value.max(1.0), max[1]]), false)
+            }
+            Property::Height => {
+                shape.resize_to_bounds(bounds, (min, [max[0], min[1] + value.max(1.0)]), false)
+            }
+            Property::StrokeWidth if !shape.is_text() => return shape.set_stroke_width(value),
+            Property::FontSize if shape.is_text() => return shape.set_stroke_width(value),
+            Property::Opacity => element.opacity = value.clamp(0.1, 1.0),
+            Property::Roughness | Property::Bowing => {
+                let Some(rough) = shape.rough_style_mut() else {
+                    return false;
+                };
+                if self == Property::Roughness {
+                    rough.roughness = value.clamp(0.0, 10.0);
+                } else {
+                    rough.bowing = value.clamp(0.0, 10.0);
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl PropertyValue {
+    pub fn display(self, property: Property) -> String {
+        match self {
+            PropertyValue::Number(value) => format_number(value * property.display_scale()),
+            PropertyValue::Flag(on) => if on { "On" } else { "Off" }.to_string(),
+            PropertyValue::Seed(seed) => seed.to_string(),
+            PropertyValue::Font(index) => fonts::families()
+                .get(index)
+                .map(|family| family.name().to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl PropertyRow {
+    fn control(&self) -> PropertyControl {
+        match self.value {
+            Some(PropertyValue::Flag(on)) => PropertyControl::Toggle { on: Some(on) },
+            None if self.property.is_toggle() => PropertyControl::Toggle { on: None },
+            _ if self.property == Property::Seed => PropertyControl::Reroll,
+            _ if self.property == Property::Font => PropertyControl::Choice,
+            _ => PropertyControl::Number,
+        }
+    }
+}
+
+impl State {
+    /// Rows for every property that applies to at least one selected element.
+    pub fn property_rows(&self) -> Vec<PropertyRow> {
+        let selected: Vec<&Element> = self
+            .elements
+            .iter()
+            .filter(|element| self.input.selection.is_selected(element.id))
+            .collect();
+        rows(&selected)
+    }
+
+    /// What the panel should draw, or `None` when nothing is selected.
+    pub fn properties_view(&self, rows: &[PropertyRow]) -> Option<PropertiesView> {
+        if rows.is_empty() || self.typing.active {
+            return None;
+        }
+        let editing = self
+            .properties
+            .editing
+            .as_ref()
+            .and_then(|edit| rows.iter().position(|row| row.property == edit.property));
+        Some(PropertiesView {
+            rows: rows.iter().map(PropertyRow::control).collect(),
+            editing,
+        })
+    }
+
+    /// React to a click on part of panel row `row`.
+    pub fn click_property(&mut self, row: usize, part: PropertyPart) {
+        self.commit_property_edit();
+        let rows = self.property_rows();
+        let Some(&PropertyRow { property, value }) = rows.get(row) else {
+            return;
+        };
+        match (part, value) {
+            (PropertyPart::Value, Some(PropertyValue::Flag(on))) => {
+                self.set_property_flag(property, !on)
+            }
+            (PropertyPart::Value, None) if property.is_toggle() => {
+                self.set_property_flag(property, true)
+            }
+            (PropertyPart::Value | PropertyPart::Increase, _) if property == Property::Font => {
+                self.step_font(value, 1)
+            }
+            (PropertyPart::Decrease, _) if property == Property::Font => self.step_font(value, -1),
+            (PropertyPart::Value, value) => {
+                self.properties.editing = Some(PropertyEdit {
+                    property,
+                    text: value.map(|v| v.display(property)).unwrap_or_default(),
+                });
+            }
+            (PropertyPart::Increase, _) if property == Property::Seed => self.reroll_seeds(),
+            (PropertyPart::Decrease, _) => self.step_property(property, -property.step()),
+            (PropertyPart::Increase, _) => self.step_property(property, property.step()),
+        }
+    }
+
+    /// Keys while a field is being typed into: digits and signs edit, Enter
+    /// or Tab applies (Tab moves on to the next numeric field), Escape cancels.
+    pub fn handle_property_key(&mut self, key_event: &KeyEvent, keycode: KeyCode) -> bool {
+        let Some(edit) = self.properties.editing.as_mut() else {
+            return false;
+        };
+        match keycode {
+            KeyCode::Escape => self.properties.editing = None,
+            KeyCode::Enter | KeyCode::NumpadEnter => self.commit_property_edit(),
+            KeyCode::Backspace => {
+                edit.text.pop();
+            }
+            KeyCode::Tab => {
+                let current = edit.property;
+                self.commit_property_edit();
+                let rows = self.property_rows();
+                let next = rows
+                    .iter()
+                    .skip_while(|row| row.property != current)
+                    .skip(1)
+                    .find(|row| {
+                        matches!(
+                            row.control(),
+                            PropertyControl::Number | PropertyControl::Reroll
+                        )
+                    });
+                if let Some(row) = next {
+                    self.properties.editing = Some(PropertyEdit {
+                        property: row.property,
+                        text: row
+                            .value
+                            .map(|v| v.display(row.property))
+                            .unwrap_or_default(),
+                    });
+                }
+            }
+            _ => {
+                if let Some(text) = &key_event.text {
+                    edit.text.extend(
+                        text.chars()
+                            .filter(|ch| ch.is_ascii_digit() || *ch == '.' || *ch == '-'),
+                    );
+                }
+            }
+        }
+        true
+    }
+
+    /// Apply the field being typed into, if it parses.
+    pub fn commit_property_edit(&mut self) {
+        let Some(edit) = self.properties.editing.take() else {
+            return;
+        };
+        if edit.property == Property::Seed {
+            if let Ok(seed) = edit.text.trim().parse::<u64>() {
+                self.modify_selection(|element| match element.shape.rough_style_mut() {
+                    Some(rough) => {
+                        rough.seed = Some(seed);
+                        true
+                    }
+                    None => false,
+                });
+            }
+            return;
+        }
+        if let Some(value) = edit.property.parse(&edit.text) {
+            self.modify_selection(|element| edit.property.write(element, value));
+        }
+    }
+
+    fn step_property(&mut self, property: Property, delta: f32) {
+        self.modify_selection(|element| match property.read(element) {
+            Some(PropertyValue::Number(value)) => property.write(element, value + delta),
+            _ => false,
+        });
+    }
+
+    /// Move the selection's font `delta` places along the family list; a
+    /// mixed selection starts from the first family.
+    fn step_font(&mut self, value: Option<PropertyValue>, delta: isize) {
+        let families = fonts::families();
+        let current = match value {
+            Some(PropertyValue::Font(index)) => index as isize,
+            _ => -delta.signum(),
+        };
+        let next = (current + delta).rem_euclid(families.len() as isize) as usize;
+        self.set_selection_font(families[next].clone());
+    }
+
+    /// Give every selected text element `family` and refit it.
+    pub fn set_selection_font(&mut self, family: FontFamily) {
+        self.modify_selection(|element| element.shape.set_font(family.clone()));
+    }
+
+    fn set_property_flag(&mut self, property: Property, on: bool) {
+        let fallback = self.style.background;
+        self.modify_selection(|element| match property {
+            Property::Fill => {
+                if element.shape.background().is_some() == on {
+                    return false;
+                }
+                let fill = on.then(|| FillStyle::Solid(fallback.unwrap_or(element.shape.color())));
+                element.shape.set_background(fill)
+            }
+            Property::Shadow => {
+                element.effects.shadow = on.then(Shadow::default);
+                true
+            }
+            Property::Rounded => element.shape.set_roundness(if on {
+                Roundness::Adaptive
+            } else {
+                Roundness::Sharp
+            }),
+            _ => false,
+        });
+    }
+
+    /// Give every selected rough shape a fresh random seed.
+    fn reroll_seeds(&mut self) {
+        let mut rng = rand::rng();
+        self.modify_selection(|element| match element.shape.rough_style_mut() {
+            Some(rough) => {
+                rough.seed = Some(rng.random::<u64>());
+                true
+            }
+            None => false,
+        });
+    }
+
+    /// Change the layout of every selected text box and refit it.
+    pub fn update_text_format(&mut self, update: impl Fn(&mut TextFormat)) {
+        self.modify_selection(|element| {
+            let DrawingElement::TextBox { format, .. } = &mut element.shape else {
+                return false;
+            };
+            update(format);
+            element.shape.refit_text();
+            true
+        });
+    }
+
+    /// Run `update` on each selected element and record the changes as one
+    /// property edit.
+    fn modify_selection(&mut self, mut update: impl FnMut(&mut Element) -> bool) {
+        let ids: Vec<ElementId> = self.input.selection.selected_ids.clone();
+        let before = self.snapshot_elements(&ids);
+        let mut changed = false;
+        for id in &ids {
+            if let Some(element) = self.find_element_mut_by_id(*id) {
+                changed |= update(element);
+            }
+        }
+        let after = self.snapshot_elements(&ids);
+        if changed && before != after {
+            self.record_action(Action::ModifyProperty { before, after });
+        }
+    }
+}
+
+/// Panel rows for `selected`: each property that applies to any of them,
+/// with its value when they all agree.
+fn rows(selected: &[&Element]) -> Vec<PropertyRow> {
+    Property::ALL
+        .iter()
+        .filter_map(|&property| {
+            let mut values = selected.iter().filter_map(|element| property.read(element));
+            let first = values.next()?;
+            let same = values.all(|value| same_value(value, first));
+            Some(PropertyRow {
+                property,
+                value: same.then_some(first),
+            })
+        })
+        .collect()
+}
+
+/// Values closer than this count as equal, so float noise isn't "mixed".
+fn same_value(a: PropertyValue, b: PropertyValue) -> bool {
+    match (a, b) {
+        (PropertyValue::Number(a), PropertyValue::Number(b)) => (a - b).abs() < 0.01,
+        _ => a == b,
+    }
+}
+
+/// Up to two decimals, without trailing zeros.
+fn format_number(value: f32) -> String {
+    let text = format!("{:.2}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" {
+        "0".to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A rectangle whose bounds, with the stroke padding, start at `x`, `y`.
+    fn rect(x: f32, y: f32, stroke_width: f32) -> Element {
+        let padding = stroke_width + 4.0;
+        Element::new(DrawingElement::Rectangle {
+            position: [x + padding, y + padding],
+            size: [100.0, 50.0],
+            color: [0.0, 0.0, 0.0, 1.0],
+            background: None,
+            stroke_width,
+            rough_style: Some(crate::rough::RoughOptions::default()),
+            roundness: Roundness::Sharp,
+        })
+    }
+
+    fn value(rows: &[PropertyRow], property: Property) -> Option<PropertyValue> {
+        rows.iter().find(|row| row.property == property)?.value
+    }
+
+    #[test]
+    fn numbers_show_at_most_two_decimals_without_trailing_zeros() {
+        assert_eq!(format_number(12.0), "12");
+        assert_eq!(format_number(1.5), "1.5");
+        assert_eq!(format_number(0.126), "0.13");
+        assert_eq!(format_number(-0.001), "0");
+        assert_eq!(format_number(-2.25), "-2.25");
+    }
+
+    #[test]
+    fn values_within_float_noise_are_the_same() {
+        let number = PropertyValue::Number;
+        assert!(same_value(number(1.0), number(1.004)));
+        assert!(!same_value(number(1.0), number(1.02)));
+        assert!(same_value(
+            PropertyValue::Flag(true),
+            PropertyValue::Flag(true)
+        ));
+        assert!(!same_value(PropertyValue::Seed(1), PropertyValue::Seed(2)));
+        assert!(!same_value(number(1.0), PropertyValue::Flag(true)));
+    }
+
+    #[test]
+    fn a_selection_shows_shared_values_and_mixed_ones_as_none() {
+        let a = rect(10.0, 20.0, 2.0);
+        let b = rect(10.0, 80.0, 4.0);
+        let rows = rows(&[&a, &b]);
+
+        assert_eq!(value(&rows, Property::X), Some(PropertyValue::Number(10.0)));
+        assert_eq!(value(&rows, Property::Y), None);
+        assert_eq!(value(&rows, Property::StrokeWidth), None);
+        assert_eq!(
+            value(&rows, Property::Fill),
+            Some(PropertyValue::Flag(false))
+        );
+        // Rows that apply to no selected element are left out altogether.
+        assert!(!rows.iter().any(|row| row.property == Property::FontSize));
+        assert!(super::rows(&[]).is_empty());
+    }
+
+    #[test]
+    fn writes_move_resize_and_clamp_out_of_range_values() {
+        let mut element = rect(0.0, 0.0, 2.0);
+        assert!(Property::X.write(&mut element, 30.0));
+        assert_eq!(element.bounding_box().0[0], 30.0);
+
+        assert!(Property::Width.write(&mut element, -5.0));
+        let (min, max) = element.bounding_box();
+        assert!(max[0] - min[0] >= 1.0);
+
+        assert!(Property::Opacity.write(&mut element, 5.0));
+        assert_eq!(element.opacity, 1.0);
+        assert!(Property::Opacity.write(&mut element, -1.0));
+        assert_eq!(element.opacity, 0.1);
+
+        assert!(Property::Roughness.write(&mut element, 25.0));
+        assert_eq!(element.shape.rough_style().unwrap().roughness, 10.0);
+
+        // A rectangle has no font size, and toggles aren't numbers.
+        assert!(!Property::FontSize.write(&mut element, 20.0));
+        assert!(!Property::Fill.write(&mut element, 1.0));
+    }
+
+    #[test]
+    fn opacity_shows_and_parses_as_a_percentage() {
+        let mut element = rect(0.0, 0.0, 2.0);
+        element.opacity = 0.5;
+        let shown = Property::Opacity.read(&element).unwrap();
+        assert_eq!(shown.display(Property::Opacity), "50");
+        assert_eq!(Property::Opacity.parse(" 75 "), Some(0.75));
+        assert_eq!(Property::X.parse("75"), Some(75.0));
+    }
+
+    #[test]
+    fn typed_text_that_is_not_a_number_is_rejected() {
+        assert_eq!(Property::Width.parse("wide"), None);
+        assert_eq!(Property::Width.parse(""), None);
+        assert_eq!(Property::Width.parse("NaN"), None);
+        assert_eq!(Property::Width.parse("inf"), None);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::app_state::State;
+use crate::state::RunPipeline;
 
 impl State {
     pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
@@ -14,52 +15,87 @@ impl State {
                 label: Some("Render Encoder"),
             });
 
+        self.effect_renderer
+            .render_targets(&mut encoder, &self.text_renderer);
+
+        // With MSAA everything draws into the multisampled target, and each pass
+        // resolves it into the surface so the frame is complete whichever runs last.
+        let (target, resolve_target) = match &self.gpu.msaa_texture {
+            Some(msaa) => (&msaa.view, Some(&view)),
+            None => (&view, None),
+        };
+
+        let background = self.theme().canvas_background;
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
+                    view: target,
+                    resolve_target,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 1.0,
-                            g: 1.0,
-                            b: 1.0,
-                            a: 1.0,
+                            r: background[0] as f64,
+                            g: background[1] as f64,
+                            b: background[2] as f64,
+                            a: background[3] as f64,
                         }),
                         store: wgpu::StoreOp::Store,
                     },
                 })],
-                depth_stencil_attachment: None,
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.gpu.depth_texture.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(0.0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }),
                 occlusion_query_set: None,
                 timestamp_writes: None,
             });
 
-            // Pass 1: Tessellated geometry (rough shapes, strokes, selection highlights)
-            render_pass.set_pipeline(&self.gpu.render_pipeline);
-            render_pass.set_bind_group(0, &self.canvas.uniform_bind_group, &[]);
-
-            if let (Some(vertex_buffer), Some(index_buffer)) =
-                (&self.geometry.vertex, &self.geometry.index)
-            {
-                render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-                render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-                render_pass.draw_indexed(0..self.geometry.count, 0, 0..1);
-            }
-
-            // Pass 2: SDF vector shapes (clean geometric shapes — resolution-independent)
-            render_pass.set_pipeline(&self.gpu.sdf_render_pipeline);
-            render_pass.set_bind_group(0, &self.canvas.uniform_bind_group, &[]);
-
-            if let (Some(sdf_vertex_buffer), Some(sdf_index_buffer)) =
-                (&self.sdf_geo.vertex, &self.sdf_geo.index)
-            {
-                render_pass.set_vertex_buffer(0, sdf_vertex_buffer.slice(..));
-                render_pass.set_index_buffer(sdf_index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-                render_pass.draw_indexed(0..self.sdf_geo.count, 0, 0..1);
+            // Canvas content: tessellated geometry (rough shapes, strokes, overlays) and SDF
+            // vector shapes, replayed run by run so both keep the elements' z-order.
+            for run in &self.draw_runs {
+                match run.pipeline {
+                    RunPipeline::Geometry => {
+                        let (Some(vertex_buffer), Some(index_buffer)) =
+                            (&self.geometry.vertex, &self.geometry.index)
+                        else {
+                            continue;
+                        };
+                        render_pass.set_pipeline(&self.gpu.render_pipeline);
+                        render_pass.set_bind_group(0, &self.canvas.uniform_bind_group, &[]);
+                        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                        render_pass
+                            .set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                    }
+                    RunPipeline::Sdf => {
+                        let (Some(vertex_buffer), Some(index_buffer)) =
+                            (&self.sdf_geo.vertex, &self.sdf_geo.index)
+                        else {
+                            continue;
+                        };
+                        render_pass.set_pipeline(&self.gpu.sdf_render_pipeline);
+                        render_pass.set_bind_group(0, &self.canvas.uniform_bind_group, &[]);
+                        render_pass.set_bind_group(1, &self.sdf_geo.paint_bind_group, &[]);
+                        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                        render_pass
+                            .set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+                    }
+                    RunPipeline::Effect(_) => {
+                        self.effect_renderer.draw(
+                            &mut render_pass,
+                            &self.canvas.uniform_bind_group,
+                            run.indices.clone(),
+                        );
+                        continue;
+                    }
+                }
+                render_pass.draw_indexed(run.indices.clone(), 0, 0..1);
             }
 
-            // Pass 3: UI elements (toolbar, color palette)
+            // UI elements (toolbar, color palette)
             render_pass.set_pipeline(&self.gpu.ui_render_pipeline);
             render_pass.set_bind_group(0, &self.ui_screen.bind_group, &[]);
 
@@ -74,7 +110,8 @@ impl State {
 
         self.text_renderer.draw(
             &mut encoder,
-            &view,
+            target,
+            resolve_target,
             &self.canvas.uniform_bind_group,
             &self.ui_screen.bind_group,
         );
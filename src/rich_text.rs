@@ -0,0 +1,434 @@
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::fonts::FontFamily;
+
+/// Color of links that don't set their own.
+pub const LINK_COLOR: [f32; 4] = [0.15, 0.4, 0.9, 1.0];
+
+/// Character formatting of part of a text box.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpanStyle {
+    pub bold: bool,
+    pub italic: bool,
+    /// Drawn in the monospace code font.
+    pub code: bool,
+    pub strikethrough: bool,
+    /// Overrides the box's color.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<[f32; 4]>,
+    /// Target opened when the text is clicked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+}
+
+/// Formatting over a byte range of a text box's content. A box's spans are
+/// sorted, don't overlap and never hold the default style.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextSpan {
+    pub range: Range<usize>,
+    pub style: SpanStyle,
+}
+
+impl SpanStyle {
+    pub fn is_plain(&self) -> bool {
+        *self == SpanStyle::default()
+    }
+
+    /// The color text in this style is drawn in, given the box's color.
+    pub fn color_or(&self, color: [f32; 4]) -> [f32; 4] {
+        match (self.color, &self.link) {
+            (Some(color), _) => color,
+            (None, Some(_)) => LINK_COLOR,
+            (None, None) => color,
+        }
+    }
+}
+
+/// A run of the content and its style, `None` for plain text.
+pub type Piece<'a> = (Range<usize>, Option<&'a SpanStyle>);
+
+/// Split `range` of the content at span edges.
+pub fn pieces(spans: &[TextSpan], range: Range<usize>) -> Vec<Piece<'_>> {
+    let mut pieces = Vec::new();
+    let mut at = range.start;
+    for span in spans {
+        if span.range.end <= at {
+            continue;
+        }
+        if span.range.start >= range.end {
+            break;
+        }
+        if span.range.start > at {
+            pieces.push((at..span.range.start, None));
+        }
+        let end = span.range.end.min(range.end);
+        pieces.push((at.max(span.range.start)..end, Some(&span.style)));
+        at = end;
+    }
+    if at < range.end {
+        pieces.push((at..range.end, None));
+    }
+    pieces
+}
+
+/// The style text typed at `index` continues: that of the character before
+/// it, minus any link.
+pub fn style_at(spans: &[TextSpan], index: usize) -> SpanStyle {
+    let style = spans
+        .iter()
+        .find(|span| span.range.start < index && index <= span.range.end)
+        .map(|span| span.style.clone())
+        .unwrap_or_default();
+    SpanStyle {
+        link: None,
+        ..style
+    }
+}
+
+/// Make room for `len` bytes inserted at `at`, styled `style`.
+pub fn insert(spans: &mut Vec<TextSpan>, at: usize, len: usize, style: &SpanStyle) {
+    let mut shifted = Vec::with_capacity(spans.len() + 2);
+    for span in spans.drain(..) {
+        let Range { start, end } = span.range;
+        if end <= at {
+            shifted.push(span);
+        } else if start >= at {
+            shifted.push(TextSpan {
+                range: start + len..end + len,
+                ..span
+            });
+        } else {
+            shifted.push(TextSpan {
+                range: start..at,
+                style: span.style.clone(),
+            });
+            shifted.push(TextSpan {
+                range: at + len..end + len,
+                ..span
+            });
+        }
+    }
+    shifted.push(TextSpan {
+        range: at..at + len,
+        style: style.clone(),
+    });
+    *spans = shifted;
+    normalize(spans);
+}
+
+/// Drop `range` of the content from the spans.
+pub fn remove(spans: &mut Vec<TextSpan>, range: Range<usize>) {
+    let len = range.end - range.start;
+    let map = |index: usize| {
+        if index <= range.start {
+            index
+        } else if index >= range.end {
+            index - len
+        } else {
+            range.start
+        }
+    };
+    for span in spans.iter_mut() {
+        span.range = map(span.range.start)..map(span.range.end);
+    }
+    normalize(spans);
+}
+
+/// Restyle `range` of the content with `update`.
+pub fn set_style(spans: &mut Vec<TextSpan>, range: Range<usize>, update: impl Fn(&mut SpanStyle)) {
+    if range.is_empty() {
+        return;
+    }
+    let inside: Vec<(Range<usize>, SpanStyle)> = pieces(spans, range.clone())
+        .into_iter()
+        .map(|(piece, style)| (piece, style.cloned().unwrap_or_default()))
+        .collect();
+    let mut restyled: Vec<TextSpan> = Vec::with_capacity(spans.len() + inside.len());
+    for span in spans.drain(..) {
+        let Range { start, end } = span.range;
+        if start < range.start {
+            restyled.push(TextSpan {
+                range: start..end.min(range.start),
+                style: span.style.clone(),
+            });
+        }
+        if end > range.end {
+            restyled.push(TextSpan {
+                range: start.max(range.end)..end,
+                style: span.style,
+            });
+        }
+    }
+    for (piece, mut style) in inside {
+        update(&mut style);
+        restyled.push(TextSpan {
+            range: piece,
+            style,
+        });
+    }
+    *spans = restyled;
+    normalize(spans);
+}
+
+/// Whether every character of `range` has a style `test` accepts.
+pub fn all_styled(
+    spans: &[TextSpan],
+    range: Range<usize>,
+    test: impl Fn(&SpanStyle) -> bool,
+) -> bool {
+    !range.is_empty()
+        && pieces(spans, range)
+            .into_iter()
+            .all(|(_, style)| style.is_some_and(&test))
+}
+
+/// The spans over `range`, measured from its start, for copying.
+pub fn slice(spans: &[TextSpan], range: Range<usize>) -> Vec<TextSpan> {
+    pieces(spans, range.clone())
+        .into_iter()
+        .filter_map(|(piece, style)| {
+            Some(TextSpan {
+                range: piece.start - range.start..piece.end - range.start,
+                style: style?.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Make room for `len` pasted bytes at `at`, formatted by `pasted`, whose
+/// ranges are measured from the start of the pasted text.
+pub fn insert_spans(spans: &mut Vec<TextSpan>, at: usize, len: usize, pasted: &[TextSpan]) {
+    insert(spans, at, len, &SpanStyle::default());
+    for span in pasted {
+        let range = at + span.range.start..at + span.range.end.min(len);
+        set_style(spans, range, |style| *style = span.style.clone());
+    }
+}
+
+type Restyle = fn(&mut SpanStyle);
+
+/// Turn a markdown-style shortcut just closed at `cursor` into formatting:
+/// `**bold**`, `*italic*` or `_italic_`, `` `code` ``, `~~struck~~` and
+/// `[label](url)`. The markers are removed and `cursor` moved to match.
+pub fn apply_shortcut(content: &mut String, spans: &mut Vec<TextSpan>, cursor: &mut usize) -> bool {
+    let shortcuts: [(&str, Restyle); 5] = [
+        ("**", |style| style.bold = true),
+        ("~~", |style| style.strikethrough = true),
+        ("*", |style| style.italic = true),
+        ("_", |style| style.italic = true),
+        ("`", |style| style.code = true),
+    ];
+    if apply_link(content, spans, cursor) {
+        return true;
+    }
+    shortcuts
+        .into_iter()
+        .any(|(marker, update)| apply_wrapped(content, spans, cursor, marker, update))
+}
+
+/// `marker` + text + `marker`, closed at `cursor`, on one line.
+fn apply_wrapped(
+    content: &mut String,
+    spans: &mut Vec<TextSpan>,
+    cursor: &mut usize,
+    marker: &str,
+    update: Restyle,
+) -> bool {
+    let before = &content[..*cursor];
+    let Some(inner_end) = before.strip_suffix(marker).map(str::len) else {
+        return false;
+    };
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    let Some(open) = content[line_start..inner_end].rfind(marker) else {
+        return false;
+    };
+    let open = line_start + open;
+    let inner = &content[open + marker.len()..inner_end];
+    let preceding = content[..open].chars().next_back();
+    let doubled = |ch: Option<char>| ch.is_some_and(|ch| marker.starts_with(ch));
+    // The opening marker has to start a word, a single marker mustn't be
+    // half of a double one, and the wrapped text may not start or end with
+    // a space.
+    if inner.is_empty()
+        || inner.starts_with(' ')
+        || inner.ends_with(' ')
+        || preceding.is_some_and(char::is_alphanumeric)
+        || (marker.len() == 1 && (doubled(preceding) || doubled(inner.chars().next_back())))
+    {
+        return false;
+    }
+    remove_text(content, spans, inner_end..*cursor);
+    remove_text(content, spans, open..open + marker.len());
+    let end = inner_end - marker.len();
+    set_style(spans, open..end, update);
+    *cursor = end;
+    true
+}
+
+/// `[label](url)` closed at `cursor`.
+fn apply_link(content: &mut String, spans: &mut Vec<TextSpan>, cursor: &mut usize) -> bool {
+    let before = &content[..*cursor];
+    if !before.ends_with(')') {
+        return false;
+    }
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    let Some(middle) = before[line_start..]
+        .rfind("](")
+        .map(|index| line_start + index)
+    else {
+        return false;
+    };
+    let Some(open) = before[line_start..middle]
+        .rfind('[')
+        .map(|index| line_start + index)
+    else {
+        return false;
+    };
+    let url = before[middle + 2..*cursor - 1].trim().to_string();
+    if url.is_empty() || url.contains(' ') || middle == open + 1 {
+        return false;
+    }
+    remove_text(content, spans, middle..*cursor);
+    remove_text(content, spans, open..open + 1);
+    let end = middle - 1;
+    set_style(spans, open..end, |style| style.link = Some(url.clone()));
+    *cursor = end;
+    true
+}
+
+fn remove_text(content: &mut String, spans: &mut Vec<TextSpan>, range: Range<usize>) {
+    content.replace_range(range.clone(), "");
+    remove(spans, range);
+}
+
+/// Sort, drop empty and plain spans, and merge touching spans of one style.
+fn normalize(spans: &mut Vec<TextSpan>) {
+    spans.retain(|span| !span.range.is_empty() && !span.style.is_plain());
+    spans.sort_by_key(|span| span.range.start);
+    let mut merged: Vec<TextSpan> = Vec::with_capacity(spans.len());
+    for span in spans.drain(..) {
+        match merged.last_mut() {
+            Some(last) if last.range.end == span.range.start && last.style == span.style => {
+                last.range.end = span.range.end;
+            }
+            _ => merged.push(span),
+        }
+    }
+    *spans = merged;
+}
+
+/// Escape text for SVG character data and attribute values.
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A `<tspan>` for `text` in `style`, wrapped in an `<a>` when it links.
+pub fn svg_tspan(text: &str, style: Option<&SpanStyle>, color: [f32; 4]) -> String {
+    let Some(style) = style else {
+        return format!("<tspan>{}</tspan>", escape_xml(text));
+    };
+    let mut attributes = String::new();
+    if style.bold {
+        attributes.push_str(" font-weight=\"bold\"");
+    }
+    if style.italic {
+        attributes.push_str(" font-style=\"italic\"");
+    }
+    if style.code {
+        attributes.push_str(&format!(" font-family=\"{}\"", FontFamily::Code.css_name()));
+    }
+    let decoration = match (style.strikethrough, style.link.is_some()) {
+        (true, true) => Some("line-through underline"),
+        (true, false) => Some("line-through"),
+        (false, true) => Some("underline"),
+        (false, false) => None,
+    };
+    if let Some(decoration) = decoration {
+        attributes.push_str(&format!(" text-decoration=\"{}\"", decoration));
+    }
+    if style.color.is_some() || style.link.is_some() {
+        attributes.push_str(&format!(" fill=\"{}\"", svg_color(style.color_or(color))));
+    }
+    let tspan = format!("<tspan{}>{}</tspan>", attributes, escape_xml(text));
+    match &style.link {
+        Some(link) => format!("<a href=\"{}\">{}</a>", escape_xml(link), tspan),
+        None => tspan,
+    }
+}
+
+/// `rgba(...)` for a linear 0..1 color.
+pub fn svg_color(color: [f32; 4]) -> String {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!(
+        "rgba({},{},{},{})",
+        channel(color[0]),
+        channel(color[1]),
+        channel(color[2]),
+        color[3].clamp(0.0, 1.0)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bold() -> SpanStyle {
+        SpanStyle {
+            bold: true,
+            ..SpanStyle::default()
+        }
+    }
+
+    #[test]
+    fn spans_follow_edits() {
+        let mut spans = vec![TextSpan {
+            range: 2..6,
+            style: bold(),
+        }];
+        insert(&mut spans, 4, 3, &bold());
+        assert_eq!(spans[0].range, 2..9);
+        insert(&mut spans, 4, 1, &SpanStyle::default());
+        assert_eq!(spans.len(), 2);
+        assert_eq!(
+            (spans[0].range.clone(), spans[1].range.clone()),
+            (2..4, 5..10)
+        );
+        remove(&mut spans, 3..6);
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].range, 2..7);
+    }
+
+    #[test]
+    fn shortcuts_become_spans() {
+        let mut content = "a **bold**".to_string();
+        let mut spans = Vec::new();
+        let mut cursor = content.len();
+        assert!(apply_shortcut(&mut content, &mut spans, &mut cursor));
+        assert_eq!(content, "a bold");
+        assert_eq!(cursor, content.len());
+        assert_eq!(
+            spans,
+            [TextSpan {
+                range: 2..6,
+                style: bold()
+            }]
+        );
+
+        let mut content = "see [docs](example.com)".to_string();
+        let mut spans = Vec::new();
+        let mut cursor = content.len();
+        assert!(apply_shortcut(&mut content, &mut spans, &mut cursor));
+        assert_eq!(content, "see docs");
+        assert_eq!(spans[0].style.link.as_deref(), Some("example.com"));
+
+        let mut content = "**half*".to_string();
+        let mut cursor = content.len();
+        assert!(!apply_shortcut(&mut content, &mut Vec::new(), &mut cursor));
+    }
+}
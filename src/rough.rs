@@ -178,6 +178,7 @@ impl RoughGenerator {
         &mut self,
         position: [f32; 2],
         size: [f32; 2],
+        corner_radius: f32,
         options: &RoughOptions,
     ) -> Vec<Vec<[f32; 2]>> {
         let corners = [
@@ -187,34 +188,18 @@ impl RoughGenerator {
             [position[0], position[1] + size[1]],
         ];
 
-        let mut lines = Vec::new();
-
-        for i in 0..4 {
-            let start = corners[i];
-            let end = corners[(i + 1) % 4];
-
-            let line = self.rough_line(start, end, options);
-            lines.push(line);
-
-            if !options.disable_multi_stroke {
-                let line2 = self.rough_line(start, end, options);
-                lines.push(line2);
-            }
-        }
-
-        lines
+        self.rough_polygon(&corners, corner_radius, options)
     }
 
     pub fn rough_diamond(
         &mut self,
         position: [f32; 2],
         size: [f32; 2],
+        corner_radius: f32,
         options: &RoughOptions,
     ) -> Vec<Vec<[f32; 2]>> {
         let center_x = position[0] + size[0] / 2.0;
         let center_y = position[1] + size[1] / 2.0;
-        let half_width = size[0] / 2.0;
-        let half_height = size[1] / 2.0;
 
         let corners = [
             [center_x, position[1]],
@@ -223,24 +208,97 @@ impl RoughGenerator {
             [position[0], center_y],
         ];
 
+        self.rough_polygon(&corners, corner_radius, options)
+    }
+
+    /// Closed polygon outline. With a positive `corner_radius`, each edge is
+    /// shortened and the corners are joined by rough arcs of that radius.
+    fn rough_polygon(
+        &mut self,
+        corners: &[[f32; 2]],
+        corner_radius: f32,
+        options: &RoughOptions,
+    ) -> Vec<Vec<[f32; 2]>> {
+        let count = corners.len();
         let mut lines = Vec::new();
 
-        for i in 0..4 {
-            let start = corners[i];
-            let end = corners[(i + 1) % 4];
+        if corner_radius <= 0.0 {
+            for i in 0..count {
+                let start = corners[i];
+                let end = corners[(i + 1) % count];
 
-            let line = self.rough_line(start, end, options);
-            lines.push(line);
+                let line = self.rough_line(start, end, options);
+                lines.push(line);
 
-            if !options.disable_multi_stroke {
-                let line2 = self.rough_line(start, end, options);
-                lines.push(line2);
+                if !options.disable_multi_stroke {
+                    let line2 = self.rough_line(start, end, options);
+                    lines.push(line2);
+                }
+            }
+            return lines;
+        }
+
+        // Tangent points where each corner's arc meets its incoming and outgoing edge.
+        let tangents: Vec<([f32; 2], [f32; 2])> = (0..count)
+            .map(|i| {
+                let prev = corners[(i + count - 1) % count];
+                let corner = corners[i];
+                let next = corners[(i + 1) % count];
+                corner_tangents(prev, corner, next, corner_radius)
+            })
+            .collect();
+
+        let passes = if options.disable_multi_stroke { 1 } else { 2 };
+        for i in 0..count {
+            let edge_start = tangents[i].1;
+            let edge_end = tangents[(i + 1) % count].0;
+            let arc_corner = corners[(i + 1) % count];
+            let arc_end = tangents[(i + 1) % count].1;
+
+            for _ in 0..passes {
+                lines.push(self.rough_line(edge_start, edge_end, options));
+                lines.push(self.rough_corner_arc(edge_end, arc_corner, arc_end, options));
             }
         }
 
         lines
     }
 
+    /// Rough arc from `start` to `end`, bending towards `corner` like a
+    /// quadratic curve with the corner as its control point.
+    fn rough_corner_arc(
+        &mut self,
+        start: [f32; 2],
+        corner: [f32; 2],
+        end: [f32; 2],
+        options: &RoughOptions,
+    ) -> Vec<[f32; 2]> {
+        let chord = ((end[0] - start[0]).powi(2) + (end[1] - start[1]).powi(2)).sqrt();
+        let offset = (options.max_randomness_offset * 0.5).min(chord * 0.1);
+        let jitter = |generator: &mut Self, point: [f32; 2]| {
+            [
+                point[0] + generator.offset_opt(offset, options, 1.0),
+                point[1] + generator.offset_opt(offset, options, 1.0),
+            ]
+        };
+
+        let p0 = jitter(self, start);
+        let p3 = jitter(self, end);
+        let control = jitter(self, corner);
+        let p1 = [
+            p0[0] + (control[0] - p0[0]) * 2.0 / 3.0,
+            p0[1] + (control[1] - p0[1]) * 2.0 / 3.0,
+        ];
+        let p2 = [
+            p3[0] + (control[0] - p3[0]) * 2.0 / 3.0,
+            p3[1] + (control[1] - p3[1]) * 2.0 / 3.0,
+        ];
+
+        let mut points = vec![p0];
+        points.extend(self.bezier_curve(p0, p1, p2, p3, 8));
+        points
+    }
+
     pub fn rough_ellipse(
         &mut self,
         center: [f32; 2],
@@ -469,18 +527,22 @@ impl RoughGenerator {
                     Vertex {
                         position: [p1[0] - nx, p1[1] - ny],
                         color,
+                        depth: 0.0,
                     },
                     Vertex {
                         position: [p1[0] + nx, p1[1] + ny],
                         color,
+                        depth: 0.0,
                     },
                     Vertex {
                         position: [p2[0] + nx, p2[1] + ny],
                         color,
+                        depth: 0.0,
                     },
                     Vertex {
                         position: [p2[0] - nx, p2[1] - ny],
                         color,
+                        depth: 0.0,
                     },
                 ]);
 
@@ -552,3 +614,39 @@ impl RoughGenerator {
         lines
     }
 }
+
+/// Points where an arc of `radius` touches the edges meeting at `corner`,
+/// clamped so neighbouring arcs never overlap.
+pub(crate) fn corner_tangents(
+    prev: [f32; 2],
+    corner: [f32; 2],
+    next: [f32; 2],
+    radius: f32,
+) -> ([f32; 2], [f32; 2]) {
+    let to_prev = [prev[0] - corner[0], prev[1] - corner[1]];
+    let to_next = [next[0] - corner[0], next[1] - corner[1]];
+    let len_prev = (to_prev[0] * to_prev[0] + to_prev[1] * to_prev[1])
+        .sqrt()
+        .max(f32::EPSILON);
+    let len_next = (to_next[0] * to_next[0] + to_next[1] * to_next[1])
+        .sqrt()
+        .max(f32::EPSILON);
+
+    let cos_angle = ((to_prev[0] * to_next[0] + to_prev[1] * to_next[1]) / (len_prev * len_next))
+        .clamp(-1.0, 1.0);
+    let half_angle = cos_angle.acos() * 0.5;
+    let distance = (radius / half_angle.tan().max(f32::EPSILON))
+        .min(len_prev * 0.5)
+        .min(len_next * 0.5);
+
+    (
+        [
+            corner[0] + to_prev[0] / len_prev * distance,
+            corner[1] + to_prev[1] / len_prev * distance,
+        ],
+        [
+            corner[0] + to_next[0] / len_next * distance,
+            corner[1] + to_next[1] / len_next * distance,
+        ],
+    )
+}
@@ -0,0 +1,313 @@
+type Bounds = ([f32; 2], [f32; 2]);
+
+/// Matches closer than this, in canvas units, count as exact when building guides.
+const GUIDE_EPSILON: f32 = 0.5;
+
+/// A transient hint drawn while a dragged or resized selection snaps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SnapGuide {
+    /// Edges or centers that line up, spanning every element that matches.
+    Align { start: [f32; 2], end: [f32; 2] },
+    /// A gap equal to a neighboring gap, labelled with its length.
+    Spacing {
+        start: [f32; 2],
+        end: [f32; 2],
+        distance: f32,
+    },
+}
+
+/// Bounds of the elements a selection can snap against.
+pub struct SnapCandidates {
+    others: Vec<Bounds>,
+}
+
+/// The closest elements before and after a selection along one axis, among
+/// those sharing its row (or column), plus the next ones out.
+#[derive(Default)]
+struct Neighbors {
+    before: Option<Bounds>,
+    before_prev: Option<Bounds>,
+    after: Option<Bounds>,
+    after_next: Option<Bounds>,
+}
+
+impl SnapCandidates {
+    pub fn new(others: Vec<Bounds>) -> Self {
+        Self { others }
+    }
+
+    /// Offset along `axis` (0 = x, 1 = y) that lines `bounds` up with another
+    /// element's edge or center, or evens out its gaps to its neighbors;
+    /// whichever is closer, provided it is within `threshold`.
+    pub fn snap_offset(&self, bounds: Bounds, axis: usize, threshold: f32) -> Option<f32> {
+        let align = nearest(
+            probes(bounds, axis)
+                .into_iter()
+                .flat_map(|probe| self.edges(axis).map(move |edge| edge - probe)),
+            threshold,
+        );
+        let spacing = nearest(
+            self.spacing_targets(bounds, axis)
+                .into_iter()
+                .map(|target| target - bounds.0[axis]),
+            threshold,
+        );
+        match (align, spacing) {
+            (Some(a), Some(s)) if s.abs() < a.abs() => Some(s),
+            (Some(a), _) => Some(a),
+            (None, s) => s,
+        }
+    }
+
+    /// Offset that moves a single edge at `value` onto the nearest candidate.
+    pub fn snap_edge(&self, value: f32, axis: usize, threshold: f32) -> Option<f32> {
+        nearest(self.edges(axis).map(|edge| edge - value), threshold)
+    }
+
+    /// Alignment and equal-spacing guides for `bounds` in its current position.
+    pub fn guides(&self, bounds: Bounds) -> Vec<SnapGuide> {
+        let mut guides = Vec::new();
+        for axis in 0..2 {
+            self.alignment_guides(bounds, axis, &mut guides);
+            self.spacing_guides(bounds, axis, &mut guides);
+        }
+        guides
+    }
+
+    fn edges(&self, axis: usize) -> impl Iterator<Item = f32> + '_ {
+        self.others
+            .iter()
+            .flat_map(move |other| probes(*other, axis))
+    }
+
+    fn alignment_guides(&self, bounds: Bounds, axis: usize, guides: &mut Vec<SnapGuide>) {
+        let across = 1 - axis;
+        let mut seen: Vec<f32> = Vec::new();
+        for probe in probes(bounds, axis) {
+            if seen
+                .iter()
+                .any(|value| (value - probe).abs() < GUIDE_EPSILON)
+            {
+                continue;
+            }
+            let matched = self.others.iter().filter(|other| {
+                probes(**other, axis)
+                    .iter()
+                    .any(|edge| (edge - probe).abs() < GUIDE_EPSILON)
+            });
+            let (mut low, mut high) = (bounds.0[across], bounds.1[across]);
+            let mut any = false;
+            for other in matched {
+                low = low.min(other.0[across]);
+                high = high.max(other.1[across]);
+                any = true;
+            }
+            if any {
+                seen.push(probe);
+                guides.push(SnapGuide::Align {
+                    start: point(axis, probe, low),
+                    end: point(axis, probe, high),
+                });
+            }
+        }
+    }
+
+    fn spacing_guides(&self, bounds: Bounds, axis: usize, guides: &mut Vec<SnapGuide>) {
+        let n = self.neighbors(bounds, axis);
+        let equal = |a: f32, b: f32| a > 0.0 && (a - b).abs() < GUIDE_EPSILON;
+        if let (Some(before), Some(after)) = (n.before, n.after)
+            && equal(gap(before, bounds, axis), gap(bounds, after, axis))
+        {
+            guides.push(gap_guide(before, bounds, axis));
+            guides.push(gap_guide(bounds, after, axis));
+        }
+        if let (Some(prev), Some(before)) = (n.before_prev, n.before)
+            && equal(gap(prev, before, axis), gap(before, bounds, axis))
+        {
+            guides.push(gap_guide(prev, before, axis));
+            guides.push(gap_guide(before, bounds, axis));
+        }
+        if let (Some(after), Some(next)) = (n.after, n.after_next)
+            && equal(gap(after, next, axis), gap(bounds, after, axis))
+        {
+            guides.push(gap_guide(bounds, after, axis));
+            guides.push(gap_guide(after, next, axis));
+        }
+    }
+
+    /// Positions for the leading edge of `bounds` that would make its gaps
+    /// match: centered between its neighbors, or repeating the gap beyond one.
+    fn spacing_targets(&self, bounds: Bounds, axis: usize) -> Vec<f32> {
+        let size = bounds.1[axis] - bounds.0[axis];
+        let n = self.neighbors(bounds, axis);
+        let mut targets = Vec::new();
+        if let (Some(before), Some(after)) = (n.before, n.after) {
+            let free = after.0[axis] - before.1[axis] - size;
+            if free > 0.0 {
+                targets.push(before.1[axis] + free * 0.5);
+            }
+        }
+        if let (Some(prev), Some(before)) = (n.before_prev, n.before)
+            && gap(prev, before, axis) > 0.0
+        {
+            targets.push(before.1[axis] + gap(prev, before, axis));
+        }
+        if let (Some(after), Some(next)) = (n.after, n.after_next)
+            && gap(after, next, axis) > 0.0
+        {
+            targets.push(after.0[axis] - gap(after, next, axis) - size);
+        }
+        targets
+    }
+
+    fn neighbors(&self, bounds: Bounds, axis: usize) -> Neighbors {
+        let across = 1 - axis;
+        let center = (bounds.0[axis] + bounds.1[axis]) * 0.5;
+        let row: Vec<Bounds> = self
+            .others
+            .iter()
+            .copied()
+            .filter(|other| {
+                other.0[across] < bounds.1[across] && other.1[across] > bounds.0[across]
+            })
+            .collect();
+        let last_before = |limit: f32| {
+            row.iter()
+                .copied()
+                .filter(|other| other.1[axis] <= limit)
+                .max_by(|a, b| a.1[axis].total_cmp(&b.1[axis]))
+        };
+        let first_after = |limit: f32| {
+            row.iter()
+                .copied()
+                .filter(|other| other.0[axis] >= limit)
+                .min_by(|a, b| a.0[axis].total_cmp(&b.0[axis]))
+        };
+
+        let mut neighbors = Neighbors::default();
+        if let Some(before) = last_before(center) {
+            neighbors.before = Some(before);
+            neighbors.before_prev = last_before(before.0[axis]);
+        }
+        if let Some(after) = first_after(center) {
+            neighbors.after = Some(after);
+            neighbors.after_next = first_after(after.1[axis]);
+        }
+        neighbors
+    }
+}
+
+/// Leading edge, center and trailing edge along `axis`.
+fn probes(bounds: Bounds, axis: usize) -> [f32; 3] {
+    [
+        bounds.0[axis],
+        (bounds.0[axis] + bounds.1[axis]) * 0.5,
+        bounds.1[axis],
+    ]
+}
+
+/// The smallest delta within `threshold`; ties keep the first.
+fn nearest(deltas: impl Iterator<Item = f32>, threshold: f32) -> Option<f32> {
+    let mut best: Option<f32> = None;
+    for delta in deltas {
+        if delta.abs() <= threshold {
+            match best {
+                Some(best_delta) if best_delta.abs() <= delta.abs() => {}
+                _ => best = Some(delta),
+            }
+        }
+    }
+    best
+}
+
+fn gap(first: Bounds, second: Bounds, axis: usize) -> f32 {
+    second.0[axis] - first.1[axis]
+}
+
+/// A spacing guide across the gap from `first` to `second`, drawn through the
+/// middle of the range the two share on the other axis.
+fn gap_guide(first: Bounds, second: Bounds, axis: usize) -> SnapGuide {
+    let across = 1 - axis;
+    let low = first.0[across].max(second.0[across]);
+    let high = first.1[across].min(second.1[across]);
+    let mid = (low + high) * 0.5;
+    SnapGuide::Spacing {
+        start: point(axis, first.1[axis], mid),
+        end: point(axis, second.0[axis], mid),
+        distance: gap(first, second, axis),
+    }
+}
+
+/// A point with `along` on `axis` and `across` on the other one.
+fn point(axis: usize, along: f32, across: f32) -> [f32; 2] {
+    if axis == 0 {
+        [along, across]
+    } else {
+        [across, along]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x: f32, y: f32) -> Bounds {
+        ([x, y], [x + 100.0, y + 100.0])
+    }
+
+    #[test]
+    fn offsets_snap_to_the_nearest_edge_or_center_within_threshold() {
+        let candidates = SnapCandidates::new(vec![([0.0, 0.0], [100.0, 50.0])]);
+        let dragged = ([3.0, 100.0], [53.0, 150.0]);
+        assert_eq!(candidates.snap_offset(dragged, 0, 5.0), Some(-3.0));
+        assert_eq!(candidates.snap_offset(dragged, 0, 2.0), None);
+        assert_eq!(candidates.snap_edge(47.0, 0, 5.0), Some(3.0));
+        assert_eq!(candidates.snap_edge(47.0, 1, 2.0), None);
+    }
+
+    #[test]
+    fn offsets_even_out_gaps_between_and_beyond_neighbors() {
+        let between = SnapCandidates::new(vec![square(0.0, 0.0), square(300.0, 0.0)]);
+        assert_eq!(between.snap_offset(square(148.0, 0.0), 0, 5.0), Some(2.0));
+
+        let beyond = SnapCandidates::new(vec![square(0.0, 0.0), square(150.0, 0.0)]);
+        assert_eq!(beyond.snap_offset(square(302.0, 0.0), 0, 5.0), Some(-2.0));
+
+        // Elements outside the selection's row don't count as neighbors.
+        let off_row = SnapCandidates::new(vec![square(0.0, 500.0), square(300.0, 500.0)]);
+        assert_eq!(off_row.snap_offset(square(148.0, 0.0), 0, 5.0), None);
+    }
+
+    #[test]
+    fn guides_show_matching_edges_and_equal_gaps() {
+        let candidates = SnapCandidates::new(vec![square(0.0, 0.0), square(300.0, 0.0)]);
+        assert_eq!(
+            candidates.guides(square(150.0, 0.0)),
+            [
+                SnapGuide::Spacing {
+                    start: [100.0, 50.0],
+                    end: [150.0, 50.0],
+                    distance: 50.0,
+                },
+                SnapGuide::Spacing {
+                    start: [250.0, 50.0],
+                    end: [300.0, 50.0],
+                    distance: 50.0,
+                },
+                SnapGuide::Align {
+                    start: [0.0, 0.0],
+                    end: [400.0, 0.0],
+                },
+                SnapGuide::Align {
+                    start: [0.0, 50.0],
+                    end: [400.0, 50.0],
+                },
+                SnapGuide::Align {
+                    start: [0.0, 100.0],
+                    end: [400.0, 100.0],
+                },
+            ]
+        );
+        assert!(candidates.guides(square(160.0, 300.0)).is_empty());
+    }
+}
@@ -5,11 +5,25 @@ cfg_if::cfg_if! {
         use std::time::Instant;
     }
 }
-use wgpu::{BindGroup, Buffer, Device, Queue, RenderPipeline, Surface, SurfaceConfiguration};
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use wgpu::{
+    BindGroup, BindGroupLayout, Buffer, Device, Queue, RenderPipeline, Surface,
+    SurfaceConfiguration,
+};
 use winit::keyboard::ModifiersState;
 
+use crate::arrange::AlignReference;
 use crate::canvas::{CanvasTransform, Uniforms};
-use crate::drawing::{DrawingElement, Element, ElementId};
+use crate::command::Command;
+use crate::drawing::{ColorTarget, DrawingElement, Element, ElementId, Tool};
+use crate::fonts::FontFamily;
+use crate::rich_text::{SpanStyle, TextSpan};
+use crate::snapping::SnapGuide;
+use crate::styles::{StylePreset, ToolStyle};
+use crate::theme::ThemeKind;
+use crate::vector::sdf::{PAINT_TEXELS, PAINTS_PER_ROW, Paint};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UserInputState {
@@ -37,6 +51,7 @@ pub enum ResizeHandle {
 pub enum ColorPickerDragMode {
     HueRing,
     SvDisk,
+    AlphaBar,
 }
 
 #[derive(Debug, Clone)]
@@ -45,6 +60,9 @@ pub struct ColorPickerState {
     pub hue: f32,
     pub saturation: f32,
     pub value: f32,
+    pub alpha: f32,
+    /// Which color the picker edits: stroke or background.
+    pub target: ColorTarget,
     pub drag_mode: Option<ColorPickerDragMode>,
 }
 
@@ -55,6 +73,8 @@ impl ColorPickerState {
             hue: 0.0,
             saturation: 0.0,
             value: 0.0,
+            alpha: 1.0,
+            target: ColorTarget::Stroke,
             drag_mode: None,
         }
     }
@@ -103,9 +123,123 @@ pub struct GpuContext {
     pub device: Device,
     pub queue: Queue,
     pub config: SurfaceConfiguration,
+    pub uniform_bind_group_layout: BindGroupLayout,
+    pub ui_uniform_bind_group_layout: BindGroupLayout,
+    /// The SDF paint table (see `SdfBatch`), bound at group 1.
+    pub sdf_paint_bind_group_layout: BindGroupLayout,
     pub render_pipeline: RenderPipeline,
     pub sdf_render_pipeline: RenderPipeline,
     pub ui_render_pipeline: RenderPipeline,
+    pub depth_texture: crate::texture::Texture,
+    /// Multisampled color target resolved into the surface; None without MSAA.
+    pub msaa_texture: Option<crate::texture::Texture>,
+    pub sample_count: u32,
+    /// Sample counts usable for both the surface and depth formats, ascending.
+    pub supported_sample_counts: Vec<u32>,
+}
+
+/// How edges of canvas geometry, UI and text are smoothed.
+///
+/// SDF shapes and MSDF text are always antialiased in their shaders; MSAA
+/// additionally smooths tessellated strokes and rough shapes so both match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AntiAliasing {
+    Off,
+    Msaa2,
+    #[default]
+    Msaa4,
+    Msaa8,
+}
+
+impl AntiAliasing {
+    pub const ALL: [AntiAliasing; 4] = [
+        AntiAliasing::Off,
+        AntiAliasing::Msaa2,
+        AntiAliasing::Msaa4,
+        AntiAliasing::Msaa8,
+    ];
+
+    pub fn sample_count(self) -> u32 {
+        match self {
+            AntiAliasing::Off => 1,
+            AntiAliasing::Msaa2 => 2,
+            AntiAliasing::Msaa4 => 4,
+            AntiAliasing::Msaa8 => 8,
+        }
+    }
+
+    /// The highest setting that uses no more than `sample_count` samples.
+    pub fn from_sample_count(sample_count: u32) -> Self {
+        Self::ALL
+            .into_iter()
+            .rev()
+            .find(|aa| aa.sample_count() <= sample_count)
+            .unwrap_or(AntiAliasing::Off)
+    }
+}
+
+/// User-adjustable preferences that aren't part of the document. Persisted
+/// separately from the autosave; see `State::save_settings`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub anti_aliasing: AntiAliasing,
+    pub theme: ThemeKind,
+    /// Overrides the theme's `invert_elements`; `None` follows the theme.
+    pub invert_elements: Option<bool>,
+    pub grid: GridSettings,
+    pub align_reference: AlignReference,
+    /// Commands last run from the command palette, most recent first.
+    pub recent_commands: Vec<Command>,
+    /// The style each drawing tool last drew with.
+    pub tool_styles: HashMap<Tool, ToolStyle>,
+    pub style_presets: Vec<StylePreset>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum GridStyle {
+    #[default]
+    Lines,
+    Dots,
+}
+
+/// The background grid and how dragged or resized selections snap.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GridSettings {
+    pub visible: bool,
+    pub style: GridStyle,
+    /// Cell size in canvas units; also the snap increment.
+    pub size: f32,
+    pub snap_to_grid: bool,
+    /// Snap selection edges and centers to those of other elements.
+    pub snap_to_objects: bool,
+    /// How close, in screen pixels, an edge must come to a candidate to snap.
+    pub snap_threshold: f32,
+}
+
+impl Default for GridSettings {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            style: GridStyle::Lines,
+            size: 10.0,
+            snap_to_grid: true,
+            snap_to_objects: true,
+            snap_threshold: 8.0,
+        }
+    }
+}
+
+impl GridSettings {
+    /// Round `value` to the nearest grid step, or leave it when grid snapping is off.
+    pub fn snap(&self, value: f32) -> f32 {
+        if self.snap_to_grid && self.size > 0.0 {
+            (value / self.size).round() * self.size
+        } else {
+            value
+        }
+    }
 }
 
 #[repr(C)]
@@ -127,6 +261,23 @@ pub struct Canvas {
     pub uniform_bind_group: BindGroup,
 }
 
+/// Which canvas pipeline draws a `DrawRun`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunPipeline {
+    Geometry,
+    Sdf,
+    /// Composite of the numbered offscreen shadow/glow job.
+    Effect(usize),
+}
+
+/// A contiguous index range in the geometry, SDF or effect buffer. Runs are
+/// replayed in order so tessellated and SDF elements interleave by z-order.
+#[derive(Debug, Clone)]
+pub struct DrawRun {
+    pub pipeline: RunPipeline,
+    pub indices: std::ops::Range<u32>,
+}
+
 pub struct GeometryBuffers {
     pub vertex: Option<Buffer>,
     pub index: Option<Buffer>,
@@ -143,6 +294,93 @@ pub struct SdfBuffers {
     pub vertex: Option<Buffer>,
     pub index: Option<Buffer>,
     pub count: u32,
+    /// Grow-only paint table texture, `PAINTS_PER_ROW` entries per row.
+    pub paint_table: wgpu::Texture,
+    pub paint_bind_group: BindGroup,
+}
+
+impl SdfBuffers {
+    pub fn new(device: &Device, paint_layout: &BindGroupLayout) -> Self {
+        let (paint_table, paint_bind_group) = Self::create_paint_table(device, paint_layout, 1);
+        Self {
+            vertex: None,
+            index: None,
+            count: 0,
+            paint_table,
+            paint_bind_group,
+        }
+    }
+
+    /// Upload a paint table from `SdfBatch::finish`, growing the texture
+    /// when it has more rows than fit.
+    pub fn write_paints(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        paint_layout: &BindGroupLayout,
+        paints: &[Paint],
+    ) {
+        let rows = (paints.len() / PAINTS_PER_ROW) as u32;
+        if rows == 0 {
+            return;
+        }
+        if rows > self.paint_table.height() {
+            let rows = rows.next_power_of_two();
+            (self.paint_table, self.paint_bind_group) =
+                Self::create_paint_table(device, paint_layout, rows);
+        }
+        let row_bytes = (PAINTS_PER_ROW * std::mem::size_of::<Paint>()) as u32;
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.paint_table,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            bytemuck::cast_slice(paints),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(row_bytes),
+                rows_per_image: Some(rows),
+            },
+            wgpu::Extent3d {
+                width: (PAINTS_PER_ROW * PAINT_TEXELS) as u32,
+                height: rows,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    fn create_paint_table(
+        device: &Device,
+        paint_layout: &BindGroupLayout,
+        rows: u32,
+    ) -> (wgpu::Texture, BindGroup) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("SDF Paint Table"),
+            size: wgpu::Extent3d {
+                width: (PAINTS_PER_ROW * PAINT_TEXELS) as u32,
+                height: rows,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba32Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: paint_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            }],
+            label: Some("sdf_paint_bind_group"),
+        });
+        (texture, bind_group)
+    }
 }
 
 pub struct InputState {
@@ -155,6 +393,8 @@ pub struct InputState {
     pub transform_snapshot: Vec<Element>,
     pub selection: SelectionState,
     pub preview_element: Option<DrawingElement>,
+    /// Smart guides for the selection being dragged or resized.
+    pub guides: Vec<SnapGuide>,
 }
 
 pub struct TextInput {
@@ -165,4 +405,46 @@ pub struct TextInput {
     pub cursor_pos: usize,
     pub cursor_visible: bool,
     pub blink_timer: Instant,
+    pub font_size: f32,
+    pub font: FontFamily,
+    /// Typing into the label of the shape `editing_id` rather than a text box.
+    pub editing_label: bool,
+    /// Formatting of `buffer`.
+    pub spans: Vec<TextSpan>,
+    /// Formatting given to the next typed text.
+    pub style: SpanStyle,
+    /// Other end of the selection, which runs to `cursor_pos`.
+    pub anchor: Option<usize>,
+    /// Earlier states of this editing session, newest last.
+    pub undo: Vec<TextSnapshot>,
+    pub redo: Vec<TextSnapshot>,
+    /// Kind of the last edit, so a run of typing undoes in one step.
+    pub last_edit: Option<TextEdit>,
+    /// Selecting by dragging the mouse.
+    pub dragging: bool,
+    /// When and where, in screen space, the last click landed.
+    pub last_click: Option<(Instant, [f32; 2])>,
+}
+
+/// The text being typed, as saved for undo inside an editing session.
+#[derive(Debug, Clone)]
+pub struct TextSnapshot {
+    pub buffer: String,
+    pub spans: Vec<TextSpan>,
+    pub cursor_pos: usize,
+    pub anchor: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEdit {
+    Typing,
+    Deleting,
+    Other,
+}
+
+/// Text copied from a text box, kept with its formatting for pasting back.
+#[derive(Debug, Clone, Default)]
+pub struct CopiedText {
+    pub text: String,
+    pub spans: Vec<TextSpan>,
 }
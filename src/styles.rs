@@ -0,0 +1,425 @@
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::State;
+use crate::drawing::{Element, Roundness, Tool};
+use crate::fonts::{self, FontFamily};
+use crate::history::Action;
+use crate::rough::RoughOptions;
+use crate::vector::style::FillStyle;
+
+/// Font size for new text when nothing else says otherwise.
+pub const DEFAULT_FONT_SIZE: f32 = 32.0;
+
+/// Element roughness that a style roughness of 1.0 stands for: about the
+/// middle of the range the drawing tools pick from at random.
+const ROUGHNESS_PER_UNIT: f32 = 0.8;
+
+/// The style new elements are drawn with. Each drawing tool keeps its own,
+/// and a named copy can be saved as a preset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolStyle {
+    pub stroke: [f32; 4],
+    /// Background for closed shapes; `None` means transparent.
+    pub background: Option<[f32; 4]>,
+    pub stroke_width: f32,
+    /// Scales the hand-drawn wobble: 1.0 is the usual sketchy look, 0.0 is
+    /// ruler-straight. Elements store an absolute roughness instead, converted
+    /// through `ROUGHNESS_PER_UNIT`.
+    pub roughness: f32,
+    /// Corner style for rectangles and diamonds.
+    pub roundness: Roundness,
+    pub font_size: f32,
+    pub font: FontFamily,
+}
+
+impl Default for ToolStyle {
+    fn default() -> Self {
+        Self {
+            stroke: [0.0, 0.0, 0.0, 1.0],
+            background: None,
+            stroke_width: 2.0,
+            roughness: 1.0,
+            roundness: Roundness::Sharp,
+            font_size: DEFAULT_FONT_SIZE,
+            font: FontFamily::default(),
+        }
+    }
+}
+
+impl ToolStyle {
+    /// The style `element` was drawn with, keeping `self` for anything the
+    /// element doesn't carry.
+    fn with_element(&self, element: &Element) -> Self {
+        let shape = &element.shape;
+        let mut style = Self {
+            stroke: shape.color(),
+            ..self.clone()
+        };
+        if let Some(font) = shape.font() {
+            style.font_size = shape.stroke_width();
+            style.font = font.clone();
+        } else {
+            style.stroke_width = shape.stroke_width();
+        }
+        if shape.is_closed() {
+            style.background = shape.background().map(FillStyle::base_color);
+        }
+        if let Some(roundness) = shape.roundness() {
+            style.roundness = roundness;
+        }
+        if let Some(rough) = shape.rough_style() {
+            style.roughness = rough.roughness / ROUGHNESS_PER_UNIT;
+        }
+        style
+    }
+
+    /// Restyle `element` in place: colors, widths and font, plus the
+    /// roughness of hand-drawn shapes.
+    fn apply_to(&self, element: &mut Element) {
+        let shape = &mut element.shape;
+        shape.set_color(self.stroke);
+        if shape.is_text() {
+            shape.set_font(self.font.clone());
+            shape.set_stroke_width(self.font_size);
+        } else {
+            shape.set_stroke_width(self.stroke_width);
+        }
+        shape.set_background(self.background.map(FillStyle::Solid));
+        shape.set_roundness(self.roundness);
+        if let Some(rough) = shape.rough_style_mut() {
+            let roughness = self.roughness * ROUGHNESS_PER_UNIT;
+            // Bowing scales along, keeping the element's own mix of the two.
+            rough.bowing = if rough.roughness > 0.0 {
+                rough.bowing * roughness / rough.roughness
+            } else {
+                roughness
+            };
+            rough.roughness = roughness;
+        }
+    }
+}
+
+/// The look of one element, lifted by Copy style. Fields the source doesn't
+/// carry are `None` and leave the target's value alone when pasted.
+#[derive(Debug, Clone)]
+pub struct CopiedStyle {
+    color: [f32; 4],
+    opacity: f32,
+    stroke_width: Option<f32>,
+    font_size: Option<f32>,
+    font: Option<FontFamily>,
+    /// Only closed shapes have one; `Some(None)` pastes a transparent fill.
+    background: Option<Option<FillStyle>>,
+    roundness: Option<Roundness>,
+    rough: Option<RoughOptions>,
+}
+
+impl CopiedStyle {
+    fn from_element(element: &Element) -> Self {
+        let shape = &element.shape;
+        let (stroke_width, font_size) = if shape.is_text() {
+            (None, Some(shape.stroke_width()))
+        } else {
+            (Some(shape.stroke_width()), None)
+        };
+        Self {
+            color: shape.color(),
+            opacity: element.opacity,
+            stroke_width,
+            font_size,
+            font: shape.font().cloned(),
+            background: shape.is_closed().then(|| shape.background().cloned()),
+            roundness: shape.roundness(),
+            rough: shape.rough_style().cloned(),
+        }
+    }
+
+    /// Paste onto `element`. Stroke width goes only to shapes and font size
+    /// only to text; the target keeps its own rough seed so copies don't
+    /// wobble in lockstep.
+    fn paste_onto(&self, element: &mut Element) {
+        element.opacity = self.opacity;
+        let shape = &mut element.shape;
+        shape.set_color(self.color);
+        match (shape.is_text(), self.stroke_width, self.font_size) {
+            (false, Some(width), _) | (true, _, Some(width)) => {
+                shape.set_stroke_width(width);
+            }
+            _ => {}
+        }
+        if let Some(font) = &self.font {
+            shape.set_font(font.clone());
+        }
+        if let Some(background) = &self.background {
+            shape.set_background(background.clone());
+        }
+        if let Some(roundness) = self.roundness {
+            shape.set_roundness(roundness);
+        }
+        if let (Some(source), Some(rough)) = (&self.rough, shape.rough_style_mut()) {
+            *rough = RoughOptions {
+                seed: rough.seed,
+                stroke_width: rough.stroke_width,
+                preserve_vertices: rough.preserve_vertices,
+                ..source.clone()
+            };
+        }
+    }
+}
+
+/// A saved style, applied to the selection from the command palette.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StylePreset {
+    pub name: String,
+    pub style: ToolStyle,
+}
+
+/// Tools that draw, and so keep a style of their own.
+fn has_style(tool: Tool) -> bool {
+    !matches!(tool, Tool::Select | Tool::Eraser)
+}
+
+impl State {
+    /// Switch tools, keeping the outgoing drawing tool's style and picking up
+    /// the incoming one's. A tool without a saved style carries on with the
+    /// current one.
+    pub fn set_tool(&mut self, tool: Tool) {
+        if tool == self.current_tool {
+            return;
+        }
+        if has_style(self.current_tool) {
+            self.settings
+                .tool_styles
+                .insert(self.current_tool, self.style.clone());
+        }
+        self.current_tool = tool;
+        if has_style(tool)
+            && let Some(style) = self.settings.tool_styles.get(&tool).cloned()
+        {
+            self.sync_picker_to_color(style.stroke);
+            self.style = style;
+        }
+        self.save_settings();
+    }
+
+    /// Save the current style, or that of the first selected element, as a
+    /// preset called `name`, replacing any preset of the same name.
+    pub fn save_style_preset(&mut self, name: &str) {
+        let name = name.trim();
+        if name.is_empty() {
+            return;
+        }
+        let style = self
+            .elements
+            .iter()
+            .find(|element| self.input.selection.is_selected(element.id))
+            .map_or_else(
+                || self.style.clone(),
+                |element| self.style.with_element(element),
+            );
+        let presets = &mut self.settings.style_presets;
+        match presets.iter_mut().find(|preset| preset.name == name) {
+            Some(preset) => preset.style = style,
+            None => presets.push(StylePreset {
+                name: name.to_string(),
+                style,
+            }),
+        }
+        log::info!("Saved style preset {:?}", name);
+        self.save_settings();
+    }
+
+    /// Restyle the selection with preset `index`, or adopt it as the current
+    /// style when nothing is selected.
+    pub fn apply_style_preset(&mut self, index: usize) {
+        let Some(style) = self
+            .settings
+            .style_presets
+            .get(index)
+            .map(|preset| preset.style.clone())
+        else {
+            return;
+        };
+        let ids = self.input.selection.selected_ids.clone();
+        if ids.is_empty() {
+            self.sync_picker_to_color(style.stroke);
+            self.style = style;
+            return;
+        }
+        let before = self.snapshot_elements(&ids);
+        for id in &ids {
+            if let Some(element) = self.find_element_mut_by_id(*id) {
+                style.apply_to(element);
+            }
+        }
+        let after = self.snapshot_elements(&ids);
+        if before != after {
+            self.record_action(Action::ModifyProperty { before, after });
+        }
+    }
+
+    /// Switch the selected text, or the current style when nothing is
+    /// selected, to the next font family.
+    pub fn cycle_font(&mut self) {
+        let current = self
+            .elements
+            .iter()
+            .filter(|element| self.input.selection.is_selected(element.id))
+            .find_map(|element| element.shape.font())
+            .unwrap_or(&self.style.font);
+        let families = fonts::families();
+        let next = families
+            .iter()
+            .position(|family| family == current)
+            .map_or(0, |index| (index + 1) % families.len());
+        self.use_font(families[next].clone());
+    }
+
+    /// Ask for a TTF/OTF file, install it and start using it.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_font(&mut self) {
+        let path = match crate::platform::open_file_dialog() {
+            crate::platform::FileDialogResult::Selected(p) => p,
+            crate::platform::FileDialogResult::Cancelled => return,
+        };
+        match fonts::install_font_file(&path) {
+            Ok(family) => {
+                log::info!("Loaded font {:?}", family.name());
+                self.use_font(family);
+            }
+            Err(e) => log::error!("Failed to load font {}: {}", path, e),
+        }
+    }
+
+    fn use_font(&mut self, family: FontFamily) {
+        if self.input.selection.selected_ids.is_empty() {
+            self.style.font = family;
+        } else {
+            self.set_selection_font(family);
+        }
+    }
+
+    /// Remember the style of the first selected element for Paste style.
+    pub fn copy_style(&mut self) {
+        if let Some(element) = self
+            .elements
+            .iter()
+            .find(|element| self.input.selection.is_selected(element.id))
+        {
+            self.copied_style = Some(CopiedStyle::from_element(element));
+        }
+    }
+
+    /// Apply the copied style to every selected element as one undo step.
+    pub fn paste_style(&mut self) {
+        let Some(style) = self.copied_style.clone() else {
+            return;
+        };
+        let ids = self.input.selection.selected_ids.clone();
+        let before = self.snapshot_elements(&ids);
+        for id in &ids {
+            if let Some(element) = self.find_element_mut_by_id(*id) {
+                style.paste_onto(element);
+            }
+        }
+        let after = self.snapshot_elements(&ids);
+        if before != after {
+            self.record_action(Action::ModifyProperty { before, after });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drawing::DrawingElement;
+
+    fn rough_rect(roughness: f32, bowing: f32, seed: u64) -> Element {
+        Element::new(DrawingElement::Rectangle {
+            position: [0.0, 0.0],
+            size: [100.0, 60.0],
+            color: [0.0, 0.0, 0.0, 1.0],
+            background: None,
+            stroke_width: 1.0,
+            rough_style: Some(RoughOptions {
+                roughness,
+                bowing,
+                seed: Some(seed),
+                ..RoughOptions::default()
+            }),
+            roundness: Roundness::Sharp,
+        })
+    }
+
+    fn style() -> ToolStyle {
+        ToolStyle {
+            stroke: [0.8, 0.1, 0.1, 1.0],
+            background: Some([0.1, 0.2, 0.9, 1.0]),
+            stroke_width: 4.0,
+            roughness: 2.0,
+            roundness: Roundness::Adaptive,
+            font_size: 20.0,
+            font: FontFamily::Code,
+        }
+    }
+
+    #[test]
+    fn tool_styles_round_trip_through_shapes() {
+        let mut element = rough_rect(0.5, 1.0, 7);
+        style().apply_to(&mut element);
+
+        let rough = element.shape.rough_style().unwrap();
+        assert_eq!(rough.roughness, 2.0 * ROUGHNESS_PER_UNIT);
+        assert_eq!(rough.bowing, 2.0 * rough.roughness);
+        // Shapes don't carry a font, so those come from the base style.
+        let base = ToolStyle {
+            font_size: 20.0,
+            font: FontFamily::Code,
+            ..ToolStyle::default()
+        };
+        assert_eq!(base.with_element(&element), style());
+    }
+
+    #[test]
+    fn tool_styles_round_trip_through_text() {
+        let mut element = Element::new(DrawingElement::Text {
+            position: [0.0, 0.0],
+            content: "hello".to_string(),
+            color: [0.0, 0.0, 0.0, 1.0],
+            size: DEFAULT_FONT_SIZE,
+            font: FontFamily::HandDrawn,
+        });
+        style().apply_to(&mut element);
+
+        let captured = ToolStyle::default().with_element(&element);
+        assert_eq!(captured.stroke, style().stroke);
+        assert_eq!(captured.font_size, 20.0);
+        assert_eq!(captured.font, FontFamily::Code);
+        // Text has no stroke width of its own to capture.
+        assert_eq!(captured.stroke_width, ToolStyle::default().stroke_width);
+    }
+
+    #[test]
+    fn copied_styles_paste_everything_but_the_rough_seed() {
+        let mut source = rough_rect(1.5, 0.3, 1);
+        style().apply_to(&mut source);
+        source.opacity = 0.5;
+        let mut target = rough_rect(0.2, 0.2, 2);
+
+        CopiedStyle::from_element(&source).paste_onto(&mut target);
+
+        assert_eq!(target.opacity, 0.5);
+        assert_eq!(target.shape.color(), source.shape.color());
+        assert_eq!(target.shape.background(), source.shape.background());
+        assert_eq!(target.shape.stroke_width(), 4.0);
+        assert_eq!(target.shape.roundness(), Some(Roundness::Adaptive));
+        let (pasted, copied) = (
+            target.shape.rough_style().unwrap(),
+            source.shape.rough_style().unwrap(),
+        );
+        assert_eq!(pasted.roughness, copied.roughness);
+        assert_eq!(pasted.bowing, copied.bowing);
+        assert_eq!(pasted.seed, Some(2));
+    }
+}
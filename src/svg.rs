@@ -0,0 +1,506 @@
+use crate::drawing::{DrawingElement, Element};
+use crate::rich_text::{escape_xml, svg_color};
+use crate::text_layout::TextLayout;
+use crate::vector::style::{FillStyle, GradientStop};
+
+/// Margin left around the drawing in an exported SVG.
+const MARGIN: f32 = 16.0;
+/// Arrowheads as the renderer draws them without roughness.
+const ARROW_HEAD_LENGTH: f32 = 20.0;
+const ARROW_HEAD_ANGLE: f32 = 0.5;
+
+/// The drawing as a standalone SVG document. Shapes are exported clean,
+/// without roughness.
+pub fn document_svg(elements: &[Element]) -> String {
+    let (min, max) = elements
+        .iter()
+        .flat_map(|element| {
+            let bounds = element.bounding_box();
+            let effects = effect_blurs(element)
+                .into_iter()
+                .map(move |(offset, sigma, _)| effect_bounds(bounds, offset, sigma));
+            std::iter::once(bounds).chain(effects)
+        })
+        .reduce(|(a_min, a_max), (b_min, b_max)| {
+            (
+                [a_min[0].min(b_min[0]), a_min[1].min(b_min[1])],
+                [a_max[0].max(b_max[0]), a_max[1].max(b_max[1])],
+            )
+        })
+        .unwrap_or(([0.0, 0.0], [0.0, 0.0]));
+    let mut defs = Defs::default();
+    let mut body = String::new();
+    for element in elements {
+        let shape = element.rendered_shape();
+        let shape_svg = shape_svg(&shape, &mut defs);
+        for (offset, sigma, color) in effect_blurs(element) {
+            let region = effect_bounds(shape.bounding_box(), offset, sigma);
+            let filter = defs.effect(region, offset, sigma, color);
+            body.push_str(&format!("<g filter=\"{filter}\">{shape_svg}</g>\n"));
+        }
+        body.push_str(&shape_svg);
+        body.push('\n');
+        let label = element.label.as_ref().map(|label| {
+            let mut label = label.clone();
+            label.color[3] *= element.opacity.clamp(0.0, 1.0);
+            label
+        });
+        if let Some(text) = label
+            .and_then(|label| shape.label_box(&label))
+            .and_then(|label| label.svg_text())
+        {
+            body.push_str(&text);
+            body.push('\n');
+        }
+    }
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">\n",
+        min[0] - MARGIN,
+        min[1] - MARGIN,
+        max[0] - min[0] + MARGIN * 2.0,
+        max[1] - min[1] + MARGIN * 2.0
+    );
+    if !defs.svg.is_empty() {
+        svg.push_str(&format!("<defs>\n{}</defs>\n", defs.svg));
+    }
+    svg.push_str(&body);
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn shape_svg(shape: &DrawingElement, defs: &mut Defs) -> String {
+    match shape {
+        DrawingElement::Stroke {
+            points,
+            color,
+            width,
+        } => format!(
+            "<polyline points=\"{}\" {}/>",
+            points_attr(points),
+            stroke_attrs(*color, *width, "none")
+        ),
+        DrawingElement::Line {
+            start,
+            end,
+            color,
+            width,
+            ..
+        } => format!(
+            "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" {}/>",
+            start[0],
+            start[1],
+            end[0],
+            end[1],
+            stroke_attrs(*color, *width, "none")
+        ),
+        DrawingElement::Arrow {
+            start,
+            end,
+            color,
+            width,
+            ..
+        } => {
+            let [dx, dy] = [end[0] - start[0], end[1] - start[1]];
+            let length = (dx * dx + dy * dy).sqrt().max(f32::EPSILON);
+            let [dir_x, dir_y] = [dx / length, dy / length];
+            let (sin, cos) = ARROW_HEAD_ANGLE.sin_cos();
+            let left = [
+                end[0] - ARROW_HEAD_LENGTH * (dir_x * cos - dir_y * sin),
+                end[1] - ARROW_HEAD_LENGTH * (dir_y * cos + dir_x * sin),
+            ];
+            let right = [
+                end[0] - ARROW_HEAD_LENGTH * (dir_x * cos + dir_y * sin),
+                end[1] - ARROW_HEAD_LENGTH * (dir_y * cos - dir_x * sin),
+            ];
+            format!(
+                "<path d=\"M{} {} L{} {} M{} {} L{} {} L{} {}\" {}/>",
+                start[0],
+                start[1],
+                end[0],
+                end[1],
+                left[0],
+                left[1],
+                end[0],
+                end[1],
+                right[0],
+                right[1],
+                stroke_attrs(*color, *width, "none")
+            )
+        }
+        DrawingElement::Rectangle {
+            position,
+            size,
+            color,
+            background,
+            stroke_width,
+            roundness,
+            ..
+        } => format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"{}\" {}/>",
+            position[0],
+            position[1],
+            size[0],
+            size[1],
+            roundness.radius_for(*size),
+            stroke_attrs(
+                *color,
+                *stroke_width,
+                &defs.paint(background.as_ref(), *position, *size)
+            )
+        ),
+        DrawingElement::Circle {
+            center,
+            radii,
+            color,
+            background,
+            stroke_width,
+            ..
+        } => format!(
+            "<ellipse cx=\"{}\" cy=\"{}\" rx=\"{}\" ry=\"{}\" {}/>",
+            center[0],
+            center[1],
+            radii[0],
+            radii[1],
+            stroke_attrs(
+                *color,
+                *stroke_width,
+                &defs.paint(
+                    background.as_ref(),
+                    [center[0] - radii[0], center[1] - radii[1]],
+                    [radii[0] * 2.0, radii[1] * 2.0]
+                )
+            )
+        ),
+        DrawingElement::Diamond {
+            position,
+            size,
+            color,
+            background,
+            stroke_width,
+            roundness,
+            ..
+        } => {
+            let [x, y] = *position;
+            let [w, h] = *size;
+            let corners = [
+                [x + w * 0.5, y],
+                [x + w, y + h * 0.5],
+                [x + w * 0.5, y + h],
+                [x, y + h * 0.5],
+            ];
+            let attrs = stroke_attrs(
+                *color,
+                *stroke_width,
+                &defs.paint(background.as_ref(), *position, *size),
+            );
+            let radius = roundness.radius_for(*size);
+            if radius > 0.0 {
+                format!("<path d=\"{}\" {}/>", rounded_path(&corners, radius), attrs)
+            } else {
+                format!("<polygon points=\"{}\" {}/>", points_attr(&corners), attrs)
+            }
+        }
+        DrawingElement::Text {
+            position,
+            content,
+            color,
+            size,
+            font,
+        } => {
+            let layout = TextLayout::new(content, font, *size, None);
+            let lines: String = layout
+                .lines
+                .iter()
+                .enumerate()
+                .map(|(index, line)| {
+                    format!(
+                        "<tspan x=\"{}\" y=\"{}\">{}</tspan>",
+                        position[0],
+                        position[1] + index as f32 * layout.line_height(),
+                        escape_xml(&content[line.range.clone()])
+                    )
+                })
+                .collect();
+            format!(
+                "<text font-family=\"{}\" font-size=\"{}\" fill=\"{}\" xml:space=\"preserve\">{}</text>",
+                escape_xml(font.css_name()),
+                size,
+                svg_color(*color),
+                lines
+            )
+        }
+        DrawingElement::TextBox { .. } => shape.svg_text().unwrap_or_default(),
+    }
+}
+
+/// Gradient and filter definitions collected while writing shapes,
+/// referenced by id.
+#[derive(Default)]
+struct Defs {
+    svg: String,
+    gradients: usize,
+    filters: usize,
+}
+
+impl Defs {
+    /// The `fill` value for a background over the bounds at `position` with
+    /// `size`, adding a gradient definition when it isn't solid.
+    fn paint(
+        &mut self,
+        background: Option<&FillStyle>,
+        position: [f32; 2],
+        size: [f32; 2],
+    ) -> String {
+        let id = format!("gradient{}", self.gradients);
+        let (tag, attrs, stops) = match background {
+            None => return "none".to_string(),
+            Some(FillStyle::Solid(color)) => return svg_color(*color),
+            Some(FillStyle::LinearGradient { angle, stops }) => {
+                // The renderer runs the gradient edge to edge along the
+                // angle in canvas space, which bounding box units would skew.
+                let (sin, cos) = angle.sin_cos();
+                let center = [position[0] + size[0] / 2.0, position[1] + size[1] / 2.0];
+                let extent = (size[0] * cos).abs() / 2.0 + (size[1] * sin).abs() / 2.0;
+                let attrs = format!(
+                    "gradientUnits=\"userSpaceOnUse\" x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"",
+                    center[0] - cos * extent,
+                    center[1] - sin * extent,
+                    center[0] + cos * extent,
+                    center[1] + sin * extent
+                );
+                ("linearGradient", attrs, stops)
+            }
+            Some(FillStyle::RadialGradient {
+                center,
+                radius,
+                stops,
+            }) => {
+                // Center and radius are already bounding box fractions.
+                let attrs = format!("cx=\"{}\" cy=\"{}\" r=\"{}\"", center[0], center[1], radius);
+                ("radialGradient", attrs, stops)
+            }
+        };
+        self.svg.push_str(&format!(
+            "<{tag} id=\"{id}\" {attrs}>{}</{tag}>\n",
+            stops_svg(stops)
+        ));
+        self.gradients += 1;
+        format!("url(#{id})")
+    }
+
+    /// A filter over `region` that turns a source into its silhouette
+    /// blurred by `sigma`, moved by `offset` and filled with `color`.
+    fn effect(
+        &mut self,
+        region: ([f32; 2], [f32; 2]),
+        offset: [f32; 2],
+        sigma: f32,
+        color: [f32; 4],
+    ) -> String {
+        let id = format!("effect{}", self.filters);
+        let (min, max) = region;
+        self.svg.push_str(&format!(
+            "<filter id=\"{}\" filterUnits=\"userSpaceOnUse\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\">\
+             <feGaussianBlur in=\"SourceAlpha\" stdDeviation=\"{}\"/>\
+             <feOffset dx=\"{}\" dy=\"{}\" result=\"silhouette\"/>\
+             <feFlood flood-color=\"{}\"/>\
+             <feComposite in2=\"silhouette\" operator=\"in\"/>\
+             </filter>\n",
+            id,
+            min[0],
+            min[1],
+            max[0] - min[0],
+            max[1] - min[1],
+            sigma,
+            offset[0],
+            offset[1],
+            svg_color(color)
+        ));
+        self.filters += 1;
+        format!("url(#{id})")
+    }
+}
+
+/// `(offset, sigma, color)` of the element's shadow and glow, in drawing
+/// order. Blurs match the renderer's: half the blur or radius.
+fn effect_blurs(element: &Element) -> Vec<([f32; 2], f32, [f32; 4])> {
+    let effects = element.rendered_effects();
+    let shadow = effects
+        .shadow
+        .map(|shadow| (shadow.offset, shadow.blur * 0.5, shadow.color));
+    let glow = effects
+        .glow
+        .map(|glow| ([0.0, 0.0], glow.radius * 0.5, glow.color));
+    shadow.into_iter().chain(glow).collect()
+}
+
+/// Area a blurred and offset copy of `bounds` can reach.
+fn effect_bounds(
+    (min, max): ([f32; 2], [f32; 2]),
+    offset: [f32; 2],
+    sigma: f32,
+) -> ([f32; 2], [f32; 2]) {
+    let margin = sigma * 3.0 + 2.0;
+    (
+        [
+            min[0] + offset[0].min(0.0) - margin,
+            min[1] + offset[1].min(0.0) - margin,
+        ],
+        [
+            max[0] + offset[0].max(0.0) + margin,
+            max[1] + offset[1].max(0.0) + margin,
+        ],
+    )
+}
+
+fn stops_svg(stops: &[GradientStop]) -> String {
+    let mut stops = stops.to_vec();
+    stops.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+    stops
+        .iter()
+        .map(|stop| {
+            format!(
+                "<stop offset=\"{}\" stop-color=\"{}\"/>",
+                stop.offset.clamp(0.0, 1.0),
+                svg_color(stop.color)
+            )
+        })
+        .collect()
+}
+
+fn stroke_attrs(color: [f32; 4], width: f32, fill: &str) -> String {
+    format!(
+        "fill=\"{}\" stroke=\"{}\" stroke-width=\"{}\" stroke-linecap=\"round\" stroke-linejoin=\"round\"",
+        fill,
+        svg_color(color),
+        width
+    )
+}
+
+fn points_attr(points: &[[f32; 2]]) -> String {
+    points
+        .iter()
+        .map(|point| format!("{},{}", point[0], point[1]))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Path data for a closed polygon with each corner rounded off by a curve
+/// between the points where an arc of `radius` would touch its edges, as
+/// the canvas draws it.
+fn rounded_path(corners: &[[f32; 2]], radius: f32) -> String {
+    let n = corners.len();
+    let mut d = String::new();
+    for i in 0..n {
+        let prev = corners[(i + n - 1) % n];
+        let corner = corners[i];
+        let next = corners[(i + 1) % n];
+        let (start, end) = crate::rough::corner_tangents(prev, corner, next, radius);
+        let command = if i == 0 { 'M' } else { 'L' };
+        d.push_str(&format!(
+            "{}{},{} Q{},{} {},{} ",
+            command, start[0], start[1], corner[0], corner[1], end[0], end[1]
+        ));
+    }
+    d.push('Z');
+    d
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drawing::Roundness;
+
+    fn rect(background: FillStyle) -> DrawingElement {
+        DrawingElement::Rectangle {
+            position: [0.0, 0.0],
+            size: [100.0, 50.0],
+            color: [0.0, 0.0, 0.0, 1.0],
+            background: Some(background),
+            stroke_width: 2.0,
+            roundness: Roundness::default(),
+            rough_style: None,
+        }
+    }
+
+    #[test]
+    fn gradients_are_defined_and_referenced() {
+        let mut defs = Defs::default();
+        let linear = shape_svg(
+            &rect(FillStyle::linear(
+                0.0,
+                [1.0, 0.0, 0.0, 1.0],
+                [0.0, 0.0, 1.0, 1.0],
+            )),
+            &mut defs,
+        );
+        let radial = shape_svg(
+            &rect(FillStyle::radial(
+                [1.0, 1.0, 1.0, 1.0],
+                [0.0, 0.0, 0.0, 1.0],
+            )),
+            &mut defs,
+        );
+        assert!(linear.contains("fill=\"url(#gradient0)\""));
+        assert!(radial.contains("fill=\"url(#gradient1)\""));
+        assert!(defs.svg.contains(
+            "<linearGradient id=\"gradient0\" gradientUnits=\"userSpaceOnUse\" x1=\"0\" y1=\"25\" x2=\"100\" y2=\"25\">"
+        ));
+        assert!(
+            defs.svg
+                .contains("<radialGradient id=\"gradient1\" cx=\"0.5\" cy=\"0.5\" r=\"0.5\">")
+        );
+        assert_eq!(defs.svg.matches("<stop ").count(), 4);
+    }
+
+    #[test]
+    fn effects_are_drawn_beneath_through_filters() {
+        let mut element = Element::new(rect(FillStyle::Solid([1.0, 1.0, 1.0, 1.0])));
+        element.effects.toggle_shadow();
+        element.effects.toggle_glow();
+        let svg = document_svg(&[element]);
+
+        let shadow = svg.find("<g filter=\"url(#effect0)\"><rect").unwrap();
+        let glow = svg.find("<g filter=\"url(#effect1)\"><rect").unwrap();
+        let shape = svg.rfind("\n<rect").unwrap();
+        assert!(shadow < glow && glow < shape);
+        assert!(svg.contains(
+            "<feGaussianBlur in=\"SourceAlpha\" stdDeviation=\"6\"/><feOffset dx=\"6\" dy=\"8\""
+        ));
+        assert!(svg.contains(
+            "<feGaussianBlur in=\"SourceAlpha\" stdDeviation=\"7\"/><feOffset dx=\"0\" dy=\"0\""
+        ));
+    }
+
+    #[test]
+    fn rounded_diamonds_get_curved_corners() {
+        let diamond = |roundness| DrawingElement::Diamond {
+            position: [0.0, 0.0],
+            size: [100.0, 100.0],
+            color: [0.0, 0.0, 0.0, 1.0],
+            background: None,
+            stroke_width: 2.0,
+            rough_style: None,
+            roundness,
+        };
+        let sharp = shape_svg(&diamond(Roundness::Sharp), &mut Defs::default());
+        assert!(sharp.starts_with("<polygon points=\"50,0 100,50 50,100 0,50\""));
+
+        let rounded = shape_svg(&diamond(Roundness::Fixed(10.0)), &mut Defs::default());
+        assert!(rounded.starts_with("<path d=\"M"));
+        assert_eq!(rounded.matches(" Q").count(), 4);
+        // Every corner's curve bends around the corner itself.
+        for corner in ["Q50,0 ", "Q100,50 ", "Q50,100 ", "Q0,50 "] {
+            assert!(rounded.contains(corner), "{corner}");
+        }
+        assert!(rounded.contains("Z\""));
+    }
+
+    #[test]
+    fn solid_fills_need_no_definition() {
+        let mut defs = Defs::default();
+        let svg = shape_svg(&rect(FillStyle::Solid([1.0, 0.0, 0.0, 1.0])), &mut defs);
+        assert!(svg.contains("fill=\"rgba(255,0,0,1)\""));
+        assert!(defs.svg.is_empty());
+    }
+}
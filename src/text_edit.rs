@@ -0,0 +1,601 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+use crate::app_state::State;
+use crate::drawing::BoxState;
+use crate::rich_text::{self, SpanStyle};
+use crate::state::{CopiedText, TextEdit, TextInput, TextSnapshot};
+use crate::text_layout::{self, TextLayout};
+
+/// Where a caret key moves the caret.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaretMotion {
+    Left,
+    Right,
+    WordLeft,
+    WordRight,
+    Up,
+    Down,
+    LineStart,
+    LineEnd,
+    TextStart,
+    TextEnd,
+}
+
+/// Caret and selection of the text being typed, in canvas space.
+pub struct TextOverlay {
+    /// Top-left and bottom-right corners of each highlighted line part.
+    pub selection: Vec<[[f32; 2]; 2]>,
+    /// Top and bottom of the caret.
+    pub caret: [[f32; 2]; 2],
+    pub color: [f32; 4],
+}
+
+/// Undo steps kept per editing session.
+const MAX_TEXT_UNDO: usize = 200;
+
+/// A second click within this time and distance, in screen pixels, of the
+/// first is a double click.
+const DOUBLE_CLICK_MS: u128 = 350;
+const DOUBLE_CLICK_DISTANCE: f32 = 4.0;
+
+impl State {
+    /// Move the caret; with `extend` the selection follows it, otherwise
+    /// the selection is dropped.
+    pub fn move_caret(&mut self, motion: CaretMotion, extend: bool) {
+        let layout = match motion {
+            CaretMotion::Up | CaretMotion::Down | CaretMotion::LineStart | CaretMotion::LineEnd => {
+                self.typing_layout()
+            }
+            _ => None,
+        };
+        let layout = layout
+            .as_ref()
+            .map(|(layout, origins)| (&**layout, origins.as_slice()));
+        let target = self.typing.caret_target(motion, extend, layout);
+        self.typing.place_caret(target, extend);
+    }
+
+    /// Copy the selection to the system clipboard, keeping a formatted copy
+    /// for pasting back into a text box.
+    pub fn copy_text_selection(&mut self) {
+        let Some(range) = self.typing.selection() else {
+            return;
+        };
+        let copied = CopiedText {
+            text: self.typing.buffer[range.clone()].to_string(),
+            spans: rich_text::slice(&self.typing.spans, range),
+        };
+        if !crate::platform::set_clipboard_text(&copied.text) {
+            log::warn!("No system clipboard; copied text stays inside the app");
+        }
+        self.text_clipboard = copied;
+    }
+
+    pub fn cut_text_selection(&mut self) {
+        if self.typing.selection().is_none() {
+            return;
+        }
+        self.copy_text_selection();
+        self.typing.record_edit(TextEdit::Other);
+        self.typing.delete_selection();
+        self.typing.show_caret();
+    }
+
+    /// Paste the clipboard over the selection. Text copied from a text box
+    /// keeps its formatting.
+    pub fn paste_text(&mut self) {
+        let copied = match crate::platform::clipboard_text() {
+            Some(text) if text != self.text_clipboard.text => CopiedText {
+                text,
+                spans: Vec::new(),
+            },
+            _ => self.text_clipboard.clone(),
+        };
+        self.typing.paste(copied);
+    }
+
+    /// Handle a click while typing: place the caret, extend the selection
+    /// with Shift, or select a word on a double click. False when the click
+    /// is outside the text being typed.
+    pub fn press_in_text(&mut self, canvas_pos: [f32; 2]) -> bool {
+        let shape = self.typing_text_box(self.typing.buffer.clone(), BoxState::Editing);
+        if !shape.hit_test(canvas_pos) {
+            return false;
+        }
+        if self.typing.register_click(self.input.mouse_pos) {
+            self.select_word_at(canvas_pos);
+            return true;
+        }
+        if let Some(index) = self.text_index_at(canvas_pos) {
+            let extend = self.input.modifiers.shift_key();
+            self.typing.place_caret(index, extend);
+            self.typing.dragging = true;
+        }
+        true
+    }
+
+    /// Extend the selection to the text under the dragging mouse.
+    pub fn drag_text_selection(&mut self, canvas_pos: [f32; 2]) {
+        if let Some(index) = self.text_index_at(canvas_pos) {
+            self.typing.place_caret(index, true);
+        }
+    }
+
+    /// Select the word under `canvas_pos` in the text being typed.
+    pub fn select_word_at(&mut self, canvas_pos: [f32; 2]) {
+        if let Some(index) = self.text_index_at(canvas_pos) {
+            let word = text_layout::word_at(&self.typing.buffer, index);
+            self.typing.anchor = Some(word.start);
+            self.typing.set_cursor(word.end);
+            self.typing.last_edit = None;
+            self.typing.show_caret();
+        }
+    }
+
+    /// Where to draw the caret and selection of the text being typed.
+    pub fn text_overlay(&self) -> Option<TextOverlay> {
+        if !self.typing.active {
+            return None;
+        }
+        let (layout, origins) = self.typing_layout()?;
+        let text = &self.typing.buffer;
+        let (line, x) = layout.caret(text, self.typing.cursor_pos);
+        let origin = origins.get(line)?;
+        let top = origin[1] - layout.font.ascent(layout.font_size);
+        let selection = self
+            .typing
+            .selection()
+            .map(|range| layout.selection_rects(text, &origins, range))
+            .unwrap_or_default();
+        let color = rich_text::style_at(&self.typing.spans, self.typing.cursor_pos)
+            .color
+            .unwrap_or_else(|| {
+                self.typing_text_box(String::new(), BoxState::Editing)
+                    .color()
+            });
+        Some(TextOverlay {
+            selection,
+            caret: [
+                [origin[0] + x, top],
+                [origin[0] + x, top + layout.line_height()],
+            ],
+            color,
+        })
+    }
+
+    fn text_index_at(&self, canvas_pos: [f32; 2]) -> Option<usize> {
+        let (layout, origins) = self.typing_layout()?;
+        Some(layout.index_at(&self.typing.buffer, &origins, canvas_pos))
+    }
+
+    /// The layout of the text being typed and the start of each line.
+    fn typing_layout(&self) -> Option<(Arc<TextLayout>, Vec<[f32; 2]>)> {
+        self.typing_text_box(self.typing.buffer.clone(), BoxState::Editing)
+            .text_layout()
+    }
+}
+
+impl TextInput {
+    /// The selected byte range, if any.
+    pub fn selection(&self) -> Option<Range<usize>> {
+        let anchor = self.anchor?;
+        let cursor = self.cursor_pos;
+        (anchor != cursor).then(|| anchor.min(cursor)..anchor.max(cursor))
+    }
+
+    /// Type `text` over the selection, or at the caret.
+    pub fn insert_text(&mut self, text: &str) {
+        self.record_edit(TextEdit::Typing);
+        self.delete_selection();
+        self.buffer.insert_str(self.cursor_pos, text);
+        // Labels are plain text, so they get no formatting.
+        if !self.editing_label {
+            rich_text::insert(&mut self.spans, self.cursor_pos, text.len(), &self.style);
+        }
+        self.cursor_pos += text.len();
+        if !self.editing_label
+            && matches!(text, "*" | "_" | "`" | "~" | ")")
+            && rich_text::apply_shortcut(&mut self.buffer, &mut self.spans, &mut self.cursor_pos)
+        {
+            self.style = SpanStyle::default();
+        }
+        self.show_caret();
+    }
+
+    /// Delete the selection, or the character or word before the caret.
+    pub fn delete_backward(&mut self, word: bool) {
+        let cursor = self.cursor_pos;
+        let range = self.selection().unwrap_or_else(|| match word {
+            true => text_layout::previous_word(&self.buffer, cursor)..cursor,
+            false => text_layout::previous_grapheme(&self.buffer, cursor)..cursor,
+        });
+        self.delete(range);
+    }
+
+    /// Delete the selection, or the character or word after the caret.
+    pub fn delete_forward(&mut self, word: bool) {
+        let cursor = self.cursor_pos;
+        let range = self.selection().unwrap_or_else(|| match word {
+            true => cursor..text_layout::next_word(&self.buffer, cursor),
+            false => cursor..text_layout::next_grapheme(&self.buffer, cursor),
+        });
+        self.delete(range);
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = Some(0);
+        self.set_cursor(self.buffer.len());
+        self.last_edit = None;
+    }
+
+    /// Flip a formatting flag of the selection, or of the text typed next.
+    pub fn toggle_style(&mut self, flag: impl Fn(&mut SpanStyle) -> &mut bool) {
+        if self.editing_label {
+            return;
+        }
+        let Some(range) = self.selection() else {
+            let flag = flag(&mut self.style);
+            *flag = !*flag;
+            return;
+        };
+        self.record_edit(TextEdit::Other);
+        let on = !rich_text::all_styled(&self.spans, range.clone(), |style| {
+            *flag(&mut style.clone())
+        });
+        rich_text::set_style(&mut self.spans, range, |style| {
+            *flag(style) = on;
+        });
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(snapshot) = self.undo.pop() {
+            let current = self.snapshot();
+            self.redo.push(current);
+            self.restore(snapshot);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(snapshot) = self.redo.pop() {
+            let current = self.snapshot();
+            self.undo.push(current);
+            self.restore(snapshot);
+        }
+    }
+
+    /// Clear the selection and undo history for a new editing session.
+    pub fn reset_session(&mut self) {
+        self.anchor = None;
+        self.undo.clear();
+        self.redo.clear();
+        self.last_edit = None;
+        self.dragging = false;
+        self.last_click = None;
+    }
+
+    /// Note a click at `screen_pos`; true when it completes a double click.
+    fn register_click(&mut self, screen_pos: [f32; 2]) -> bool {
+        let double_click = self.last_click.is_some_and(|(instant, pos)| {
+            instant.elapsed().as_millis() < DOUBLE_CLICK_MS
+                && (pos[0] - screen_pos[0]).hypot(pos[1] - screen_pos[1]) <= DOUBLE_CLICK_DISTANCE
+        });
+        self.last_click = Some((Instant::now(), screen_pos));
+        double_click
+    }
+
+    /// Move the caret to `index`, picking up the formatting there.
+    pub fn set_cursor(&mut self, index: usize) {
+        self.cursor_pos = index;
+        self.style = rich_text::style_at(&self.spans, index);
+    }
+
+    /// Paste `copied` over the selection. Control characters are dropped,
+    /// and with them the formatting, which would no longer line up.
+    fn paste(&mut self, copied: CopiedText) {
+        let text: String = copied
+            .text
+            .replace("\r\n", "\n")
+            .chars()
+            .filter(|ch| *ch == '\n' || !ch.is_control())
+            .collect();
+        if text.is_empty() {
+            return;
+        }
+        // Stripping characters would shift the copied formatting.
+        let spans = match text.len() == copied.text.len() && !self.editing_label {
+            true => copied.spans,
+            false => Vec::new(),
+        };
+        self.record_edit(TextEdit::Other);
+        self.delete_selection();
+        self.buffer.insert_str(self.cursor_pos, &text);
+        rich_text::insert_spans(&mut self.spans, self.cursor_pos, text.len(), &spans);
+        self.set_cursor(self.cursor_pos + text.len());
+        self.show_caret();
+    }
+
+    /// Where `motion` takes the caret. Moving by line needs the `layout`
+    /// of the text and the start of each line; without it the caret stays.
+    fn caret_target(
+        &self,
+        motion: CaretMotion,
+        extend: bool,
+        layout: Option<(&TextLayout, &[[f32; 2]])>,
+    ) -> usize {
+        let text = &self.buffer;
+        let cursor = self.cursor_pos;
+        let selection = self.selection().filter(|_| !extend);
+        match motion {
+            // Without Shift, Left and Right first collapse a selection.
+            CaretMotion::Left => selection.map_or_else(
+                || text_layout::previous_grapheme(text, cursor),
+                |range| range.start,
+            ),
+            CaretMotion::Right => selection.map_or_else(
+                || text_layout::next_grapheme(text, cursor),
+                |range| range.end,
+            ),
+            CaretMotion::WordLeft => text_layout::previous_word(text, cursor),
+            CaretMotion::WordRight => text_layout::next_word(text, cursor),
+            CaretMotion::Up | CaretMotion::Down => {
+                let direction = if motion == CaretMotion::Up { -1 } else { 1 };
+                layout.map_or(cursor, |(layout, origins)| {
+                    self.caret_on_line(layout, origins, direction)
+                })
+            }
+            CaretMotion::LineStart | CaretMotion::LineEnd => layout
+                .and_then(|(layout, _)| {
+                    let (line, _) = layout.caret(text, cursor);
+                    let range = layout.lines.get(line)?.range.clone();
+                    Some(match motion {
+                        CaretMotion::LineStart => range.start,
+                        _ => range.end,
+                    })
+                })
+                .unwrap_or(cursor),
+            CaretMotion::TextStart => 0,
+            CaretMotion::TextEnd => text.len(),
+        }
+    }
+
+    /// The caret position one visual line up or down, keeping its offset.
+    fn caret_on_line(&self, layout: &TextLayout, origins: &[[f32; 2]], direction: isize) -> usize {
+        let text = &self.buffer;
+        let (line, x) = layout.caret(text, self.cursor_pos);
+        let target = line.saturating_add_signed(direction);
+        match (origins.get(line), origins.get(target)) {
+            (Some(from), Some(to)) if target != line => {
+                layout.index_at(text, origins, [from[0] + x, to[1]])
+            }
+            _ if direction < 0 => 0,
+            _ => text.len(),
+        }
+    }
+
+    fn place_caret(&mut self, index: usize, extend: bool) {
+        match extend {
+            true => {
+                self.anchor.get_or_insert(self.cursor_pos);
+            }
+            false => self.anchor = None,
+        }
+        self.set_cursor(index.min(self.buffer.len()));
+        self.last_edit = None;
+        self.show_caret();
+    }
+
+    /// Delete `range` and put the caret where it was. Nothing to delete,
+    /// as at either end of the text, is no edit and leaves no undo step.
+    fn delete(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+        self.record_edit(TextEdit::Deleting);
+        self.remove_range(range.clone());
+        self.anchor = None;
+        self.set_cursor(range.start);
+        self.show_caret();
+    }
+
+    fn delete_selection(&mut self) -> bool {
+        let Some(range) = self.selection() else {
+            self.anchor = None;
+            return false;
+        };
+        self.remove_range(range.clone());
+        self.anchor = None;
+        self.set_cursor(range.start);
+        true
+    }
+
+    fn remove_range(&mut self, range: Range<usize>) {
+        self.buffer.replace_range(range.clone(), "");
+        rich_text::remove(&mut self.spans, range);
+    }
+
+    /// Save the text for undo before an edit, unless it continues a run of
+    /// the same kind of edit.
+    fn record_edit(&mut self, edit: TextEdit) {
+        if edit == TextEdit::Other || self.last_edit != Some(edit) {
+            let snapshot = self.snapshot();
+            self.undo.push(snapshot);
+            if self.undo.len() > MAX_TEXT_UNDO {
+                self.undo.remove(0);
+            }
+        }
+        self.redo.clear();
+        self.last_edit = Some(edit);
+    }
+
+    fn snapshot(&self) -> TextSnapshot {
+        TextSnapshot {
+            buffer: self.buffer.clone(),
+            spans: self.spans.clone(),
+            cursor_pos: self.cursor_pos,
+            anchor: self.anchor,
+        }
+    }
+
+    fn restore(&mut self, snapshot: TextSnapshot) {
+        self.buffer = snapshot.buffer;
+        self.spans = snapshot.spans;
+        self.anchor = snapshot.anchor;
+        self.set_cursor(snapshot.cursor_pos);
+        self.last_edit = None;
+        self.show_caret();
+    }
+
+    fn show_caret(&mut self) {
+        self.cursor_visible = true;
+        self.blink_timer = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fonts::FontFamily;
+    use crate::rich_text::TextSpan;
+
+    fn input(buffer: &str) -> TextInput {
+        TextInput {
+            active: true,
+            buffer: buffer.to_string(),
+            pos_canvas: [0.0; 2],
+            editing_id: None,
+            cursor_pos: buffer.len(),
+            cursor_visible: true,
+            blink_timer: Instant::now(),
+            font_size: 20.0,
+            font: FontFamily::default(),
+            editing_label: false,
+            spans: Vec::new(),
+            style: SpanStyle::default(),
+            anchor: None,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            last_edit: None,
+            dragging: false,
+            last_click: None,
+        }
+    }
+
+    fn bold(range: Range<usize>) -> TextSpan {
+        TextSpan {
+            range,
+            style: SpanStyle {
+                bold: true,
+                ..SpanStyle::default()
+            },
+        }
+    }
+
+    #[test]
+    fn runs_of_one_kind_of_edit_undo_together() {
+        let mut typing = input("");
+        for text in ["a", "b", "c"] {
+            typing.insert_text(text);
+        }
+        typing.delete_backward(false);
+        typing.delete_backward(false);
+        assert_eq!(typing.undo.len(), 2);
+        typing.undo();
+        assert_eq!(typing.buffer, "abc");
+        typing.undo();
+        assert_eq!(typing.buffer, "");
+        typing.redo();
+        assert_eq!(typing.buffer, "abc");
+
+        // Moving the caret ends the run; pasting never joins one.
+        typing.insert_text("d");
+        typing.place_caret(0, false);
+        typing.insert_text("e");
+        assert_eq!(typing.undo.len(), 3);
+        assert!(typing.redo.is_empty());
+    }
+
+    #[test]
+    fn deleting_nothing_leaves_no_undo_step() {
+        let mut typing = input("ab");
+        typing.cursor_pos = 0;
+        typing.delete_backward(false);
+        typing.delete_backward(true);
+        typing.cursor_pos = 2;
+        typing.delete_forward(false);
+        assert!(typing.undo.is_empty());
+        assert_eq!(typing.last_edit, None);
+
+        typing.delete_backward(false);
+        assert_eq!((typing.buffer.as_str(), typing.cursor_pos), ("a", 1));
+        assert_eq!(typing.undo.len(), 1);
+    }
+
+    #[test]
+    fn pasting_keeps_formatting_only_when_the_text_is_unchanged() {
+        let mut typing = input("xy");
+        typing.cursor_pos = 1;
+        typing.paste(CopiedText {
+            text: "ab".to_string(),
+            spans: vec![bold(0..1)],
+        });
+        assert_eq!(typing.buffer, "xaby");
+        assert_eq!(typing.spans, [bold(1..2)]);
+        assert_eq!(typing.cursor_pos, 3);
+
+        let mut typing = input("");
+        typing.paste(CopiedText {
+            text: "a\tb\r\nc".to_string(),
+            spans: vec![bold(0..1)],
+        });
+        assert_eq!(typing.buffer, "ab\nc");
+        assert!(typing.spans.is_empty());
+
+        let mut label = input("");
+        label.editing_label = true;
+        label.paste(CopiedText {
+            text: "ab".to_string(),
+            spans: vec![bold(0..1)],
+        });
+        assert!(label.spans.is_empty());
+    }
+
+    #[test]
+    fn left_and_right_collapse_a_selection_unless_extending() {
+        let mut typing = input("one two");
+        typing.anchor = Some(1);
+        typing.cursor_pos = 3;
+        assert_eq!(typing.caret_target(CaretMotion::Left, false, None), 1);
+        assert_eq!(typing.caret_target(CaretMotion::Right, false, None), 3);
+        assert_eq!(typing.caret_target(CaretMotion::Left, true, None), 2);
+        assert_eq!(typing.caret_target(CaretMotion::Right, true, None), 4);
+        // Word motions move from the caret even over a selection.
+        assert_eq!(typing.caret_target(CaretMotion::WordRight, false, None), 7);
+        // Line motions need a layout; without one the caret stays.
+        assert_eq!(typing.caret_target(CaretMotion::Up, false, None), 3);
+
+        typing.place_caret(1, false);
+        assert_eq!((typing.anchor, typing.cursor_pos), (None, 1));
+        typing.place_caret(5, true);
+        assert_eq!(typing.selection(), Some(1..5));
+    }
+
+    #[test]
+    fn double_clicks_need_the_second_click_nearby() {
+        let mut typing = input("");
+        assert!(!typing.register_click([10.0, 10.0]));
+        assert!(typing.register_click([12.0, 13.0]));
+        assert!(!typing.register_click([40.0, 13.0]));
+        typing.last_click = Some((
+            Instant::now() - std::time::Duration::from_secs(1),
+            [40.0, 13.0],
+        ));
+        assert!(!typing.register_click([40.0, 13.0]));
+    }
+}
@@ -0,0 +1,766 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::Range;
+use std::sync::Arc;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::drawing::{TextAlign, TextFormat, TextSizing, VerticalAlign};
+use crate::fonts::{self, ClusterBox, FontFamily, ResolvedFont, StyledFont};
+use crate::rich_text::{self, SpanStyle, TextSpan};
+
+/// Line advance as a multiple of the font size.
+pub const LINE_HEIGHT: f32 = 1.2;
+/// Space between a text box's edge and its text.
+pub const TEXT_PADDING: f32 = 8.0;
+
+/// One visual line: a byte range of the content, trailing spaces excluded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutLine {
+    pub range: Range<usize>,
+    pub width: f32,
+}
+
+/// Content broken into measured lines in one font and size.
+#[derive(Debug, Clone)]
+pub struct TextLayout {
+    pub lines: Vec<LayoutLine>,
+    pub font: ResolvedFont,
+    pub font_size: f32,
+    family: FontFamily,
+    spans: Vec<TextSpan>,
+    /// What the layout was made from, so a cached one can be matched.
+    content: String,
+    wrap_width: Option<f32>,
+    generation: u64,
+}
+
+thread_local! {
+    static CACHE: RefCell<LayoutCache> = RefCell::default();
+}
+
+/// Layouts used this frame and the last, by a hash of what they were made
+/// from. Text that is drawn, measured and hit-tested every frame is shaped
+/// once; a layout unused for a whole frame is dropped.
+#[derive(Default)]
+struct LayoutCache {
+    current: HashMap<u64, Vec<Arc<TextLayout>>>,
+    previous: HashMap<u64, Vec<Arc<TextLayout>>>,
+}
+
+impl LayoutCache {
+    /// The layout `matches` accepts, carried over into this frame if only
+    /// the last one used it.
+    fn find(
+        &mut self,
+        hash: u64,
+        matches: impl Fn(&TextLayout) -> bool,
+    ) -> Option<Arc<TextLayout>> {
+        let current = self.current.get(&hash);
+        if let Some(layout) =
+            current.and_then(|bucket| bucket.iter().find(|layout| matches(layout)))
+        {
+            return Some(layout.clone());
+        }
+        let bucket = self.previous.get_mut(&hash)?;
+        let index = bucket.iter().position(|layout| matches(layout))?;
+        let layout = bucket.swap_remove(index);
+        self.insert(hash, layout.clone());
+        Some(layout)
+    }
+
+    fn insert(&mut self, hash: u64, layout: Arc<TextLayout>) {
+        self.current.entry(hash).or_default().push(layout);
+    }
+}
+
+/// Start a frame of the layout cache: layouts the last frame didn't use
+/// are dropped at the start of the next one.
+pub fn begin_frame() {
+    CACHE.with_borrow_mut(|cache| cache.previous = std::mem::take(&mut cache.current));
+}
+
+/// Part of a line drawn in one style, `x` measured from the line start.
+#[derive(Debug, Clone)]
+pub struct LinePiece<'a> {
+    pub range: Range<usize>,
+    pub style: Option<&'a SpanStyle>,
+    pub font: StyledFont,
+    pub x: f32,
+    pub width: f32,
+}
+
+impl TextLayout {
+    /// Break `content` at newlines and, with `wrap_width`, between words so
+    /// no line is wider. A word wider than the limit breaks between letters.
+    pub fn new(
+        content: &str,
+        family: &FontFamily,
+        font_size: f32,
+        wrap_width: Option<f32>,
+    ) -> Self {
+        Self::styled(content, &[], family, font_size, wrap_width)
+    }
+
+    /// Like [`TextLayout::new`], measuring each span in its own face.
+    pub fn styled(
+        content: &str,
+        spans: &[TextSpan],
+        family: &FontFamily,
+        font_size: f32,
+        wrap_width: Option<f32>,
+    ) -> Self {
+        let mut layout = Self {
+            lines: Vec::new(),
+            font: fonts::resolve(family),
+            font_size,
+            family: family.clone(),
+            spans: spans.to_vec(),
+            content: content.to_string(),
+            wrap_width,
+            generation: 0,
+        };
+        let mut lines = Vec::new();
+        let mut offset = 0;
+        for paragraph in content.split('\n') {
+            let text = Paragraph {
+                content,
+                text: paragraph,
+                offset,
+                layout: &layout,
+            };
+            match wrap_width {
+                Some(max_width) => text.wrap(max_width, &mut lines),
+                None => lines.push(text.line(0..paragraph.len())),
+            }
+            offset += paragraph.len() + 1;
+        }
+        layout.lines = lines;
+        // Read last: laying out may have loaded a fallback font.
+        layout.generation = fonts::generation();
+        layout
+    }
+
+    /// Like [`TextLayout::styled`], but shared with every other caller
+    /// laying out the same content, spans, font, size and wrap width while
+    /// the layout stays in use. An edit changes the inputs and so makes a
+    /// new layout.
+    pub fn shared(
+        content: &str,
+        spans: &[TextSpan],
+        family: &FontFamily,
+        font_size: f32,
+        wrap_width: Option<f32>,
+    ) -> Arc<Self> {
+        let generation = fonts::generation();
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        family.hash(&mut hasher);
+        font_size.to_bits().hash(&mut hasher);
+        wrap_width.map(f32::to_bits).hash(&mut hasher);
+        spans.len().hash(&mut hasher);
+        let hash = hasher.finish();
+        let matches = |layout: &TextLayout| {
+            layout.generation == generation
+                && layout.font_size == font_size
+                && layout.wrap_width == wrap_width
+                && layout.family == *family
+                && layout.content == content
+                && layout.spans == spans
+        };
+        if let Some(layout) = CACHE.with_borrow_mut(|cache| cache.find(hash, matches)) {
+            return layout;
+        }
+        let layout = Arc::new(Self::styled(content, spans, family, font_size, wrap_width));
+        CACHE.with_borrow_mut(|cache| cache.insert(hash, layout.clone()));
+        layout
+    }
+
+    /// Layout of a text box's content, wrapped when the box has a fixed width.
+    pub fn for_box(
+        content: &str,
+        spans: &[TextSpan],
+        family: &FontFamily,
+        font_size: f32,
+        size: [f32; 2],
+        format: &TextFormat,
+    ) -> Self {
+        Self::styled(
+            content,
+            spans,
+            family,
+            font_size,
+            box_wrap_width(font_size, size, format),
+        )
+    }
+
+    /// [`TextLayout::for_box`] through [`TextLayout::shared`].
+    pub fn shared_for_box(
+        content: &str,
+        spans: &[TextSpan],
+        family: &FontFamily,
+        font_size: f32,
+        size: [f32; 2],
+        format: &TextFormat,
+    ) -> Arc<Self> {
+        Self::shared(
+            content,
+            spans,
+            family,
+            font_size,
+            box_wrap_width(font_size, size, format),
+        )
+    }
+
+    /// Split `range` of the content into runs of one style and face.
+    pub fn pieces(&self, content: &str, range: Range<usize>) -> Vec<LinePiece<'_>> {
+        let mut x = 0.0;
+        rich_text::pieces(&self.spans, range)
+            .into_iter()
+            .map(|(range, style)| {
+                let font = self.face(style);
+                let width = font.font.measure(&content[range.clone()], self.font_size);
+                let piece = LinePiece {
+                    range,
+                    style,
+                    font,
+                    x,
+                    width,
+                };
+                x += width;
+                piece
+            })
+            .collect()
+    }
+
+    /// The style under `point`, given the line origins from
+    /// [`TextLayout::box_origins`].
+    pub fn style_at(
+        &self,
+        content: &str,
+        origins: &[[f32; 2]],
+        point: [f32; 2],
+    ) -> Option<&SpanStyle> {
+        let ascent = self.font.ascent(self.font_size);
+        let (line, origin) = self.lines.iter().zip(origins).find(|(_, origin)| {
+            let top = origin[1] - ascent;
+            point[1] >= top && point[1] < top + self.line_height()
+        })?;
+        let x = point[0] - origin[0];
+        self.pieces(content, line.range.clone())
+            .into_iter()
+            .find(|piece| x >= piece.x && x < piece.x + piece.width)
+            .and_then(|piece| piece.style)
+    }
+
+    /// The line holding the caret at byte `index` and the caret's offset
+    /// from that line's start. In right-to-left text the caret sits on the
+    /// right edge of the character after it.
+    pub fn caret(&self, content: &str, index: usize) -> (usize, f32) {
+        let line = self
+            .lines
+            .iter()
+            .rposition(|line| line.range.start <= index)
+            .unwrap_or(0);
+        let Some(range) = self.lines.get(line).map(|line| line.range.clone()) else {
+            return (line, 0.0);
+        };
+        // Carets in the spaces a wrapped line ends with sit past its text.
+        let clusters = self.clusters(content, range.start..index.max(range.end));
+        let x = clusters
+            .iter()
+            .find(|cluster| cluster.range.contains(&index))
+            .map(ClusterBox::leading_x)
+            .or_else(|| {
+                clusters
+                    .iter()
+                    .find(|cluster| cluster.range.end == index)
+                    .map(ClusterBox::trailing_x)
+            })
+            .unwrap_or(0.0);
+        (line, x)
+    }
+
+    /// The caret position closest to `point`, given the line origins from
+    /// [`TextLayout::box_origins`].
+    pub fn index_at(&self, content: &str, origins: &[[f32; 2]], point: [f32; 2]) -> usize {
+        let top = self.font.ascent(self.font_size);
+        let line = origins
+            .iter()
+            .position(|origin| point[1] < origin[1] - top + self.line_height())
+            .unwrap_or(origins.len().saturating_sub(1));
+        let (Some(line), Some(origin)) = (self.lines.get(line), origins.get(line)) else {
+            return 0;
+        };
+        let x = point[0] - origin[0];
+        // Each edge of a cluster is a caret position: its start on the
+        // leading edge, its end on the trailing one.
+        self.clusters(content, line.range.clone())
+            .iter()
+            .flat_map(|cluster| {
+                [
+                    (cluster.leading_x(), cluster.range.start),
+                    (cluster.trailing_x(), cluster.range.end),
+                ]
+            })
+            .min_by(|a, b| (a.0 - x).abs().total_cmp(&(b.0 - x).abs()))
+            .map_or(line.range.start, |(_, index)| index)
+    }
+
+    /// Highlight rectangles, as top-left and bottom-right corners, covering
+    /// `range` of the content. Selected line breaks show as a sliver. A
+    /// range crossing a change of direction can cover separate parts of a
+    /// line, each getting its own rectangle.
+    pub fn selection_rects(
+        &self,
+        content: &str,
+        origins: &[[f32; 2]],
+        range: Range<usize>,
+    ) -> Vec<[[f32; 2]; 2]> {
+        let ascent = self.font.ascent(self.font_size);
+        let mut rects = Vec::new();
+        for (index, (line, origin)) in self.lines.iter().zip(origins).enumerate() {
+            let next = self
+                .lines
+                .get(index + 1)
+                .map_or(content.len(), |next| next.range.start);
+            if range.end < line.range.start || range.start > next || range.start == next {
+                continue;
+            }
+            let mut spans: Vec<(f32, f32)> = Vec::new();
+            for cluster in self.clusters(content, line.range.clone()) {
+                if cluster.range.start < range.start || cluster.range.end > range.end {
+                    continue;
+                }
+                match spans.last_mut() {
+                    Some(last) if (last.1 - cluster.x).abs() < 0.01 => {
+                        last.1 = cluster.x + cluster.width
+                    }
+                    _ => spans.push((cluster.x, cluster.x + cluster.width)),
+                }
+            }
+            if range.end.min(next) > line.range.end {
+                let sliver = (line.width, line.width + self.font_size * 0.3);
+                match spans.last_mut() {
+                    Some(last) if (last.1 - sliver.0).abs() < 0.01 => last.1 = sliver.1,
+                    _ => spans.push(sliver),
+                }
+            }
+            let top = origin[1] - ascent;
+            for (x0, x1) in spans.into_iter().filter(|(x0, x1)| x1 > x0) {
+                rects.push([
+                    [origin[0] + x0, top],
+                    [origin[0] + x1, top + self.line_height()],
+                ]);
+            }
+        }
+        rects
+    }
+
+    /// The clusters of `range` in visual order, with byte ranges into the
+    /// content and `x` measured from the range start.
+    fn clusters(&self, content: &str, range: Range<usize>) -> Vec<ClusterBox> {
+        let mut clusters = Vec::new();
+        for piece in self.pieces(content, range) {
+            let text = &content[piece.range.clone()];
+            for cluster in piece.font.font.clusters(text, self.font_size) {
+                clusters.push(ClusterBox {
+                    range: piece.range.start + cluster.range.start
+                        ..piece.range.start + cluster.range.end,
+                    x: piece.x + cluster.x,
+                    ..cluster
+                });
+            }
+        }
+        clusters
+    }
+
+    fn face(&self, style: Option<&SpanStyle>) -> StyledFont {
+        match style {
+            Some(style) if style.code => {
+                fonts::resolve_styled(&FontFamily::Code, style.bold, style.italic)
+            }
+            Some(style) if style.bold || style.italic => {
+                fonts::resolve_styled(&self.family, style.bold, style.italic)
+            }
+            _ => StyledFont {
+                font: self.font.clone(),
+                fake_bold: false,
+                fake_italic: false,
+            },
+        }
+    }
+
+    fn measure(&self, content: &str, range: Range<usize>) -> f32 {
+        if self.spans.is_empty() {
+            return self.font.measure(&content[range], self.font_size);
+        }
+        self.pieces(content, range)
+            .iter()
+            .map(|piece| piece.width)
+            .sum()
+    }
+
+    pub fn line_height(&self) -> f32 {
+        self.font_size * LINE_HEIGHT
+    }
+
+    pub fn width(&self) -> f32 {
+        self.lines.iter().map(|line| line.width).fold(0.0, f32::max)
+    }
+
+    pub fn height(&self) -> f32 {
+        self.lines.len() as f32 * self.line_height()
+    }
+
+    /// The size a text box needs for this layout: auto-grow boxes hug the
+    /// text, fixed-width ones keep their width and grow to fit.
+    pub fn box_size(&self, size: [f32; 2], format: &TextFormat) -> [f32; 2] {
+        let height = self.height() + TEXT_PADDING * 2.0;
+        match format.sizing {
+            TextSizing::AutoGrow => [
+                self.width().max(self.font_size * 0.8) + TEXT_PADDING * 2.0,
+                height,
+            ],
+            TextSizing::FixedWidth => [size[0], size[1].max(height)],
+        }
+    }
+
+    /// Baseline start of each line inside a box at `pos` of `size`.
+    pub fn box_origins(&self, pos: [f32; 2], size: [f32; 2], format: &TextFormat) -> Vec<[f32; 2]> {
+        let spare = (size[1] - TEXT_PADDING * 2.0 - self.height()).max(0.0);
+        let top = pos[1]
+            + TEXT_PADDING
+            + match format.vertical_align {
+                VerticalAlign::Top => 0.0,
+                VerticalAlign::Middle => spare * 0.5,
+                VerticalAlign::Bottom => spare,
+            };
+        let baseline = top + self.font.ascent(self.font_size);
+        self.lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| {
+                let x = match format.align {
+                    TextAlign::Left => pos[0] + TEXT_PADDING,
+                    TextAlign::Center => pos[0] + (size[0] - line.width) * 0.5,
+                    TextAlign::Right => pos[0] + size[0] - TEXT_PADDING - line.width,
+                };
+                [x, baseline + index as f32 * self.line_height()]
+            })
+            .collect()
+    }
+}
+
+/// Byte offset of the grapheme cluster boundary before `index`, for moving
+/// a caret or deleting backwards.
+pub fn previous_grapheme(text: &str, index: usize) -> usize {
+    text[..index]
+        .grapheme_indices(true)
+        .next_back()
+        .map_or(0, |(start, _)| start)
+}
+
+/// Byte offset of the grapheme cluster boundary after `index`.
+pub fn next_grapheme(text: &str, index: usize) -> usize {
+    text[index..]
+        .graphemes(true)
+        .next()
+        .map_or(index, |cluster| index + cluster.len())
+}
+
+/// Start of the word before `index`, skipping spaces, for Ctrl+Left.
+pub fn previous_word(text: &str, index: usize) -> usize {
+    text[..index]
+        .split_word_bound_indices()
+        .rev()
+        .find(|(_, word)| !word.chars().all(char::is_whitespace))
+        .map_or(0, |(start, _)| start)
+}
+
+/// End of the word after `index`, skipping spaces, for Ctrl+Right.
+pub fn next_word(text: &str, index: usize) -> usize {
+    text[index..]
+        .split_word_bound_indices()
+        .find(|(_, word)| !word.chars().all(char::is_whitespace))
+        .map_or(text.len(), |(start, word)| index + start + word.len())
+}
+
+/// The word, run of spaces or symbol at `index`, for double-click
+/// selection. At the end of the text it is the last one.
+pub fn word_at(text: &str, index: usize) -> Range<usize> {
+    let mut words = text
+        .split_word_bound_indices()
+        .map(|(start, word)| start..start + word.len());
+    let mut last = index..index;
+    for word in words.by_ref() {
+        if word.contains(&index) {
+            return word;
+        }
+        if word.end == index && word.start != word.end {
+            last = word;
+        }
+    }
+    last
+}
+
+/// How wide a text box's lines may be, when its width is fixed.
+fn box_wrap_width(font_size: f32, size: [f32; 2], format: &TextFormat) -> Option<f32> {
+    match format.sizing {
+        TextSizing::AutoGrow => None,
+        TextSizing::FixedWidth => Some((size[0] - TEXT_PADDING * 2.0).max(font_size)),
+    }
+}
+
+/// One newline-free run of the content, starting at byte `offset`.
+struct Paragraph<'a> {
+    content: &'a str,
+    text: &'a str,
+    offset: usize,
+    layout: &'a TextLayout,
+}
+
+impl Paragraph<'_> {
+    /// Greedy word wrap into lines no wider than `max_width`. Long words
+    /// break between grapheme clusters, never inside one.
+    ///
+    /// The paragraph is shaped once; a candidate line's width is the sum of
+    /// its clusters' advances rather than a fresh measurement.
+    fn wrap(&self, max_width: f32, lines: &mut Vec<LayoutLine>) {
+        let advance = self.advances();
+        let mut start = 0;
+        // Where the next line may start: just after the last run of spaces.
+        let mut break_at = None;
+        for (index, cluster) in self.text.grapheme_indices(true) {
+            let end = index + cluster.len();
+            if cluster.chars().all(char::is_whitespace) {
+                break_at = Some(end);
+                continue;
+            }
+            if advance(end) - advance(start) <= max_width {
+                continue;
+            }
+            let split = match break_at {
+                Some(split) if split > start => split,
+                _ if index > start => index,
+                _ => continue,
+            };
+            lines.push(self.line(start..split));
+            start = split;
+            break_at = None;
+        }
+        lines.push(self.line(start..self.text.len()));
+    }
+
+    /// Total advance of the clusters ending at or before each byte offset
+    /// of the paragraph, in logical order.
+    fn advances(&self) -> impl Fn(usize) -> f32 {
+        let range = self.offset..self.offset + self.text.len();
+        let mut clusters = self.layout.clusters(self.content, range);
+        clusters.sort_by_key(|cluster| cluster.range.start);
+        let ends: Vec<usize> = clusters
+            .iter()
+            .map(|cluster| cluster.range.end - self.offset)
+            .collect();
+        let totals: Vec<f32> = clusters
+            .iter()
+            .scan(0.0, |total, cluster| {
+                *total += cluster.width;
+                Some(*total)
+            })
+            .collect();
+        move |index| match ends.partition_point(|&end| end <= index) {
+            0 => 0.0,
+            count => totals[count - 1],
+        }
+    }
+
+    fn line(&self, range: Range<usize>) -> LayoutLine {
+        let text = self.text[range.clone()].trim_end();
+        let range = self.offset + range.start..self.offset + range.start + text.len();
+        LayoutLine {
+            width: self.layout.measure(self.content, range.clone()),
+            range,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line_texts<'a>(content: &'a str, layout: &TextLayout) -> Vec<&'a str> {
+        layout
+            .lines
+            .iter()
+            .map(|line| &content[line.range.clone()])
+            .collect()
+    }
+
+    #[test]
+    fn wraps_between_words() {
+        let content = "one two three\nfour";
+        let font = fonts::resolve(&FontFamily::HandDrawn);
+        let width = font.measure("one two", 20.0) + 1.0;
+        let layout = TextLayout::new(content, &FontFamily::HandDrawn, 20.0, Some(width));
+        assert_eq!(line_texts(content, &layout), ["one two", "three", "four"]);
+        assert!(layout.lines.iter().all(|line| line.width <= width));
+    }
+
+    #[test]
+    fn breaks_long_words_between_letters() {
+        let content = "abcdefghij";
+        let width = fonts::resolve(&FontFamily::Normal).measure("abcd", 20.0);
+        let layout = TextLayout::new(content, &FontFamily::Normal, 20.0, Some(width));
+        assert!(layout.lines.len() > 1);
+        assert_eq!(line_texts(content, &layout).concat(), content);
+    }
+
+    #[test]
+    fn wraps_styled_text_by_its_shaped_widths() {
+        let content = "plain words then some code words and more plain words";
+        let code = content.find("code").unwrap();
+        let spans = [TextSpan {
+            range: code..code + "code words".len(),
+            style: SpanStyle {
+                code: true,
+                ..SpanStyle::default()
+            },
+        }];
+        let width = 120.0;
+        let layout = TextLayout::styled(content, &spans, &FontFamily::Normal, 20.0, Some(width));
+        assert!(layout.lines.len() > 2);
+        assert!(layout.lines.iter().all(|line| line.width <= width + 0.5));
+        // Each line is as full as it can be: the next word would not fit.
+        for pair in layout.lines.windows(2) {
+            let next_word = content[pair[1].range.clone()].split(' ').next().unwrap();
+            let joined = pair[0].range.start..pair[1].range.start + next_word.len();
+            assert!(layout.measure(content, joined) > width);
+        }
+    }
+
+    #[test]
+    fn shared_layouts_are_reused_until_an_edit_or_a_frame_without_them() {
+        let family = FontFamily::Normal;
+        let layout = TextLayout::shared("shared text", &[], &family, 20.0, None);
+        let again = TextLayout::shared("shared text", &[], &family, 20.0, None);
+        assert!(Arc::ptr_eq(&layout, &again));
+
+        let edited = TextLayout::shared("shared text!", &[], &family, 20.0, None);
+        assert!(!Arc::ptr_eq(&layout, &edited));
+        assert_eq!(&edited.content, "shared text!");
+        let wrapped = TextLayout::shared("shared text", &[], &family, 20.0, Some(40.0));
+        assert!(!Arc::ptr_eq(&layout, &wrapped));
+        assert!(wrapped.lines.len() > 1);
+
+        // Used in every frame, a layout lives on.
+        begin_frame();
+        let next_frame = TextLayout::shared("shared text", &[], &family, 20.0, None);
+        assert!(Arc::ptr_eq(&layout, &next_frame));
+        begin_frame();
+        begin_frame();
+        let rebuilt = TextLayout::shared("shared text", &[], &family, 20.0, None);
+        assert!(!Arc::ptr_eq(&layout, &rebuilt));
+        assert_eq!(rebuilt.lines, layout.lines);
+    }
+
+    #[test]
+    fn code_font_is_monospaced() {
+        let font = fonts::resolve(&FontFamily::Code);
+        assert_ne!(font.id, fonts::resolve(&FontFamily::Normal).id);
+        let advance = font.measure("M", 20.0);
+        assert!(advance > 0.0);
+        for ch in (' '..='~').map(String::from) {
+            assert_eq!(font.measure(&ch, 20.0), advance, "{ch:?}");
+        }
+        let (glyphs, width) = font.place_line("il|W.m_", 20.0);
+        assert_eq!(width, advance * 7.0);
+        for (index, glyph) in glyphs.iter().enumerate() {
+            let left = advance * index as f32;
+            assert!(glyph.x >= left && glyph.x < left + advance, "{index}");
+        }
+        let missing = fonts::resolve(&FontFamily::Custom("No such font".to_string()));
+        assert_eq!(missing.id, fonts::resolve(&FontFamily::HandDrawn).id);
+    }
+
+    #[test]
+    fn right_to_left_runs_are_reordered() {
+        let font = fonts::resolve(&FontFamily::Normal);
+        let (glyphs, width) = font.place_line("\u{202E}ab", 20.0);
+        let (b, _) = font.place_line("b", 20.0);
+        assert_eq!(glyphs.len(), 2);
+        assert_eq!(glyphs[0].id, b[0].id);
+        assert!(glyphs[0].x < glyphs[1].x);
+        assert_eq!(width, font.measure("ab", 20.0));
+    }
+
+    #[test]
+    fn caret_moves_over_whole_clusters() {
+        let text = "ae\u{301}b";
+        assert_eq!(next_grapheme(text, 1), 4);
+        assert_eq!(previous_grapheme(text, 4), 1);
+        assert_eq!(previous_grapheme(text, 0), 0);
+        assert_eq!(next_grapheme(text, text.len()), text.len());
+    }
+
+    #[test]
+    fn words_are_found_for_navigation() {
+        let text = "one  two, three";
+        assert_eq!(next_word(text, 0), 3);
+        assert_eq!(next_word(text, 3), 8);
+        assert_eq!(previous_word(text, text.len()), 10);
+        assert_eq!(previous_word(text, 5), 0);
+        assert_eq!(word_at(text, 6), 5..8);
+        assert_eq!(word_at(text, text.len()), 10..15);
+    }
+
+    #[test]
+    fn carets_land_between_clusters() {
+        let content = "ab\ncd";
+        let layout = TextLayout::new(content, &FontFamily::Normal, 20.0, None);
+        let origins = [[0.0, 20.0], [0.0, 20.0 + layout.line_height()]];
+        let (line, x) = layout.caret(content, 4);
+        assert_eq!(line, 1);
+        assert_eq!(x, layout.font.measure("c", 20.0));
+        assert_eq!(layout.index_at(content, &origins, [x + 0.1, 30.0]), 4);
+        assert_eq!(layout.index_at(content, &origins, [-5.0, 0.0]), 0);
+        let rects = layout.selection_rects(content, &origins, 1..4);
+        assert_eq!(rects.len(), 2);
+    }
+
+    #[test]
+    fn right_to_left_carets_follow_the_reordered_glyphs() {
+        // Monospaced, so each letter is one advance wide wherever it lands.
+        let content = "ab \u{627}\u{62F}";
+        let layout = TextLayout::new(content, &FontFamily::Code, 20.0, None);
+        let w = layout.font.measure("a", 20.0);
+        let origins = [[0.0, 20.0]];
+        // Alef is drawn at 4w, right of dal at 3w.
+        assert_eq!(layout.caret(content, 3), (0, w * 5.0));
+        assert_eq!(layout.caret(content, 5), (0, w * 4.0));
+        assert_eq!(layout.caret(content, content.len()), (0, w * 3.0));
+        assert_eq!(layout.index_at(content, &origins, [w * 4.9, 20.0]), 3);
+        assert_eq!(layout.index_at(content, &origins, [w * 4.4, 20.0]), 5);
+        let top = 20.0 - layout.font.ascent(20.0);
+        let bottom = top + layout.line_height();
+        // "b", the space and alef are logically adjacent but visually apart.
+        assert_eq!(
+            layout.selection_rects(content, &origins, 1..5),
+            [
+                [[w, top], [w * 3.0, bottom]],
+                [[w * 4.0, top], [w * 5.0, bottom]]
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_glyphs_become_tofu() {
+        let font = fonts::resolve(&FontFamily::HandDrawn);
+        // Plane 16 private use: no font has it.
+        let (glyphs, width) = font.place_line("\u{10FFFD}\u{200D}", 20.0);
+        assert_eq!(glyphs.len(), 1);
+        assert_eq!(glyphs[0].id, fonts::TOFU);
+        assert_eq!(width, 20.0 * fonts::TOFU_ADVANCE);
+    }
+}
@@ -1,7 +1,8 @@
 use crate::drawing::{DrawingElement, Element};
-use ab_glyph::{Font, FontArc, ScaleFont, point};
+use crate::fonts::{self, FontFamily, FontId, PlacedGlyph, ResolvedFont};
+use ab_glyph::{Font, FontArc, GlyphImageFormat, point};
 use std::collections::HashMap;
-use std::collections::hash_map::Entry;
+use std::ops::Range;
 use wgpu::util::DeviceExt;
 
 #[repr(C)]
@@ -10,6 +11,7 @@ pub struct TextVertex {
     pos: [f32; 2],
     uv: [f32; 2],
     color: [f32; 4],
+    page: u32,
 }
 impl TextVertex {
     fn desc() -> wgpu::VertexBufferLayout<'static> {
@@ -33,11 +35,30 @@ impl TextVertex {
                     shader_location: 2,
                     format: wgpu::VertexFormat::Float32x4,
                 },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 8]>() as _,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Uint32,
+                },
             ],
         }
     }
 }
 
+/// One line of placed glyphs and where its baseline starts.
+struct GlyphRun<'a> {
+    glyphs: &'a [PlacedGlyph],
+    origin: [f32; 2],
+    px: f32,
+    color: [f32; 4],
+    /// Draw color glyphs as solid shapes in `color`, as effects do.
+    silhouette: bool,
+    /// Fake a bold face by drawing each glyph twice, slightly apart.
+    bold: bool,
+    /// Fake an italic face by slanting each glyph.
+    italic: bool,
+}
+
 #[derive(Clone, Copy)]
 pub struct GlyphInfo {
     uv_min: [f32; 2],
@@ -45,24 +66,170 @@ pub struct GlyphInfo {
     size: [f32; 2],
     bearing: [f32; 2],
     advance: f32,
+    page: u32,
+    /// The MSDF cell the glyph owns, if it owns one.
+    cell: Option<usize>,
 }
 
 const MSDF_SIZE: u32 = 64;
 const MSDF_RANGE: f32 = 6.0;
 const MSDF_BASE_SIZE: f32 = 64.0;
 const ATLAS_SIZE: u32 = 2048;
+/// Color glyphs such as emoji are kept as bitmaps at up to this size.
+const COLOR_GLYPH_SIZE: u32 = 96;
+const COLOR_ATLAS_SIZE: u32 = 1024;
+
+/// Atlas pages, told apart by the shaders per vertex.
+const MSDF_PAGE: u32 = 0;
+const COLOR_PAGE: u32 = 1;
+/// The color page sampled for coverage only.
+const SILHOUETTE_PAGE: u32 = 2;
+
+/// Faked styles and decorations, as fractions of the font size.
+const BOLD_OFFSET: f32 = 0.035;
+const ITALIC_SLANT: f32 = 0.2;
+const DECORATION_WIDTH: f32 = 0.06;
+
+/// Packs glyph cells row by row into a square atlas.
+struct Shelf {
+    size: u32,
+    next_x: u32,
+    next_y: u32,
+    row_h: u32,
+}
+
+impl Shelf {
+    fn new(size: u32) -> Self {
+        Self {
+            size,
+            next_x: 0,
+            next_y: 0,
+            row_h: 0,
+        }
+    }
+
+    /// Top-left corner of a free `w` x `h` cell, or `None` when full.
+    fn allocate(&mut self, w: u32, h: u32) -> Option<[u32; 2]> {
+        if self.next_x + w >= self.size {
+            self.next_x = 0;
+            self.next_y += self.row_h;
+            self.row_h = 0;
+        }
+        if self.next_y + h >= self.size {
+            return None;
+        }
+        self.row_h = self.row_h.max(h);
+        let corner = [self.next_x, self.next_y];
+        self.next_x += w + 1;
+        Some(corner)
+    }
+}
+
+/// Equal cells of the MSDF page. Once every cell is taken, the one drawn
+/// least recently is handed out again, but never one drawn this frame.
+struct CellPool<K> {
+    columns: u32,
+    /// Cell edge plus the gutter between cells.
+    pitch: u32,
+    capacity: usize,
+    cells: Vec<Cell<K>>,
+}
+
+struct Cell<K> {
+    owner: Option<K>,
+    /// Frame the cell was last drawn in; pinned cells are never reused.
+    last_used: u64,
+}
+
+impl<K: Copy> CellPool<K> {
+    fn new(atlas_size: u32, cell_size: u32) -> Self {
+        let pitch = cell_size + 1;
+        let columns = atlas_size / pitch;
+        Self {
+            columns,
+            pitch,
+            capacity: (columns * columns) as usize,
+            cells: Vec::new(),
+        }
+    }
+
+    fn corner(&self, index: usize) -> [u32; 2] {
+        let index = index as u32;
+        [
+            index % self.columns * self.pitch,
+            index / self.columns * self.pitch,
+        ]
+    }
+
+    /// The corner of a cell kept for good, for contents shared by many
+    /// glyphs.
+    fn pin(&mut self) -> Option<[u32; 2]> {
+        if self.cells.len() == self.capacity {
+            return None;
+        }
+        self.cells.push(Cell {
+            owner: None,
+            last_used: u64::MAX,
+        });
+        Some(self.corner(self.cells.len() - 1))
+    }
+
+    /// A cell for `owner`, drawn in `frame`, and the owner it was taken
+    /// from. `None` when every cell is pinned or drawn this frame.
+    fn allocate(&mut self, owner: K, frame: u64) -> Option<(usize, Option<K>)> {
+        let index = if self.cells.len() < self.capacity {
+            self.cells.push(Cell {
+                owner: None,
+                last_used: frame,
+            });
+            self.cells.len() - 1
+        } else {
+            self.cells
+                .iter()
+                .enumerate()
+                .filter(|(_, cell)| cell.last_used < frame)
+                .min_by_key(|(_, cell)| cell.last_used)?
+                .0
+        };
+        let cell = &mut self.cells[index];
+        cell.last_used = frame;
+        Some((index, cell.owner.replace(owner)))
+    }
+
+    /// Mark the cell as drawn in `frame`.
+    fn touch(&mut self, index: usize, frame: u64) {
+        let cell = &mut self.cells[index];
+        cell.last_used = cell.last_used.max(frame);
+    }
+}
 
 pub struct TextRenderer {
-    font: FontArc,
+    /// Font of screen labels.
+    ui_font: ResolvedFont,
     tex: wgpu::Texture,
     view: wgpu::TextureView,
     sampler: wgpu::Sampler,
-    cache: HashMap<ab_glyph::GlyphId, GlyphInfo>,
-    next_x: u32,
-    next_y: u32,
-    row_h: u32,
+    cache: HashMap<(FontId, ab_glyph::GlyphId), GlyphInfo>,
+    cells: CellPool<(FontId, ab_glyph::GlyphId)>,
+    /// Counts calls to `begin_frame`, for picking cells to reuse.
+    frame: u64,
+    /// The tofu box every font shares, without an advance.
+    tofu: GlyphInfo,
+    /// Whether running out of cells has been reported.
+    warned_full: bool,
+    /// Second atlas page, holding color glyphs as premultiplied RGBA.
+    color_tex: wgpu::Texture,
+    color_shelf: Shelf,
+    /// A solid cell of the glyph atlas that decorations are drawn with.
+    solid_uv: [f32; 2],
     pipeline: wgpu::RenderPipeline,
     screen_pipeline: Option<wgpu::RenderPipeline>,
+    effect_pipeline: wgpu::RenderPipeline,
+    format: wgpu::TextureFormat,
+    shader: wgpu::ShaderModule,
+    pl_layout: wgpu::PipelineLayout,
+    screen_shader: wgpu::ShaderModule,
+    screen_pl_layout: wgpu::PipelineLayout,
     bind_group: wgpu::BindGroup,
     vertices: Vec<TextVertex>,
     indices: Vec<u16>,
@@ -72,6 +239,8 @@ pub struct TextRenderer {
     screen_indices: Vec<u16>,
     screen_vbuf: Option<wgpu::Buffer>,
     screen_ibuf: Option<wgpu::Buffer>,
+    effect_vbuf: Option<wgpu::Buffer>,
+    effect_ibuf: Option<wgpu::Buffer>,
 }
 
 fn generate_msdf(bitmap: &[u8], width: u32, height: u32) -> Vec<u8> {
@@ -208,6 +377,7 @@ impl TextRenderer {
         fmt: wgpu::TextureFormat,
         canvas_bind_group_layout: &wgpu::BindGroupLayout,
         ui_screen_bind_group_layout: &wgpu::BindGroupLayout,
+        sample_count: u32,
     ) -> Self {
         let tex = device.create_texture(&wgpu::TextureDescriptor {
             label: Some("msdf atlas"),
@@ -224,6 +394,21 @@ impl TextRenderer {
             view_formats: &[],
         });
         let view = tex.create_view(&wgpu::TextureViewDescriptor::default());
+        let color_tex = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("color glyph atlas"),
+            size: wgpu::Extent3d {
+                width: COLOR_ATLAS_SIZE,
+                height: COLOR_ATLAS_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let color_view = color_tex.create_view(&wgpu::TextureViewDescriptor::default());
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             mag_filter: wgpu::FilterMode::Linear,
             min_filter: wgpu::FilterMode::Linear,
@@ -248,6 +433,16 @@ impl TextRenderer {
                     ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
                     count: None,
                 },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
             ],
         });
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
@@ -262,6 +457,10 @@ impl TextRenderer {
                     binding: 1,
                     resource: wgpu::BindingResource::Sampler(&sampler),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&color_view),
+                },
             ],
         });
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
@@ -275,31 +474,10 @@ impl TextRenderer {
             bind_group_layouts: &[canvas_bind_group_layout, &bgl],
             push_constant_ranges: &[],
         });
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("text‑pipe"),
-            layout: Some(&pl_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
-                buffers: &[TextVertex::desc()],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: fmt,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            }),
-            primitive: wgpu::PrimitiveState::default(),
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
-            cache: None,
-        });
+        let pipeline = create_pipeline(device, "text‑pipe", &shader, &pl_layout, fmt, sample_count);
+        // Effect targets are single-sampled regardless of the MSAA setting.
+        let effect_pipeline =
+            create_pipeline(device, "effect-text-pipe", &shader, &pl_layout, fmt, 1);
 
         let screen_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("screen-text shader"),
@@ -312,43 +490,44 @@ impl TextRenderer {
             bind_group_layouts: &[ui_screen_bind_group_layout, &bgl],
             push_constant_ranges: &[],
         });
-        let screen_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("screen-text-pipe"),
-            layout: Some(&screen_pl_layout),
-            vertex: wgpu::VertexState {
-                module: &screen_shader,
-                entry_point: Some("vs_main"),
-                buffers: &[TextVertex::desc()],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &screen_shader,
-                entry_point: Some("fs_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: fmt,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            }),
-            primitive: wgpu::PrimitiveState::default(),
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
-            cache: None,
-        });
+        let screen_pipeline = create_pipeline(
+            device,
+            "screen-text-pipe",
+            &screen_shader,
+            &screen_pl_layout,
+            fmt,
+            sample_count,
+        );
+
+        let mut cells = CellPool::new(ATLAS_SIZE, MSDF_SIZE);
+        let solid = cells.pin().unwrap();
+        write_cell(queue, &tex, solid, [4, 4], &[255; 4 * 4 * 4]);
+        let solid_uv = solid.map(|edge| (edge as f32 + 2.0) / ATLAS_SIZE as f32);
+        let (bitmap, size, bearing) = tofu_bitmap();
+        let tofu_cell = cells.pin().unwrap();
+        let tofu = upload_msdf(queue, &tex, tofu_cell, &bitmap, size, bearing);
 
         Self {
-            font: FontArc::try_from_slice(include_bytes!("../data/fonts/Virgil.ttf")).unwrap(),
+            ui_font: fonts::resolve(&FontFamily::HandDrawn),
             tex,
             view,
             sampler,
             cache: HashMap::new(),
-            next_x: 0,
-            next_y: 0,
-            row_h: 0,
+            cells,
+            frame: 0,
+            tofu,
+            warned_full: false,
+            color_tex,
+            color_shelf: Shelf::new(COLOR_ATLAS_SIZE),
+            solid_uv,
             pipeline,
             screen_pipeline: Some(screen_pipeline),
+            effect_pipeline,
+            format: fmt,
+            shader,
+            pl_layout,
+            screen_shader,
+            screen_pl_layout,
             bind_group,
             vertices: Vec::new(),
             indices: Vec::new(),
@@ -358,115 +537,117 @@ impl TextRenderer {
             screen_indices: Vec::new(),
             screen_vbuf: None,
             screen_ibuf: None,
+            effect_vbuf: None,
+            effect_ibuf: None,
         }
     }
 
+    /// Rebuild the canvas and screen text pipelines for a new MSAA sample count.
+    pub fn set_sample_count(&mut self, device: &wgpu::Device, sample_count: u32) {
+        self.pipeline = create_pipeline(
+            device,
+            "text‑pipe",
+            &self.shader,
+            &self.pl_layout,
+            self.format,
+            sample_count,
+        );
+        self.screen_pipeline = Some(create_pipeline(
+            device,
+            "screen-text-pipe",
+            &self.screen_shader,
+            &self.screen_pl_layout,
+            self.format,
+            sample_count,
+        ));
+    }
+
+    /// Start a frame. Glyph cells drawn from here on aren't reused until
+    /// the next frame.
+    pub fn begin_frame(&mut self) {
+        self.frame += 1;
+    }
+
     fn cache_glyph(
         &mut self,
-        device: &wgpu::Device,
         queue: &wgpu::Queue,
+        font_id: FontId,
         gid: ab_glyph::GlyphId,
-    ) -> &GlyphInfo {
-        match self.cache.entry(gid) {
-            Entry::Occupied(entry) => entry.into_mut(),
-            Entry::Vacant(vacant) => {
-                let scale = ab_glyph::PxScale::from(MSDF_BASE_SIZE);
-                let mut gl = gid.with_scale(scale);
-                gl.position = point(0.0, 0.0);
-
-                let maybe_out = self.font.outline_glyph(gl.clone());
-
-                if maybe_out.is_none() {
-                    let info = GlyphInfo {
+    ) -> GlyphInfo {
+        let key = (font_id, gid);
+        if let Some(info) = self.cache.get(&key) {
+            if let Some(cell) = info.cell {
+                self.cells.touch(cell, self.frame);
+            }
+            return *info;
+        }
+        let font = fonts::font_by_id(font_id);
+        let advance = font.h_advance_unscaled(gid) / MSDF_BASE_SIZE;
+        if gid == fonts::TOFU {
+            return *self.cache.entry(key).or_insert(GlyphInfo {
+                advance,
+                ..self.tofu
+            });
+        }
+
+        let scale = ab_glyph::PxScale::from(MSDF_BASE_SIZE);
+        let mut gl = gid.with_scale(scale);
+        gl.position = point(0.0, 0.0);
+
+        let Some(out) = font.outline_glyph(gl) else {
+            // No outline: a color bitmap such as an emoji, or blank.
+            let info =
+                upload_color_glyph(queue, &self.color_tex, &mut self.color_shelf, &font, gid)
+                    .unwrap_or(GlyphInfo {
                         uv_min: [0.0, 0.0],
                         uv_max: [0.0, 0.0],
                         size: [0.0, 0.0],
                         bearing: [0.0, 0.0],
-                        advance: self.font.h_advance_unscaled(gl.id) / MSDF_BASE_SIZE,
-                    };
-                    return vacant.insert(info);
-                }
-
-                let out = maybe_out.unwrap();
-                let bb = out.px_bounds();
-                let glyph_w = bb.width() as u32;
-                let glyph_h = bb.height() as u32;
-
-                let atlas_w = MSDF_SIZE;
-                let atlas_h = MSDF_SIZE;
-
-                if self.next_x + atlas_w >= ATLAS_SIZE {
-                    self.next_x = 0;
-                    self.next_y += self.row_h;
-                    self.row_h = 0;
-                }
-                if self.next_y + atlas_h >= ATLAS_SIZE {
-                    panic!("glyph atlas full");
-                }
-                self.row_h = self.row_h.max(atlas_h);
-
-                let mut bitmap = vec![0u8; (glyph_w * glyph_h) as usize];
-                out.draw(|x, y, v| {
-                    bitmap[(y * glyph_w + x) as usize] = (v * 255.0) as u8;
-                });
-
-                let msdf_data = generate_msdf(&bitmap, glyph_w, glyph_h);
-
-                let mut rgba_data = Vec::with_capacity((atlas_w * atlas_h * 4) as usize);
-                for i in 0..(atlas_w * atlas_h) as usize {
-                    if i < msdf_data.len() / 3 {
-                        rgba_data.push(msdf_data[i * 3]); // R
-                        rgba_data.push(msdf_data[i * 3 + 1]); // G
-                        rgba_data.push(msdf_data[i * 3 + 2]); // B
-                        rgba_data.push(255); // A
-                    } else {
-                        rgba_data.extend_from_slice(&[0, 0, 0, 0]);
-                    }
-                }
-
-                queue.write_texture(
-                    wgpu::TexelCopyTextureInfo {
-                        texture: &self.tex,
-                        mip_level: 0,
-                        origin: wgpu::Origin3d {
-                            x: self.next_x,
-                            y: self.next_y,
-                            z: 0,
-                        },
-                        aspect: wgpu::TextureAspect::All,
-                    },
-                    &rgba_data,
-                    wgpu::TexelCopyBufferLayout {
-                        offset: 0,
-                        bytes_per_row: Some(atlas_w * 4),
-                        rows_per_image: Some(atlas_h),
-                    },
-                    wgpu::Extent3d {
-                        width: atlas_w,
-                        height: atlas_h,
-                        depth_or_array_layers: 1,
-                    },
-                );
-
-                let info = GlyphInfo {
-                    uv_min: [
-                        self.next_x as f32 / ATLAS_SIZE as f32,
-                        self.next_y as f32 / ATLAS_SIZE as f32,
-                    ],
-                    uv_max: [
-                        (self.next_x + atlas_w) as f32 / ATLAS_SIZE as f32,
-                        (self.next_y + atlas_h) as f32 / ATLAS_SIZE as f32,
-                    ],
-                    size: [bb.width(), bb.height()],
-                    bearing: [bb.min.x, bb.min.y],
-                    advance: self.font.h_advance_unscaled(gl.id) / MSDF_BASE_SIZE,
-                };
-
-                self.next_x += atlas_w + 1;
-
-                vacant.insert(info)
+                        advance: 0.0,
+                        page: MSDF_PAGE,
+                        cell: None,
+                    });
+            return *self
+                .cache
+                .entry(key)
+                .or_insert(GlyphInfo { advance, ..info });
+        };
+
+        // Not cached, so the glyph is tried again once cells free up.
+        let Some((cell, evicted)) = self.cells.allocate(key, self.frame) else {
+            if !self.warned_full {
+                log::warn!("Glyph atlas full; drawing missing glyphs as boxes");
+                self.warned_full = true;
             }
+            return GlyphInfo {
+                advance,
+                ..self.tofu
+            };
+        };
+        if let Some(evicted) = evicted {
+            self.cache.remove(&evicted);
         }
+
+        let bb = out.px_bounds();
+        let glyph_w = bb.width() as u32;
+        let glyph_h = bb.height() as u32;
+        let mut bitmap = vec![0u8; (glyph_w * glyph_h) as usize];
+        out.draw(|x, y, v| {
+            bitmap[(y * glyph_w + x) as usize] = (v * 255.0) as u8;
+        });
+        let info = upload_msdf(
+            queue,
+            &self.tex,
+            self.cells.corner(cell),
+            &bitmap,
+            [glyph_w, glyph_h],
+            [bb.min.x, bb.min.y],
+        );
+        *self.cache.entry(key).or_insert(GlyphInfo {
+            advance,
+            cell: Some(cell),
+            ..info
+        })
     }
 
     pub fn prepare(
@@ -476,203 +657,207 @@ impl TextRenderer {
         elems: &[Element],
         viewport: (f32, f32),
     ) {
-        self.vertices.clear();
-        self.indices.clear();
-        let mut off: u16 = 0;
+        let mut vertices = std::mem::take(&mut self.vertices);
+        let mut indices = std::mem::take(&mut self.indices);
+        vertices.clear();
+        indices.clear();
         for e in elems {
-            match &e.shape {
-                DrawingElement::Text {
-                    position,
-                    content,
-                    color,
-                    size,
-                } => {
-                    let px = *size;
-                    let mut pen_x = position[0];
-                    let mut pen_y = position[1];
-                    let scale = ab_glyph::PxScale::from(px);
-                    let mut prev_gid: Option<ab_glyph::GlyphId> = None;
-
-                    for ch in content.chars() {
-                        if ch == '\n' {
-                            pen_x = position[0];
-                            pen_y += px * 1.2;
-                            prev_gid = None;
-                            continue;
-                        }
-
-                        let gid = self.font.glyph_id(ch);
-
-                        if let Some(prev) = prev_gid {
-                            let kern_px = self.font.as_scaled(scale).kern(prev, gid);
-                            pen_x += kern_px;
-                        }
-
-                        let info = {
-                            let info_ref = self.cache_glyph(device, queue, gid);
-                            *info_ref
-                        };
-
-                        let adv_px = self.font.as_scaled(scale).h_advance(gid);
-
-                        if info.size[0] == 0.0 || info.size[1] == 0.0 {
-                            pen_x += adv_px;
-                            prev_gid = Some(gid);
-                            continue;
-                        }
-
-                        let scale_factor = px / MSDF_BASE_SIZE;
-                        let scaled_size =
-                            [info.size[0] * scale_factor, info.size[1] * scale_factor];
-                        let scaled_bearing = [
-                            info.bearing[0] * scale_factor,
-                            info.bearing[1] * scale_factor,
-                        ];
-
-                        let x0 = pen_x + scaled_bearing[0];
-                        let y0 = pen_y + scaled_bearing[1];
-                        let x1 = x0 + scaled_size[0];
-                        let y1 = y0 + scaled_size[1];
-
-                        let [u0, v0] = info.uv_min;
-                        let [u1, v1] = info.uv_max;
-
-                        self.vertices.extend_from_slice(&[
-                            TextVertex {
-                                pos: [x0, y0],
-                                uv: [u0, v0],
-                                color: *color,
-                            },
-                            TextVertex {
-                                pos: [x1, y0],
-                                uv: [u1, v0],
-                                color: *color,
-                            },
-                            TextVertex {
-                                pos: [x1, y1],
-                                uv: [u1, v1],
-                                color: *color,
-                            },
-                            TextVertex {
-                                pos: [x0, y1],
-                                uv: [u0, v1],
-                                color: *color,
-                            },
-                        ]);
-                        self.indices.extend_from_slice(&[
-                            off,
-                            off + 1,
-                            off + 2,
-                            off,
-                            off + 2,
-                            off + 3,
-                        ]);
-                        off += 4;
-
-                        pen_x += adv_px;
-                        prev_gid = Some(gid);
-                    }
-                }
-                DrawingElement::TextBox {
-                    pos,
-                    content,
+            self.layout_element(queue, &e.shape, None, &mut vertices, &mut indices);
+        }
+        (self.vbuf, self.ibuf) = upload(device, "text", &vertices, &indices);
+        self.vertices = vertices;
+        self.indices = indices;
+    }
+
+    /// Lay out glyphs for text that casts a shadow or glow, each source drawn
+    /// solidly in its effect color. Returns every source's index range for
+    /// `draw_effect_glyphs`; non-text sources get an empty range.
+    pub fn prepare_effects(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        sources: &[(&DrawingElement, [f32; 4])],
+    ) -> Vec<Range<u32>> {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        let ranges = sources
+            .iter()
+            .map(|(shape, color)| {
+                let start = indices.len() as u32;
+                self.layout_element(queue, shape, Some(*color), &mut vertices, &mut indices);
+                start..indices.len() as u32
+            })
+            .collect();
+        (self.effect_vbuf, self.effect_ibuf) = upload(device, "effect text", &vertices, &indices);
+        ranges
+    }
+
+    /// Draw effect glyphs from `prepare_effects` with an offscreen transform.
+    pub fn draw_effect_glyphs(
+        &self,
+        rp: &mut wgpu::RenderPass<'_>,
+        transform_bind_group: &wgpu::BindGroup,
+        range: Range<u32>,
+    ) {
+        if range.is_empty() {
+            return;
+        }
+        if let (Some(vb), Some(ib)) = (&self.effect_vbuf, &self.effect_ibuf) {
+            rp.set_pipeline(&self.effect_pipeline);
+            rp.set_bind_group(0, transform_bind_group, &[]);
+            rp.set_bind_group(1, &self.bind_group, &[]);
+            rp.set_vertex_buffer(0, vb.slice(..));
+            rp.set_index_buffer(ib.slice(..), wgpu::IndexFormat::Uint16);
+            rp.draw_indexed(range, 0, 0..1);
+        }
+    }
+
+    /// Append glyph quads for a Text or TextBox element, optionally in
+    /// `color_override` instead of the element's own color.
+    fn layout_element(
+        &mut self,
+        queue: &wgpu::Queue,
+        shape: &DrawingElement,
+        color_override: Option<[f32; 4]>,
+        vertices: &mut Vec<TextVertex>,
+        indices: &mut Vec<u16>,
+    ) {
+        let (content, color) = match shape {
+            DrawingElement::Text { content, color, .. }
+            | DrawingElement::TextBox { content, color, .. } => (content, *color),
+            _ => return,
+        };
+        let Some((layout, origins)) = shape.text_layout() else {
+            return;
+        };
+        let px = layout.font_size;
+        for (line, origin) in layout.lines.iter().zip(origins) {
+            for piece in layout.pieces(content, line.range.clone()) {
+                let (glyphs, _) = piece
+                    .font
+                    .font
+                    .place_line(&content[piece.range.clone()], px);
+                let color = color_override
+                    .unwrap_or_else(|| piece.style.map_or(color, |style| style.color_or(color)));
+                let start = [origin[0] + piece.x, origin[1]];
+                let run = GlyphRun {
+                    glyphs: &glyphs,
+                    origin: start,
+                    px,
                     color,
-                    font_size,
-                    ..
-                } => {
-                    let px = *font_size;
-                    let line_height = px * 1.2;
-                    for (line_index, line) in content.lines().enumerate() {
-                        let mut pen_x = pos[0] + 8.0;
-                        let pen_y = pos[1] + px + 4.0 + line_index as f32 * line_height;
-                        let scale = ab_glyph::PxScale::from(px);
-                        let mut prev_gid: Option<ab_glyph::GlyphId> = None;
-                        for ch in line.chars() {
-                            let gid = self.font.glyph_id(ch);
-                            if let Some(prev) = prev_gid {
-                                let kern_px = self.font.as_scaled(scale).kern(prev, gid);
-                                pen_x += kern_px;
-                            }
-                            let info = {
-                                let info_ref = self.cache_glyph(device, queue, gid);
-                                *info_ref
-                            };
-                            let adv_px = self.font.as_scaled(scale).h_advance(gid);
-                            if info.size[0] == 0.0 || info.size[1] == 0.0 {
-                                pen_x += adv_px;
-                                prev_gid = Some(gid);
-                                continue;
-                            }
-                            let scale_factor = px / MSDF_BASE_SIZE;
-                            let scaled_size =
-                                [info.size[0] * scale_factor, info.size[1] * scale_factor];
-                            let scaled_bearing = [
-                                info.bearing[0] * scale_factor,
-                                info.bearing[1] * scale_factor,
-                            ];
-                            let x0 = pen_x + scaled_bearing[0];
-                            let y0 = pen_y + scaled_bearing[1];
-                            let x1 = x0 + scaled_size[0];
-                            let y1 = y0 + scaled_size[1];
-                            let [u0, v0] = info.uv_min;
-                            let [u1, v1] = info.uv_max;
-                            self.vertices.extend_from_slice(&[
-                                TextVertex {
-                                    pos: [x0, y0],
-                                    uv: [u0, v0],
-                                    color: *color,
-                                },
-                                TextVertex {
-                                    pos: [x1, y0],
-                                    uv: [u1, v0],
-                                    color: *color,
-                                },
-                                TextVertex {
-                                    pos: [x1, y1],
-                                    uv: [u1, v1],
-                                    color: *color,
-                                },
-                                TextVertex {
-                                    pos: [x0, y1],
-                                    uv: [u0, v1],
-                                    color: *color,
-                                },
-                            ]);
-                            self.indices.extend_from_slice(&[
-                                off,
-                                off + 1,
-                                off + 2,
-                                off,
-                                off + 2,
-                                off + 3,
-                            ]);
-                            off += 4;
-                            pen_x += adv_px;
-                            prev_gid = Some(gid);
-                        }
-                    }
+                    silhouette: color_override.is_some(),
+                    bold: piece.font.fake_bold,
+                    italic: piece.font.fake_italic,
+                };
+                self.push_glyphs(queue, run, vertices, indices);
+                let Some(style) = piece.style else {
+                    continue;
+                };
+                // Strikethrough near the x-height, link underline just below
+                // the baseline.
+                let offsets = [(style.strikethrough, -0.3), (style.link.is_some(), 0.12)];
+                for (_, offset) in offsets.into_iter().filter(|(drawn, _)| *drawn) {
+                    let y = start[1] + px * offset;
+                    let rect = [
+                        [start[0], y - px * DECORATION_WIDTH * 0.5],
+                        [start[0] + piece.width, y + px * DECORATION_WIDTH * 0.5],
+                    ];
+                    self.push_rect(rect, color, vertices, indices);
                 }
-                _ => {}
             }
         }
-        if !self.vertices.is_empty() {
-            self.vbuf = Some(
-                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("text vbuf"),
-                    contents: bytemuck::cast_slice(&self.vertices),
-                    usage: wgpu::BufferUsages::VERTEX,
-                }),
-            );
-            self.ibuf = Some(
-                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("text ibuf"),
-                    contents: bytemuck::cast_slice(&self.indices),
-                    usage: wgpu::BufferUsages::INDEX,
-                }),
-            );
-        } else {
-            self.vbuf = None;
-            self.ibuf = None;
+    }
+
+    /// Append a solid quad covering `rect`, for text decorations.
+    fn push_rect(
+        &mut self,
+        rect: [[f32; 2]; 2],
+        color: [f32; 4],
+        vertices: &mut Vec<TextVertex>,
+        indices: &mut Vec<u16>,
+    ) {
+        let uv = self.solid_uv;
+        let off = vertices.len() as u16;
+        let [[x0, y0], [x1, y1]] = rect;
+        for pos in [[x0, y0], [x1, y0], [x1, y1], [x0, y1]] {
+            vertices.push(TextVertex {
+                pos,
+                uv,
+                color,
+                page: MSDF_PAGE,
+            });
+        }
+        indices.extend_from_slice(&[off, off + 1, off + 2, off, off + 2, off + 3]);
+    }
+
+    /// Append a quad per visible glyph of `run`.
+    fn push_glyphs(
+        &mut self,
+        queue: &wgpu::Queue,
+        run: GlyphRun<'_>,
+        vertices: &mut Vec<TextVertex>,
+        indices: &mut Vec<u16>,
+    ) {
+        let scale_factor = run.px / MSDF_BASE_SIZE;
+        let mut off = vertices.len() as u16;
+        for glyph in run.glyphs {
+            let info = self.cache_glyph(queue, glyph.font, glyph.id);
+            if info.size[0] == 0.0 || info.size[1] == 0.0 {
+                continue;
+            }
+
+            let x0 = run.origin[0] + glyph.x + info.bearing[0] * scale_factor;
+            let y0 = run.origin[1] + glyph.y + info.bearing[1] * scale_factor;
+            let x1 = x0 + info.size[0] * scale_factor;
+            let y1 = y0 + info.size[1] * scale_factor;
+            let [u0, v0] = info.uv_min;
+            let [u1, v1] = info.uv_max;
+            // Color glyphs keep their own colors and only take the alpha.
+            let (color, page) = match info.page {
+                COLOR_PAGE if run.silhouette => (run.color, SILHOUETTE_PAGE),
+                COLOR_PAGE => ([1.0, 1.0, 1.0, run.color[3]], COLOR_PAGE),
+                page => (run.color, page),
+            };
+            let slant = |y: f32| match run.italic {
+                true => (run.origin[1] - y) * ITALIC_SLANT,
+                false => 0.0,
+            };
+            let copies: &[f32] = match run.bold {
+                true => &[0.0, run.px * BOLD_OFFSET],
+                false => &[0.0],
+            };
+            for dx in copies {
+                let (top, bottom) = (x0 + dx + slant(y0), x0 + dx + slant(y1));
+                let width = x1 - x0;
+                vertices.extend_from_slice(&[
+                    TextVertex {
+                        pos: [top, y0],
+                        uv: [u0, v0],
+                        color,
+                        page,
+                    },
+                    TextVertex {
+                        pos: [top + width, y0],
+                        uv: [u1, v0],
+                        color,
+                        page,
+                    },
+                    TextVertex {
+                        pos: [bottom + width, y1],
+                        uv: [u1, v1],
+                        color,
+                        page,
+                    },
+                    TextVertex {
+                        pos: [bottom, y1],
+                        uv: [u0, v1],
+                        color,
+                        page,
+                    },
+                ]);
+                indices.extend_from_slice(&[off, off + 1, off + 2, off, off + 2, off + 3]);
+                off += 4;
+            }
         }
     }
 
@@ -680,6 +865,7 @@ impl TextRenderer {
         &self,
         encoder: &mut wgpu::CommandEncoder,
         view: &wgpu::TextureView,
+        resolve_target: Option<&wgpu::TextureView>,
         canvas_bind_group: &wgpu::BindGroup,
         ui_screen_bind_group: &wgpu::BindGroup,
     ) {
@@ -688,7 +874,7 @@ impl TextRenderer {
                 label: Some("text pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                     view,
-                    resolve_target: None,
+                    resolve_target,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Load,
                         store: wgpu::StoreOp::Store,
@@ -717,75 +903,34 @@ impl TextRenderer {
         self.screen_indices.clear();
     }
 
+    /// Advance width of `text` as `add_screen_label` would lay it out.
+    pub fn measure_screen_label(&self, text: &str, px: f32) -> f32 {
+        self.ui_font.measure(text, px)
+    }
+
     pub fn add_screen_label(
         &mut self,
-        device: &wgpu::Device,
         queue: &wgpu::Queue,
         text: &str,
         pos_screen: [f32; 2],
         px: f32,
         color: [f32; 4],
     ) {
-        let mut pen_x = pos_screen[0];
-        let scale = ab_glyph::PxScale::from(px);
-        let mut prev_gid: Option<ab_glyph::GlyphId> = None;
-        let mut off: u16 = self.screen_vertices.len() as u16;
-
-        for ch in text.chars() {
-            let gid = self.font.glyph_id(ch);
-            if let Some(prev) = prev_gid {
-                let kern = self.font.as_scaled(scale).kern(prev, gid);
-                pen_x += kern;
-            }
-            let info = { *self.cache_glyph(device, queue, gid) };
-            let adv = self.font.as_scaled(scale).h_advance(gid);
-            if info.size[0] == 0.0 || info.size[1] == 0.0 {
-                pen_x += adv;
-                prev_gid = Some(gid);
-                continue;
-            }
-
-            let scale_factor = px / MSDF_BASE_SIZE;
-            let scaled_size = [info.size[0] * scale_factor, info.size[1] * scale_factor];
-            let scaled_bearing = [
-                info.bearing[0] * scale_factor,
-                info.bearing[1] * scale_factor,
-            ];
-
-            let x0 = pen_x + scaled_bearing[0];
-            let y0 = pos_screen[1] + scaled_bearing[1];
-            let x1 = x0 + scaled_size[0];
-            let y1 = y0 + scaled_size[1];
-            let [u0, v0] = info.uv_min;
-            let [u1, v1] = info.uv_max;
-            self.screen_vertices.extend_from_slice(&[
-                TextVertex {
-                    pos: [x0, y0],
-                    uv: [u0, v0],
-                    color,
-                },
-                TextVertex {
-                    pos: [x1, y0],
-                    uv: [u1, v0],
-                    color,
-                },
-                TextVertex {
-                    pos: [x1, y1],
-                    uv: [u1, v1],
-                    color,
-                },
-                TextVertex {
-                    pos: [x0, y1],
-                    uv: [u0, v1],
-                    color,
-                },
-            ]);
-            self.screen_indices
-                .extend_from_slice(&[off, off + 1, off + 2, off, off + 2, off + 3]);
-            off += 4;
-            pen_x += adv;
-            prev_gid = Some(gid);
-        }
+        let (glyphs, _) = self.ui_font.place_line(text, px);
+        let run = GlyphRun {
+            glyphs: &glyphs,
+            origin: pos_screen,
+            px,
+            color,
+            silhouette: false,
+            bold: false,
+            italic: false,
+        };
+        let mut vertices = std::mem::take(&mut self.screen_vertices);
+        let mut indices = std::mem::take(&mut self.screen_indices);
+        self.push_glyphs(queue, run, &mut vertices, &mut indices);
+        self.screen_vertices = vertices;
+        self.screen_indices = indices;
     }
 
     pub fn build_screen_buffers(&mut self, device: &wgpu::Device) {
@@ -826,3 +971,280 @@ impl TextRenderer {
         }
     }
 }
+
+/// Turn a glyph coverage bitmap into the MSDF cell at `[x, y]`. `bearing`
+/// is the bitmap's top-left corner relative to the pen, at the base size.
+fn upload_msdf(
+    queue: &wgpu::Queue,
+    tex: &wgpu::Texture,
+    [x, y]: [u32; 2],
+    bitmap: &[u8],
+    size: [u32; 2],
+    bearing: [f32; 2],
+) -> GlyphInfo {
+    let [glyph_w, glyph_h] = size;
+    let atlas_w = MSDF_SIZE;
+    let atlas_h = MSDF_SIZE;
+
+    let msdf_data = generate_msdf(bitmap, glyph_w, glyph_h);
+
+    let mut rgba_data = Vec::with_capacity((atlas_w * atlas_h * 4) as usize);
+    for i in 0..(atlas_w * atlas_h) as usize {
+        if i < msdf_data.len() / 3 {
+            rgba_data.push(msdf_data[i * 3]); // R
+            rgba_data.push(msdf_data[i * 3 + 1]); // G
+            rgba_data.push(msdf_data[i * 3 + 2]); // B
+            rgba_data.push(255); // A
+        } else {
+            rgba_data.extend_from_slice(&[0, 0, 0, 0]);
+        }
+    }
+    write_cell(queue, tex, [x, y], [atlas_w, atlas_h], &rgba_data);
+
+    GlyphInfo {
+        uv_min: [x as f32 / ATLAS_SIZE as f32, y as f32 / ATLAS_SIZE as f32],
+        uv_max: [
+            (x + atlas_w) as f32 / ATLAS_SIZE as f32,
+            (y + atlas_h) as f32 / ATLAS_SIZE as f32,
+        ],
+        size: [glyph_w as f32, glyph_h as f32],
+        bearing,
+        advance: 0.0,
+        page: MSDF_PAGE,
+        cell: None,
+    }
+}
+
+/// Copy a glyph bitmap onto the color page, shrunk to fit a cell. `None`
+/// when the font has no bitmap for `gid` or the page is full.
+fn upload_color_glyph(
+    queue: &wgpu::Queue,
+    tex: &wgpu::Texture,
+    shelf: &mut Shelf,
+    font: &FontArc,
+    gid: ab_glyph::GlyphId,
+) -> Option<GlyphInfo> {
+    let raster = font.glyph_raster_image2(gid, u16::MAX)?;
+    let bitmap = decode_glyph_image(&raster)?;
+    let (w, h) = bitmap.dimensions();
+    let fit = (COLOR_GLYPH_SIZE as f32 / w.max(h) as f32).min(1.0);
+    let cell_w = ((w as f32 * fit).round() as u32).max(1);
+    let cell_h = ((h as f32 * fit).round() as u32).max(1);
+    let bitmap = if fit < 1.0 {
+        image::imageops::resize(
+            &bitmap,
+            cell_w,
+            cell_h,
+            image::imageops::FilterType::Triangle,
+        )
+    } else {
+        bitmap
+    };
+    let Some([x, y]) = shelf.allocate(cell_w, cell_h) else {
+        log::warn!("Color glyph atlas full");
+        return None;
+    };
+    write_cell(queue, tex, [x, y], [cell_w, cell_h], bitmap.as_raw());
+
+    // Bitmaps come at the strike's pixels per em; glyph metrics are in
+    // pixels at the base size, which scales the font's height, not its em.
+    let em = MSDF_BASE_SIZE * font.units_per_em()? / font.height_unscaled();
+    let k = em / raster.pixels_per_em as f32;
+    let atlas = COLOR_ATLAS_SIZE as f32;
+    Some(GlyphInfo {
+        uv_min: [x as f32 / atlas, y as f32 / atlas],
+        uv_max: [(x + cell_w) as f32 / atlas, (y + cell_h) as f32 / atlas],
+        size: [w as f32 * k, h as f32 * k],
+        // The image origin is its bottom-left corner, y up from the baseline.
+        bearing: [raster.origin.x * k, -(raster.origin.y + h as f32) * k],
+        advance: 0.0,
+        page: COLOR_PAGE,
+        cell: None,
+    })
+}
+
+/// Premultiplied RGBA pixels of a color glyph image.
+fn decode_glyph_image(raster: &ab_glyph::v2::GlyphImage) -> Option<image::RgbaImage> {
+    match raster.format {
+        GlyphImageFormat::Png => {
+            let mut bitmap =
+                image::load_from_memory_with_format(raster.data, image::ImageFormat::Png)
+                    .ok()?
+                    .to_rgba8();
+            for pixel in bitmap.pixels_mut() {
+                let alpha = pixel[3] as u16;
+                for channel in &mut pixel.0[..3] {
+                    *channel = (*channel as u16 * alpha / 255) as u8;
+                }
+            }
+            Some(bitmap)
+        }
+        GlyphImageFormat::BitmapPremulBgra32 => {
+            let mut data = raster.data.to_vec();
+            for pixel in data.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+            image::RgbaImage::from_raw(raster.width.into(), raster.height.into(), data)
+        }
+        _ => None,
+    }
+}
+
+/// An outlined box standing in for a character no font has, with its size
+/// and bearing at the base size.
+fn tofu_bitmap() -> (Vec<u8>, [u32; 2], [f32; 2]) {
+    let advance = fonts::TOFU_ADVANCE * MSDF_BASE_SIZE;
+    let w = (advance * 0.8) as u32;
+    let h = (MSDF_BASE_SIZE * 0.7) as u32;
+    let stroke = 4;
+    let bitmap = (0..h)
+        .flat_map(|y| (0..w).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let edge = x < stroke || y < stroke || x >= w - stroke || y >= h - stroke;
+            if edge { 255 } else { 0 }
+        })
+        .collect();
+    (bitmap, [w, h], [(advance - w as f32) * 0.5, -(h as f32)])
+}
+
+fn write_cell(
+    queue: &wgpu::Queue,
+    tex: &wgpu::Texture,
+    corner: [u32; 2],
+    size: [u32; 2],
+    rgba: &[u8],
+) {
+    queue.write_texture(
+        wgpu::TexelCopyTextureInfo {
+            texture: tex,
+            mip_level: 0,
+            origin: wgpu::Origin3d {
+                x: corner[0],
+                y: corner[1],
+                z: 0,
+            },
+            aspect: wgpu::TextureAspect::All,
+        },
+        rgba,
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(size[0] * 4),
+            rows_per_image: Some(size[1]),
+        },
+        wgpu::Extent3d {
+            width: size[0],
+            height: size[1],
+            depth_or_array_layers: 1,
+        },
+    );
+}
+
+fn upload(
+    device: &wgpu::Device,
+    label: &str,
+    vertices: &[TextVertex],
+    indices: &[u16],
+) -> (Option<wgpu::Buffer>, Option<wgpu::Buffer>) {
+    if vertices.is_empty() {
+        return (None, None);
+    }
+    let vbuf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(&format!("{label} vbuf")),
+        contents: bytemuck::cast_slice(vertices),
+        usage: wgpu::BufferUsages::VERTEX,
+    });
+    let ibuf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(&format!("{label} ibuf")),
+        contents: bytemuck::cast_slice(indices),
+        usage: wgpu::BufferUsages::INDEX,
+    });
+    (Some(vbuf), Some(ibuf))
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    label: &str,
+    shader: &wgpu::ShaderModule,
+    layout: &wgpu::PipelineLayout,
+    fmt: wgpu::TextureFormat,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            buffers: &[TextVertex::desc()],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: fmt,
+                blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+        cache: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_full_atlas_reuses_the_least_recently_drawn_cell() {
+        let mut cells = CellPool::new(ATLAS_SIZE, MSDF_SIZE);
+        let pinned = cells.pin().unwrap();
+        let capacity = cells.capacity;
+        for glyph in 1..capacity {
+            let (_, evicted) = cells.allocate(glyph, glyph as u64).unwrap();
+            assert_eq!(evicted, None);
+        }
+        let frame = capacity as u64;
+        // Glyph 1 was drawn again, so glyph 2 is now the oldest.
+        cells.touch(1, frame);
+        let (index, evicted) = cells.allocate(capacity, frame).unwrap();
+        assert_eq!(evicted, Some(2));
+        assert_eq!(index, 2);
+        assert_ne!(cells.corner(index), pinned);
+    }
+
+    #[test]
+    fn cells_drawn_this_frame_are_never_reused() {
+        let mut cells = CellPool::new(4 * (MSDF_SIZE + 1), MSDF_SIZE);
+        cells.pin().unwrap();
+        for glyph in 0..cells.capacity - 1 {
+            assert!(cells.allocate(glyph, 1).is_some());
+        }
+        assert!(cells.pin().is_none());
+        assert_eq!(cells.allocate(99, 1), None);
+        // The next frame may take the cells back, but never the pinned one.
+        for glyph in 100..100 + cells.capacity - 1 {
+            let (index, evicted) = cells.allocate(glyph, 2).unwrap();
+            assert!(index > 0 && evicted.is_some());
+        }
+        assert_eq!(cells.allocate(200, 2), None);
+    }
+
+    #[test]
+    fn cells_tile_the_atlas_without_overlapping() {
+        let cells = CellPool::<()>::new(ATLAS_SIZE, MSDF_SIZE);
+        assert_eq!(cells.capacity, 31 * 31);
+        let last = cells.corner(cells.capacity - 1);
+        assert!(last.iter().all(|&edge| edge + MSDF_SIZE <= ATLAS_SIZE));
+        assert_eq!(cells.corner(1), [MSDF_SIZE + 1, 0]);
+        assert_eq!(cells.corner(31), [0, MSDF_SIZE + 1]);
+    }
+}
@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+
+/// The selectable color schemes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ThemeKind {
+    #[default]
+    Light,
+    Dark,
+    HighContrast,
+}
+
+impl ThemeKind {
+    pub const ALL: [ThemeKind; 3] = [ThemeKind::Light, ThemeKind::Dark, ThemeKind::HighContrast];
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|kind| *kind == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub fn theme(self) -> &'static Theme {
+        match self {
+            ThemeKind::Light => &Theme::LIGHT,
+            ThemeKind::Dark => &Theme::DARK,
+            ThemeKind::HighContrast => &Theme::HIGH_CONTRAST,
+        }
+    }
+}
+
+/// Colors for everything that isn't element content: the canvas, the UI
+/// chrome and selection overlays.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub canvas_background: [f32; 4],
+    pub grid: [f32; 4],
+    /// Toolbar and color picker panels.
+    pub ui_surface: [f32; 4],
+    pub ui_shadow: [f32; 4],
+    pub ui_button: [f32; 4],
+    pub ui_button_selected: [f32; 4],
+    /// Icon and glyph color on unselected buttons.
+    pub ui_icon: [f32; 4],
+    /// Background of the zoom indicator.
+    pub ui_badge: [f32; 4],
+    /// Outline of the picker knobs.
+    pub ui_outline: [f32; 4],
+    pub selection: [f32; 4],
+    /// Smart guides and their spacing labels.
+    pub guide: [f32; 4],
+    pub handle_fill: [f32; 4],
+    /// Screen labels such as the zoom level.
+    pub text: [f32; 4],
+    /// Draw element colors inverted, so dark ink stays legible on a dark
+    /// canvas. Stored colors are left untouched.
+    pub invert_elements: bool,
+}
+
+impl Theme {
+    pub const LIGHT: Theme = Theme {
+        canvas_background: [1.0, 1.0, 1.0, 1.0],
+        grid: [0.0, 0.0, 0.0, 0.08],
+        ui_surface: [0.96, 0.96, 0.97, 0.98],
+        ui_shadow: [0.0, 0.0, 0.0, 0.15],
+        ui_button: [0.85, 0.85, 0.87, 1.0],
+        ui_button_selected: [0.25, 0.55, 0.95, 1.0],
+        ui_icon: [0.2, 0.2, 0.2, 1.0],
+        ui_badge: [0.2, 0.2, 0.2, 0.9],
+        ui_outline: [0.08, 0.1, 0.14, 1.0],
+        selection: [0.0, 0.5, 1.0, 1.0],
+        guide: [1.0, 0.2, 0.45, 1.0],
+        handle_fill: [1.0, 1.0, 1.0, 1.0],
+        text: [1.0, 1.0, 1.0, 1.0],
+        invert_elements: false,
+    };
+
+    pub const DARK: Theme = Theme {
+        canvas_background: [0.07, 0.07, 0.07, 1.0],
+        grid: [1.0, 1.0, 1.0, 0.08],
+        ui_surface: [0.14, 0.14, 0.16, 0.98],
+        ui_shadow: [0.0, 0.0, 0.0, 0.4],
+        ui_button: [0.24, 0.24, 0.27, 1.0],
+        ui_button_selected: [0.3, 0.5, 0.9, 1.0],
+        ui_icon: [0.88, 0.88, 0.9, 1.0],
+        ui_badge: [0.88, 0.88, 0.9, 0.9],
+        ui_outline: [0.88, 0.9, 0.94, 1.0],
+        selection: [0.4, 0.66, 1.0, 1.0],
+        guide: [1.0, 0.4, 0.6, 1.0],
+        handle_fill: [0.07, 0.07, 0.07, 1.0],
+        text: [0.1, 0.1, 0.1, 1.0],
+        invert_elements: true,
+    };
+
+    pub const HIGH_CONTRAST: Theme = Theme {
+        canvas_background: [1.0, 1.0, 1.0, 1.0],
+        grid: [0.0, 0.0, 0.0, 0.25],
+        ui_surface: [1.0, 1.0, 1.0, 1.0],
+        ui_shadow: [0.0, 0.0, 0.0, 0.0],
+        ui_button: [1.0, 1.0, 1.0, 1.0],
+        ui_button_selected: [0.0, 0.0, 0.0, 1.0],
+        ui_icon: [0.0, 0.0, 0.0, 1.0],
+        ui_badge: [0.0, 0.0, 0.0, 1.0],
+        ui_outline: [0.0, 0.0, 0.0, 1.0],
+        selection: [0.85, 0.0, 0.55, 1.0],
+        guide: [0.9, 0.3, 0.0, 1.0],
+        handle_fill: [1.0, 1.0, 1.0, 1.0],
+        text: [1.0, 1.0, 1.0, 1.0],
+        invert_elements: false,
+    };
+
+    /// How an element color appears under this theme.
+    pub fn element_color(&self, color: [f32; 4]) -> [f32; 4] {
+        if self.invert_elements {
+            invert_for_dark(color)
+        } else {
+            color
+        }
+    }
+}
+
+/// Excalidraw's dark mode filter, `invert(93%) hue-rotate(180deg)`: lightness
+/// flips while hues stay recognisable, so red ink remains red.
+pub fn invert_for_dark(color: [f32; 4]) -> [f32; 4] {
+    const AMOUNT: f32 = 0.93;
+    let [r, g, b] = [color[0], color[1], color[2]].map(|c| AMOUNT * (1.0 - c) + (1.0 - AMOUNT) * c);
+    // CSS hue-rotate matrix for 180 degrees.
+    let rotated = [
+        -0.574 * r + 1.430 * g + 0.144 * b,
+        0.426 * r + 0.430 * g + 0.144 * b,
+        0.426 * r + 1.430 * g - 0.856 * b,
+    ];
+    [
+        rotated[0].clamp(0.0, 1.0),
+        rotated[1].clamp(0.0, 1.0),
+        rotated[2].clamp(0.0, 1.0),
+        color[3],
+    ]
+}
@@ -1,6 +1,8 @@
 use crate::{
-    drawing::Tool,
+    arrange::{AlignReference, Alignment, ArrangeCommand, Distribution},
+    drawing::{ColorTarget, Tool},
     state::{ColorPickerDragMode, ColorPickerState},
+    theme::Theme,
     vertex::UiVertex,
 };
 
@@ -16,6 +18,8 @@ const PICKER_INSET: f32 = 18.0;
 const TOOLBAR_BASE_ICON_SIZE: f32 = 36.0;
 const TOOLBAR_BASE_SPACING: f32 = 12.0;
 const TOOLBAR_BASE_PADDING: f32 = 20.0;
+/// Tool buttons plus the trailing corner-style toggle.
+const TOOLBAR_SLOT_COUNT: f32 = 10.0;
 
 const PALETTE_COLORS: [[f32; 4]; 6] = [
     [0.0, 0.0, 0.0, 1.0],
@@ -26,9 +30,110 @@ const PALETTE_COLORS: [[f32; 4]; 6] = [
     [0.14, 0.75, 0.37, 1.0],
 ];
 
+/// Background swatches; the leading `None` clears the background.
+const BACKGROUND_COLORS: [Option<[f32; 4]>; 5] = [
+    None,
+    Some([1.0, 0.79, 0.79, 1.0]),
+    Some([0.7, 0.95, 0.73, 1.0]),
+    Some([0.65, 0.85, 1.0, 1.0]),
+    Some([1.0, 0.93, 0.6, 1.0]),
+];
+const TRANSPARENT_SWATCH_SLASH: [f32; 4] = [0.86, 0.2, 0.2, 1.0];
+
 pub struct UiRenderer {
     tool_icons: Vec<ToolIcon>,
     color_palette: Vec<ColorSwatch>,
+    theme: &'static Theme,
+    /// Shown while something is selected, with the current align reference.
+    arrange_bar: Option<AlignReference>,
+    command_palette: Option<PaletteView>,
+    find_bar: Option<FindBarView>,
+    properties: Option<PropertiesView>,
+}
+
+/// What the command palette shows: how many rows, and which one is
+/// highlighted, counted from the first visible row.
+#[derive(Clone, Copy)]
+pub struct PaletteView {
+    pub rows: usize,
+    pub selected: usize,
+}
+
+/// What the find bar shows: which options are on and which field is focused.
+#[derive(Clone, Copy)]
+pub struct FindBarView {
+    pub match_case: bool,
+    pub regex: bool,
+    pub replacing: bool,
+}
+
+/// The parts of the find bar that respond to clicks. The query and its
+/// option toggles make the first row; the replacement and its buttons the
+/// second.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FindBarPart {
+    Query,
+    MatchCase,
+    Regex,
+    Replacement,
+    Replace,
+    ReplaceAll,
+}
+
+impl FindBarPart {
+    pub const ALL: [FindBarPart; 6] = [
+        FindBarPart::Query,
+        FindBarPart::MatchCase,
+        FindBarPart::Regex,
+        FindBarPart::Replacement,
+        FindBarPart::Replace,
+        FindBarPart::ReplaceAll,
+    ];
+}
+
+/// How a properties panel row is edited.
+#[derive(Clone, Copy, PartialEq)]
+pub enum PropertyControl {
+    /// A typed value between -/+ steppers.
+    Number,
+    /// A switch; `None` when the selection is mixed.
+    Toggle { on: Option<bool> },
+    /// A typed value with a re-roll button.
+    Reroll,
+    /// One of a list of named options, stepped with chevrons.
+    Choice,
+}
+
+/// The parts of a properties panel row that respond to clicks.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PropertyPart {
+    Decrease,
+    Value,
+    Increase,
+}
+
+/// What the properties panel shows: one control per row, and the row whose
+/// value is being typed.
+#[derive(Clone)]
+pub struct PropertiesView {
+    pub rows: Vec<PropertyControl>,
+    pub editing: Option<usize>,
+}
+
+/// Where a properties panel row's text goes: the label's baseline start,
+/// the baseline center of the value field, and the font size.
+pub struct PropertyLabel {
+    pub label: [f32; 2],
+    pub value_center: [f32; 2],
+    pub font_size: f32,
+}
+
+/// Where a line of command palette or find bar text goes: its baseline
+/// start, the right edge shortcut hints align to, and the font size.
+pub struct PaletteLabel {
+    pub pos: [f32; 2],
+    pub right: f32,
+    pub font_size: f32,
 }
 
 struct ColorSwatch {
@@ -44,10 +149,30 @@ struct ToolIcon {
     key_binding: &'static str,
 }
 
+pub enum ToolbarInteraction {
+    Tool(Tool),
+    ToggleRoundness,
+    Arrange(ArrangeCommand),
+}
+
+/// A piece of an arrange bar icon, in coordinates where the icon spans -1..1.
+enum IconShape {
+    Bar { center: [f32; 2], size: [f32; 2] },
+    Line(Vec<[f32; 2]>),
+}
+
+/// The colors new shapes are drawn with, highlighted in the palette.
+#[derive(Clone, Copy)]
+pub struct ActiveColors {
+    pub stroke: [f32; 4],
+    pub background: Option<[f32; 4]>,
+}
+
 pub enum ColorInteraction {
     None,
     Color([f32; 4]),
-    TogglePicker,
+    Background(Option<[f32; 4]>),
+    TogglePicker(ColorTarget),
     BeginDrag(ColorPickerDragMode, [f32; 4]),
 }
 
@@ -60,12 +185,15 @@ struct UiLayout {
     swatch_size: f32,
     swatch_padding: f32,
     palette_size: [f32; 2],
+    background_origin: [f32; 2],
     picker_origin: [f32; 2],
     picker_size: [f32; 2],
     sv_origin: [f32; 2],
     sv_size: [f32; 2],
     hue_origin: [f32; 2],
     hue_size: [f32; 2],
+    alpha_origin: [f32; 2],
+    alpha_size: [f32; 2],
     toolbar_icon_size: f32,
     toolbar_spacing: f32,
     toolbar_padding: f32,
@@ -85,7 +213,13 @@ impl UiLayout {
         let swatch_padding = (swatch_size * 0.24).clamp(8.0, 14.0);
         let palette_origin = [edge_padding, top_padding + 26.0];
         let palette_width = swatch_size * 2.0 + swatch_padding;
-        let palette_height = swatch_size * 4.0 + swatch_padding * 3.0;
+        // Four stroke rows, a gap, then three background rows.
+        let background_origin = [
+            palette_origin[0],
+            palette_origin[1] + 4.0 * (swatch_size + swatch_padding) + swatch_padding,
+        ];
+        let palette_height =
+            background_origin[1] - palette_origin[1] + swatch_size * 3.0 + swatch_padding * 2.0;
 
         let desired_picker_width = (screen_size.0 * 0.22).max(PICKER_PANEL_BASE_SIZE[0] * scale);
         let mut picker_width = desired_picker_width.clamp(PICKER_MIN_SIZE[0], PICKER_MAX_SIZE[0]);
@@ -115,17 +249,22 @@ impl UiLayout {
             picker_origin[0] + inset,
             picker_origin[1] + inset + preview_height + 14.0,
         ];
+        let alpha_height = (hue_width * 0.7).clamp(18.0, 26.0);
         let sv_size = [
             picker_width - inset * 3.0 - hue_width,
-            picker_height - inset * 2.0 - preview_height - 14.0,
+            picker_height - inset * 3.0 - preview_height - 14.0 - alpha_height,
         ];
         let hue_origin = [sv_origin[0] + sv_size[0] + inset, sv_origin[1]];
         let hue_size = [hue_width, sv_size[1]];
+        let alpha_origin = [sv_origin[0], sv_origin[1] + sv_size[1] + inset];
+        let alpha_size = [picker_width - inset * 2.0, alpha_height];
 
         let toolbar_icon_size = (TOOLBAR_BASE_ICON_SIZE * scale).clamp(34.0, 52.0);
         let toolbar_spacing = (TOOLBAR_BASE_SPACING * scale).clamp(10.0, 18.0);
         let toolbar_padding = (TOOLBAR_BASE_PADDING * scale).clamp(18.0, 30.0);
-        let toolbar_width = 9.0 * toolbar_icon_size + 8.0 * toolbar_spacing + 2.0 * toolbar_padding;
+        let toolbar_width = TOOLBAR_SLOT_COUNT * toolbar_icon_size
+            + (TOOLBAR_SLOT_COUNT - 1.0) * toolbar_spacing
+            + 2.0 * toolbar_padding;
         let toolbar_height = toolbar_icon_size + 2.0 * toolbar_padding;
         let toolbar_center = [screen_size.0 * 0.5, top_padding];
 
@@ -137,12 +276,15 @@ impl UiLayout {
             swatch_size,
             swatch_padding,
             palette_size: [palette_width, palette_height],
+            background_origin,
             picker_origin,
             picker_size: [picker_width, picker_height],
             sv_origin,
             sv_size,
             hue_origin,
             hue_size,
+            alpha_origin,
+            alpha_size,
             toolbar_icon_size,
             toolbar_spacing,
             toolbar_padding,
@@ -151,15 +293,35 @@ impl UiLayout {
         }
     }
 
-    fn custom_swatch_center(&self) -> [f32; 2] {
+    /// Center of the `index`th swatch in a two-column grid starting at `origin`.
+    fn swatch_center(&self, origin: [f32; 2], index: usize) -> [f32; 2] {
+        let col = (index % 2) as f32;
+        let row = (index / 2) as f32;
         [
-            self.palette_origin[0] + self.swatch_size * 0.5,
-            self.palette_origin[1]
-                + 3.0 * (self.swatch_size + self.swatch_padding)
-                + self.swatch_size * 0.5,
+            origin[0] + col * (self.swatch_size + self.swatch_padding) + self.swatch_size * 0.5,
+            origin[1] + row * (self.swatch_size + self.swatch_padding) + self.swatch_size * 0.5,
         ]
     }
 
+    fn custom_swatch_center(&self) -> [f32; 2] {
+        self.swatch_center(self.palette_origin, 6)
+    }
+
+    fn custom_background_center(&self) -> [f32; 2] {
+        self.swatch_center(self.background_origin, BACKGROUND_COLORS.len())
+    }
+
+    fn swatch_contains(&self, center: [f32; 2], point: [f32; 2]) -> bool {
+        point_in_rect(
+            point,
+            [
+                center[0] - self.swatch_size * 0.5,
+                center[1] - self.swatch_size * 0.5,
+            ],
+            [self.swatch_size, self.swatch_size],
+        )
+    }
+
     fn picker_center(&self) -> [f32; 2] {
         [
             self.picker_origin[0] + self.picker_size[0] * 0.5,
@@ -192,6 +354,202 @@ impl UiLayout {
     fn zoom_font_size(&self) -> f32 {
         (15.0 * self.scale).clamp(13.0, 22.0)
     }
+
+    fn arrange_button_size(&self) -> f32 {
+        self.toolbar_icon_size * 0.7
+    }
+
+    fn arrange_bar_size(&self) -> [f32; 2] {
+        let count = ArrangeCommand::TOOLBAR.len() as f32;
+        let button = self.arrange_button_size();
+        let spacing = self.toolbar_spacing * 0.5;
+        [
+            count * button + (count - 1.0) * spacing + self.toolbar_padding,
+            button + self.toolbar_padding * 0.5,
+        ]
+    }
+
+    /// The arrange bar sits centered just below the toolbar.
+    fn arrange_bar_center(&self) -> [f32; 2] {
+        [
+            self.toolbar_center[0],
+            self.toolbar_center[1]
+                + self.toolbar_size[1] * 0.5
+                + self.toolbar_spacing
+                + self.arrange_bar_size()[1] * 0.5,
+        ]
+    }
+
+    fn arrange_button_center(&self, index: usize) -> [f32; 2] {
+        let center = self.arrange_bar_center();
+        let button = self.arrange_button_size();
+        let spacing = self.toolbar_spacing * 0.5;
+        let left = center[0] - self.arrange_bar_size()[0] * 0.5 + self.toolbar_padding * 0.5;
+        [
+            left + index as f32 * (button + spacing) + button * 0.5,
+            center[1],
+        ]
+    }
+
+    fn command_palette_row_height(&self) -> f32 {
+        (30.0 * self.scale).clamp(28.0, 44.0)
+    }
+
+    fn command_palette_input_height(&self) -> f32 {
+        (38.0 * self.scale).clamp(34.0, 54.0)
+    }
+
+    fn command_palette_padding(&self) -> f32 {
+        (8.0 * self.scale).clamp(8.0, 12.0)
+    }
+
+    fn command_palette_font_size(&self) -> f32 {
+        (15.0 * self.scale).clamp(13.0, 22.0)
+    }
+
+    /// The palette hangs centered below the toolbar, overlapping the arrange bar.
+    fn command_palette_origin(&self) -> [f32; 2] {
+        let width = self.command_palette_size(0)[0];
+        [
+            (self.screen_size.0 - width) * 0.5,
+            self.toolbar_center[1] + self.toolbar_size[1] * 0.5 + self.toolbar_spacing,
+        ]
+    }
+
+    fn command_palette_size(&self, rows: usize) -> [f32; 2] {
+        let padding = self.command_palette_padding();
+        [
+            (560.0 * self.scale).min(self.screen_size.0 - self.edge_padding * 2.0),
+            padding * 3.0
+                + self.command_palette_input_height()
+                + rows as f32 * self.command_palette_row_height(),
+        ]
+    }
+
+    fn command_palette_row_top(&self, row: usize) -> f32 {
+        let padding = self.command_palette_padding();
+        self.command_palette_origin()[1]
+            + padding * 2.0
+            + self.command_palette_input_height()
+            + row as f32 * self.command_palette_row_height()
+    }
+
+    fn find_bar_field_height(&self) -> f32 {
+        (34.0 * self.scale).clamp(30.0, 48.0)
+    }
+
+    /// The find bar hangs centered below the toolbar, where the palette does.
+    fn find_bar_origin(&self) -> [f32; 2] {
+        let width = self.find_bar_size()[0];
+        [
+            (self.screen_size.0 - width) * 0.5,
+            self.toolbar_center[1] + self.toolbar_size[1] * 0.5 + self.toolbar_spacing,
+        ]
+    }
+
+    fn find_bar_size(&self) -> [f32; 2] {
+        let padding = self.command_palette_padding();
+        [
+            (480.0 * self.scale).min(self.screen_size.0 - self.edge_padding * 2.0),
+            padding * 3.0 + self.find_bar_field_height() * 2.0,
+        ]
+    }
+
+    /// Origin and size of a part of the find bar: each row is a text field
+    /// followed by two buttons.
+    fn find_bar_part_rect(&self, part: FindBarPart) -> ([f32; 2], [f32; 2]) {
+        let origin = self.find_bar_origin();
+        let width = self.find_bar_size()[0];
+        let padding = self.command_palette_padding();
+        let height = self.find_bar_field_height();
+        let row = match part {
+            FindBarPart::Query | FindBarPart::MatchCase | FindBarPart::Regex => 0.0,
+            _ => 1.0,
+        };
+        let top = origin[1] + padding + row * (height + padding);
+        let gap = padding * 0.5;
+        let button = height * 1.8;
+        let buttons_left = origin[0] + width - padding - button * 2.0 - gap;
+        match part {
+            FindBarPart::Query | FindBarPart::Replacement => (
+                [origin[0] + padding, top],
+                [buttons_left - gap - origin[0] - padding, height],
+            ),
+            FindBarPart::MatchCase | FindBarPart::Replace => {
+                ([buttons_left, top], [button, height])
+            }
+            FindBarPart::Regex | FindBarPart::ReplaceAll => {
+                ([buttons_left + button + gap, top], [button, height])
+            }
+        }
+    }
+
+    fn properties_row_height(&self) -> f32 {
+        (30.0 * self.scale).clamp(28.0, 40.0)
+    }
+
+    fn properties_padding(&self) -> f32 {
+        (10.0 * self.scale).clamp(8.0, 14.0)
+    }
+
+    /// The properties panel runs down the right edge, level with the arrange bar.
+    fn properties_origin(&self) -> [f32; 2] {
+        [
+            self.screen_size.0 - self.edge_padding - self.properties_size(0)[0],
+            self.toolbar_center[1] + self.toolbar_size[1] * 0.5 + self.toolbar_spacing,
+        ]
+    }
+
+    fn properties_size(&self, rows: usize) -> [f32; 2] {
+        [
+            (232.0 * self.scale).clamp(220.0, 320.0),
+            self.properties_padding() * 2.0 + rows as f32 * self.properties_row_height(),
+        ]
+    }
+
+    fn properties_row_top(&self, row: usize) -> f32 {
+        self.properties_origin()[1]
+            + self.properties_padding()
+            + row as f32 * self.properties_row_height()
+    }
+
+    /// Origin and size of the decrease button, value field and increase
+    /// button of a row. The value field of a toggle spans all three.
+    fn property_part_rects(
+        &self,
+        row: usize,
+        control: PropertyControl,
+    ) -> [([f32; 2], [f32; 2]); 3] {
+        let origin = self.properties_origin();
+        let width = self.properties_size(0)[0];
+        let row_height = self.properties_row_height();
+        let height = row_height * 0.8;
+        let top = self.properties_row_top(row) + (row_height - height) * 0.5;
+        let left = origin[0] + width * 0.42;
+        let right = origin[0] + width - self.properties_padding();
+        let gap = 4.0 * self.scale;
+        let decrease = ([left, top], [height, height]);
+        let increase = ([right - height, top], [height, height]);
+        let value = match control {
+            PropertyControl::Number | PropertyControl::Choice => (
+                [left + height + gap, top],
+                [right - left - (height + gap) * 2.0, height],
+            ),
+            PropertyControl::Toggle { .. } => ([left, top], [right - left, height]),
+            PropertyControl::Reroll => ([left, top], [right - left - height - gap, height]),
+        };
+        [decrease, value, increase]
+    }
+
+    fn arrange_bar_contains(&self, point: [f32; 2]) -> bool {
+        let center = self.arrange_bar_center();
+        let size = self.arrange_bar_size();
+        point_in_rect(
+            point,
+            [center[0] - size[0] * 0.5, center[1] - size[1] * 0.5],
+            size,
+        )
+    }
 }
 
 impl UiRenderer {
@@ -280,9 +638,34 @@ impl UiRenderer {
         Self {
             tool_icons,
             color_palette,
+            theme: &Theme::LIGHT,
+            arrange_bar: None,
+            command_palette: None,
+            find_bar: None,
+            properties: None,
         }
     }
 
+    pub fn set_arrange_bar(&mut self, arrange_bar: Option<AlignReference>) {
+        self.arrange_bar = arrange_bar;
+    }
+
+    pub fn set_command_palette(&mut self, command_palette: Option<PaletteView>) {
+        self.command_palette = command_palette;
+    }
+
+    pub fn set_find_bar(&mut self, find_bar: Option<FindBarView>) {
+        self.find_bar = find_bar;
+    }
+
+    pub fn set_properties(&mut self, properties: Option<PropertiesView>) {
+        self.properties = properties;
+    }
+
+    pub fn set_theme(&mut self, theme: &'static Theme) {
+        self.theme = theme;
+    }
+
     fn draw_tool_icon(
         &self,
         vertices: &mut Vec<UiVertex>,
@@ -1191,10 +1574,14 @@ impl UiRenderer {
         vertices: &mut Vec<UiVertex>,
         indices: &mut Vec<u16>,
         index_offset: &mut u16,
-        current_color: [f32; 4],
+        colors: ActiveColors,
         picker: &ColorPickerState,
         screen_size: (f32, f32),
     ) {
+        let ActiveColors {
+            stroke: current_color,
+            background: current_background,
+        } = colors;
         let layout = UiLayout::new(screen_size);
         let palette_panel_size = [
             layout.palette_size[0] + layout.edge_padding * 0.75,
@@ -1211,7 +1598,7 @@ impl UiRenderer {
             index_offset,
             [palette_panel_center[0] + 3.0, palette_panel_center[1] + 5.0],
             palette_panel_size,
-            [0.02, 0.03, 0.05, 0.10],
+            self.theme.ui_shadow,
             14.0,
             0.0,
         );
@@ -1221,27 +1608,14 @@ impl UiRenderer {
             index_offset,
             palette_panel_center,
             palette_panel_size,
-            [0.95, 0.96, 0.98, 0.96],
+            self.theme.ui_surface,
             14.0,
             1.0,
         );
 
         for (i, color) in PALETTE_COLORS.iter().enumerate() {
-            let col = (i % 2) as f32;
-            let row = (i / 2) as f32;
-
-            let center = [
-                layout.palette_origin[0]
-                    + col * (layout.swatch_size + layout.swatch_padding)
-                    + layout.swatch_size * 0.5,
-                layout.palette_origin[1]
-                    + row * (layout.swatch_size + layout.swatch_padding)
-                    + layout.swatch_size * 0.5,
-            ];
-
-            let is_selected = (color[0] - current_color[0]).abs() < 0.01
-                && (color[1] - current_color[1]).abs() < 0.01
-                && (color[2] - current_color[2]).abs() < 0.01;
+            let center = layout.swatch_center(layout.palette_origin, i);
+            let is_selected = same_rgb(*color, current_color);
 
             let border_width = if is_selected { 2.0 } else { 1.0 };
             let final_color = if is_selected {
@@ -1262,8 +1636,13 @@ impl UiRenderer {
             );
         }
 
+        let editing_stroke = picker.open && picker.target == ColorTarget::Stroke;
+        let custom_color = if picker.target == ColorTarget::Stroke {
+            self.color_from_picker(picker)
+        } else {
+            current_color
+        };
         let custom_center = layout.custom_swatch_center();
-        let custom_color = self.color_from_picker(picker);
         self.create_rounded_rect(
             vertices,
             indices,
@@ -1272,7 +1651,7 @@ impl UiRenderer {
             [layout.swatch_size, layout.swatch_size],
             custom_color,
             8.0,
-            if picker.open { 2.0 } else { 1.0 },
+            if editing_stroke { 2.0 } else { 1.0 },
         );
         self.draw_plus_icon(
             vertices,
@@ -1283,6 +1662,64 @@ impl UiRenderer {
             CUSTOM_SWATCH_LABEL,
         );
 
+        for (i, background) in BACKGROUND_COLORS.iter().enumerate() {
+            let center = layout.swatch_center(layout.background_origin, i);
+            let is_selected = match (background, current_background) {
+                (Some(color), Some(current)) => same_rgb(*color, current),
+                (None, None) => true,
+                _ => false,
+            };
+            let border_width = if is_selected { 2.0 } else { 1.0 };
+            self.create_rounded_rect(
+                vertices,
+                indices,
+                index_offset,
+                center,
+                [layout.swatch_size, layout.swatch_size],
+                background.unwrap_or([1.0, 1.0, 1.0, 1.0]),
+                8.0,
+                border_width,
+            );
+            if background.is_none() {
+                let half = layout.swatch_size * 0.28;
+                self.draw_thick_line(
+                    vertices,
+                    indices,
+                    index_offset,
+                    [center[0] - half, center[1] + half],
+                    [center[0] + half, center[1] - half],
+                    2.0,
+                    TRANSPARENT_SWATCH_SLASH,
+                );
+            }
+        }
+
+        let editing_background = picker.open && picker.target == ColorTarget::Background;
+        let custom_background = if picker.target == ColorTarget::Background {
+            self.color_from_picker(picker)
+        } else {
+            current_background.unwrap_or([1.0, 1.0, 1.0, 1.0])
+        };
+        let background_center = layout.custom_background_center();
+        self.create_rounded_rect(
+            vertices,
+            indices,
+            index_offset,
+            background_center,
+            [layout.swatch_size, layout.swatch_size],
+            custom_background,
+            8.0,
+            if editing_background { 2.0 } else { 1.0 },
+        );
+        self.draw_plus_icon(
+            vertices,
+            indices,
+            index_offset,
+            background_center,
+            layout.swatch_size * 0.44,
+            CUSTOM_SWATCH_LABEL,
+        );
+
         if !picker.open {
             return;
         }
@@ -1299,7 +1736,7 @@ impl UiRenderer {
             index_offset,
             [picker_center[0] + 4.0, picker_center[1] + 6.0],
             layout.picker_size,
-            [0.04, 0.05, 0.08, 0.12],
+            self.theme.ui_shadow,
             18.0,
             0.0,
         );
@@ -1309,7 +1746,7 @@ impl UiRenderer {
             index_offset,
             picker_center,
             layout.picker_size,
-            [0.93, 0.93, 0.95, 0.98],
+            self.theme.ui_surface,
             18.0,
             1.0,
         );
@@ -1319,7 +1756,7 @@ impl UiRenderer {
             index_offset,
             preview_center,
             [layout.picker_size[0] - 26.0, layout.picker_size[1] * 0.16],
-            [0.98, 0.985, 0.99, 0.85],
+            self.theme.ui_surface,
             14.0,
             0.0,
         );
@@ -1336,6 +1773,25 @@ impl UiRenderer {
 
         self.generate_sv_panel(vertices, indices, index_offset, picker, layout);
         self.generate_hue_slider(vertices, indices, index_offset, picker, layout);
+        self.generate_alpha_slider(vertices, indices, index_offset, picker, layout);
+
+        self.draw_filled_circle(
+            vertices,
+            indices,
+            index_offset,
+            self.alpha_knob_position(picker, layout),
+            5.5,
+            self.theme.handle_fill,
+        );
+        self.draw_smooth_circle_outline(
+            vertices,
+            indices,
+            index_offset,
+            self.alpha_knob_position(picker, layout),
+            6.0,
+            1.5,
+            self.theme.ui_outline,
+        );
 
         self.draw_filled_circle(
             vertices,
@@ -1343,7 +1799,7 @@ impl UiRenderer {
             index_offset,
             self.hue_knob_position(picker, layout),
             5.5,
-            [1.0, 1.0, 1.0, 1.0],
+            self.theme.handle_fill,
         );
         self.draw_smooth_circle_outline(
             vertices,
@@ -1352,7 +1808,7 @@ impl UiRenderer {
             self.hue_knob_position(picker, layout),
             6.0,
             1.5,
-            [0.08, 0.1, 0.14, 1.0],
+            self.theme.ui_outline,
         );
 
         self.draw_filled_circle(
@@ -1361,7 +1817,7 @@ impl UiRenderer {
             index_offset,
             self.sv_knob_position(picker, layout),
             5.5,
-            [1.0, 1.0, 1.0, 1.0],
+            self.theme.handle_fill,
         );
         self.draw_smooth_circle_outline(
             vertices,
@@ -1370,7 +1826,7 @@ impl UiRenderer {
             self.sv_knob_position(picker, layout),
             6.0,
             1.5,
-            [0.08, 0.1, 0.14, 1.0],
+            self.theme.ui_outline,
         );
     }
 
@@ -1380,6 +1836,7 @@ impl UiRenderer {
         indices: &mut Vec<u16>,
         index_offset: &mut u16,
         current_tool: Tool,
+        rounded_corners: bool,
         screen_size: (f32, f32),
     ) {
         let layout = UiLayout::new(screen_size);
@@ -1400,7 +1857,7 @@ impl UiRenderer {
                 toolbar_center[1] + shadow_offset,
             ],
             [toolbar_width, toolbar_height],
-            [0.0, 0.0, 0.0, 0.15],
+            self.theme.ui_shadow,
             12.0 * layout.scale,
             0.0,
         );
@@ -1411,7 +1868,7 @@ impl UiRenderer {
             index_offset,
             toolbar_center,
             [toolbar_width, toolbar_height],
-            [0.96, 0.96, 0.97, 0.98],
+            self.theme.ui_surface,
             12.0 * layout.scale,
             1.5,
         );
@@ -1425,9 +1882,9 @@ impl UiRenderer {
             let is_selected = icon.tool == current_tool;
 
             let button_color = if is_selected {
-                [0.25, 0.55, 0.95, 1.0]
+                self.theme.ui_button_selected
             } else {
-                [0.85, 0.85, 0.87, 1.0]
+                self.theme.ui_button
             };
 
             let luminance =
@@ -1446,7 +1903,7 @@ impl UiRenderer {
             let icon_color = if luminance < 0.5 {
                 [1.0, 1.0, 1.0, 1.0]
             } else {
-                [0.2, 0.2, 0.2, 1.0]
+                self.theme.ui_icon
             };
 
             self.draw_tool_icon(
@@ -1459,6 +1916,52 @@ impl UiRenderer {
                 icon_color,
             );
         }
+
+        let toggle_center = [
+            start_x + self.tool_icons.len() as f32 * (icon_size + icon_spacing),
+            toolbar_center[1],
+        ];
+        self.create_rounded_rect(
+            vertices,
+            indices,
+            index_offset,
+            toggle_center,
+            [icon_size, icon_size],
+            self.theme.ui_button,
+            8.0 * layout.scale,
+            1.5,
+        );
+        let corner = corner_style_points(toggle_center, icon_size * 0.22, rounded_corners);
+        self.draw_polyline(
+            vertices,
+            indices,
+            index_offset,
+            &corner,
+            icon_size * 0.08,
+            self.theme.ui_icon,
+        );
+    }
+
+    fn draw_polyline(
+        &self,
+        vertices: &mut Vec<UiVertex>,
+        indices: &mut Vec<u16>,
+        index_offset: &mut u16,
+        points: &[[f32; 2]],
+        thickness: f32,
+        color: [f32; 4],
+    ) {
+        for segment in points.windows(2) {
+            self.draw_thick_line(
+                vertices,
+                indices,
+                index_offset,
+                segment[0],
+                segment[1],
+                thickness,
+                color,
+            );
+        }
     }
 
     fn draw_plus_icon(
@@ -1531,6 +2034,80 @@ impl UiRenderer {
         );
     }
 
+    fn generate_alpha_slider(
+        &self,
+        vertices: &mut Vec<UiVertex>,
+        indices: &mut Vec<u16>,
+        index_offset: &mut u16,
+        picker: &ColorPickerState,
+        layout: UiLayout,
+    ) {
+        let origin = layout.alpha_origin;
+        let size = layout.alpha_size;
+
+        // Checkerboard so translucency is visible behind the ramp.
+        let cell = size[1] * 0.5;
+        let columns = (size[0] / cell).ceil() as usize;
+        for column in 0..columns {
+            for row in 0..2 {
+                let width = cell.min(size[0] - column as f32 * cell);
+                let shade = if (column + row) % 2 == 0 { 0.98 } else { 0.8 };
+                self.create_simple_rect(
+                    vertices,
+                    indices,
+                    index_offset,
+                    [
+                        origin[0] + column as f32 * cell + width * 0.5,
+                        origin[1] + row as f32 * cell + cell * 0.5,
+                    ],
+                    [width, cell],
+                    [shade, shade, shade, 1.0],
+                );
+            }
+        }
+
+        let [r, g, b, _] = hsv_to_rgb(picker.hue, picker.saturation, picker.value);
+        let clear = [r, g, b, 0.0];
+        let opaque = [r, g, b, 1.0];
+        let x0 = origin[0];
+        let x1 = origin[0] + size[0];
+        let y0 = origin[1];
+        let y1 = origin[1] + size[1];
+        self.create_colored_triangle(
+            vertices,
+            indices,
+            index_offset,
+            [x0, y0],
+            clear,
+            [x1, y0],
+            opaque,
+            [x1, y1],
+            opaque,
+        );
+        self.create_colored_triangle(
+            vertices,
+            indices,
+            index_offset,
+            [x0, y0],
+            clear,
+            [x1, y1],
+            opaque,
+            [x0, y1],
+            clear,
+        );
+
+        self.create_rounded_rect(
+            vertices,
+            indices,
+            index_offset,
+            [origin[0] + size[0] * 0.5, origin[1] + size[1] * 0.5],
+            size,
+            [0.0, 0.0, 0.0, 0.0],
+            8.0,
+            1.0,
+        );
+    }
+
     fn generate_sv_panel(
         &self,
         vertices: &mut Vec<UiVertex>,
@@ -1614,8 +2191,35 @@ impl UiRenderer {
         ]
     }
 
+    fn alpha_knob_position(&self, picker: &ColorPickerState, layout: UiLayout) -> [f32; 2] {
+        [
+            layout.alpha_origin[0] + picker.alpha * layout.alpha_size[0],
+            layout.alpha_origin[1] + layout.alpha_size[1] * 0.5,
+        ]
+    }
+
     fn color_from_picker(&self, picker: &ColorPickerState) -> [f32; 4] {
-        hsv_to_rgb(picker.hue, picker.saturation, picker.value)
+        with_alpha(
+            hsv_to_rgb(picker.hue, picker.saturation, picker.value),
+            picker.alpha,
+        )
+    }
+
+    fn pick_alpha_color(
+        &self,
+        mouse_pos: [f32; 2],
+        picker: &ColorPickerState,
+        layout: UiLayout,
+    ) -> Option<[f32; 4]> {
+        if !point_in_rect(mouse_pos, layout.alpha_origin, layout.alpha_size) {
+            return None;
+        }
+        let alpha =
+            ((mouse_pos[0] - layout.alpha_origin[0]) / layout.alpha_size[0]).clamp(0.0, 1.0);
+        Some(with_alpha(
+            hsv_to_rgb(picker.hue, picker.saturation, picker.value),
+            alpha,
+        ))
     }
 
     fn pick_hue_color(
@@ -1630,7 +2234,10 @@ impl UiRenderer {
         let hue = (1.0
             - ((mouse_pos[1] - layout.hue_origin[1]) / layout.hue_size[1]).clamp(0.0, 1.0))
             * 360.0;
-        Some(hsv_to_rgb(hue, picker.saturation, picker.value))
+        Some(with_alpha(
+            hsv_to_rgb(hue, picker.saturation, picker.value),
+            picker.alpha,
+        ))
     }
 
     fn pick_sv_color(
@@ -1646,7 +2253,10 @@ impl UiRenderer {
         let saturation = ((mouse_pos[0] - layout.sv_origin[0]) / layout.sv_size[0]).clamp(0.0, 1.0);
         let value =
             (1.0 - ((mouse_pos[1] - layout.sv_origin[1]) / layout.sv_size[1])).clamp(0.0, 1.0);
-        Some(hsv_to_rgb(picker.hue, saturation, value))
+        Some(with_alpha(
+            hsv_to_rgb(picker.hue, saturation, value),
+            picker.alpha,
+        ))
     }
 
     fn generate_zoom_indicator(
@@ -1670,7 +2280,7 @@ impl UiRenderer {
                 zoom_bg_center[1] + shadow_offset,
             ],
             zoom_size,
-            [0.0, 0.0, 0.0, 0.1],
+            self.theme.ui_shadow,
             8.0 * layout.scale,
             0.0,
         );
@@ -1681,21 +2291,583 @@ impl UiRenderer {
             index_offset,
             zoom_bg_center,
             zoom_size,
-            [0.2, 0.2, 0.2, 0.9],
+            self.theme.ui_badge,
             8.0 * layout.scale,
             1.0,
         );
     }
 
-    pub fn zoom_label_layout(&self, screen_size: (f32, f32)) -> ([f32; 2], f32) {
-        let layout = UiLayout::new(screen_size);
-        (layout.zoom_text_pos(), layout.zoom_font_size())
-    }
+    fn generate_arrange_bar(
+        &self,
+        vertices: &mut Vec<UiVertex>,
+        indices: &mut Vec<u16>,
+        index_offset: &mut u16,
+        reference: AlignReference,
+        screen_size: (f32, f32),
+    ) {
+        let layout = UiLayout::new(screen_size);
+        let center = layout.arrange_bar_center();
+        let size = layout.arrange_bar_size();
+        let radius = 10.0 * layout.scale;
+        self.create_rounded_rect(
+            vertices,
+            indices,
+            index_offset,
+            [center[0] + 2.0, center[1] + 2.0],
+            size,
+            self.theme.ui_shadow,
+            radius,
+            0.0,
+        );
+        self.create_rounded_rect(
+            vertices,
+            indices,
+            index_offset,
+            center,
+            size,
+            self.theme.ui_surface,
+            radius,
+            1.0,
+        );
+
+        let button = layout.arrange_button_size();
+        let half = button * 0.3;
+        for (i, command) in ArrangeCommand::TOOLBAR.iter().enumerate() {
+            let button_center = layout.arrange_button_center(i);
+            self.create_rounded_rect(
+                vertices,
+                indices,
+                index_offset,
+                button_center,
+                [button, button],
+                self.theme.ui_button,
+                6.0 * layout.scale,
+                1.0,
+            );
+            let local = |p: [f32; 2]| {
+                [
+                    button_center[0] + p[0] * half,
+                    button_center[1] + p[1] * half,
+                ]
+            };
+            for shape in arrange_icon(*command, reference) {
+                match shape {
+                    IconShape::Bar { center, size } => self.create_rounded_rect(
+                        vertices,
+                        indices,
+                        index_offset,
+                        local(center),
+                        [size[0] * half, size[1] * half],
+                        self.theme.ui_icon,
+                        0.0,
+                        0.0,
+                    ),
+                    IconShape::Line(points) => {
+                        let points: Vec<[f32; 2]> = points.into_iter().map(local).collect();
+                        self.draw_polyline(
+                            vertices,
+                            indices,
+                            index_offset,
+                            &points,
+                            (1.5 * layout.scale).max(1.0),
+                            self.theme.ui_icon,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn generate_command_palette(
+        &self,
+        vertices: &mut Vec<UiVertex>,
+        indices: &mut Vec<u16>,
+        index_offset: &mut u16,
+        view: PaletteView,
+        screen_size: (f32, f32),
+    ) {
+        let layout = UiLayout::new(screen_size);
+        let origin = layout.command_palette_origin();
+        let size = layout.command_palette_size(view.rows);
+        let padding = layout.command_palette_padding();
+        let center = [origin[0] + size[0] * 0.5, origin[1] + size[1] * 0.5];
+        self.create_rounded_rect(
+            vertices,
+            indices,
+            index_offset,
+            [center[0] + 3.0, center[1] + 3.0],
+            size,
+            self.theme.ui_shadow,
+            10.0 * layout.scale,
+            0.0,
+        );
+        self.create_rounded_rect(
+            vertices,
+            indices,
+            index_offset,
+            center,
+            size,
+            self.theme.ui_surface,
+            10.0 * layout.scale,
+            1.0,
+        );
+
+        let inner_width = size[0] - padding * 2.0;
+        let input_height = layout.command_palette_input_height();
+        self.create_rounded_rect(
+            vertices,
+            indices,
+            index_offset,
+            [center[0], origin[1] + padding + input_height * 0.5],
+            [inner_width, input_height],
+            self.theme.ui_button,
+            6.0 * layout.scale,
+            1.0,
+        );
+
+        if view.selected < view.rows {
+            let row_height = layout.command_palette_row_height();
+            self.create_rounded_rect(
+                vertices,
+                indices,
+                index_offset,
+                [
+                    center[0],
+                    layout.command_palette_row_top(view.selected) + row_height * 0.5,
+                ],
+                [inner_width, row_height],
+                self.theme.ui_button_selected,
+                6.0 * layout.scale,
+                1.0,
+            );
+        }
+    }
+
+    fn generate_find_bar(
+        &self,
+        vertices: &mut Vec<UiVertex>,
+        indices: &mut Vec<u16>,
+        index_offset: &mut u16,
+        view: FindBarView,
+        screen_size: (f32, f32),
+    ) {
+        let layout = UiLayout::new(screen_size);
+        let origin = layout.find_bar_origin();
+        let size = layout.find_bar_size();
+        let center = [origin[0] + size[0] * 0.5, origin[1] + size[1] * 0.5];
+        self.create_rounded_rect(
+            vertices,
+            indices,
+            index_offset,
+            [center[0] + 3.0, center[1] + 3.0],
+            size,
+            self.theme.ui_shadow,
+            10.0 * layout.scale,
+            0.0,
+        );
+        self.create_rounded_rect(
+            vertices,
+            indices,
+            index_offset,
+            center,
+            size,
+            self.theme.ui_surface,
+            10.0 * layout.scale,
+            1.0,
+        );
+
+        let focused = if view.replacing {
+            FindBarPart::Replacement
+        } else {
+            FindBarPart::Query
+        };
+        for part in FindBarPart::ALL {
+            let (origin, size) = layout.find_bar_part_rect(part);
+            let center = [origin[0] + size[0] * 0.5, origin[1] + size[1] * 0.5];
+            if part == focused {
+                let ring = 1.5 * layout.scale;
+                self.create_rounded_rect(
+                    vertices,
+                    indices,
+                    index_offset,
+                    center,
+                    [size[0] + ring * 2.0, size[1] + ring * 2.0],
+                    self.theme.ui_button_selected,
+                    6.0 * layout.scale,
+                    1.0,
+                );
+            }
+            let on = match part {
+                FindBarPart::MatchCase => view.match_case,
+                FindBarPart::Regex => view.regex,
+                _ => false,
+            };
+            let color = if on {
+                self.theme.ui_button_selected
+            } else {
+                self.theme.ui_button
+            };
+            self.create_rounded_rect(
+                vertices,
+                indices,
+                index_offset,
+                center,
+                size,
+                color,
+                6.0 * layout.scale,
+                1.0,
+            );
+        }
+    }
+
+    fn generate_properties_panel(
+        &self,
+        vertices: &mut Vec<UiVertex>,
+        indices: &mut Vec<u16>,
+        index_offset: &mut u16,
+        view: &PropertiesView,
+        screen_size: (f32, f32),
+    ) {
+        let layout = UiLayout::new(screen_size);
+        let origin = layout.properties_origin();
+        let size = layout.properties_size(view.rows.len());
+        let center = [origin[0] + size[0] * 0.5, origin[1] + size[1] * 0.5];
+        let radius = 10.0 * layout.scale;
+        self.create_rounded_rect(
+            vertices,
+            indices,
+            index_offset,
+            [center[0] + 2.0, center[1] + 2.0],
+            size,
+            self.theme.ui_shadow,
+            radius,
+            0.0,
+        );
+        self.create_rounded_rect(
+            vertices,
+            indices,
+            index_offset,
+            center,
+            size,
+            self.theme.ui_surface,
+            radius,
+            1.0,
+        );
+
+        let rect_center = |(origin, size): ([f32; 2], [f32; 2])| {
+            [origin[0] + size[0] * 0.5, origin[1] + size[1] * 0.5]
+        };
+        for (row, control) in view.rows.iter().enumerate() {
+            let [decrease, value, increase] = layout.property_part_rects(row, *control);
+            let field_color = match control {
+                PropertyControl::Toggle { on: Some(true) } => self.theme.ui_button_selected,
+                _ => self.theme.ui_button,
+            };
+            if view.editing == Some(row) {
+                let outline = 2.0;
+                self.create_rounded_rect(
+                    vertices,
+                    indices,
+                    index_offset,
+                    rect_center(value),
+                    [value.1[0] + outline * 2.0, value.1[1] + outline * 2.0],
+                    self.theme.ui_button_selected,
+                    4.0 * layout.scale,
+                    1.0,
+                );
+            }
+            self.create_rounded_rect(
+                vertices,
+                indices,
+                index_offset,
+                rect_center(value),
+                value.1,
+                field_color,
+                4.0 * layout.scale,
+                1.0,
+            );
+
+            let icon = value.1[1] * 0.4;
+            let thickness = (1.5 * layout.scale).max(1.0);
+            if *control == PropertyControl::Number {
+                for (rect, plus) in [(decrease, false), (increase, true)] {
+                    let button_center = rect_center(rect);
+                    self.create_rounded_rect(
+                        vertices,
+                        indices,
+                        index_offset,
+                        button_center,
+                        rect.1,
+                        self.theme.ui_button,
+                        4.0 * layout.scale,
+                        1.0,
+                    );
+                    if plus {
+                        self.draw_plus_icon(
+                            vertices,
+                            indices,
+                            index_offset,
+                            button_center,
+                            icon,
+                            self.theme.ui_icon,
+                        );
+                    } else {
+                        self.create_simple_rect(
+                            vertices,
+                            indices,
+                            index_offset,
+                            button_center,
+                            [icon, 2.0],
+                            self.theme.ui_icon,
+                        );
+                    }
+                }
+            }
+            if *control == PropertyControl::Choice {
+                for (rect, direction) in [(decrease, -1.0), (increase, 1.0)] {
+                    let button_center = rect_center(rect);
+                    self.create_rounded_rect(
+                        vertices,
+                        indices,
+                        index_offset,
+                        button_center,
+                        rect.1,
+                        self.theme.ui_button,
+                        4.0 * layout.scale,
+                        1.0,
+                    );
+                    let half = icon * 0.5;
+                    self.draw_polyline(
+                        vertices,
+                        indices,
+                        index_offset,
+                        &[
+                            [
+                                button_center[0] - half * 0.5 * direction,
+                                button_center[1] - half,
+                            ],
+                            [button_center[0] + half * 0.5 * direction, button_center[1]],
+                            [
+                                button_center[0] - half * 0.5 * direction,
+                                button_center[1] + half,
+                            ],
+                        ],
+                        thickness,
+                        self.theme.ui_icon,
+                    );
+                }
+            }
+            if *control == PropertyControl::Reroll {
+                let button_center = rect_center(increase);
+                self.create_rounded_rect(
+                    vertices,
+                    indices,
+                    index_offset,
+                    button_center,
+                    increase.1,
+                    self.theme.ui_button,
+                    4.0 * layout.scale,
+                    1.0,
+                );
+                // A three-quarter circle with an arrowhead: "spin again".
+                let radius = icon * 0.6;
+                let arc: Vec<[f32; 2]> = (0..=12)
+                    .map(|i| {
+                        let angle = -std::f32::consts::FRAC_PI_2
+                            + i as f32 / 12.0 * std::f32::consts::PI * 1.5;
+                        [
+                            button_center[0] + radius * angle.cos(),
+                            button_center[1] + radius * angle.sin(),
+                        ]
+                    })
+                    .collect();
+                let tip = arc[0];
+                self.draw_polyline(
+                    vertices,
+                    indices,
+                    index_offset,
+                    &arc,
+                    thickness,
+                    self.theme.ui_icon,
+                );
+                self.draw_polyline(
+                    vertices,
+                    indices,
+                    index_offset,
+                    &[
+                        [tip[0] - radius * 0.5, tip[1] - radius * 0.5],
+                        tip,
+                        [tip[0] - radius * 0.5, tip[1] + radius * 0.5],
+                    ],
+                    thickness,
+                    self.theme.ui_icon,
+                );
+            }
+        }
+    }
+
+    /// Text placement for properties panel row `row`.
+    pub fn property_label(
+        &self,
+        screen_size: (f32, f32),
+        row: usize,
+        control: PropertyControl,
+    ) -> PropertyLabel {
+        let layout = UiLayout::new(screen_size);
+        let font_size = layout.command_palette_font_size() * 0.9;
+        let baseline = layout.properties_row_top(row)
+            + layout.properties_row_height() * 0.5
+            + font_size * 0.35;
+        let (origin, size) = layout.property_part_rects(row, control)[1];
+        PropertyLabel {
+            label: [
+                layout.properties_origin()[0] + layout.properties_padding(),
+                baseline,
+            ],
+            value_center: [origin[0] + size[0] * 0.5, baseline],
+            font_size,
+        }
+    }
+
+    /// The properties panel row and part under `mouse_pos`, if the panel is shown.
+    pub fn property_at(
+        &self,
+        mouse_pos: [f32; 2],
+        screen_size: (f32, f32),
+    ) -> Option<(usize, PropertyPart)> {
+        let view = self.properties.as_ref()?;
+        let layout = UiLayout::new(screen_size);
+        view.rows.iter().enumerate().find_map(|(row, control)| {
+            let [decrease, value, increase] = layout.property_part_rects(row, *control);
+            let parts = [
+                (PropertyPart::Value, value),
+                (PropertyPart::Decrease, decrease),
+                (PropertyPart::Increase, increase),
+            ];
+            parts.into_iter().find_map(|(part, (origin, size))| {
+                let shown = match control {
+                    PropertyControl::Number | PropertyControl::Choice => true,
+                    PropertyControl::Toggle { .. } => part == PropertyPart::Value,
+                    PropertyControl::Reroll => part != PropertyPart::Decrease,
+                };
+                (shown && point_in_rect(mouse_pos, origin, size)).then_some((row, part))
+            })
+        })
+    }
+
+    fn properties_contains(&self, mouse_pos: [f32; 2], layout: UiLayout) -> bool {
+        self.properties.as_ref().is_some_and(|view| {
+            point_in_rect(
+                mouse_pos,
+                layout.properties_origin(),
+                layout.properties_size(view.rows.len()),
+            )
+        })
+    }
+
+    /// Text placement for the palette's query field (`None`) or a result row.
+    pub fn command_palette_label(
+        &self,
+        screen_size: (f32, f32),
+        row: Option<usize>,
+    ) -> PaletteLabel {
+        let layout = UiLayout::new(screen_size);
+        let origin = layout.command_palette_origin();
+        let padding = layout.command_palette_padding();
+        let font_size = layout.command_palette_font_size();
+        let (top, height) = match row {
+            None => (origin[1] + padding, layout.command_palette_input_height()),
+            Some(row) => (
+                layout.command_palette_row_top(row),
+                layout.command_palette_row_height(),
+            ),
+        };
+        let inset = padding * 2.0;
+        PaletteLabel {
+            pos: [origin[0] + inset, top + height * 0.5 + font_size * 0.35],
+            right: origin[0] + layout.command_palette_size(0)[0] - inset,
+            font_size,
+        }
+    }
+
+    /// The visible row under `mouse_pos`, if the palette is open.
+    pub fn command_palette_row_at(
+        &self,
+        mouse_pos: [f32; 2],
+        screen_size: (f32, f32),
+    ) -> Option<usize> {
+        let view = self.command_palette?;
+        let layout = UiLayout::new(screen_size);
+        let origin = layout.command_palette_origin();
+        let width = layout.command_palette_size(view.rows)[0];
+        if mouse_pos[0] < origin[0] || mouse_pos[0] > origin[0] + width {
+            return None;
+        }
+        let row_height = layout.command_palette_row_height();
+        (0..view.rows).find(|&row| {
+            let top = layout.command_palette_row_top(row);
+            mouse_pos[1] >= top && mouse_pos[1] < top + row_height
+        })
+    }
+
+    pub fn command_palette_contains(&self, mouse_pos: [f32; 2], screen_size: (f32, f32)) -> bool {
+        let Some(view) = self.command_palette else {
+            return false;
+        };
+        let layout = UiLayout::new(screen_size);
+        point_in_rect(
+            mouse_pos,
+            layout.command_palette_origin(),
+            layout.command_palette_size(view.rows),
+        )
+    }
+
+    /// Text placement inside a part of the find bar.
+    pub fn find_bar_label(&self, screen_size: (f32, f32), part: FindBarPart) -> PaletteLabel {
+        let layout = UiLayout::new(screen_size);
+        let (origin, size) = layout.find_bar_part_rect(part);
+        let font_size = layout.command_palette_font_size() * 0.9;
+        let inset = layout.command_palette_padding();
+        PaletteLabel {
+            pos: [
+                origin[0] + inset,
+                origin[1] + size[1] * 0.5 + font_size * 0.35,
+            ],
+            right: origin[0] + size[0] - inset,
+            font_size,
+        }
+    }
+
+    /// The find bar part under `mouse_pos`, if the bar is shown.
+    pub fn find_bar_part_at(
+        &self,
+        mouse_pos: [f32; 2],
+        screen_size: (f32, f32),
+    ) -> Option<FindBarPart> {
+        self.find_bar?;
+        let layout = UiLayout::new(screen_size);
+        FindBarPart::ALL.into_iter().find(|part| {
+            let (origin, size) = layout.find_bar_part_rect(*part);
+            point_in_rect(mouse_pos, origin, size)
+        })
+    }
+
+    pub fn find_bar_contains(&self, mouse_pos: [f32; 2], screen_size: (f32, f32)) -> bool {
+        let layout = UiLayout::new(screen_size);
+        self.find_bar.is_some()
+            && point_in_rect(mouse_pos, layout.find_bar_origin(), layout.find_bar_size())
+    }
+
+    pub fn zoom_label_layout(&self, screen_size: (f32, f32)) -> ([f32; 2], f32) {
+        let layout = UiLayout::new(screen_size);
+        (layout.zoom_text_pos(), layout.zoom_font_size())
+    }
 
     pub fn generate_ui_vertices(
         &self,
         current_tool: Tool,
-        current_color: [f32; 4],
+        rounded_corners: bool,
+        colors: ActiveColors,
         picker: &ColorPickerState,
         screen_size: (f32, f32),
         _zoom_level: f32,
@@ -1708,7 +2880,7 @@ impl UiRenderer {
             &mut vertices,
             &mut indices,
             &mut index_offset,
-            current_color,
+            colors,
             picker,
             screen_size,
         );
@@ -1717,9 +2889,46 @@ impl UiRenderer {
             &mut indices,
             &mut index_offset,
             current_tool,
+            rounded_corners,
             screen_size,
         );
         self.generate_zoom_indicator(&mut vertices, &mut indices, &mut index_offset, screen_size);
+        if let Some(reference) = self.arrange_bar {
+            self.generate_arrange_bar(
+                &mut vertices,
+                &mut indices,
+                &mut index_offset,
+                reference,
+                screen_size,
+            );
+        }
+        if let Some(view) = &self.properties {
+            self.generate_properties_panel(
+                &mut vertices,
+                &mut indices,
+                &mut index_offset,
+                view,
+                screen_size,
+            );
+        }
+        if let Some(view) = self.find_bar {
+            self.generate_find_bar(
+                &mut vertices,
+                &mut indices,
+                &mut index_offset,
+                view,
+                screen_size,
+            );
+        }
+        if let Some(view) = self.command_palette {
+            self.generate_command_palette(
+                &mut vertices,
+                &mut indices,
+                &mut index_offset,
+                view,
+                screen_size,
+            );
+        }
 
         (vertices, indices)
     }
@@ -1732,31 +2941,24 @@ impl UiRenderer {
     ) -> ColorInteraction {
         let layout = UiLayout::new(screen_size);
         for (i, &color) in PALETTE_COLORS.iter().enumerate() {
-            let col = (i % 2) as f32;
-            let row = (i / 2) as f32;
+            if layout.swatch_contains(layout.swatch_center(layout.palette_origin, i), mouse_pos) {
+                return ColorInteraction::Color(color);
+            }
+        }
 
-            let x = layout.palette_origin[0] + col * (layout.swatch_size + layout.swatch_padding);
-            let y = layout.palette_origin[1] + row * (layout.swatch_size + layout.swatch_padding);
+        if layout.swatch_contains(layout.custom_swatch_center(), mouse_pos) {
+            return ColorInteraction::TogglePicker(ColorTarget::Stroke);
+        }
 
-            if mouse_pos[0] >= x
-                && mouse_pos[0] <= x + layout.swatch_size
-                && mouse_pos[1] >= y
-                && mouse_pos[1] <= y + layout.swatch_size
+        for (i, &background) in BACKGROUND_COLORS.iter().enumerate() {
+            if layout.swatch_contains(layout.swatch_center(layout.background_origin, i), mouse_pos)
             {
-                return ColorInteraction::Color(color);
+                return ColorInteraction::Background(background);
             }
         }
 
-        let custom_center = layout.custom_swatch_center();
-        if point_in_rect(
-            mouse_pos,
-            [
-                custom_center[0] - layout.swatch_size * 0.5,
-                custom_center[1] - layout.swatch_size * 0.5,
-            ],
-            [layout.swatch_size, layout.swatch_size],
-        ) {
-            return ColorInteraction::TogglePicker;
+        if layout.swatch_contains(layout.custom_background_center(), mouse_pos) {
+            return ColorInteraction::TogglePicker(ColorTarget::Background);
         }
 
         if !picker.open {
@@ -1771,6 +2973,10 @@ impl UiRenderer {
             return ColorInteraction::BeginDrag(ColorPickerDragMode::HueRing, color);
         }
 
+        if let Some(color) = self.pick_alpha_color(mouse_pos, picker, layout) {
+            return ColorInteraction::BeginDrag(ColorPickerDragMode::AlphaBar, color);
+        }
+
         ColorInteraction::None
     }
 
@@ -1785,11 +2991,28 @@ impl UiRenderer {
         match drag_mode {
             ColorPickerDragMode::HueRing => self.pick_hue_color(mouse_pos, picker, layout),
             ColorPickerDragMode::SvDisk => self.pick_sv_color(mouse_pos, picker, layout),
+            ColorPickerDragMode::AlphaBar => self.pick_alpha_color(mouse_pos, picker, layout),
         }
     }
 
-    pub fn handle_click(&self, mouse_pos: [f32; 2], screen_size: (f32, f32)) -> Option<Tool> {
+    pub fn handle_click(
+        &self,
+        mouse_pos: [f32; 2],
+        screen_size: (f32, f32),
+    ) -> Option<ToolbarInteraction> {
         let layout = UiLayout::new(screen_size);
+        if self.arrange_bar.is_some() && layout.arrange_bar_contains(mouse_pos) {
+            let half = layout.arrange_button_size() * 0.5;
+            return ArrangeCommand::TOOLBAR
+                .iter()
+                .enumerate()
+                .find(|(i, _)| {
+                    let center = layout.arrange_button_center(*i);
+                    (mouse_pos[0] - center[0]).abs() <= half
+                        && (mouse_pos[1] - center[1]).abs() <= half
+                })
+                .map(|(_, command)| ToolbarInteraction::Arrange(*command));
+        }
         let icon_size = layout.toolbar_icon_size;
         let icon_spacing = layout.toolbar_spacing;
         let toolbar_padding = layout.toolbar_padding;
@@ -1812,7 +3035,13 @@ impl UiRenderer {
 
         let start_x = toolbar_center[0] - toolbar_width / 2.0 + toolbar_padding;
 
-        for (i, icon) in self.tool_icons.iter().enumerate() {
+        let buttons = self
+            .tool_icons
+            .iter()
+            .map(|icon| ToolbarInteraction::Tool(icon.tool))
+            .chain(std::iter::once(ToolbarInteraction::ToggleRoundness));
+
+        for (i, button) in buttons.enumerate() {
             let button_left = start_x + i as f32 * (icon_size + icon_spacing);
             let button_right = button_left + icon_size;
             let button_top = toolbar_center[1] - icon_size / 2.0;
@@ -1823,7 +3052,7 @@ impl UiRenderer {
                 && mouse_pos[1] >= button_top
                 && mouse_pos[1] <= button_bottom
             {
-                return Some(icon.tool);
+                return Some(button);
             }
         }
 
@@ -1854,22 +3083,15 @@ impl UiRenderer {
             return true;
         }
 
-        let padding = layout.swatch_padding;
-        let start_x = layout.palette_origin[0];
-        let start_y = layout.palette_origin[1];
-        let swatch_size = layout.swatch_size;
-        let cols = 2;
-        let rows = (PALETTE_COLORS.len() + cols) / cols + 1;
-
-        let palette_width = cols as f32 * swatch_size + (cols - 1) as f32 * padding;
-        let palette_height = rows as f32 * swatch_size + (rows - 1) as f32 * padding;
+        if point_in_rect(mouse_pos, layout.palette_origin, layout.palette_size) {
+            return true;
+        }
 
-        let over_palette = mouse_pos[0] >= start_x
-            && mouse_pos[0] <= start_x + palette_width
-            && mouse_pos[1] >= start_y
-            && mouse_pos[1] <= start_y + palette_height;
+        if self.arrange_bar.is_some() && layout.arrange_bar_contains(mouse_pos) {
+            return true;
+        }
 
-        if over_palette {
+        if self.properties_contains(mouse_pos, layout) {
             return true;
         }
 
@@ -1879,7 +3101,6 @@ impl UiRenderer {
     pub fn generate_toolbar_icons(
         &self,
         text_renderer: &mut crate::text_renderer::TextRenderer,
-        device: &wgpu::Device,
         queue: &wgpu::Queue,
         current_tool: Tool,
         screen_size: (f32, f32),
@@ -1936,9 +3157,139 @@ impl UiRenderer {
 
             let text_pos = [x - 8.0, y + 6.0];
 
-            text_renderer.add_screen_label(device, queue, icon_text, text_pos, 16.0, text_color);
+            text_renderer.add_screen_label(queue, icon_text, text_pos, 16.0, text_color);
+        }
+    }
+}
+
+/// Glyph for an arrange bar button. Alignment icons show two bars against a
+/// reference line; distribution icons show three bars; the reference button
+/// shows what alignment measures against.
+fn arrange_icon(command: ArrangeCommand, reference: AlignReference) -> Vec<IconShape> {
+    // Two bars meeting the line at `at` (-1, 0 or 1) along `axis`.
+    let aligned = |axis: usize, at: f32| {
+        let bar = |length: f32, across: f32| {
+            let along = at * (1.0 - length * 0.5);
+            let (center, size) = ([along, across], [length, 0.5]);
+            if axis == 0 {
+                IconShape::Bar { center, size }
+            } else {
+                IconShape::Bar {
+                    center: [center[1], center[0]],
+                    size: [size[1], size[0]],
+                }
+            }
+        };
+        let line = if axis == 0 {
+            vec![[at, -1.0], [at, 1.0]]
+        } else {
+            vec![[-1.0, at], [1.0, at]]
+        };
+        vec![IconShape::Line(line), bar(1.6, -0.45), bar(1.0, 0.45)]
+    };
+    // Three bars spread along `axis`, with a line through their centers.
+    let spread = |axis: usize, centers: bool| {
+        let mut shapes: Vec<IconShape> = [(-0.75, 1.4), (0.0, 0.9), (0.75, 1.6)]
+            .into_iter()
+            .map(|(along, length)| {
+                if axis == 0 {
+                    IconShape::Bar {
+                        center: [along, 0.0],
+                        size: [0.35, length],
+                    }
+                } else {
+                    IconShape::Bar {
+                        center: [0.0, along],
+                        size: [length, 0.35],
+                    }
+                }
+            })
+            .collect();
+        if centers {
+            shapes.push(IconShape::Line(if axis == 0 {
+                vec![[-1.0, 0.0], [1.0, 0.0]]
+            } else {
+                vec![[0.0, -1.0], [0.0, 1.0]]
+            }));
+        }
+        shapes
+    };
+    let square = |center: [f32; 2], half: f32| {
+        vec![
+            [center[0] - half, center[1] - half],
+            [center[0] + half, center[1] - half],
+            [center[0] + half, center[1] + half],
+            [center[0] - half, center[1] + half],
+            [center[0] - half, center[1] - half],
+        ]
+    };
+
+    match command {
+        ArrangeCommand::Align(alignment) => match alignment {
+            Alignment::Left => aligned(0, -1.0),
+            Alignment::CenterHorizontal => aligned(0, 0.0),
+            Alignment::Right => aligned(0, 1.0),
+            Alignment::Top => aligned(1, -1.0),
+            Alignment::CenterVertical => aligned(1, 0.0),
+            Alignment::Bottom => aligned(1, 1.0),
+        },
+        ArrangeCommand::Distribute(distribution) => match distribution {
+            Distribution::HorizontalGaps => spread(0, false),
+            Distribution::VerticalGaps => spread(1, false),
+            Distribution::HorizontalCenters => spread(0, true),
+            Distribution::VerticalCenters => spread(1, true),
+        },
+        ArrangeCommand::TidyUp => [[-0.5, -0.5], [0.5, -0.5], [-0.5, 0.5], [0.5, 0.5]]
+            .into_iter()
+            .map(|center| IconShape::Bar {
+                center,
+                size: [0.7, 0.7],
+            })
+            .collect(),
+        ArrangeCommand::CycleReference => match reference {
+            AlignReference::Selection => vec![IconShape::Line(square([0.0, 0.0], 0.8))],
+            AlignReference::Canvas => vec![
+                IconShape::Line(square([0.0, 0.0], 1.0)),
+                IconShape::Bar {
+                    center: [0.0, 0.0],
+                    size: [0.8, 0.8],
+                },
+            ],
+            AlignReference::KeyObject => vec![
+                IconShape::Bar {
+                    center: [-0.4, -0.4],
+                    size: [1.0, 1.0],
+                },
+                IconShape::Line(square([0.4, 0.4], 0.5)),
+            ],
+        },
+    }
+}
+
+/// Glyph for the corner-style toggle: a top-left corner, sharp or rounded.
+fn corner_style_points(center: [f32; 2], half: f32, rounded: bool) -> Vec<[f32; 2]> {
+    let left = center[0] - half;
+    let top = center[1] - half;
+    let mut points = vec![[left, center[1] + half]];
+    if rounded {
+        let radius = half * 1.2;
+        let segments = 8;
+        for i in 0..=segments {
+            let angle = std::f32::consts::PI * (1.0 + 0.5 * i as f32 / segments as f32);
+            points.push([
+                left + radius + radius * angle.cos(),
+                top + radius + radius * angle.sin(),
+            ]);
         }
+    } else {
+        points.push([left, top]);
     }
+    points.push([center[0] + half, top]);
+    points
+}
+
+fn same_rgb(a: [f32; 4], b: [f32; 4]) -> bool {
+    (a[0] - b[0]).abs() < 0.01 && (a[1] - b[1]).abs() < 0.01 && (a[2] - b[2]).abs() < 0.01
 }
 
 fn point_in_rect(point: [f32; 2], origin: [f32; 2], size: [f32; 2]) -> bool {
@@ -1967,6 +3318,10 @@ fn picker_bounds_contains(mouse_pos: [f32; 2], layout: UiLayout) -> bool {
     )
 }
 
+fn with_alpha(color: [f32; 4], alpha: f32) -> [f32; 4] {
+    [color[0], color[1], color[2], alpha]
+}
+
 fn hsv_to_rgb(h: f32, s: f32, v: f32) -> [f32; 4] {
     let c = v * s;
     let hue_sector = (h / 60.0).rem_euclid(6.0);
@@ -1,14 +1,25 @@
 use crate::app_state::State;
-use crate::drawing::{DrawingElement, Element, ElementId};
-use crate::state::ResizeHandle;
+use crate::drawing::{BoxState, DrawingElement, Effects, Element, ElementId};
+use crate::effects::EffectJob;
+use crate::find::FindHighlight;
+use crate::palette::{PALETTE_ROWS, PaletteItem};
+use crate::properties::PropertyRow;
+use crate::snapping::SnapGuide;
+use crate::state::{DrawRun, GridStyle, ResizeHandle, RunPipeline};
+use crate::text_edit::TextOverlay;
+use crate::text_layout;
+use crate::theme::Theme;
+use crate::ui::{ActiveColors, FindBarPart, FindBarView, PaletteView, PropertyControl};
 use crate::vector::path::Path;
-use crate::vector::sdf::SdfBatch;
-use crate::vector::style::StrokeStyle;
+use crate::vector::sdf::{SdfBatch, SdfEffect};
+use crate::vector::style::{FillStyle, StrokeStyle};
 use crate::vector::tessellator::PathTessellator;
 use wgpu::util::DeviceExt;
 
 impl State {
     pub fn update(&mut self) {
+        text_layout::begin_frame();
+        self.text_renderer.begin_frame();
         if self.typing.active {
             let elapsed = self.typing.blink_timer.elapsed();
             if elapsed.as_millis() > 500 {
@@ -24,9 +35,48 @@ impl State {
         }
         self.update_buffers();
 
+        let arrange_bar = (!self.input.selection.selected_ids.is_empty())
+            .then_some(self.settings.align_reference);
+        self.ui_renderer.set_arrange_bar(arrange_bar);
+        let property_rows = self.property_rows();
+        let properties_view = self.properties_view(&property_rows);
+        if properties_view.is_none() {
+            self.properties.editing = None;
+        }
+        self.ui_renderer.set_properties(properties_view.clone());
+        let palette_matches = if self.palette.open {
+            self.palette_matches()
+        } else {
+            Vec::new()
+        };
+        let palette_scroll = self.palette.scroll();
+        let palette_rows = palette_matches
+            .len()
+            .saturating_sub(palette_scroll)
+            .min(PALETTE_ROWS);
+        self.ui_renderer
+            .set_command_palette(self.palette.open.then_some(PaletteView {
+                rows: palette_rows,
+                selected: self.palette.selected - palette_scroll,
+            }));
+        let find_count = self.find.open.then(|| self.find_matches().len());
+        if let Some(count) = find_count {
+            self.find.current = self.find.current.min(count.saturating_sub(1));
+        }
+        self.ui_renderer
+            .set_find_bar(self.find.open.then_some(FindBarView {
+                match_case: self.find.match_case,
+                regex: self.find.regex,
+                replacing: self.find.replacing,
+            }));
+
         let (ui_vertices, ui_indices) = self.ui_renderer.generate_ui_vertices(
             self.current_tool,
-            self.current_color,
+            self.style.roundness.is_round(),
+            ActiveColors {
+                stroke: self.style.stroke,
+                background: self.style.background,
+            },
             &self.color_picker,
             (self.size.width as f32, self.size.height as f32),
             self.canvas.transform.scale,
@@ -52,45 +102,67 @@ impl State {
             self.ui_geo.count = ui_indices.len() as u32;
         }
 
-        let mut drawing_elements = self.elements.clone();
+        let theme = &self.theme();
+        let mut drawing_elements: Vec<Element> = self
+            .elements
+            .iter()
+            .map(|element| {
+                let mut shape = element.rendered_shape();
+                shape.map_colors(|color| theme.element_color(color));
+                Element {
+                    shape,
+                    opacity: 1.0,
+                    ..element.clone()
+                }
+            })
+            .collect();
 
         if let Some(preview) = &self.input.preview_element {
             drawing_elements.push(Element {
                 id: ElementId(0),
                 group_id: None,
                 shape: preview.clone(),
+                opacity: 1.0,
+                effects: Effects::default(),
+                label: None,
             });
         }
 
         if self.typing.active {
-            let mut display_text = self.typing.buffer.clone();
-            if self.typing.cursor_visible {
-                display_text.push('|');
-            }
+            let mut shape = self.typing_text_box(self.typing.buffer.clone(), BoxState::Editing);
+            shape.map_colors(|color| theme.element_color(color));
             drawing_elements.push(Element {
                 id: ElementId(0),
                 group_id: None,
-                shape: DrawingElement::TextBox {
-                    id: self.typing.editing_id.map(|id| id.0).unwrap_or(0),
-                    pos: self.typing.pos_canvas,
-                    size: [
-                        display_text
-                            .lines()
-                            .map(|line| line.chars().count())
-                            .max()
-                            .unwrap_or(1) as f32
-                            * 19.2
-                            + 16.0,
-                        display_text.lines().count() as f32 * 38.0 + 16.0,
-                    ],
-                    content: display_text,
-                    color: self.current_color,
-                    font_size: 32.0,
-                    state: crate::drawing::BoxState::Editing,
-                },
+                shape,
+                opacity: 1.0,
+                effects: Effects::default(),
+                label: None,
             });
         }
 
+        let labels: Vec<Element> = self
+            .elements
+            .iter()
+            .filter(|element| !self.is_editing_label(element.id))
+            .filter_map(|element| {
+                let mut shape = element.label_box()?;
+                let opacity = element.opacity.clamp(0.0, 1.0);
+                shape.map_colors(|color| {
+                    theme.element_color([color[0], color[1], color[2], color[3] * opacity])
+                });
+                Some(Element {
+                    id: element.id,
+                    group_id: None,
+                    shape,
+                    opacity: 1.0,
+                    effects: Effects::default(),
+                    label: None,
+                })
+            })
+            .collect();
+        drawing_elements.extend(labels);
+
         self.text_renderer.prepare(
             &self.gpu.device,
             &self.gpu.queue,
@@ -105,17 +177,232 @@ impl State {
             .ui_renderer
             .zoom_label_layout((self.size.width as f32, self.size.height as f32));
         self.text_renderer.add_screen_label(
-            &self.gpu.device,
             &self.gpu.queue,
             &zoom_text,
             screen_pos,
             font_size,
-            [1.0, 1.0, 1.0, 1.0],
+            theme.text,
         );
 
+        for guide in &self.input.guides {
+            if let SnapGuide::Spacing {
+                start,
+                end,
+                distance,
+            } = *guide
+            {
+                let mid = self
+                    .canvas
+                    .transform
+                    .canvas_to_screen([(start[0] + end[0]) * 0.5, (start[1] + end[1]) * 0.5]);
+                self.text_renderer.add_screen_label(
+                    &self.gpu.queue,
+                    &format!("{}", distance.round() as i32),
+                    [mid[0] + 4.0, mid[1] - 4.0],
+                    GUIDE_LABEL_SIZE,
+                    theme.guide,
+                );
+            }
+        }
+
+        if let Some(view) = &properties_view {
+            self.add_property_labels(theme, &property_rows, &view.rows);
+        }
+        if let Some(count) = find_count {
+            self.add_find_bar_labels(theme, count);
+        }
+        if self.palette.open {
+            self.add_command_palette_labels(
+                theme,
+                &palette_matches[palette_scroll..][..palette_rows],
+            );
+        }
+
         self.text_renderer.build_screen_buffers(&self.gpu.device);
     }
 
+    /// Names and values for the properties panel. Mixed values read "Mixed";
+    /// the field being typed into shows its text and a caret.
+    fn add_property_labels(
+        &mut self,
+        theme: &Theme,
+        rows: &[PropertyRow],
+        controls: &[PropertyControl],
+    ) {
+        let screen_size = (self.size.width as f32, self.size.height as f32);
+        for (row, (property_row, control)) in rows.iter().zip(controls).enumerate() {
+            let label = self.ui_renderer.property_label(screen_size, row, *control);
+            self.text_renderer.add_screen_label(
+                &self.gpu.queue,
+                property_row.property.label(),
+                label.label,
+                label.font_size,
+                theme.ui_icon,
+            );
+
+            let editing = self
+                .properties
+                .editing
+                .as_ref()
+                .filter(|edit| edit.property == property_row.property);
+            let (value, color) = match (editing, property_row.value) {
+                (Some(edit), _) => (format!("{}|", edit.text), theme.ui_icon),
+                (None, Some(value)) => {
+                    let color = if *control == (PropertyControl::Toggle { on: Some(true) }) {
+                        theme.text
+                    } else {
+                        theme.ui_icon
+                    };
+                    (value.display(property_row.property), color)
+                }
+                (None, None) => ("Mixed".to_string(), with_alpha(theme.ui_icon, 0.55)),
+            };
+            let width = self
+                .text_renderer
+                .measure_screen_label(&value, label.font_size);
+            self.text_renderer.add_screen_label(
+                &self.gpu.queue,
+                &value,
+                [label.value_center[0] - width * 0.5, label.value_center[1]],
+                label.font_size,
+                color,
+            );
+        }
+    }
+
+    /// Query text, command titles and shortcut hints for the open palette.
+    fn add_command_palette_labels(&mut self, theme: &Theme, rows: &[PaletteItem]) {
+        let screen_size = (self.size.width as f32, self.size.height as f32);
+        let hint_color = with_alpha(theme.ui_icon, 0.55);
+        let label = self.ui_renderer.command_palette_label(screen_size, None);
+        let placeholder = if self.palette.naming_preset {
+            "Name the style preset..."
+        } else {
+            "Type a command..."
+        };
+        let (query, query_color) = if self.palette.query.is_empty() {
+            (placeholder, hint_color)
+        } else {
+            (self.palette.query.as_str(), theme.ui_icon)
+        };
+        self.text_renderer.add_screen_label(
+            &self.gpu.queue,
+            query,
+            label.pos,
+            label.font_size,
+            query_color,
+        );
+
+        let selected = self.palette.selected - self.palette.scroll();
+        for (row, item) in rows.iter().enumerate() {
+            let label = self
+                .ui_renderer
+                .command_palette_label(screen_size, Some(row));
+            let (color, hint) = if row == selected {
+                (theme.text, with_alpha(theme.text, 0.75))
+            } else {
+                (theme.ui_icon, hint_color)
+            };
+            self.text_renderer.add_screen_label(
+                &self.gpu.queue,
+                &self.palette_item_title(*item),
+                label.pos,
+                label.font_size,
+                color,
+            );
+            if let PaletteItem::Command(command) = item
+                && let Some(shortcut) = self.keymap.shortcut(*command)
+            {
+                let width = self
+                    .text_renderer
+                    .measure_screen_label(&shortcut, label.font_size);
+                self.text_renderer.add_screen_label(
+                    &self.gpu.queue,
+                    &shortcut,
+                    [label.right - width, label.pos[1]],
+                    label.font_size,
+                    hint,
+                );
+            }
+        }
+    }
+
+    /// Field text, the hit count and button captions for the open find bar.
+    /// The focused field shows a caret.
+    fn add_find_bar_labels(&mut self, theme: &Theme, count: usize) {
+        let screen_size = (self.size.width as f32, self.size.height as f32);
+        let hint_color = with_alpha(theme.ui_icon, 0.55);
+        let fields = [
+            (
+                FindBarPart::Query,
+                &self.find.query,
+                "Find text...",
+                !self.find.replacing,
+            ),
+            (
+                FindBarPart::Replacement,
+                &self.find.replacement,
+                "Replace with...",
+                self.find.replacing,
+            ),
+        ];
+        for (part, text, placeholder, focused) in fields {
+            let label = self.ui_renderer.find_bar_label(screen_size, part);
+            let (text, color) = match (text.is_empty(), focused) {
+                (true, false) => (placeholder.to_string(), hint_color),
+                (_, true) => (format!("{}|", text), theme.ui_icon),
+                (false, false) => (text.clone(), theme.ui_icon),
+            };
+            self.text_renderer.add_screen_label(
+                &self.gpu.queue,
+                &text,
+                label.pos,
+                label.font_size,
+                color,
+            );
+        }
+
+        let status = match (self.find.query.is_empty(), self.find.pattern(), count) {
+            (true, ..) => String::new(),
+            (false, None, _) => "Invalid pattern".to_string(),
+            (false, Some(_), 0) => "No results".to_string(),
+            (false, Some(_), count) => format!("{} of {}", self.find.current + 1, count),
+        };
+        let label = self
+            .ui_renderer
+            .find_bar_label(screen_size, FindBarPart::Query);
+        let width = self
+            .text_renderer
+            .measure_screen_label(&status, label.font_size);
+        self.text_renderer.add_screen_label(
+            &self.gpu.queue,
+            &status,
+            [label.right - width, label.pos[1]],
+            label.font_size,
+            hint_color,
+        );
+
+        let buttons = [
+            (FindBarPart::MatchCase, "Aa", self.find.match_case),
+            (FindBarPart::Regex, ".*", self.find.regex),
+            (FindBarPart::Replace, "Replace", false),
+            (FindBarPart::ReplaceAll, "All", false),
+        ];
+        for (part, caption, on) in buttons {
+            let label = self.ui_renderer.find_bar_label(screen_size, part);
+            let width = self
+                .text_renderer
+                .measure_screen_label(caption, label.font_size);
+            self.text_renderer.add_screen_label(
+                &self.gpu.queue,
+                caption,
+                [(label.pos[0] + label.right - width) * 0.5, label.pos[1]],
+                label.font_size,
+                if on { theme.text } else { theme.ui_icon },
+            );
+        }
+    }
+
     fn update_buffers(&mut self) {
         let mut tess = PathTessellator::new();
         let mut sdf_batch = SdfBatch::new();
@@ -126,30 +413,91 @@ impl State {
                 id: ElementId(0),
                 group_id: None,
                 shape: preview.clone(),
+                opacity: 1.0,
+                effects: Effects::default(),
+                label: None,
             });
         }
 
+        let theme = &self.theme();
+        let mut layers = LayerRecorder::new();
+        let mut effects = EffectBatch::default();
+
+        if self.settings.grid.visible {
+            layers.begin(&mut tess, &mut sdf_batch);
+            self.tessellate_grid(theme, &mut tess);
+            layers.end(&tess, &sdf_batch);
+        }
+
         for element in all_elements.iter() {
-            Self::tessellate_element(&element.shape, &mut tess, &mut sdf_batch);
+            let mut shape = element.rendered_shape();
+            shape.map_colors(|color| theme.element_color(color));
+            let mut element_effects = element.rendered_effects();
+            element_effects.map_colors(|color| theme.element_color(color));
+            // Shadows and glows sit in their own layers beneath the element.
+            for effect in sdf_effects(&element_effects) {
+                layers.begin(&mut tess, &mut sdf_batch);
+                if !Self::tessellate_sdf_effect(&shape, effect, &mut sdf_batch) {
+                    effects.push(&shape, effect, &mut layers);
+                }
+                layers.end(&tess, &sdf_batch);
+            }
+            // Rough backgrounds get their own layer so the outline can draw over them.
+            if let Some(fill) = rough_background(&shape) {
+                layers.begin(&mut tess, &mut sdf_batch);
+                fill.tessellate(&mut tess);
+                layers.end(&tess, &sdf_batch);
+            }
+            layers.begin(&mut tess, &mut sdf_batch);
+            Self::tessellate_element(&shape, &mut tess, &mut sdf_batch);
+            layers.end(&tess, &sdf_batch);
         }
 
         if let Some(bounds) = selection_bounds(&self.elements, &self.input.selection.selected_ids) {
-            Self::tessellate_selection_highlight(bounds, &mut tess);
-            Self::tessellate_resize_handles(bounds, &mut tess);
+            layers.begin(&mut tess, &mut sdf_batch);
+            Self::tessellate_selection_highlight(bounds, theme, &mut tess);
+            layers.end(&tess, &sdf_batch);
+            Self::tessellate_resize_handles(bounds, theme, &mut tess, &mut sdf_batch, &mut layers);
+        }
+
+        let highlights = self.find_highlights();
+        if !highlights.is_empty() {
+            layers.begin(&mut tess, &mut sdf_batch);
+            Self::tessellate_find_highlights(&highlights, theme, &mut tess);
+            layers.end(&tess, &sdf_batch);
+        }
+
+        if let Some(overlay) = self.text_overlay() {
+            layers.begin(&mut tess, &mut sdf_batch);
+            self.tessellate_text_overlay(&overlay, theme, &mut tess);
+            layers.end(&tess, &sdf_batch);
         }
 
         if let (Some(start), Some(current)) = (
             self.input.selection.marquee_start,
             self.input.selection.marquee_current,
         ) {
-            Self::tessellate_marquee(start, current, &mut tess);
+            layers.begin(&mut tess, &mut sdf_batch);
+            Self::tessellate_marquee(start, current, theme, &mut tess);
+            layers.end(&tess, &sdf_batch);
+        }
+
+        if !self.input.guides.is_empty() {
+            layers.begin(&mut tess, &mut sdf_batch);
+            self.tessellate_guides(theme, &mut tess);
+            layers.end(&tess, &sdf_batch);
         }
 
         // Active pen stroke
         if self.input.state == crate::state::UserInputState::Drawing {
-            self.tessellate_active_drawing(&mut tess);
+            layers.begin(&mut tess, &mut sdf_batch);
+            self.tessellate_active_drawing(&mut tess, &mut sdf_batch);
+            layers.end(&tess, &sdf_batch);
         }
 
+        self.draw_runs = layers.finish();
+        effects.upload(self);
+
         // Upload tessellated geometry
         let (vertices, indices) = tess.finish();
         if !vertices.is_empty() {
@@ -175,8 +523,14 @@ impl State {
         }
 
         // Upload SDF geometry
-        let (sdf_vertices, sdf_indices) = sdf_batch.finish();
+        let (sdf_vertices, sdf_indices, sdf_paints) = sdf_batch.finish();
         if !sdf_vertices.is_empty() {
+            self.sdf_geo.write_paints(
+                &self.gpu.device,
+                &self.gpu.queue,
+                &self.gpu.sdf_paint_bind_group_layout,
+                &sdf_paints,
+            );
             self.sdf_geo.vertex = Some(self.gpu.device.create_buffer_init(
                 &wgpu::util::BufferInitDescriptor {
                     label: Some("SDF Vertex Buffer"),
@@ -223,14 +577,17 @@ impl State {
                 position,
                 size,
                 color,
-                fill,
+                background,
                 stroke_width,
                 rough_style,
+                roundness,
             } => {
+                let corner_radius = roundness.radius_for(*size);
                 if let Some(rough_options) = rough_style {
                     // Rough style: tessellate the rough path segments
                     let mut generator = crate::rough::RoughGenerator::new(rough_options.seed);
-                    let rough_lines = generator.rough_rectangle(*position, *size, rough_options);
+                    let rough_lines =
+                        generator.rough_rectangle(*position, *size, corner_radius, rough_options);
                     let style = StrokeStyle::new(*color, rough_options.stroke_width);
                     for line_points in rough_lines {
                         let path = Path::from_points(&line_points);
@@ -238,23 +595,33 @@ impl State {
                     }
                 } else {
                     // Clean shape: SDF vector rendering
-                    sdf_batch.add_rect(*position, *size, *color, *stroke_width, *fill);
+                    sdf_batch.add_rect(
+                        *position,
+                        *size,
+                        *color,
+                        *stroke_width,
+                        background.as_ref(),
+                        corner_radius,
+                    );
                 }
             }
 
             DrawingElement::Circle {
                 center,
-                radius,
+                radii,
                 color,
-                fill,
+                background,
                 stroke_width,
                 rough_style,
             } => {
                 if let Some(rough_options) = rough_style {
                     let mut generator = crate::rough::RoughGenerator::new(rough_options.seed);
-                    let diameter = *radius * 2.0;
-                    let rough_lines =
-                        generator.rough_ellipse(*center, diameter, diameter, rough_options);
+                    let rough_lines = generator.rough_ellipse(
+                        *center,
+                        radii[0] * 2.0,
+                        radii[1] * 2.0,
+                        rough_options,
+                    );
                     let style = StrokeStyle::new(*color, rough_options.stroke_width);
                     for line_points in rough_lines {
                         let path = Path::from_points(&line_points);
@@ -262,7 +629,13 @@ impl State {
                     }
                 } else {
                     // Clean shape: SDF vector rendering
-                    sdf_batch.add_circle(*center, *radius, *color, *stroke_width, *fill);
+                    sdf_batch.add_ellipse(
+                        *center,
+                        *radii,
+                        *color,
+                        *stroke_width,
+                        background.as_ref(),
+                    );
                 }
             }
 
@@ -270,13 +643,16 @@ impl State {
                 position,
                 size,
                 color,
-                fill,
+                background,
                 stroke_width,
                 rough_style,
+                roundness,
             } => {
+                let corner_radius = roundness.radius_for(*size);
                 if let Some(rough_options) = rough_style {
                     let mut generator = crate::rough::RoughGenerator::new(rough_options.seed);
-                    let rough_lines = generator.rough_diamond(*position, *size, rough_options);
+                    let rough_lines =
+                        generator.rough_diamond(*position, *size, corner_radius, rough_options);
                     let style = StrokeStyle::new(*color, rough_options.stroke_width);
                     for line_points in rough_lines {
                         let path = Path::from_points(&line_points);
@@ -284,7 +660,14 @@ impl State {
                     }
                 } else {
                     // Clean shape: SDF vector rendering
-                    sdf_batch.add_diamond(*position, *size, *color, *stroke_width, *fill);
+                    sdf_batch.add_diamond(
+                        *position,
+                        *size,
+                        *color,
+                        *stroke_width,
+                        background.as_ref(),
+                        corner_radius,
+                    );
                 }
             }
 
@@ -304,11 +687,14 @@ impl State {
                         tess.stroke(&path, &style);
                     }
                 } else {
-                    let style = StrokeStyle::new(*color, *width);
-                    let paths = Path::arrow(*start, *end, 20.0, 0.5);
-                    for path in &paths {
-                        tess.stroke(path, &style);
-                    }
+                    sdf_batch.add_arrow(
+                        *start,
+                        *end,
+                        ARROW_HEAD_LEN,
+                        ARROW_HEAD_ANGLE,
+                        *color,
+                        *width,
+                    );
                 }
             }
 
@@ -332,8 +718,7 @@ impl State {
                         tess.stroke(&path2, &style);
                     }
                 } else {
-                    let path = Path::line(*start, *end);
-                    tess.stroke(&path, &StrokeStyle::new(*color, *width));
+                    sdf_batch.add_segment(*start, *end, *color, *width);
                 }
             }
 
@@ -343,9 +728,204 @@ impl State {
         }
     }
 
+    /// Draw `effect` for a clean or rough Rectangle, Circle or Diamond, or a
+    /// clean Line or Arrow, straight from its SDF silhouette. Returns false for
+    /// shapes that need the offscreen blur instead.
+    fn tessellate_sdf_effect(
+        element: &DrawingElement,
+        effect: SdfEffect,
+        sdf_batch: &mut SdfBatch,
+    ) -> bool {
+        sdf_batch.set_effect(Some(effect));
+        let handled = match element {
+            DrawingElement::Rectangle {
+                position,
+                size,
+                color,
+                background,
+                stroke_width,
+                rough_style,
+                roundness,
+            } => {
+                let width = rough_style
+                    .as_ref()
+                    .map_or(*stroke_width, |o| o.stroke_width);
+                sdf_batch.add_rect(
+                    *position,
+                    *size,
+                    *color,
+                    width,
+                    background.as_ref(),
+                    roundness.radius_for(*size),
+                );
+                true
+            }
+            DrawingElement::Circle {
+                center,
+                radii,
+                color,
+                background,
+                stroke_width,
+                rough_style,
+            } => {
+                let width = rough_style
+                    .as_ref()
+                    .map_or(*stroke_width, |o| o.stroke_width);
+                sdf_batch.add_ellipse(*center, *radii, *color, width, background.as_ref());
+                true
+            }
+            DrawingElement::Diamond {
+                position,
+                size,
+                color,
+                background,
+                stroke_width,
+                rough_style,
+                roundness,
+            } => {
+                let width = rough_style
+                    .as_ref()
+                    .map_or(*stroke_width, |o| o.stroke_width);
+                sdf_batch.add_diamond(
+                    *position,
+                    *size,
+                    *color,
+                    width,
+                    background.as_ref(),
+                    roundness.radius_for(*size),
+                );
+                true
+            }
+            DrawingElement::Line {
+                start,
+                end,
+                color,
+                width,
+                rough_style: None,
+            } => {
+                sdf_batch.add_segment(*start, *end, *color, *width);
+                true
+            }
+            DrawingElement::Arrow {
+                start,
+                end,
+                color,
+                width,
+                rough_style: None,
+            } => {
+                sdf_batch.add_arrow(
+                    *start,
+                    *end,
+                    ARROW_HEAD_LEN,
+                    ARROW_HEAD_ANGLE,
+                    *color,
+                    *width,
+                );
+                true
+            }
+            _ => false,
+        };
+        sdf_batch.set_effect(None);
+        handled
+    }
+
+    /// Background grid over the visible canvas. Cells grow fivefold whenever
+    /// they would get too dense on screen, and every fifth line is stronger.
+    fn tessellate_grid(&self, theme: &Theme, tess: &mut PathTessellator) {
+        let grid = self.settings.grid;
+        let transform = &self.canvas.transform;
+        if grid.size <= 0.0 || transform.scale <= 0.0 {
+            return;
+        }
+        let (min_spacing, max_cells) = match grid.style {
+            GridStyle::Lines => (GRID_MIN_LINE_SPACING, f32::INFINITY),
+            GridStyle::Dots => (GRID_MIN_DOT_SPACING, GRID_MAX_DOTS),
+        };
+        let screen_area = self.size.width as f32 * self.size.height as f32;
+        let mut cell = grid.size;
+        while cell * transform.scale < min_spacing
+            || screen_area / (cell * transform.scale).powi(2) > max_cells
+        {
+            cell *= GRID_MAJOR_EVERY as f32;
+        }
+
+        let top_left = transform.screen_to_canvas([0.0, 0.0]);
+        let bottom_right =
+            transform.screen_to_canvas([self.size.width as f32, self.size.height as f32]);
+        let first = [
+            (top_left[0] / cell).floor() as i64,
+            (top_left[1] / cell).floor() as i64,
+        ];
+        let last = [
+            (bottom_right[0] / cell).ceil() as i64,
+            (bottom_right[1] / cell).ceil() as i64,
+        ];
+        let pixel = 1.0 / transform.scale;
+        let minor = theme.grid;
+        let major = with_alpha(theme.grid, (theme.grid[3] * 2.0).min(1.0));
+        let color_for = |index: i64| {
+            if index.rem_euclid(GRID_MAJOR_EVERY) == 0 {
+                major
+            } else {
+                minor
+            }
+        };
+
+        match grid.style {
+            GridStyle::Lines => {
+                for i in first[0]..=last[0] {
+                    let x = i as f32 * cell;
+                    tess.add_line_segment(
+                        [x, top_left[1]],
+                        [x, bottom_right[1]],
+                        color_for(i),
+                        pixel,
+                    );
+                }
+                for j in first[1]..=last[1] {
+                    let y = j as f32 * cell;
+                    tess.add_line_segment(
+                        [top_left[0], y],
+                        [bottom_right[0], y],
+                        color_for(j),
+                        pixel,
+                    );
+                }
+            }
+            GridStyle::Dots => {
+                for i in first[0]..=last[0] {
+                    for j in first[1]..=last[1] {
+                        let [x, y] = [i as f32 * cell, j as f32 * cell];
+                        let color = if i.rem_euclid(GRID_MAJOR_EVERY) == 0
+                            && j.rem_euclid(GRID_MAJOR_EVERY) == 0
+                        {
+                            major
+                        } else {
+                            minor
+                        };
+                        let half = pixel;
+                        tess.fill_convex(
+                            &[
+                                [x - half, y - half],
+                                [x + half, y - half],
+                                [x + half, y + half],
+                                [x - half, y + half],
+                            ],
+                            color,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
     /// Generate selection highlight geometry using the PathTessellator.
-    fn tessellate_selection_highlight(bounds: ([f32; 2], [f32; 2]), tess: &mut PathTessellator) {
-        let style = StrokeStyle::new([0.0, 0.5, 1.0, 0.8], 3.0);
+    fn tessellate_selection_highlight(
+        bounds: ([f32; 2], [f32; 2]),
+        theme: &Theme,
+        tess: &mut PathTessellator,
+    ) {
+        let style = StrokeStyle::new(with_alpha(theme.selection, 0.8), 3.0);
         let margin = 6.0;
         let min = [bounds.0[0] - margin, bounds.0[1] - margin];
         let max = [bounds.1[0] + margin, bounds.1[1] + margin];
@@ -353,13 +933,20 @@ impl State {
         tess.stroke(&path, &style);
     }
 
-    fn tessellate_resize_handles(bounds: ([f32; 2], [f32; 2]), tess: &mut PathTessellator) {
+    fn tessellate_resize_handles(
+        bounds: ([f32; 2], [f32; 2]),
+        theme: &Theme,
+        tess: &mut PathTessellator,
+        sdf_batch: &mut SdfBatch,
+        layers: &mut LayerRecorder,
+    ) {
         for handle_pos in handle_positions(bounds).values() {
             let size = 10.0;
             let path = Path::rect(
                 [handle_pos[0] - size * 0.5, handle_pos[1] - size * 0.5],
                 [size, size],
             );
+            layers.begin(tess, sdf_batch);
             tess.fill_convex(
                 &[
                     [handle_pos[0] - size * 0.5, handle_pos[1] - size * 0.5],
@@ -367,39 +954,116 @@ impl State {
                     [handle_pos[0] + size * 0.5, handle_pos[1] + size * 0.5],
                     [handle_pos[0] - size * 0.5, handle_pos[1] + size * 0.5],
                 ],
-                [1.0, 1.0, 1.0, 1.0],
+                theme.handle_fill,
             );
-            tess.stroke(&path, &StrokeStyle::new([0.0, 0.5, 1.0, 1.0], 1.5));
+            layers.end(tess, sdf_batch);
+            layers.begin(tess, sdf_batch);
+            tess.stroke(&path, &StrokeStyle::new(theme.selection, 1.5));
+            layers.end(tess, sdf_batch);
         }
     }
 
-    fn tessellate_marquee(start: [f32; 2], current: [f32; 2], tess: &mut PathTessellator) {
+    /// Selection highlight and blinking caret of the text being typed.
+    fn tessellate_text_overlay(
+        &self,
+        overlay: &TextOverlay,
+        theme: &Theme,
+        tess: &mut PathTessellator,
+    ) {
+        for [min, max] in &overlay.selection {
+            let corners = [*min, [max[0], min[1]], *max, [min[0], max[1]]];
+            tess.fill_convex(&corners, with_alpha(theme.selection, 0.3));
+        }
+        if self.typing.cursor_visible {
+            let [top, bottom] = overlay.caret;
+            let width = 1.5 / self.canvas.transform.scale;
+            tess.add_line_segment(top, bottom, theme.element_color(overlay.color), width);
+        }
+    }
+
+    /// Every hit tinted in the guide color, the one being shown more strongly.
+    fn tessellate_find_highlights(
+        highlights: &[FindHighlight],
+        theme: &Theme,
+        tess: &mut PathTessellator,
+    ) {
+        for highlight in highlights {
+            let alpha = if highlight.current { 0.55 } else { 0.2 };
+            for [min, max] in highlight.rects {
+                let corners = [*min, [max[0], min[1]], *max, [min[0], max[1]]];
+                tess.fill_convex(&corners, with_alpha(theme.guide, alpha));
+            }
+        }
+    }
+
+    fn tessellate_marquee(
+        start: [f32; 2],
+        current: [f32; 2],
+        theme: &Theme,
+        tess: &mut PathTessellator,
+    ) {
         let position = [start[0].min(current[0]), start[1].min(current[1])];
         let size = [(current[0] - start[0]).abs(), (current[1] - start[1]).abs()];
         let path = Path::rect(position, size);
-        tess.stroke(&path, &StrokeStyle::new([0.0, 0.5, 1.0, 0.7], 1.5));
+        tess.stroke(
+            &path,
+            &StrokeStyle::new(with_alpha(theme.selection, 0.7), 1.5),
+        );
+    }
+
+    /// Smart guides as hairlines; spacing guides get end ticks.
+    fn tessellate_guides(&self, theme: &Theme, tess: &mut PathTessellator) {
+        let pixel = 1.0 / self.canvas.transform.scale;
+        for guide in &self.input.guides {
+            match *guide {
+                SnapGuide::Align { start, end } => {
+                    tess.add_line_segment(start, end, theme.guide, pixel);
+                }
+                SnapGuide::Spacing { start, end, .. } => {
+                    tess.add_line_segment(start, end, theme.guide, pixel);
+                    let direction = [end[0] - start[0], end[1] - start[1]];
+                    let length = direction[0].hypot(direction[1]);
+                    if length <= 0.0 {
+                        continue;
+                    }
+                    let tick = [
+                        -direction[1] / length * GUIDE_TICK * pixel,
+                        direction[0] / length * GUIDE_TICK * pixel,
+                    ];
+                    for p in [start, end] {
+                        tess.add_line_segment(
+                            [p[0] - tick[0], p[1] - tick[1]],
+                            [p[0] + tick[0], p[1] + tick[1]],
+                            theme.guide,
+                            pixel,
+                        );
+                    }
+                }
+            }
+        }
     }
 
     /// Tessellate the in-progress drawing (active pen stroke or arrow preview).
-    fn tessellate_active_drawing(&self, tess: &mut PathTessellator) {
+    fn tessellate_active_drawing(&self, tess: &mut PathTessellator, sdf_batch: &mut SdfBatch) {
+        let color = self.theme().element_color(self.style.stroke);
         match self.current_tool {
             crate::drawing::Tool::Pen => {
                 if self.input.current_stroke.len() > 1 {
                     let path = Path::from_points(&self.input.current_stroke);
-                    tess.stroke(
-                        &path,
-                        &StrokeStyle::new(self.current_color, self.stroke_width),
-                    );
+                    tess.stroke(&path, &StrokeStyle::new(color, self.style.stroke_width));
                 }
             }
             crate::drawing::Tool::Arrow => {
                 if let Some(start) = self.input.drag_start {
                     let end = self.canvas.transform.screen_to_canvas(self.input.mouse_pos);
-                    let style = StrokeStyle::new(self.current_color, self.stroke_width);
-                    let paths = Path::arrow(start, end, 20.0, 0.5);
-                    for path in &paths {
-                        tess.stroke(path, &style);
-                    }
+                    sdf_batch.add_arrow(
+                        start,
+                        end,
+                        ARROW_HEAD_LEN,
+                        ARROW_HEAD_ANGLE,
+                        color,
+                        self.style.stroke_width,
+                    );
                 }
             }
             _ => {}
@@ -407,6 +1071,302 @@ impl State {
     }
 }
 
+/// Smallest on-screen grid spacing, in pixels, before cells are enlarged.
+/// Dots get more room since each one costs a quad.
+const GRID_MIN_LINE_SPACING: f32 = 12.0;
+const GRID_MIN_DOT_SPACING: f32 = 24.0;
+/// Keeps dot grids on large, high-DPI surfaces within the 16-bit index range.
+const GRID_MAX_DOTS: f32 = 4096.0;
+const GRID_MAJOR_EVERY: i64 = 5;
+
+/// Half-length of the ticks closing a spacing guide, in screen pixels.
+const GUIDE_TICK: f32 = 4.0;
+const GUIDE_LABEL_SIZE: f32 = 12.0;
+
+/// Arrowhead barb length and angle off the shaft, in canvas units and radians.
+const ARROW_HEAD_LEN: f32 = 20.0;
+const ARROW_HEAD_ANGLE: f32 = 0.5;
+
+/// Assigns each drawn layer (an element or an overlay piece) its own depth and
+/// records the index ranges it produced as ordered draw runs.
+struct LayerRecorder {
+    layer: u32,
+    runs: Vec<DrawRun>,
+    geometry_start: u32,
+    sdf_start: u32,
+}
+
+impl LayerRecorder {
+    /// Depth step between consecutive layers; exactly representable in a
+    /// `Depth32Float` buffer for the first 2^20 layers.
+    const DEPTH_STEP: f32 = 1.0 / (1 << 20) as f32;
+
+    fn new() -> Self {
+        Self {
+            layer: 0,
+            runs: Vec::new(),
+            geometry_start: 0,
+            sdf_start: 0,
+        }
+    }
+
+    fn begin(&mut self, tess: &mut PathTessellator, sdf_batch: &mut SdfBatch) {
+        self.layer += 1;
+        tess.set_depth(self.depth());
+        sdf_batch.set_depth(self.depth());
+    }
+
+    /// Depth of the current layer.
+    fn depth(&self) -> f32 {
+        (self.layer as f32 * Self::DEPTH_STEP).min(1.0)
+    }
+
+    fn end(&mut self, tess: &PathTessellator, sdf_batch: &SdfBatch) {
+        let geometry_end = tess.counts().1 as u32;
+        let sdf_end = sdf_batch.index_count() as u32;
+        self.push(RunPipeline::Geometry, self.geometry_start..geometry_end);
+        self.push(RunPipeline::Sdf, self.sdf_start..sdf_end);
+        self.geometry_start = geometry_end;
+        self.sdf_start = sdf_end;
+    }
+
+    fn push(&mut self, pipeline: RunPipeline, indices: std::ops::Range<u32>) {
+        if indices.is_empty() {
+            return;
+        }
+        if let Some(last) = self.runs.last_mut()
+            && last.pipeline == pipeline
+            && last.indices.end == indices.start
+        {
+            last.indices.end = indices.end;
+            return;
+        }
+        self.runs.push(DrawRun { pipeline, indices });
+    }
+
+    fn finish(self) -> Vec<DrawRun> {
+        self.runs
+    }
+}
+
+fn with_alpha(color: [f32; 4], alpha: f32) -> [f32; 4] {
+    [color[0], color[1], color[2], alpha]
+}
+
+/// Shadow first so a glow on the same element draws over it.
+fn sdf_effects(effects: &Effects) -> Vec<SdfEffect> {
+    let shadow = effects.shadow.map(|shadow| SdfEffect::Shadow {
+        offset: shadow.offset,
+        blur: shadow.blur,
+        color: shadow.color,
+    });
+    let glow = effects.glow.map(|glow| SdfEffect::Glow {
+        radius: glow.radius,
+        color: glow.color,
+    });
+    shadow.into_iter().chain(glow).collect()
+}
+
+/// Offscreen shadow and glow jobs for shapes the SDF pipeline can't blur:
+/// strokes and rough lines and arrows are tessellated into a mask, text is
+/// laid out by the text renderer.
+#[derive(Default)]
+struct EffectBatch {
+    jobs: Vec<EffectJob>,
+    sources: Vec<(DrawingElement, [f32; 4])>,
+    mask: Option<PathTessellator>,
+}
+
+impl EffectBatch {
+    /// Record `effect` for `element` at the current layer.
+    fn push(&mut self, element: &DrawingElement, effect: SdfEffect, layers: &mut LayerRecorder) {
+        let (offset, sigma, color) = match effect {
+            SdfEffect::Shadow {
+                offset,
+                blur,
+                color,
+            } => (offset, blur * 0.5, color),
+            SdfEffect::Glow { radius, color } => ([0.0, 0.0], radius * 0.5, color),
+        };
+        let opaque = [color[0], color[1], color[2], 1.0];
+        let mut silhouette = element.clone();
+        silhouette.map_colors(|_| opaque);
+
+        let mask = self.mask.get_or_insert_with(PathTessellator::new);
+        let start = mask.counts().1 as u32;
+        // Only non-SDF shapes reach here, so the scratch SDF batch stays empty.
+        State::tessellate_element(&silhouette, mask, &mut SdfBatch::new());
+        let end = mask.counts().1 as u32;
+
+        let (mut min, mut max) = element.bounding_box();
+        let margin = sigma * 3.0 + 2.0;
+        min = [min[0] - margin, min[1] - margin];
+        max = [max[0] + margin, max[1] + margin];
+
+        let index = self.jobs.len();
+        self.jobs.push(EffectJob {
+            bounds: (min, max),
+            offset,
+            sigma,
+            alpha: color[3],
+            depth: layers.depth(),
+            mask: start..end,
+            text: 0..0,
+        });
+        self.sources.push((element.clone(), opaque));
+        layers.push(
+            RunPipeline::Effect(index),
+            EffectJob::composite_indices(index),
+        );
+    }
+
+    fn upload(self, state: &mut State) {
+        let sources: Vec<_> = self
+            .sources
+            .iter()
+            .map(|(shape, color)| (shape, *color))
+            .collect();
+        let text_ranges =
+            state
+                .text_renderer
+                .prepare_effects(&state.gpu.device, &state.gpu.queue, &sources);
+        let jobs = self
+            .jobs
+            .into_iter()
+            .zip(text_ranges)
+            .map(|(job, text)| EffectJob { text, ..job })
+            .collect();
+        let mask = self.mask.map(PathTessellator::finish).unwrap_or_default();
+        state.effect_renderer.prepare(
+            &state.gpu.device,
+            &state.gpu.queue,
+            jobs,
+            mask,
+            state.canvas.transform.scale,
+        );
+    }
+}
+
+/// Interior of a rough shape with a background, ready for tessellation.
+struct RoughFill<'a> {
+    outline: Vec<[f32; 2]>,
+    /// Center and half-size of the shape's bounds, for gradient evaluation.
+    center: [f32; 2],
+    half: [f32; 2],
+    paint: &'a FillStyle,
+}
+
+impl RoughFill<'_> {
+    fn tessellate(&self, tess: &mut PathTessellator) {
+        match self.paint {
+            FillStyle::Solid(color) => tess.fill_convex(&self.outline, *color),
+            gradient => tess.fill_convex_shaded(&self.outline, |p| {
+                gradient.color_at([p[0] - self.center[0], p[1] - self.center[1]], self.half)
+            }),
+        }
+    }
+}
+
+/// Outline polygon and paint for the interior of a rough shape with a background.
+///
+/// Clean shapes fill inside the SDF shader, so only rough shapes need this.
+fn rough_background(element: &DrawingElement) -> Option<RoughFill<'_>> {
+    let paint = element.background()?;
+    let (min, max) = match element {
+        DrawingElement::Rectangle { position, size, .. }
+        | DrawingElement::Diamond { position, size, .. } => {
+            (*position, [position[0] + size[0], position[1] + size[1]])
+        }
+        DrawingElement::Circle { center, radii, .. } => (
+            [center[0] - radii[0], center[1] - radii[1]],
+            [center[0] + radii[0], center[1] + radii[1]],
+        ),
+        _ => return None,
+    };
+    let outline = match element {
+        DrawingElement::Rectangle {
+            position,
+            size,
+            roundness,
+            rough_style: Some(_),
+            ..
+        } => {
+            let corners = [
+                *position,
+                [position[0] + size[0], position[1]],
+                [position[0] + size[0], position[1] + size[1]],
+                [position[0], position[1] + size[1]],
+            ];
+            rounded_outline(&corners, roundness.radius_for(*size))
+        }
+        DrawingElement::Diamond {
+            position,
+            size,
+            roundness,
+            rough_style: Some(_),
+            ..
+        } => {
+            let center = [position[0] + size[0] / 2.0, position[1] + size[1] / 2.0];
+            let corners = [
+                [center[0], position[1]],
+                [position[0] + size[0], center[1]],
+                [center[0], position[1] + size[1]],
+                [position[0], center[1]],
+            ];
+            rounded_outline(&corners, roundness.radius_for(*size))
+        }
+        DrawingElement::Circle {
+            center,
+            radii,
+            rough_style: Some(_),
+            ..
+        } => {
+            const SEGMENTS: usize = 48;
+            (0..SEGMENTS)
+                .map(|i| {
+                    let angle = i as f32 * std::f32::consts::TAU / SEGMENTS as f32;
+                    [
+                        center[0] + angle.cos() * radii[0],
+                        center[1] + angle.sin() * radii[1],
+                    ]
+                })
+                .collect()
+        }
+        _ => return None,
+    };
+    Some(RoughFill {
+        outline,
+        center: [(min[0] + max[0]) * 0.5, (min[1] + max[1]) * 0.5],
+        half: [(max[0] - min[0]) * 0.5, (max[1] - min[1]) * 0.5],
+        paint,
+    })
+}
+
+/// Convex polygon outline with each corner replaced by a sampled arc.
+fn rounded_outline(corners: &[[f32; 2]], radius: f32) -> Vec<[f32; 2]> {
+    if radius <= 0.0 {
+        return corners.to_vec();
+    }
+    const ARC_STEPS: usize = 6;
+    let n = corners.len();
+    let mut outline = Vec::with_capacity(n * (ARC_STEPS + 1));
+    for i in 0..n {
+        let prev = corners[(i + n - 1) % n];
+        let corner = corners[i];
+        let next = corners[(i + 1) % n];
+        let (start, end) = crate::rough::corner_tangents(prev, corner, next, radius);
+        for step in 0..=ARC_STEPS {
+            let t = step as f32 / ARC_STEPS as f32;
+            let u = 1.0 - t;
+            outline.push([
+                u * u * start[0] + 2.0 * u * t * corner[0] + t * t * end[0],
+                u * u * start[1] + 2.0 * u * t * corner[1] + t * t * end[1],
+            ]);
+        }
+    }
+    outline
+}
+
 fn selection_bounds(elements: &[Element], ids: &[ElementId]) -> Option<([f32; 2], [f32; 2])> {
     let mut iter = elements.iter().filter(|element| ids.contains(&element.id));
     let first = iter.next()?;
@@ -1,9 +1,42 @@
+use crate::vector::style::FillStyle;
 use crate::vertex::SdfVertex;
 
 /// Shape types for SDF rendering. Must match the shader constants.
 pub const SHAPE_RECT: f32 = 0.0;
-pub const SHAPE_CIRCLE: f32 = 1.0;
+pub const SHAPE_ELLIPSE: f32 = 1.0;
 pub const SHAPE_DIAMOND: f32 = 2.0;
+pub const SHAPE_SEGMENT: f32 = 3.0;
+pub const SHAPE_ARROWHEAD: f32 = 4.0;
+
+/// Local x axis for shapes that aren't rotated.
+const AXIS_X: [f32; 2] = [1.0, 0.0];
+
+/// Texels per entry of the paint table and entries per texture row. Must
+/// match the shader.
+pub const PAINT_TEXELS: usize = 6;
+pub const PAINTS_PER_ROW: usize = 256;
+
+/// One paint table entry: `[gradient, stop_offsets, stop_colors...]`.
+pub type Paint = [[f32; 4]; PAINT_TEXELS];
+
+/// Fill modes carried in `fill_params.x`. Must match the shader.
+const MODE_OUTLINE: f32 = 0.0;
+const MODE_FILLED: f32 = 1.0;
+const MODE_SHADOW: f32 = 2.0;
+const MODE_GLOW: f32 = 3.0;
+
+/// A blurred copy of a shape's silhouette drawn in place of the shape.
+#[derive(Debug, Clone, Copy)]
+pub enum SdfEffect {
+    /// Silhouette moved by `offset` and blurred by `blur` canvas units.
+    Shadow {
+        offset: [f32; 2],
+        blur: f32,
+        color: [f32; 4],
+    },
+    /// Halo fading out `radius` canvas units from the outline.
+    Glow { radius: f32, color: [f32; 4] },
+}
 
 /// Generates SDF quad geometry for resolution-independent shape rendering.
 ///
@@ -11,10 +44,48 @@ pub const SHAPE_DIAMOND: f32 = 2.0;
 /// simple quad (bounding box). The fragment shader evaluates a signed distance
 /// function to determine per-pixel coverage, producing perfectly smooth edges
 /// at any zoom level.
+///
+/// Gradients and stop colors don't fit the inter-stage limits of WebGL2 as
+/// per-vertex data, so each quad's paint goes into a table the shader looks
+/// up by index.
 pub struct SdfBatch {
     vertices: Vec<SdfVertex>,
     indices: Vec<u16>,
+    paints: Vec<Paint>,
     index_offset: u16,
+    depth: f32,
+    effect: Option<SdfEffect>,
+}
+
+/// Per-shape interior paint: the vertex's `fill_params` and its paint table entry.
+struct FillPaint {
+    /// `[mode, corner_radius, depth, gradient_kind]`; depth is filled in by
+    /// `add_shape_quad`. Effects reuse the last slot as "source was filled".
+    params: [f32; 4],
+    gradient: [f32; 4],
+    stop_offsets: [f32; 4],
+    stop_colors: [[f32; 4]; 4],
+}
+
+impl FillPaint {
+    fn new(background: Option<&FillStyle>, half: [f32; 2], corner_radius: f32) -> Self {
+        let Some(fill) = background else {
+            return Self {
+                params: [MODE_OUTLINE, corner_radius, 0.0, 0.0],
+                gradient: [0.0; 4],
+                stop_offsets: [0.0; 4],
+                stop_colors: [[0.0; 4]; 4],
+            };
+        };
+        let (kind, gradient) = fill.shader_params(half);
+        let (stop_offsets, stop_colors) = fill.packed_stops();
+        Self {
+            params: [MODE_FILLED, corner_radius, 0.0, kind],
+            gradient,
+            stop_offsets,
+            stop_colors,
+        }
+    }
 }
 
 impl SdfBatch {
@@ -22,21 +93,37 @@ impl SdfBatch {
         Self {
             vertices: Vec::new(),
             indices: Vec::new(),
+            paints: Vec::new(),
             index_offset: 0,
+            depth: 0.0,
+            effect: None,
         }
     }
 
+    /// Set the depth for subsequently added shapes (see `PathTessellator::set_depth`).
+    pub fn set_depth(&mut self, depth: f32) {
+        self.depth = depth;
+    }
+
+    /// Draw subsequently added shapes as `effect` instead of as themselves;
+    /// `None` restores normal drawing.
+    pub fn set_effect(&mut self, effect: Option<SdfEffect>) {
+        self.effect = effect;
+    }
+
     /// Add a rectangle shape.
     ///
     /// `position`: top-left corner in canvas space
     /// `size`: width and height in canvas space
+    /// `corner_radius`: corner rounding in canvas space (0 for sharp corners)
     pub fn add_rect(
         &mut self,
         position: [f32; 2],
         size: [f32; 2],
         color: [f32; 4],
         stroke_width: f32,
-        filled: bool,
+        background: Option<&FillStyle>,
+        corner_radius: f32,
     ) {
         let center = [position[0] + size[0] / 2.0, position[1] + size[1] / 2.0];
         let half_w = size[0] / 2.0;
@@ -46,39 +133,34 @@ impl SdfBatch {
         self.add_shape_quad(
             center,
             [half_w + padding, half_h + padding],
+            AXIS_X,
             color,
-            SHAPE_RECT,
-            half_w,
-            half_h,
-            stroke_width,
-            filled,
+            [SHAPE_RECT, half_w, half_h, stroke_width],
+            FillPaint::new(background, [half_w, half_h], corner_radius),
         );
     }
 
-    /// Add a circle shape.
+    /// Add an ellipse shape (a circle when both radii are equal).
     ///
     /// `center`: center position in canvas space
-    /// `radius`: radius in canvas space
-    pub fn add_circle(
+    /// `radii`: horizontal and vertical radius in canvas space
+    pub fn add_ellipse(
         &mut self,
         center: [f32; 2],
-        radius: f32,
+        radii: [f32; 2],
         color: [f32; 4],
         stroke_width: f32,
-        filled: bool,
+        background: Option<&FillStyle>,
     ) {
         let padding = stroke_width + 2.0;
-        let extent = radius + padding;
 
         self.add_shape_quad(
             center,
-            [extent, extent],
+            [radii[0] + padding, radii[1] + padding],
+            AXIS_X,
             color,
-            SHAPE_CIRCLE,
-            radius,
-            radius,
-            stroke_width,
-            filled,
+            [SHAPE_ELLIPSE, radii[0], radii[1], stroke_width],
+            FillPaint::new(background, radii, 0.0),
         );
     }
 
@@ -86,13 +168,15 @@ impl SdfBatch {
     ///
     /// `position`: top-left of bounding box in canvas space
     /// `size`: width and height of bounding box
+    /// `corner_radius`: corner rounding in canvas space (0 for sharp corners)
     pub fn add_diamond(
         &mut self,
         position: [f32; 2],
         size: [f32; 2],
         color: [f32; 4],
         stroke_width: f32,
-        filled: bool,
+        background: Option<&FillStyle>,
+        corner_radius: f32,
     ) {
         let center = [position[0] + size[0] / 2.0, position[1] + size[1] / 2.0];
         let half_w = size[0] / 2.0;
@@ -102,61 +186,134 @@ impl SdfBatch {
         self.add_shape_quad(
             center,
             [half_w + padding, half_h + padding],
+            AXIS_X,
+            color,
+            [SHAPE_DIAMOND, half_w, half_h, stroke_width],
+            FillPaint::new(background, [half_w, half_h], corner_radius),
+        );
+    }
+
+    /// Add a straight line with round caps.
+    pub fn add_segment(&mut self, start: [f32; 2], end: [f32; 2], color: [f32; 4], width: f32) {
+        let (axis, len) = direction(start, end);
+        let half_len = len / 2.0;
+        let padding = width / 2.0 + 2.0;
+
+        self.add_shape_quad(
+            [(start[0] + end[0]) / 2.0, (start[1] + end[1]) / 2.0],
+            [half_len + padding, padding],
+            axis,
+            color,
+            [SHAPE_SEGMENT, half_len, 0.0, width],
+            FillPaint::new(None, [half_len, 0.0], 0.0),
+        );
+    }
+
+    /// Add the two barbs of an arrowhead whose tip is at `tip`, pointing along
+    /// `direction`. Each barb is `head_len` long and `head_angle` radians off the shaft.
+    pub fn add_arrowhead(
+        &mut self,
+        tip: [f32; 2],
+        direction: [f32; 2],
+        head_len: f32,
+        head_angle: f32,
+        color: [f32; 4],
+        width: f32,
+    ) {
+        let (axis, _) = self::direction([0.0, 0.0], direction);
+        // The barbs span a box with the tip at the middle of its right edge.
+        let half_w = head_len * head_angle.cos() / 2.0;
+        let half_h = head_len * head_angle.sin();
+        let padding = width / 2.0 + 2.0;
+
+        self.add_shape_quad(
+            [tip[0] - axis[0] * half_w, tip[1] - axis[1] * half_w],
+            [half_w + padding, half_h + padding],
+            axis,
             color,
-            SHAPE_DIAMOND,
-            half_w,
-            half_h,
-            stroke_width,
-            filled,
+            [SHAPE_ARROWHEAD, half_w, half_h, width],
+            FillPaint::new(None, [half_w, half_h], 0.0),
         );
     }
 
+    /// Add an arrow: a shaft from `start` to `end` plus an arrowhead at `end`.
+    pub fn add_arrow(
+        &mut self,
+        start: [f32; 2],
+        end: [f32; 2],
+        head_len: f32,
+        head_angle: f32,
+        color: [f32; 4],
+        width: f32,
+    ) {
+        self.add_segment(start, end, color, width);
+        if start != end {
+            let direction = [end[0] - start[0], end[1] - start[1]];
+            self.add_arrowhead(end, direction, head_len, head_angle, color, width);
+        }
+    }
+
     /// Core method: add a quad for SDF rendering.
     ///
-    /// The quad covers the bounding box of the shape plus padding.
-    /// `local_pos` at each vertex encodes the offset from the shape center,
-    /// which the fragment shader uses for SDF evaluation.
+    /// The quad covers the bounding box of the shape plus padding, rotated so
+    /// its local x axis points along `axis` (a unit vector).
+    /// `local_pos` at each vertex encodes the offset from the shape center in
+    /// that frame, which the fragment shader uses for SDF evaluation.
     fn add_shape_quad(
         &mut self,
         center: [f32; 2],
         extent: [f32; 2], // half-size of the quad (including padding)
+        axis: [f32; 2],
         color: [f32; 4],
-        shape_type: f32,
-        half_w: f32,
-        half_h: f32,
-        stroke_width: f32,
-        filled: bool,
+        shape_params: [f32; 4], // [shape_type, half_w, half_h, stroke_width]
+        mut fill: FillPaint,
     ) {
-        let fill_flag = if filled { 1.0 } else { 0.0 };
-        let shape_params = [shape_type, half_w, half_h, stroke_width];
-        let fill_params = [fill_flag, 0.0, 0.0, 0.0];
+        let mut center = center;
+        let mut extent = extent;
+        if let Some(effect) = self.effect {
+            let (mode, offset, reach, color) = match effect {
+                SdfEffect::Shadow {
+                    offset,
+                    blur,
+                    color,
+                } => (MODE_SHADOW, offset, blur, color),
+                SdfEffect::Glow { radius, color } => (MODE_GLOW, [0.0, 0.0], radius, color),
+            };
+            let was_filled = if fill.params[0] == MODE_FILLED {
+                1.0
+            } else {
+                0.0
+            };
+            center = [center[0] + offset[0], center[1] + offset[1]];
+            extent = [extent[0] + reach, extent[1] + reach];
+            fill.params = [mode, fill.params[1], 0.0, was_filled];
+            fill.gradient = [reach, 0.0, 0.0, 0.0];
+            fill.stop_colors = [color; 4];
+        }
+        fill.params[2] = self.depth;
+        let paint = self.paints.len() as u32;
+        let [c0, c1, c2, c3] = fill.stop_colors;
+        self.paints
+            .push([fill.gradient, fill.stop_offsets, c0, c1, c2, c3]);
 
         let corners = [
-            (
-                [-extent[0], -extent[1]],
-                [center[0] - extent[0], center[1] - extent[1]],
-            ),
-            (
-                [extent[0], -extent[1]],
-                [center[0] + extent[0], center[1] - extent[1]],
-            ),
-            (
-                [extent[0], extent[1]],
-                [center[0] + extent[0], center[1] + extent[1]],
-            ),
-            (
-                [-extent[0], extent[1]],
-                [center[0] - extent[0], center[1] + extent[1]],
-            ),
+            [-extent[0], -extent[1]],
+            [extent[0], -extent[1]],
+            [extent[0], extent[1]],
+            [-extent[0], extent[1]],
         ];
 
-        for &(local, pos) in &corners {
+        for local in corners {
             self.vertices.push(SdfVertex {
-                position: pos,
+                position: [
+                    center[0] + axis[0] * local[0] - axis[1] * local[1],
+                    center[1] + axis[1] * local[0] + axis[0] * local[1],
+                ],
                 local_pos: local,
                 color,
                 shape_params,
-                fill_params,
+                fill_params: fill.params,
+                paint,
             });
         }
 
@@ -171,12 +328,34 @@ impl SdfBatch {
         self.index_offset += 4;
     }
 
+    /// Number of indices emitted so far.
+    pub fn index_count(&self) -> usize {
+        self.indices.len()
+    }
+
     pub fn is_empty(&self) -> bool {
         self.vertices.is_empty()
     }
 
-    /// Consume the batch and return the accumulated geometry.
-    pub fn finish(self) -> (Vec<SdfVertex>, Vec<u16>) {
-        (self.vertices, self.indices)
+    /// Consume the batch and return the accumulated geometry and paint
+    /// table, padded to whole table rows.
+    pub fn finish(mut self) -> (Vec<SdfVertex>, Vec<u16>, Vec<Paint>) {
+        let rows = self.paints.len().div_ceil(PAINTS_PER_ROW).max(1);
+        self.paints
+            .resize(rows * PAINTS_PER_ROW, [[0.0; 4]; PAINT_TEXELS]);
+        (self.vertices, self.indices, self.paints)
+    }
+}
+
+/// Unit vector from `from` to `to` and the distance between them; points
+/// along x when they coincide.
+fn direction(from: [f32; 2], to: [f32; 2]) -> ([f32; 2], f32) {
+    let dx = to[0] - from[0];
+    let dy = to[1] - from[1];
+    let len = (dx * dx + dy * dy).sqrt();
+    if len > f32::EPSILON {
+        ([dx / len, dy / len], len)
+    } else {
+        (AXIS_X, 0.0)
     }
 }
@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 /// Visual style for vector path strokes.
 #[derive(Debug, Clone, Copy)]
 pub struct StrokeStyle {
@@ -11,14 +13,182 @@ impl StrokeStyle {
     }
 }
 
-/// Visual style for vector shape fills.
-#[derive(Debug, Clone, Copy)]
-pub struct FillStyle {
+/// Number of gradient stops the renderers evaluate; later stops are ignored.
+pub const MAX_GRADIENT_STOPS: usize = 4;
+
+/// A color at a position (0..=1) along a gradient.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GradientStop {
+    pub offset: f32,
     pub color: [f32; 4],
 }
 
+/// Visual style for vector shape fills.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FillStyle {
+    Solid([f32; 4]),
+    /// Runs across the shape's bounds along `angle` (radians, 0 is left to right).
+    LinearGradient {
+        angle: f32,
+        stops: Vec<GradientStop>,
+    },
+    /// `center` and `radius` are fractions of the shape's bounds, so the
+    /// gradient stretches with the shape like SVG's `objectBoundingBox` units.
+    RadialGradient {
+        center: [f32; 2],
+        radius: f32,
+        stops: Vec<GradientStop>,
+    },
+}
+
 impl FillStyle {
-    pub fn new(color: [f32; 4]) -> Self {
-        Self { color }
+    /// Two-stop linear gradient from `from` to `to`.
+    pub fn linear(angle: f32, from: [f32; 4], to: [f32; 4]) -> Self {
+        FillStyle::LinearGradient {
+            angle,
+            stops: two_stops(from, to),
+        }
+    }
+
+    /// Two-stop radial gradient centered in the bounds, reaching the edges.
+    pub fn radial(from: [f32; 4], to: [f32; 4]) -> Self {
+        FillStyle::RadialGradient {
+            center: [0.5, 0.5],
+            radius: 0.5,
+            stops: two_stops(from, to),
+        }
+    }
+
+    /// The solid color, or the first stop of a gradient.
+    pub fn base_color(&self) -> [f32; 4] {
+        match self {
+            FillStyle::Solid(color) => *color,
+            FillStyle::LinearGradient { stops, .. } | FillStyle::RadialGradient { stops, .. } => {
+                stops.first().map_or([0.0; 4], |stop| stop.color)
+            }
+        }
+    }
+
+    pub fn map_colors(&mut self, f: impl Fn([f32; 4]) -> [f32; 4]) {
+        match self {
+            FillStyle::Solid(color) => *color = f(*color),
+            FillStyle::LinearGradient { stops, .. } | FillStyle::RadialGradient { stops, .. } => {
+                for stop in stops {
+                    stop.color = f(stop.color);
+                }
+            }
+        }
+    }
+
+    /// Gradient position for a point given as an offset from the center of
+    /// bounds with half-size `half`. Solid fills always return 0.
+    pub fn gradient_t(&self, local: [f32; 2], half: [f32; 2]) -> f32 {
+        match self {
+            FillStyle::Solid(_) => 0.0,
+            FillStyle::LinearGradient { angle, .. } => {
+                let [x, y] = linear_axis(*angle, half);
+                local[0] * x + local[1] * y + 0.5
+            }
+            FillStyle::RadialGradient { center, radius, .. } => {
+                let [cx, cy, inv_rx, inv_ry] = radial_frame(*center, *radius, half);
+                let dx = (local[0] - cx) * inv_rx;
+                let dy = (local[1] - cy) * inv_ry;
+                (dx * dx + dy * dy).sqrt()
+            }
+        }
+    }
+
+    /// Color at a point, using the same stop evaluation as the SDF shader.
+    pub fn color_at(&self, local: [f32; 2], half: [f32; 2]) -> [f32; 4] {
+        if let FillStyle::Solid(color) = self {
+            return *color;
+        }
+        let (offsets, colors) = self.packed_stops();
+        let t = self.gradient_t(local, half).clamp(0.0, 1.0);
+        let mut color = colors[0];
+        for i in 1..MAX_GRADIENT_STOPS {
+            let span = (offsets[i] - offsets[i - 1]).max(1e-5);
+            let k = ((t - offsets[i - 1]) / span).clamp(0.0, 1.0);
+            for (channel, target) in color.iter_mut().zip(colors[i]) {
+                *channel += (target - *channel) * k;
+            }
+        }
+        color
+    }
+
+    /// Stops sorted and padded to exactly `MAX_GRADIENT_STOPS` by repeating
+    /// the last one. A solid fill packs as a single repeated stop.
+    pub fn packed_stops(&self) -> ([f32; MAX_GRADIENT_STOPS], [[f32; 4]; MAX_GRADIENT_STOPS]) {
+        let mut stops: Vec<GradientStop> = match self {
+            FillStyle::Solid(color) => vec![GradientStop {
+                offset: 0.0,
+                color: *color,
+            }],
+            FillStyle::LinearGradient { stops, .. } | FillStyle::RadialGradient { stops, .. } => {
+                stops.iter().take(MAX_GRADIENT_STOPS).copied().collect()
+            }
+        };
+        stops.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+        let last = stops.last().copied().unwrap_or(GradientStop {
+            offset: 0.0,
+            color: [0.0; 4],
+        });
+
+        let mut offsets = [last.offset.clamp(0.0, 1.0); MAX_GRADIENT_STOPS];
+        let mut colors = [last.color; MAX_GRADIENT_STOPS];
+        for (i, stop) in stops.iter().enumerate() {
+            offsets[i] = stop.offset.clamp(0.0, 1.0);
+            colors[i] = stop.color;
+        }
+        (offsets, colors)
+    }
+
+    /// Shader parameters: `(kind, params)` where kind is 0 solid, 1 linear,
+    /// 2 radial. Linear params hold the axis scaled so that `dot(local, axis) + 0.5`
+    /// spans 0..1; radial params hold the local center and inverse radii.
+    pub fn shader_params(&self, half: [f32; 2]) -> (f32, [f32; 4]) {
+        match self {
+            FillStyle::Solid(_) => (0.0, [0.0; 4]),
+            FillStyle::LinearGradient { angle, .. } => {
+                let [x, y] = linear_axis(*angle, half);
+                (1.0, [x, y, 0.0, 0.0])
+            }
+            FillStyle::RadialGradient { center, radius, .. } => {
+                (2.0, radial_frame(*center, *radius, half))
+            }
+        }
     }
 }
+
+fn two_stops(from: [f32; 4], to: [f32; 4]) -> Vec<GradientStop> {
+    vec![
+        GradientStop {
+            offset: 0.0,
+            color: from,
+        },
+        GradientStop {
+            offset: 1.0,
+            color: to,
+        },
+    ]
+}
+
+/// Direction of a linear gradient divided by the length of the bounds'
+/// projection onto it, so the gradient runs exactly edge to edge.
+fn linear_axis(angle: f32, half: [f32; 2]) -> [f32; 2] {
+    let (sin, cos) = angle.sin_cos();
+    let extent = (half[0] * cos).abs() + (half[1] * sin).abs();
+    let scale = 1.0 / (2.0 * extent).max(1e-5);
+    [cos * scale, sin * scale]
+}
+
+/// Radial center as an offset from the bounds center, plus inverse radii.
+fn radial_frame(center: [f32; 2], radius: f32, half: [f32; 2]) -> [f32; 4] {
+    let size = [half[0] * 2.0, half[1] * 2.0];
+    [
+        (center[0] - 0.5) * size[0],
+        (center[1] - 0.5) * size[1],
+        1.0 / (radius * size[0]).max(1e-5),
+        1.0 / (radius * size[1]).max(1e-5),
+    ]
+}
@@ -10,8 +10,9 @@ use crate::vertex::Vertex;
 /// was previously scattered throughout update_logic.rs.
 pub struct PathTessellator {
     vertices: Vec<Vertex>,
-    indices: Vec<u16>,
-    index_offset: u16,
+    indices: Vec<u32>,
+    index_offset: u32,
+    depth: f32,
 }
 
 impl PathTessellator {
@@ -20,9 +21,20 @@ impl PathTessellator {
             vertices: Vec::new(),
             indices: Vec::new(),
             index_offset: 0,
+            depth: 0.0,
         }
     }
 
+    /// Set the depth for subsequently emitted geometry.
+    ///
+    /// The draw pipeline only accepts fragments strictly above what is already
+    /// in the depth buffer, so overlapping quads that share a depth cover each
+    /// pixel once. Give every element its own depth and a translucent stroke
+    /// blends evenly instead of darkening where its segments overlap.
+    pub fn set_depth(&mut self, depth: f32) {
+        self.depth = depth;
+    }
+
     /// Tessellate a path as a stroked polyline.
     ///
     /// Flattens curves to line segments, then expands each segment into
@@ -58,18 +70,22 @@ impl PathTessellator {
             Vertex {
                 position: [p1[0] - nx, p1[1] - ny],
                 color,
+                depth: self.depth,
             },
             Vertex {
                 position: [p1[0] + nx, p1[1] + ny],
                 color,
+                depth: self.depth,
             },
             Vertex {
                 position: [p2[0] + nx, p2[1] + ny],
                 color,
+                depth: self.depth,
             },
             Vertex {
                 position: [p2[0] - nx, p2[1] - ny],
                 color,
+                depth: self.depth,
             },
         ]);
 
@@ -105,6 +121,7 @@ impl PathTessellator {
         self.vertices.push(Vertex {
             position: [cx, cy],
             color,
+            depth: self.depth,
         });
         self.index_offset += 1;
 
@@ -112,10 +129,11 @@ impl PathTessellator {
             self.vertices.push(Vertex {
                 position: *p,
                 color,
+                depth: self.depth,
             });
         }
 
-        let count = points.len() as u16;
+        let count = points.len() as u32;
         for i in 0..count {
             self.indices.extend_from_slice(&[
                 center_idx,
@@ -126,6 +144,76 @@ impl PathTessellator {
         self.index_offset += count;
     }
 
+    /// Tessellate a filled convex polygon whose color varies across it.
+    ///
+    /// The fan is subdivided into rings and the edges are split so that
+    /// per-vertex interpolation closely follows `color_at` (e.g. gradients).
+    pub fn fill_convex_shaded(
+        &mut self,
+        points: &[[f32; 2]],
+        color_at: impl Fn([f32; 2]) -> [f32; 4],
+    ) {
+        const EDGE_STEPS: usize = 4;
+        const RINGS: usize = 8;
+
+        if points.len() < 3 {
+            return;
+        }
+
+        let mut outline = Vec::with_capacity(points.len() * EDGE_STEPS);
+        for (i, &a) in points.iter().enumerate() {
+            let b = points[(i + 1) % points.len()];
+            for step in 0..EDGE_STEPS {
+                let t = step as f32 / EDGE_STEPS as f32;
+                outline.push([a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t]);
+            }
+        }
+
+        let n = points.len() as f32;
+        let cx = points.iter().map(|p| p[0]).sum::<f32>() / n;
+        let cy = points.iter().map(|p| p[1]).sum::<f32>() / n;
+
+        let center_idx = self.index_offset;
+        self.vertices.push(Vertex {
+            position: [cx, cy],
+            color: color_at([cx, cy]),
+            depth: self.depth,
+        });
+
+        for ring in 1..=RINGS {
+            let k = ring as f32 / RINGS as f32;
+            for p in &outline {
+                let position = [cx + (p[0] - cx) * k, cy + (p[1] - cy) * k];
+                self.vertices.push(Vertex {
+                    position,
+                    color: color_at(position),
+                    depth: self.depth,
+                });
+            }
+        }
+
+        let count = outline.len() as u32;
+        let ring_start = |ring: u32| center_idx + 1 + (ring - 1) * count;
+        for i in 0..count {
+            let next = (i + 1) % count;
+            self.indices
+                .extend_from_slice(&[center_idx, ring_start(1) + i, ring_start(1) + next]);
+            for ring in 2..=RINGS as u32 {
+                let inner = ring_start(ring - 1);
+                let outer = ring_start(ring);
+                self.indices.extend_from_slice(&[
+                    inner + i,
+                    outer + i,
+                    outer + next,
+                    inner + i,
+                    outer + next,
+                    inner + next,
+                ]);
+            }
+        }
+        self.index_offset += 1 + count * RINGS as u32;
+    }
+
     /// Tessellate a stroked closed polygon (e.g. rectangle, diamond outline).
     pub fn stroke_polygon(&mut self, points: &[[f32; 2]], style: &StrokeStyle) {
         if points.is_empty() {
@@ -139,7 +227,7 @@ impl PathTessellator {
     }
 
     /// Consume the tessellator and return the accumulated geometry.
-    pub fn finish(self) -> (Vec<Vertex>, Vec<u16>) {
+    pub fn finish(self) -> (Vec<Vertex>, Vec<u32>) {
         (self.vertices, self.indices)
     }
 
@@ -149,7 +237,7 @@ impl PathTessellator {
     }
 
     /// Get the current index offset (useful for external geometry merging).
-    pub fn index_offset(&self) -> u16 {
+    pub fn index_offset(&self) -> u32 {
         self.index_offset
     }
 }
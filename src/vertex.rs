@@ -3,6 +3,8 @@
 pub struct Vertex {
     pub position: [f32; 2],
     pub color: [f32; 4],
+    /// Layer depth; see `PathTessellator::set_depth`.
+    pub depth: f32,
 }
 
 #[repr(C)]
@@ -40,6 +42,12 @@ impl Vertex {
                     shader_location: 1,
                     format: wgpu::VertexFormat::Float32x4,
                 },
+                wgpu::VertexAttribute {
+                    offset: (mem::size_of::<[f32; 2]>() + mem::size_of::<[f32; 4]>())
+                        as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32,
+                },
             ],
         }
     }
@@ -56,7 +64,8 @@ pub struct SdfVertex {
     pub local_pos: [f32; 2],    // Offset from shape center (for SDF evaluation)
     pub color: [f32; 4],        // Shape color
     pub shape_params: [f32; 4], // [shape_type, half_width, half_height, stroke_width]
-    pub fill_params: [f32; 4],  // [fill_flag, unused, unused, unused]
+    pub fill_params: [f32; 4],  // [mode, corner_radius, depth, gradient_kind]
+    pub paint: u32,             // Paint table entry with the gradient and stop colors
 }
 
 impl SdfVertex {
@@ -93,6 +102,12 @@ impl SdfVertex {
                     shader_location: 4,
                     format: wgpu::VertexFormat::Float32x4, // fill_params
                 },
+                wgpu::VertexAttribute {
+                    offset: (mem::size_of::<[f32; 2]>() * 2 + mem::size_of::<[f32; 4]>() * 3)
+                        as wgpu::BufferAddress,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Uint32, // paint
+                },
             ],
         }
     }
@@ -125,3 +140,49 @@ impl UiVertex {
         }
     }
 }
+
+/// Vertex for compositing a blurred offscreen effect (shadow or glow) back
+/// onto the canvas.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct EffectVertex {
+    pub position: [f32; 2],
+    pub uv: [f32; 2],
+    /// Layer depth; see `PathTessellator::set_depth`.
+    pub depth: f32,
+    /// Effect opacity, applied to the blurred texture.
+    pub alpha: f32,
+}
+
+impl EffectVertex {
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        use std::mem;
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<EffectVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: (mem::size_of::<[f32; 2]>() * 2) as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32,
+                },
+                wgpu::VertexAttribute {
+                    offset: (mem::size_of::<[f32; 2]>() * 2 + mem::size_of::<f32>())
+                        as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32,
+                },
+            ],
+        }
+    }
+}